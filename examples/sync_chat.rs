@@ -152,6 +152,8 @@ fn main() -> anyhow::Result<()> {
             )
             .with_srtp_protection_profiles(vec![SrtpProtectionProfile::Srtp_Aes128_Cm_Hmac_Sha1_80])
             .with_extended_master_secret(dtls::config::ExtendedMasterSecretType::Require)
+            // the client certificate is verified against the fingerprints of the remote description
+            .with_client_auth(dtls::config::ClientAuthType::RequireAnyClientCert)
             .build(false, None)?,
     );
     let sctp_endpoint_config = Arc::new(sctp::EndpointConfig::default());
@@ -1,25 +1,37 @@
 use crate::description::{
     codecs_from_media_description, fmtp,
+    payload_type_allocator::{validate_dynamic_payload_type_range, PayloadTypeAllocator},
     rtp_codec::{
         codec_parameters_fuzzy_search, CodecMatch, RTCRtpCodecCapability, RTCRtpCodecParameters,
         RTCRtpHeaderExtensionCapability, RTCRtpHeaderExtensionParameters, RTCRtpParameters,
         RTPCodecType,
     },
     rtp_extensions_from_media_description,
-    rtp_transceiver::{PayloadType, RTCPFeedback, TYPE_RTCP_FB_TRANSPORT_CC},
+    rtp_transceiver::{
+        PayloadType, RTCPFeedback, RTCP_FB_CCM_TMMBR, TYPE_RTCP_FB_CCM, TYPE_RTCP_FB_TRANSPORT_CC,
+    },
     rtp_transceiver_direction::RTCRtpTransceiverDirection,
+    SDES_REPAIRED_RTP_STREAM_ID_URI,
 };
 
 //TODO: use crate::stats::stats_collector::StatsCollector;
 //use crate::stats::CodecStats;
 //use crate::stats::StatsReportType::Codec;
+use crate::interceptors::bitrate_limiter::limiter::Limiter;
+use crate::interceptors::jitter_buffer::buffer::JitterBuffer;
+use crate::interceptors::remb::capper::Capper;
 use crate::interceptors::report::receiver_report::ReceiverReport;
 use crate::interceptors::report::sender_report::SenderReport;
+use crate::interceptors::tmmbr::responder::Responder;
+use crate::interceptors::twcc::receiver::Receiver;
+use crate::interceptors::twcc::sender::Sender;
 use crate::interceptors::Registry;
+use crate::session::dominant_speaker::CSRC_AUDIO_LEVEL_URI;
 use sdp::description::session::SessionDescription;
 use shared::error::{Error, Result};
 use std::collections::HashMap;
-use std::ops::Range;
+use std::ops::{Range, RangeInclusive};
+use std::time::Duration;
 
 /// MIME_TYPE_H264 H264 MIME type.
 /// Note: Matching should be case insensitive.
@@ -48,6 +60,12 @@ pub const MIME_TYPE_PCMA: &str = "audio/PCMA";
 /// MIME_TYPE_TELEPHONE_EVENT telephone-event MIME type
 /// Note: Matching should be case insensitive.
 pub const MIME_TYPE_TELEPHONE_EVENT: &str = "audio/telephone-event";
+/// MIME_TYPE_RTX RTX MIME type
+/// Note: Matching should be case insensitive.
+pub const MIME_TYPE_RTX: &str = "video/rtx";
+/// MIME_TYPE_RED RED (redundant audio) MIME type
+/// Note: Matching should be case insensitive.
+pub const MIME_TYPE_RED: &str = "audio/red";
 
 const VALID_EXT_IDS: Range<isize> = 1..15;
 
@@ -71,6 +89,18 @@ impl RTCRtpHeaderExtension {
             true
         }
     }
+
+    /// extmap_direction returns the direction qualifier of the extmap attribute,
+    /// Unspecified if the extension is used in both directions
+    pub(crate) fn extmap_direction(&self) -> RTCRtpTransceiverDirection {
+        match self.allowed_direction {
+            Some(
+                direction @ (RTCRtpTransceiverDirection::Sendonly
+                | RTCRtpTransceiverDirection::Recvonly),
+            ) => direction,
+            _ => RTCRtpTransceiverDirection::Unspecified,
+        }
+    }
 }
 
 /// A MediaConfig defines the codecs supported by a PeerConnection, and the
@@ -78,6 +108,7 @@ impl RTCRtpHeaderExtension {
 /// PeerConnections.
 pub struct MediaConfig {
     registry: Registry,
+    interceptor_profiles: HashMap<String, Registry>,
 
     // If we have attempted to negotiate a codec type yet.
     pub(crate) negotiated_video: bool,
@@ -91,12 +122,17 @@ pub struct MediaConfig {
     header_extensions: Vec<RTCRtpHeaderExtension>,
     proposed_header_extensions: HashMap<isize, RTCRtpHeaderExtension>,
     pub(crate) negotiated_header_extensions: HashMap<isize, RTCRtpHeaderExtension>,
+
+    // range of dynamic payload types allocated to codecs of offered media sections,
+    // None keeps the registered payload types
+    dynamic_payload_type_range: Option<RangeInclusive<PayloadType>>,
 }
 
 impl Default for MediaConfig {
     fn default() -> Self {
         let mut media_config = MediaConfig {
             registry: Registry::new(),
+            interceptor_profiles: HashMap::new(),
 
             negotiated_video: false,
             negotiated_audio: false,
@@ -107,10 +143,13 @@ impl Default for MediaConfig {
             header_extensions: vec![],
             proposed_header_extensions: HashMap::new(),
             negotiated_header_extensions: HashMap::new(),
+            dynamic_payload_type_range: None,
         };
 
         let _ = media_config.register_default_codecs();
         let _ = media_config.register_default_interceptors();
+        // rids of simulcast layers are read by the gateway to forward the selected one
+        let _ = media_config.configure_simulcast_extension_headers();
 
         media_config
     }
@@ -122,6 +161,23 @@ impl MediaConfig {
         &self.registry
     }
 
+    /// register_interceptor_profile registers a named Registry, which builds interceptor chains
+    /// of endpoints selecting the profile, e.g., different ones for publishers and subscribers.
+    pub fn register_interceptor_profile(&mut self, profile: String, registry: Registry) {
+        self.interceptor_profiles.insert(profile, registry);
+    }
+
+    /// get Registry of the interceptor profile, or the default Registry if no profile is selected
+    pub(crate) fn get_registry(&self, profile: Option<&String>) -> &Registry {
+        match profile {
+            Some(profile) => self.interceptor_profiles.get(profile).unwrap_or_else(|| {
+                log::warn!("Unknown interceptor profile {}, use default one", profile);
+                &self.registry
+            }),
+            None => &self.registry,
+        }
+    }
+
     /// register_default_codecs registers the default codecs supported by Pion WebRTC.
     /// register_default_codecs is not safe for concurrent use.
     pub fn register_default_codecs(&mut self) -> Result<()> {
@@ -374,6 +430,32 @@ impl MediaConfig {
         }
     }
 
+    /// set_dynamic_payload_type_range sets the range of dynamic payload types allocated to codecs
+    /// in offered media sections, so that codecs of a media section never share a payload type.
+    /// Registered payload types are kept when they are in range, and static ones are never changed.
+    /// The range must be within 35..=63 or 96..=127, which don't collide with RTCP packet types.
+    pub fn set_dynamic_payload_type_range(
+        &mut self,
+        range: RangeInclusive<PayloadType>,
+    ) -> Result<()> {
+        validate_dynamic_payload_type_range(&range)?;
+        self.dynamic_payload_type_range = Some(range);
+        Ok(())
+    }
+
+    /// get_offered_codecs_by_kind returns the local codecs for offered media sections,
+    /// with payload types allocated within the dynamic payload type range if it is set
+    pub(crate) fn get_offered_codecs_by_kind(
+        &self,
+        typ: RTPCodecType,
+    ) -> Result<Vec<RTCRtpCodecParameters>> {
+        let codecs = self.get_codecs_by_kind(typ);
+        match self.dynamic_payload_type_range.as_ref() {
+            Some(range) => PayloadTypeAllocator::new(range.clone()).allocate_codecs(codecs),
+            None => Ok(codecs.to_vec()),
+        }
+    }
+
     /// Adds a header extension to the MediaConfig
     /// To determine the negotiated value use [`get_header_extension_id`] after signaling is complete.
     ///
@@ -483,6 +565,7 @@ impl MediaConfig {
             video_codecs: self.video_codecs.clone(),
             audio_codecs: self.audio_codecs.clone(),
             header_extensions: self.header_extensions.clone(),
+            dynamic_payload_type_range: self.dynamic_payload_type_range.clone(),
             ..Default::default()
         }
     }
@@ -724,6 +807,7 @@ impl MediaConfig {
                     header_extensions.push(RTCRtpHeaderExtensionParameters {
                         id: *id,
                         uri: e.uri.clone(),
+                        direction: e.extmap_direction(),
                     });
                 }
             }
@@ -750,6 +834,7 @@ impl MediaConfig {
                     header_extensions.push(RTCRtpHeaderExtensionParameters {
                         id: *id,
                         uri: negotiated_extension.uri.clone(),
+                        direction: negotiated_extension.extmap_direction(),
                     });
 
                     continue;
@@ -764,6 +849,7 @@ impl MediaConfig {
                     header_extensions.push(RTCRtpHeaderExtensionParameters {
                         id: *id,
                         uri: negotiated_extension.uri.clone(),
+                        direction: negotiated_extension.extmap_direction(),
                     });
 
                     continue;
@@ -792,6 +878,7 @@ impl MediaConfig {
                     header_extensions.push(RTCRtpHeaderExtensionParameters {
                         id,
                         uri: local_extension.uri.clone(),
+                        direction: local_extension.extmap_direction(),
                     });
                 } else {
                     log::warn!("No available RTP extension ID for {}", local_extension.uri);
@@ -823,6 +910,7 @@ impl MediaConfig {
                     header_extensions.push(RTCRtpHeaderExtensionParameters {
                         id: *id,
                         uri: e.uri.clone(),
+                        direction: e.extmap_direction(),
                     });
                 }
             }
@@ -849,6 +937,7 @@ impl MediaConfig {
                     header_extensions.push(RTCRtpHeaderExtensionParameters {
                         id: *id,
                         uri: negotiated_extension.uri.clone(),
+                        direction: negotiated_extension.extmap_direction(),
                     });
 
                     continue;
@@ -863,6 +952,7 @@ impl MediaConfig {
                     header_extensions.push(RTCRtpHeaderExtensionParameters {
                         id: *id,
                         uri: negotiated_extension.uri.clone(),
+                        direction: negotiated_extension.extmap_direction(),
                     });
 
                     continue;
@@ -891,6 +981,7 @@ impl MediaConfig {
                     header_extensions.push(RTCRtpHeaderExtensionParameters {
                         id,
                         uri: local_extension.uri.clone(),
+                        direction: local_extension.extmap_direction(),
                     });
                 } else {
                     log::warn!("No available RTP extension ID for {}", local_extension.uri);
@@ -919,6 +1010,7 @@ impl MediaConfig {
                     header_extensions.push(RTCRtpHeaderExtensionParameters {
                         uri: e.uri.clone(),
                         id: *id,
+                        direction: e.extmap_direction(),
                     });
                 }
             }
@@ -963,6 +1055,99 @@ impl MediaConfig {
         registry*/
     }
 
+    /// configure_tmmbr will setup everything necessary for negotiating "ccm tmmbr" feedback,
+    /// handling TMMBR messages and responding them with TMMBN messages.
+    pub fn configure_tmmbr(&mut self) {
+        for typ in [RTPCodecType::Video, RTPCodecType::Audio] {
+            self.register_rtcp_feedback(
+                RTCPFeedback {
+                    typ: TYPE_RTCP_FB_CCM.to_owned(),
+                    parameter: RTCP_FB_CCM_TMMBR.to_owned(),
+                },
+                typ,
+            );
+        }
+
+        let responder = Box::new(Responder::builder());
+        self.registry.add(responder);
+    }
+
+    /// configure_bitrate_limit will setup dropping inbound RTP packets of each SSRC
+    /// exceeding max_bitrate in bits per second.
+    pub fn configure_bitrate_limit(&mut self, max_bitrate: u64) {
+        let limiter = Box::new(Limiter::builder().with_max_bitrate(max_bitrate));
+        self.registry.add(limiter);
+    }
+
+    /// configure_jitter_buffer will setup holding inbound RTP packets of each SSRC for target_delay
+    /// to undo reordering, the delay adapts to interarrival jitter up to max_delay if it is some.
+    /// Endpoints selecting an interceptor profile are built from its Registry instead, which may have
+    /// a JitterBufferBuilder of different delays.
+    pub fn configure_jitter_buffer(&mut self, target_delay: Duration, max_delay: Option<Duration>) {
+        let mut jitter_buffer = JitterBuffer::builder().with_target_delay(target_delay);
+        if let Some(max_delay) = max_delay {
+            jitter_buffer = jitter_buffer.with_adaptive(true).with_max_delay(max_delay);
+        }
+        self.registry.add(Box::new(jitter_buffer));
+    }
+
+    /// configure_remb_cap will setup clamping the bitrate advertised by outbound REMB messages
+    /// to max_bitrate in bits per second, regardless of the receivers' estimates.
+    pub fn configure_remb_cap(&mut self, max_bitrate: u64) {
+        let capper = Box::new(Capper::builder().with_max_bitrate(max_bitrate));
+        self.registry.add(capper);
+    }
+
+    /// configure_audio_level_extension_headers registers the client-to-mixer and mixer-to-client
+    /// audio level header extensions of audio, the former is forwarded as is in selective forward
+    /// mode, and replaced by the latter for the contributing sources in dominant speaker mode
+    pub fn configure_audio_level_extension_headers(&mut self) -> Result<()> {
+        for uri in [sdp::extmap::AUDIO_LEVEL_URI, CSRC_AUDIO_LEVEL_URI] {
+            self.register_header_extension(
+                RTCRtpHeaderExtensionCapability {
+                    uri: uri.to_owned(),
+                },
+                RTPCodecType::Audio,
+                None,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// configure_simulcast_extension_headers registers the RTP stream id and repaired RTP stream id
+    /// header extensions of video, which associate simulcast streams and their RTX streams with rids,
+    /// they are registered by default
+    pub fn configure_simulcast_extension_headers(&mut self) -> Result<()> {
+        for uri in [
+            sdp::extmap::SDES_RTP_STREAM_ID_URI,
+            SDES_REPAIRED_RTP_STREAM_ID_URI,
+        ] {
+            self.register_header_extension(
+                RTCRtpHeaderExtensionCapability {
+                    uri: uri.to_owned(),
+                },
+                RTPCodecType::Video,
+                None,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// configure_abs_send_time_extension_headers registers the absolute send time header extension
+    /// of audio and video, which carries the send time of packets for bandwidth estimation
+    pub fn configure_abs_send_time_extension_headers(&mut self) -> Result<()> {
+        for typ in [RTPCodecType::Audio, RTPCodecType::Video] {
+            self.register_header_extension(
+                RTCRtpHeaderExtensionCapability {
+                    uri: sdp::extmap::ABS_SEND_TIME_URI.to_owned(),
+                },
+                typ,
+                None,
+            )?;
+        }
+        Ok(())
+    }
+
     /// configure_twcc will setup everything necessary for adding
     /// a TWCC header extension to outgoing RTP packets and generating TWCC reports.
     pub fn configure_twcc(&mut self) -> Result<()> {
@@ -996,11 +1181,9 @@ impl MediaConfig {
             None,
         )?;
 
-        /*TODO:
-        let sender = Box::new(Sender::builder());
-        let receiver = Box::new(Receiver::builder());
-        registry.add(sender);
-        registry.add(receiver); */
+        self.configure_twcc_sender();
+        self.configure_twcc_receiver();
+
         Ok(())
     }
 
@@ -1023,13 +1206,29 @@ impl MediaConfig {
             None,
         )?;
 
-        /*TODO:
-        let sender = Box::new(Sender::builder());
-        registry.add(sender);*/
+        self.configure_twcc_sender();
 
         Ok(())
     }
 
+    /// configure_twcc_sender adds the TWCC Sender interceptor with the transport-cc header extension id
+    /// proposed in local descriptions, so that it matches the extmap which remote peers agree on.
+    fn configure_twcc_sender(&mut self) {
+        let header_extension_id = self
+            .get_rtp_parameters_by_kind(RTPCodecType::Video, RTCRtpTransceiverDirection::Sendonly)
+            .header_extensions
+            .iter()
+            .find(|ext| ext.uri == sdp::extmap::TRANSPORT_CC_URI)
+            .map(|ext| ext.id as u8);
+
+        if let Some(header_extension_id) = header_extension_id {
+            let sender = Box::new(Sender::builder().with_header_extension_id(header_extension_id));
+            self.registry.add(sender);
+        } else {
+            log::warn!("No transport-cc header extension id for TWCC Sender");
+        }
+    }
+
     /// configure_twcc_receiver will setup everything necessary for generating TWCC reports.
     pub fn configure_twcc_receiver_only(&mut self) -> Result<()> {
         self.register_rtcp_feedback(
@@ -1062,10 +1261,27 @@ impl MediaConfig {
             None,
         )?;
 
-        /*TODO:
-        let receiver = Box::new(Receiver::builder());
-        registry.add(receiver);*/
+        self.configure_twcc_receiver();
 
         Ok(())
     }
+
+    /// configure_twcc_receiver adds the TWCC Receiver interceptor with the transport-cc header extension id
+    /// proposed in local descriptions, as configure_twcc_sender does.
+    fn configure_twcc_receiver(&mut self) {
+        let header_extension_id = self
+            .get_rtp_parameters_by_kind(RTPCodecType::Video, RTCRtpTransceiverDirection::Recvonly)
+            .header_extensions
+            .iter()
+            .find(|ext| ext.uri == sdp::extmap::TRANSPORT_CC_URI)
+            .map(|ext| ext.id as u8);
+
+        if let Some(header_extension_id) = header_extension_id {
+            let receiver =
+                Box::new(Receiver::builder().with_header_extension_id(header_extension_id));
+            self.registry.add(receiver);
+        } else {
+            log::warn!("No transport-cc header extension id for TWCC Receiver");
+        }
+    }
 }
@@ -1,5 +1,12 @@
 use crate::configs::media_config::MediaConfig;
+use crate::configs::session_config::AudioForwardingMode;
+use crate::description::bundle_policy::RTCBundlePolicy;
+use crate::description::rtcp_rsize_policy::RtcpRsizePolicy;
+use crate::interceptors::DEFAULT_MAX_INTERCEPTOR_EVENTS;
 use crate::server::certificate::RTCCertificate;
+use crate::server::certificate_verifier::{CertificateVerifier, FingerprintVerifier};
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -7,10 +14,28 @@ use std::time::Duration;
 pub struct ServerConfig {
     pub(crate) certificates: Vec<RTCCertificate>,
     pub(crate) dtls_handshake_config: Arc<dtls::config::HandshakeConfig>,
+    pub(crate) certificate_verifier: Arc<dyn CertificateVerifier>,
+    pub(crate) dtls_client_handshake_config: Option<Arc<dtls::config::HandshakeConfig>>,
     pub(crate) sctp_endpoint_config: Arc<sctp::EndpointConfig>,
     pub(crate) sctp_server_config: Arc<sctp::ServerConfig>,
     pub(crate) media_config: MediaConfig,
     pub(crate) idle_timeout: Duration,
+    pub(crate) transceiver_pool_size: usize,
+    pub(crate) stun_binding_rate_limit: Option<u32>,
+    pub(crate) host_addrs: HashMap<SocketAddr, Vec<SocketAddr>>,
+    pub(crate) server_reflexive_addrs: HashMap<SocketAddr, SocketAddr>,
+    pub(crate) mask_related_addr: bool,
+    pub(crate) audio_forwarding_mode: AudioForwardingMode,
+    pub(crate) bundle_policy: RTCBundlePolicy,
+    pub(crate) echo_ssrc_attributes: bool,
+    pub(crate) echo_vendor_fmtp_parameters: bool,
+    pub(crate) max_interceptor_events: usize,
+    pub(crate) require_rtcp_mux: bool,
+    pub(crate) rtcp_rsize_policy: RtcpRsizePolicy,
+    pub(crate) max_subscribers_per_publisher: Option<usize>,
+    pub(crate) strip_padding_only_packets: bool,
+    pub(crate) svc_layer_switch_grace_period: Duration,
+    pub(crate) sdp_origin_username: String,
 }
 
 impl ServerConfig {
@@ -22,7 +47,25 @@ impl ServerConfig {
             sctp_endpoint_config: Arc::new(sctp::EndpointConfig::default()),
             sctp_server_config: Arc::new(sctp::ServerConfig::default()),
             dtls_handshake_config: Arc::new(dtls::config::HandshakeConfig::default()),
+            dtls_client_handshake_config: None,
+            certificate_verifier: Arc::new(FingerprintVerifier),
             idle_timeout: Duration::from_secs(30),
+            transceiver_pool_size: 0,
+            stun_binding_rate_limit: None,
+            host_addrs: HashMap::new(),
+            server_reflexive_addrs: HashMap::new(),
+            mask_related_addr: false,
+            audio_forwarding_mode: AudioForwardingMode::default(),
+            bundle_policy: RTCBundlePolicy::default(),
+            echo_ssrc_attributes: false,
+            echo_vendor_fmtp_parameters: false,
+            max_interceptor_events: DEFAULT_MAX_INTERCEPTOR_EVENTS,
+            require_rtcp_mux: false,
+            rtcp_rsize_policy: RtcpRsizePolicy::default(),
+            max_subscribers_per_publisher: None,
+            strip_padding_only_packets: false,
+            svc_layer_switch_grace_period: Duration::ZERO,
+            sdp_origin_username: "-".to_string(),
         }
     }
 
@@ -47,7 +90,9 @@ impl ServerConfig {
         self
     }
 
-    /// build with provided dtls::config::HandshakeConfig
+    /// build with provided dtls::config::HandshakeConfig for DTLS server role, which must request
+    /// client certificates, e.g., with ClientAuthType::RequireAnyClientCert, for the default
+    /// FingerprintVerifier to accept remotes with fingerprints
     pub fn with_dtls_handshake_config(
         mut self,
         dtls_handshake_config: Arc<dtls::config::HandshakeConfig>,
@@ -56,9 +101,156 @@ impl ServerConfig {
         self
     }
 
+    /// build with provided dtls::config::HandshakeConfig for DTLS client role,
+    /// which is resolved when remote offers with setup:passive, or setup:actpass
+    /// so that the DTLS handshake starts along with the answer
+    pub fn with_dtls_client_handshake_config(
+        mut self,
+        dtls_client_handshake_config: Arc<dtls::config::HandshakeConfig>,
+    ) -> Self {
+        self.dtls_client_handshake_config = Some(dtls_client_handshake_config);
+        self
+    }
+
+    /// build with the verifier of remote DTLS certificates, which replaces the default
+    /// FingerprintVerifier, client certificates are only presented if HandshakeConfig requests them
+    pub fn with_certificate_verifier(
+        mut self,
+        certificate_verifier: Arc<dyn CertificateVerifier>,
+    ) -> Self {
+        self.certificate_verifier = certificate_verifier;
+        self
+    }
+
+    /// build with the number of transceivers pre-allocated for each endpoint, so subscribing to
+    /// streams of up to that many transceivers doesn't grow the endpoint's transceiver storage
+    pub fn with_transceiver_pool_size(mut self, transceiver_pool_size: usize) -> Self {
+        self.transceiver_pool_size = transceiver_pool_size;
+        self
+    }
+
     /// build with idle timeout
     pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
         self.idle_timeout = idle_timeout;
         self
     }
+
+    /// build with max STUN binding requests per second per four-tuple, excess requests are dropped
+    pub fn with_stun_binding_rate_limit(mut self, max_binding_requests_per_second: u32) -> Self {
+        self.stun_binding_rate_limit = Some(max_binding_requests_per_second);
+        self
+    }
+
+    /// build with other addresses of the interfaces local_addr is reachable at, e.g., by a
+    /// multi-homed server listening on all interfaces, advertised as host candidates
+    /// after the host candidate of local_addr
+    pub fn with_host_addrs(mut self, local_addr: SocketAddr, host_addrs: Vec<SocketAddr>) -> Self {
+        self.host_addrs.insert(local_addr, host_addrs);
+        self
+    }
+
+    /// build with the public address which local_addr is statically mapped to, e.g., by 1:1 NAT,
+    /// advertised as server reflexive candidate along with the host candidate of local_addr
+    pub fn with_server_reflexive_addr(
+        mut self,
+        local_addr: SocketAddr,
+        server_reflexive_addr: SocketAddr,
+    ) -> Self {
+        self.server_reflexive_addrs
+            .insert(local_addr, server_reflexive_addr);
+        self
+    }
+
+    /// build with privacy mode of server reflexive candidates, which advertise
+    /// raddr 0.0.0.0 rport 0 instead of the host address they are mapped from
+    pub fn with_mask_related_addr(mut self, mask_related_addr: bool) -> Self {
+        self.mask_related_addr = mask_related_addr;
+        self
+    }
+
+    /// build with the default audio forwarding mode of sessions
+    pub fn with_audio_forwarding_mode(
+        mut self,
+        audio_forwarding_mode: AudioForwardingMode,
+    ) -> Self {
+        self.audio_forwarding_mode = audio_forwarding_mode;
+        self
+    }
+
+    /// build with the bundle policy, which decides the media sections with their own
+    /// ICE candidates in local descriptions, though only the BUNDLE transport is served
+    pub fn with_bundle_policy(mut self, bundle_policy: RTCBundlePolicy) -> Self {
+        self.bundle_policy = bundle_policy;
+        self
+    }
+
+    /// build with echoing the remote's cname of each ssrc in local descriptions,
+    /// instead of the cname of its first ssrc for all ssrcs of the track
+    pub fn with_echo_ssrc_attributes(mut self, echo_ssrc_attributes: bool) -> Self {
+        self.echo_ssrc_attributes = echo_ssrc_attributes;
+        self
+    }
+
+    /// build with echoing the offered vendor fmtp parameters of each codec in answers,
+    /// e.g., x-google-min-bitrate and x-google-max-bitrate, verbatim after the local ones
+    pub fn with_echo_vendor_fmtp_parameters(mut self, echo_vendor_fmtp_parameters: bool) -> Self {
+        self.echo_vendor_fmtp_parameters = echo_vendor_fmtp_parameters;
+        self
+    }
+
+    /// build with the max messages produced by interceptors per read, write or timeout,
+    /// e.g., in a NACK storm, excess messages are dropped
+    pub fn with_max_interceptor_events(mut self, max_interceptor_events: usize) -> Self {
+        self.max_interceptor_events = max_interceptor_events;
+        self
+    }
+
+    /// build with rejecting offered media sections without rtcp-mux, instead of
+    /// answering them without rtcp-mux as well
+    pub fn with_require_rtcp_mux(mut self, require_rtcp_mux: bool) -> Self {
+        self.require_rtcp_mux = require_rtcp_mux;
+        self
+    }
+
+    /// build with the media kinds advertising a=rtcp-rsize, e.g., video only,
+    /// which is still only answered if offered
+    pub fn with_rtcp_rsize_policy(mut self, rtcp_rsize_policy: RtcpRsizePolicy) -> Self {
+        self.rtcp_rsize_policy = rtcp_rsize_policy;
+        self
+    }
+
+    /// build with the default max endpoints of a session receiving streams of the same publisher,
+    /// further endpoints don't subscribe to the publisher
+    pub fn with_max_subscribers_per_publisher(
+        mut self,
+        max_subscribers_per_publisher: usize,
+    ) -> Self {
+        self.max_subscribers_per_publisher = Some(max_subscribers_per_publisher);
+        self
+    }
+
+    /// build with not forwarding RTP packets which only carry padding, e.g., bandwidth probes
+    /// of publishers, they are still counted in inbound stats
+    pub fn with_strip_padding_only_packets(mut self, strip_padding_only_packets: bool) -> Self {
+        self.strip_padding_only_packets = strip_padding_only_packets;
+        self
+    }
+
+    /// build with the grace period after max SVC layers of a subscriber are lowered, or its
+    /// simulcast layer is switched, within which packets of layers allowed by the previous cap or
+    /// of the previous simulcast layer are still forwarded, since they were in flight
+    pub fn with_svc_layer_switch_grace_period(
+        mut self,
+        svc_layer_switch_grace_period: Duration,
+    ) -> Self {
+        self.svc_layer_switch_grace_period = svc_layer_switch_grace_period;
+        self
+    }
+
+    /// build with the username of o= line of local descriptions, "-" by default, the origin is
+    /// kept across renegotiations of an endpoint except the session version
+    pub fn with_sdp_origin_username(mut self, sdp_origin_username: String) -> Self {
+        self.sdp_origin_username = sdp_origin_username;
+        self
+    }
 }
@@ -1,17 +1,48 @@
 use crate::configs::server_config::ServerConfig;
+use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// AudioForwardingMode controls how audio streams are forwarded to subscribers in a session
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AudioForwardingMode {
+    /// forward every publisher's audio stream as is
+    #[default]
+    SelectiveForward,
+    /// forward only the dominant speaker's audio as a single stream per subscriber, i.e., the
+    /// loudest talker with a hold time and a level margin before switching, reporting the
+    /// top-N loudest talkers as contributing sources, audio is not decoded nor mixed
+    DominantSpeaker { top_n: usize },
+}
 
 pub(crate) struct SessionConfig {
     pub(crate) server_config: Arc<ServerConfig>,
     pub(crate) local_addr: SocketAddr,
+    pub(crate) audio_forwarding_mode: AudioForwardingMode,
+    pub(crate) max_subscribers_per_publisher: Option<usize>,
+    // lowercase mime types of MediaConfig's codecs excluded from the session's descriptions
+    pub(crate) disabled_codecs: HashSet<String>,
+    // interval of RTCP reports and TWCC feedback sent by interceptors of the session's endpoints,
+    // None uses the interval configured in MediaConfig's interceptor builders
+    pub(crate) report_interval: Option<Duration>,
+    // a=sctp-port of the session's application media sections unless echoing the offered one,
+    // None uses the port of ServerConfig's SCTP transport
+    pub(crate) sctp_port: Option<u16>,
 }
 
 impl SessionConfig {
     pub(crate) fn new(server_config: Arc<ServerConfig>, local_addr: SocketAddr) -> Self {
+        let audio_forwarding_mode = server_config.audio_forwarding_mode;
+        let max_subscribers_per_publisher = server_config.max_subscribers_per_publisher;
         Self {
             server_config,
             local_addr,
+            audio_forwarding_mode,
+            max_subscribers_per_publisher,
+            disabled_codecs: HashSet::new(),
+            report_interval: None,
+            sctp_port: None,
         }
     }
 }
@@ -0,0 +1,43 @@
+use crate::description::UNSPECIFIED_STR;
+use std::fmt;
+
+/// BundlePolicy affects which media tracks are negotiated if the remote
+/// endpoint is not bundle-aware, and what ICE candidates are gathered.
+/// <https://datatracker.ietf.org/doc/html/rfc8829#section-4.1.1>
+///
+/// The policy only decides how local descriptions advertise transports, while all media sections
+/// are still offered in one BUNDLE group. The SFU serves only one transport per endpoint, so an
+/// answer not bundling its media sections is rejected, and checks of other transports than the
+/// BUNDLE one are answered with 400 Bad Request.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RTCBundlePolicy {
+    Unspecified = 0,
+
+    /// indicates to gather ICE candidates for each media type in use
+    /// (audio, video, and data), i.e., the first media section of each type isn't bundle-only.
+    Balanced,
+
+    /// indicates to gather ICE candidates for each media section,
+    /// i.e., each of them has its own transport if the remote endpoint is not bundle-aware.
+    MaxCompat,
+
+    /// indicates to gather ICE candidates for only one media section,
+    /// i.e., all media sections are bundled on one transport.
+    #[default]
+    MaxBundle,
+}
+
+const BUNDLE_POLICY_BALANCED_STR: &str = "balanced";
+const BUNDLE_POLICY_MAX_COMPAT_STR: &str = "max-compat";
+const BUNDLE_POLICY_MAX_BUNDLE_STR: &str = "max-bundle";
+
+impl fmt::Display for RTCBundlePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RTCBundlePolicy::Balanced => write!(f, "{BUNDLE_POLICY_BALANCED_STR}"),
+            RTCBundlePolicy::MaxCompat => write!(f, "{BUNDLE_POLICY_MAX_COMPAT_STR}"),
+            RTCBundlePolicy::MaxBundle => write!(f, "{BUNDLE_POLICY_MAX_BUNDLE_STR}"),
+            _ => write!(f, "{}", UNSPECIFIED_STR),
+        }
+    }
+}
@@ -0,0 +1,1538 @@
+use super::*;
+use crate::configs::media_config::MediaConfig;
+use crate::configs::server_config::ServerConfig;
+use crate::description::bundle_policy::RTCBundlePolicy;
+use crate::description::rtp_codec::RTPCodecType;
+use crate::description::rtp_transceiver::RTCRtpSender;
+
+fn parse_sdp(sdp: &str) -> SessionDescription {
+    let mut reader = Cursor::new(sdp.as_bytes());
+    SessionDescription::unmarshal(&mut reader).unwrap()
+}
+
+const SESSION_RECVONLY_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=recvonly\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:0\r\n\
+a=rtpmap:111 opus/48000/2\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:1\r\n\
+a=sendonly\r\n\
+a=rtpmap:96 VP8/90000\r\n";
+
+#[test]
+fn test_get_peer_direction_inherits_session_level() {
+    let parsed = parse_sdp(SESSION_RECVONLY_SDP);
+
+    assert_eq!(
+        get_session_direction(&parsed),
+        RTCRtpTransceiverDirection::Recvonly
+    );
+    assert_eq!(
+        get_peer_direction(&parsed, &parsed.media_descriptions[0]),
+        RTCRtpTransceiverDirection::Recvonly
+    );
+    // media-level direction takes precedence over the session-level one
+    assert_eq!(
+        get_peer_direction(&parsed, &parsed.media_descriptions[1]),
+        RTCRtpTransceiverDirection::Sendonly
+    );
+}
+
+#[test]
+fn test_get_peer_direction_defaults_to_sendrecv() {
+    let parsed = parse_sdp(&SESSION_RECVONLY_SDP.replace("a=recvonly\r\n", ""));
+
+    assert_eq!(
+        get_session_direction(&parsed),
+        RTCRtpTransceiverDirection::Unspecified
+    );
+    assert_eq!(
+        get_peer_direction(&parsed, &parsed.media_descriptions[0]),
+        RTCRtpTransceiverDirection::Sendrecv
+    );
+}
+
+/// new_session_config_on returns the session config on local_addr of a server config without
+/// certificates, which is further built by with_server_config
+fn new_session_config_on(
+    local_addr: SocketAddr,
+    with_server_config: impl FnOnce(ServerConfig) -> ServerConfig,
+) -> SessionConfig {
+    SessionConfig::new(
+        std::sync::Arc::new(with_server_config(ServerConfig::new(vec![]))),
+        local_addr,
+    )
+}
+
+fn new_session_config_with(
+    with_server_config: impl FnOnce(ServerConfig) -> ServerConfig,
+) -> SessionConfig {
+    new_session_config_on("127.0.0.1:3478".parse().unwrap(), with_server_config)
+}
+
+fn new_session_config() -> SessionConfig {
+    new_session_config_with(|server_config| server_config)
+}
+
+fn new_media_config_with_default_codecs() -> Result<MediaConfig> {
+    let mut media_config = MediaConfig::default();
+    media_config.register_default_codecs()?;
+    Ok(media_config)
+}
+
+#[test]
+fn test_gather_candidates_with_server_reflexive_addr() {
+    let local_addr = "10.0.0.1:3478".parse().unwrap();
+    let server_reflexive_addr = "203.0.113.1:3478".parse().unwrap();
+    let get_candidate_values = |mask_related_addr: bool| -> Vec<String> {
+        let session_config = new_session_config_on(local_addr, |server_config| {
+            server_config
+                .with_server_reflexive_addr(local_addr, server_reflexive_addr)
+                .with_mask_related_addr(mask_related_addr)
+        });
+        let candidates = gather_candidates(&session_config);
+        candidates
+            .iter()
+            .map(|candidate| candidate.marshal(1))
+            .collect()
+    };
+
+    assert_eq!(
+        get_candidate_values(false),
+        vec![
+            "2510594538 1 UDP 2130706175 10.0.0.1 3478 typ host",
+            "1780467672 1 UDP 1694498559 203.0.113.1 3478 typ srflx raddr 10.0.0.1 rport 3478",
+        ]
+    );
+    // privacy mode hides the host address, but keeps the reflexive one
+    assert_eq!(
+        get_candidate_values(true),
+        vec![
+            "2510594538 1 UDP 2130706175 10.0.0.1 3478 typ host",
+            "2592086078 1 UDP 1694498559 203.0.113.1 3478 typ srflx raddr 0.0.0.0 rport 0",
+        ]
+    );
+}
+
+#[test]
+fn test_add_candidates_with_server_reflexive_port() -> Result<()> {
+    // a load balancer may map the host address to a public one of another port
+    let host_addr = "10.0.0.5:40000".parse().unwrap();
+    let candidates = [
+        LocalCandidate::host(host_addr),
+        LocalCandidate {
+            addr: "203.0.113.5:3478".parse().unwrap(),
+            related_addr: Some(host_addr),
+        },
+    ];
+    let media = SharedMediaAttributes::new(&[], &candidates, RTCIceGatheringState::Complete)
+        .add_candidates(
+            MediaDescription::new_jsep_media_description("audio".to_owned(), vec![]),
+            true,
+        );
+
+    let values: Vec<Option<&str>> = media
+        .attributes
+        .iter()
+        .filter(|a| a.key == "candidate")
+        .map(|a| a.value.as_deref())
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            Some("3031136574 1 UDP 2130706175 10.0.0.5 40000 typ host"),
+            Some(
+                "224735224 1 UDP 1694498559 203.0.113.5 3478 typ srflx raddr 10.0.0.5 rport 40000"
+            ),
+        ]
+    );
+
+    Ok(())
+}
+
+fn new_ipv6_session_config() -> Result<SessionConfig> {
+    let media_config = new_media_config_with_default_codecs()?;
+    Ok(new_session_config_on(
+        "[2001:db8::1]:3478".parse().unwrap(),
+        |server_config| server_config.with_media_config(media_config),
+    ))
+}
+
+#[test]
+fn test_populate_sdp_with_ipv6_host_candidate() -> Result<()> {
+    let session_config = new_ipv6_session_config()?;
+    let mut transceiver = new_sendonly_transceiver("1", vec![]);
+    transceiver.kind = RTPCodecType::Audio;
+
+    let d = populate_sdp(
+        SessionDescription::default(),
+        &[],
+        &session_config,
+        &RTCIceParameters::default(),
+        ConnectionRole::Passive,
+        &[
+            MediaSection {
+                mid: "0".to_string(),
+                data: true,
+                ..Default::default()
+            },
+            MediaSection {
+                mid: "1".to_string(),
+                ..Default::default()
+            },
+        ],
+        &HashMap::from([("1".to_string(), transceiver)]),
+        false,
+        None,
+        false,
+        &[],
+    )?;
+    let sdp = d.marshal();
+    assert!(!sdp.contains("IP4"));
+    for media in &d.media_descriptions {
+        let connection_information = media.connection_information.as_ref().unwrap();
+        assert_eq!(connection_information.to_string(), "IN IP6 ::");
+    }
+
+    // IPv6 host candidate has its own foundation and a higher local preference than IPv4 one
+    let media = &d.media_descriptions[0];
+    assert_eq!(
+        media.attribute("candidate"),
+        Some(Some(
+            "2951389196 1 UDP 2130706431 2001:db8::1 3478 typ host"
+        ))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_add_transceiver_sdp_rtcp_attribute_without_rtcp_mux() -> Result<()> {
+    let session_config = new_ipv6_session_config()?;
+    let mut transceiver = new_sendonly_transceiver("0", vec![]);
+    transceiver.kind = RTPCodecType::Audio;
+    let add_audio_media = |offered_rtcp_mux: bool| -> Result<MediaDescription> {
+        add_transceiver_sdp_with(
+            &session_config,
+            &[LocalCandidate::host(session_config.local_addr)],
+            &MediaSection {
+                mid: "0".to_string(),
+                offered_rtcp_mux: Some(offered_rtcp_mux),
+                ..Default::default()
+            },
+            &transceiver,
+            Some(RTCRtpTransceiverDirection::Recvonly),
+        )
+    };
+    let components = |media: &MediaDescription| -> Vec<String> {
+        media
+            .attributes
+            .iter()
+            .filter(|a| a.key == "candidate")
+            .filter_map(|a| a.value.as_ref()?.split(' ').nth(1).map(str::to_owned))
+            .collect()
+    };
+
+    let media = add_audio_media(false)?;
+    assert!(media.attribute("rtcp-mux").is_none());
+    assert_eq!(
+        media.attribute("rtcp"),
+        Some(Some("3478 IN IP6 2001:db8::1"))
+    );
+    assert_eq!(components(&media), vec!["1", "2"]);
+
+    // the RTCP component is not advertised with rtcp-mux
+    let media = add_audio_media(true)?;
+    assert!(media.attribute("rtcp-mux").is_some());
+    assert!(media.attribute("rtcp").is_none());
+    assert_eq!(components(&media), vec!["1"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_add_transceiver_sdp_with_multiple_host_addrs() -> Result<()> {
+    let media_config = new_media_config_with_default_codecs()?;
+    let local_addr: SocketAddr = "10.0.0.1:3478".parse().unwrap();
+    let session_config = new_session_config_on(local_addr, |server_config| {
+        server_config
+            .with_media_config(media_config)
+            .with_host_addrs(
+                local_addr,
+                vec![
+                    "192.168.0.1:3478".parse().unwrap(),
+                    local_addr,
+                    "[2001:db8::1]:3478".parse().unwrap(),
+                ],
+            )
+    });
+    let candidates = gather_candidates(&session_config);
+    assert_eq!(candidates.len(), 3);
+
+    let mut transceiver = new_sendonly_transceiver("0", vec![]);
+    transceiver.kind = RTPCodecType::Audio;
+    let media = add_transceiver_sdp_with(
+        &session_config,
+        &candidates,
+        &MediaSection {
+            mid: "0".to_string(),
+            offered_rtcp_mux: Some(false),
+            ..Default::default()
+        },
+        &transceiver,
+        Some(RTCRtpTransceiverDirection::Recvonly),
+    )?;
+
+    // RTP and RTCP candidates per address, followed by a single end-of-candidates, the
+    // foundation differs per base address, and is shared by the components of one
+    let keys: Vec<(&str, Option<&str>)> = media
+        .attributes
+        .iter()
+        .filter(|a| a.key == "candidate" || a.key == "end-of-candidates")
+        .map(|a| (a.key.as_str(), a.value.as_deref()))
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            (
+                "candidate",
+                Some("2510594538 1 UDP 2130706175 10.0.0.1 3478 typ host")
+            ),
+            (
+                "candidate",
+                Some("2510594538 2 UDP 2130706174 10.0.0.1 3478 typ host")
+            ),
+            (
+                "candidate",
+                Some("2215045032 1 UDP 2130706175 192.168.0.1 3478 typ host")
+            ),
+            (
+                "candidate",
+                Some("2215045032 2 UDP 2130706174 192.168.0.1 3478 typ host")
+            ),
+            (
+                "candidate",
+                Some("2951389196 1 UDP 2130706431 2001:db8::1 3478 typ host")
+            ),
+            (
+                "candidate",
+                Some("2951389196 2 UDP 2130706430 2001:db8::1 3478 typ host")
+            ),
+            ("end-of-candidates", None),
+        ]
+    );
+
+    Ok(())
+}
+
+fn new_sendonly_transceiver(mid: &str, rids: Vec<String>) -> RTCRtpTransceiver {
+    RTCRtpTransceiver {
+        mid: mid.to_string(),
+        sender: Some(RTCRtpSender {
+            cname: "cname".to_string(),
+            msid: MediaStreamId {
+                stream_id: "stream".to_string(),
+                track_id: "track".to_string(),
+            },
+            ssrcs: vec![],
+            ssrc_attributes: HashMap::new(),
+            ssrc_groups: vec![],
+            rids,
+        }),
+        direction: RTCRtpTransceiverDirection::Sendonly,
+        current_direction: RTCRtpTransceiverDirection::Unspecified,
+        direction_override: None,
+        rtp_params: Default::default(),
+        kind: RTPCodecType::Video,
+        negotiated_header_extensions: vec![],
+        selected_rid: None,
+    }
+}
+
+/// add_transceiver_sdp_with returns the media description of the transceiver added to an empty
+/// session description with the candidates, offered if offered_direction is None, or answered with
+/// the passive DTLS role otherwise
+fn add_transceiver_sdp_with(
+    session_config: &SessionConfig,
+    candidates: &[LocalCandidate],
+    media_section: &MediaSection,
+    transceiver: &RTCRtpTransceiver,
+    offered_direction: Option<RTCRtpTransceiverDirection>,
+) -> Result<MediaDescription> {
+    let shared_attributes = if candidates.is_empty() {
+        SharedMediaAttributes::default()
+    } else {
+        SharedMediaAttributes::new(&[], candidates, RTCIceGatheringState::Complete)
+    };
+    let (d, _) = add_transceiver_sdp(
+        SessionDescription::default(),
+        &shared_attributes,
+        &RTCIceParameters::default(),
+        session_config,
+        media_section,
+        transceiver,
+        AddTransceiverSdpParams {
+            has_candidates: !candidates.is_empty(),
+            mid_value: transceiver.mid.clone(),
+            dtls_role: if offered_direction.is_some() {
+                ConnectionRole::Passive
+            } else {
+                ConnectionRole::Actpass
+            },
+            offered_direction,
+        },
+    )?;
+    Ok(d.media_descriptions[0].clone())
+}
+
+fn add_transceiver_sdp_for_test(
+    media_section: &MediaSection,
+    transceiver: &RTCRtpTransceiver,
+) -> Result<MediaDescription> {
+    add_transceiver_sdp_with(&new_session_config(), &[], media_section, transceiver, None)
+}
+
+#[test]
+fn test_add_transceiver_sdp_with_send_rids() -> Result<()> {
+    let transceiver =
+        new_sendonly_transceiver("0", vec!["h".to_string(), "m".to_string(), "l".to_string()]);
+    let media = add_transceiver_sdp_for_test(
+        &MediaSection {
+            mid: "0".to_string(),
+            ..Default::default()
+        },
+        &transceiver,
+    )?;
+
+    let rids: Vec<&str> = media
+        .attributes
+        .iter()
+        .filter(|a| a.key == SDP_ATTRIBUTE_RID)
+        .filter_map(|a| a.value.as_deref())
+        .collect();
+    assert_eq!(rids, vec!["h send", "m send", "l send"]);
+    assert_eq!(media.attribute("simulcast").flatten(), Some("send h;m;l"));
+
+    let media = add_transceiver_sdp_for_test(
+        &MediaSection {
+            mid: "0".to_string(),
+            ..Default::default()
+        },
+        &new_sendonly_transceiver("0", vec![]),
+    )?;
+    assert!(media.attribute("simulcast").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_gather_candidates_of_host_addr() -> Result<()> {
+    let session_config = new_session_config();
+
+    let candidates = gather_candidates(&session_config);
+    assert_eq!(
+        candidates,
+        vec![LocalCandidate::host(session_config.local_addr)]
+    );
+
+    let d = populate_sdp(
+        SessionDescription::default(),
+        &[],
+        &session_config,
+        &RTCIceParameters::default(),
+        ConnectionRole::Passive,
+        &[MediaSection {
+            mid: "0".to_string(),
+            data: true,
+            ..Default::default()
+        }],
+        &HashMap::new(),
+        true,
+        None,
+        false,
+        &[],
+    )?;
+    let media = &d.media_descriptions[0];
+    let candidate_values: Vec<&str> = media
+        .attributes
+        .iter()
+        .filter(|a| a.key == "candidate")
+        .filter_map(|a| a.value.as_deref())
+        .collect();
+    assert_eq!(
+        candidate_values,
+        vec!["928981705 1 UDP 2130706175 127.0.0.1 3478 typ host"]
+    );
+    assert!(media.attribute("end-of-candidates").is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_populate_sdp_keeps_offered_bundle_group() -> Result<()> {
+    let offer =
+        parse_sdp(&SESSION_RECVONLY_SDP.replace("a=recvonly\r\n", "a=group:BUNDLE 1 0\r\n"));
+    let offered_bundle_group = get_bundle_group(&offer)?;
+    assert_eq!(
+        offered_bundle_group,
+        Some(vec!["1".to_string(), "0".to_string()])
+    );
+
+    let mut transceivers = HashMap::new();
+    let mut media_sections = vec![];
+    for mid in ["0", "1", "2"] {
+        transceivers.insert(mid.to_string(), new_sendonly_transceiver(mid, vec![]));
+        media_sections.push(MediaSection {
+            mid: mid.to_string(),
+            ..Default::default()
+        });
+    }
+
+    let answer = populate_sdp(
+        SessionDescription::default(),
+        &[],
+        &new_session_config(),
+        &RTCIceParameters::default(),
+        ConnectionRole::Passive,
+        &media_sections,
+        &transceivers,
+        true,
+        offered_bundle_group.as_deref(),
+        false,
+        &[],
+    )?;
+    assert_eq!(answer.attribute(ATTR_KEY_GROUP), Some("BUNDLE 1 0"));
+
+    Ok(())
+}
+
+#[test]
+fn test_get_bundle_group_rejects_unknown_mid() {
+    let offer =
+        parse_sdp(&SESSION_RECVONLY_SDP.replace("a=recvonly\r\n", "a=group:BUNDLE 0 1 2\r\n"));
+    assert!(get_bundle_group(&offer).is_err());
+}
+
+fn new_session_config_with_default_codecs() -> Result<SessionConfig> {
+    new_session_config_with_echo_vendor_fmtp_parameters(false)
+}
+
+fn new_session_config_with_echo_vendor_fmtp_parameters(
+    echo_vendor_fmtp_parameters: bool,
+) -> Result<SessionConfig> {
+    let media_config = new_media_config_with_default_codecs()?;
+    Ok(new_session_config_with(|server_config| {
+        server_config
+            .with_media_config(media_config)
+            .with_echo_vendor_fmtp_parameters(echo_vendor_fmtp_parameters)
+    }))
+}
+
+fn new_opus_answer_media(remote_sdp_fmtp_line: &str) -> Result<MediaDescription> {
+    new_opus_answer_media_with_session_config(
+        &new_session_config_with_default_codecs()?,
+        remote_sdp_fmtp_line,
+    )
+}
+
+fn new_opus_answer_media_with_session_config(
+    session_config: &SessionConfig,
+    remote_sdp_fmtp_line: &str,
+) -> Result<MediaDescription> {
+    let mut transceiver = new_sendonly_transceiver("0", vec![]);
+    transceiver.kind = RTPCodecType::Audio;
+    transceiver.direction = RTCRtpTransceiverDirection::Recvonly;
+    transceiver.rtp_params.codecs = vec![RTCRtpCodecParameters {
+        capability: RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_OPUS.to_owned(),
+            clock_rate: 48000,
+            channels: 2,
+            sdp_fmtp_line: remote_sdp_fmtp_line.to_owned(),
+            rtcp_feedbacks: vec![],
+        },
+        payload_type: 109,
+        ..Default::default()
+    }];
+
+    add_transceiver_sdp_with(
+        session_config,
+        &[],
+        &MediaSection {
+            mid: "0".to_string(),
+            ..Default::default()
+        },
+        &transceiver,
+        Some(RTCRtpTransceiverDirection::Sendonly),
+    )
+}
+
+fn get_opus_min_ptime(media: &MediaDescription) -> Option<u32> {
+    let codecs = codecs_from_media_description(media).ok()?;
+    let opus = codecs
+        .iter()
+        .find(|c| c.capability.mime_type.eq_ignore_ascii_case(MIME_TYPE_OPUS))?;
+    fmtp::parse(&opus.capability.mime_type, &opus.capability.sdp_fmtp_line)
+        .parameter("minptime")
+        .and_then(|v| v.parse().ok())
+}
+
+#[test]
+fn test_add_transceiver_sdp_opus_min_ptime() -> Result<()> {
+    let media = new_opus_answer_media("minptime=10;useinbandfec=1")?;
+    assert!(get_opus_min_ptime(&media).is_some_and(|min_ptime| min_ptime >= 10));
+
+    // the local minptime is raised to the offered one
+    let media = new_opus_answer_media("minptime=20;useinbandfec=1")?;
+    assert_eq!(get_opus_min_ptime(&media), Some(20));
+
+    // and stays the local one without minptime offered
+    let media = new_opus_answer_media("useinbandfec=1")?;
+    assert_eq!(get_opus_min_ptime(&media), Some(10));
+
+    Ok(())
+}
+
+#[test]
+fn test_vendor_fmtp_parameters() -> Result<()> {
+    let remote_sdp_fmtp_line =
+        "minptime=10;useinbandfec=1;x-google-min-bitrate=300;x-google-max-bitrate=2500;X-Custom=AbC";
+    assert_eq!(
+        fmtp::vendor::bitrate_hints(MIME_TYPE_OPUS, remote_sdp_fmtp_line),
+        fmtp::vendor::BitrateHints {
+            min_bitrate: Some(300_000),
+            max_bitrate: Some(2_500_000),
+        }
+    );
+    assert!(fmtp::vendor::bitrate_hints(MIME_TYPE_OPUS, "useinbandfec=1").is_empty());
+
+    // vendor parameters round-trip through the answer verbatim if echoed
+    let session_config = new_session_config_with_echo_vendor_fmtp_parameters(true)?;
+    let media = new_opus_answer_media_with_session_config(&session_config, remote_sdp_fmtp_line)?;
+    let codecs = codecs_from_media_description(&media)?;
+    assert_eq!(
+        codecs[0].capability.sdp_fmtp_line,
+        "minptime=10;useinbandfec=1;x-google-min-bitrate=300;x-google-max-bitrate=2500;X-Custom=AbC"
+    );
+    let fmtp = fmtp::parse(MIME_TYPE_OPUS, &codecs[0].capability.sdp_fmtp_line);
+    assert_eq!(fmtp.parameter("x-custom"), Some(&"AbC".to_string()));
+
+    let media = new_opus_answer_media(remote_sdp_fmtp_line)?;
+    let codecs = codecs_from_media_description(&media)?;
+    assert_eq!(
+        codecs[0].capability.sdp_fmtp_line,
+        "minptime=10;useinbandfec=1"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_opus_fmtp_ignores_min_ptime() {
+    let a = fmtp::parse(MIME_TYPE_OPUS, "minptime=10;useinbandfec=1");
+    let b = fmtp::parse(MIME_TYPE_OPUS, "minptime=20;useinbandfec=1");
+    assert!(a.match_fmtp(&*b));
+
+    let c = fmtp::parse(MIME_TYPE_OPUS, "minptime=10;useinbandfec=0");
+    assert!(!a.match_fmtp(&*c));
+}
+
+const REUSED_PAYLOAD_TYPE_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:0\r\n\
+a=sendonly\r\n\
+a=rtpmap:96 VP8/90000\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96 97\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:1\r\n\
+a=sendonly\r\n\
+a=rtpmap:96 VP9/90000\r\n\
+a=fmtp:96 profile-id=0\r\n\
+a=rtpmap:97 H264/90000\r\n\
+a=fmtp:97 level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f\r\n";
+
+#[test]
+fn test_add_transceiver_sdp_keeps_offered_payload_types_per_section() -> Result<()> {
+    let session_config = new_session_config_with_default_codecs()?;
+    let offer = parse_sdp(REUSED_PAYLOAD_TYPE_SDP);
+
+    let mut answer_codecs = vec![];
+    for (i, offered_media) in offer.media_descriptions.iter().enumerate() {
+        let mid = i.to_string();
+        let mut transceiver = new_sendonly_transceiver(&mid, vec![]);
+        transceiver.direction = RTCRtpTransceiverDirection::Recvonly;
+        let media = add_transceiver_sdp_with(
+            &session_config,
+            &[],
+            &MediaSection {
+                mid,
+                offered_direction: Some(RTCRtpTransceiverDirection::Sendonly),
+                offered_codecs: codecs_from_media_description(offered_media)?,
+                ..Default::default()
+            },
+            &transceiver,
+            Some(RTCRtpTransceiverDirection::Sendonly),
+        )?;
+        let codecs: Vec<(u8, String)> = codecs_from_media_description(&media)?
+            .into_iter()
+            .map(|c| (c.payload_type, c.capability.mime_type.to_lowercase()))
+            .collect();
+        answer_codecs.push(codecs);
+    }
+
+    assert_eq!(answer_codecs[0], vec![(96, "video/vp8".to_string())]);
+    assert_eq!(
+        answer_codecs[1],
+        vec![
+            (96, "video/vp9".to_string()),
+            (97, "video/h264".to_string())
+        ]
+    );
+
+    Ok(())
+}
+
+const H264_PROFILES_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 100 102 104\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:0\r\n\
+a=sendonly\r\n\
+a=rtpmap:100 H264/90000\r\n\
+a=fmtp:100 level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=4d001f\r\n\
+a=rtpmap:102 H264/90000\r\n\
+a=fmtp:102 level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e034\r\n\
+a=rtpmap:104 H264/90000\r\n\
+a=fmtp:104 level-asymmetry-allowed=1;packetization-mode=0;profile-level-id=640032\r\n";
+
+#[test]
+fn test_add_transceiver_sdp_matches_h264_profiles() -> Result<()> {
+    let session_config = new_session_config_with_default_codecs()?;
+    let offer = parse_sdp(H264_PROFILES_SDP);
+    let offered_media = &offer.media_descriptions[0];
+
+    let mut transceiver = new_sendonly_transceiver("0", vec![]);
+    transceiver.direction = RTCRtpTransceiverDirection::Recvonly;
+    let media = add_transceiver_sdp_with(
+        &session_config,
+        &[],
+        &MediaSection {
+            mid: "0".to_string(),
+            offered_direction: Some(RTCRtpTransceiverDirection::Sendonly),
+            offered_codecs: codecs_from_media_description(offered_media)?,
+            ..Default::default()
+        },
+        &transceiver,
+        Some(RTCRtpTransceiverDirection::Sendonly),
+    )?;
+
+    // only the constrained baseline profile with packetization-mode 1 is supported locally,
+    // and it is answered with the offered payload type regardless of the level
+    let codecs: Vec<(u8, String, String)> = codecs_from_media_description(&media)?
+        .into_iter()
+        .map(|c| {
+            (
+                c.payload_type,
+                c.capability.mime_type.to_lowercase(),
+                c.capability.sdp_fmtp_line,
+            )
+        })
+        .collect();
+    assert_eq!(codecs.len(), 1, "{:?}", codecs);
+    assert_eq!(codecs[0].0, 102);
+    assert_eq!(codecs[0].1, "video/h264");
+    assert!(codecs[0].2.contains("packetization-mode=1"));
+
+    Ok(())
+}
+
+const RTX_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 100 101 102 103\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:0\r\n\
+a=sendonly\r\n\
+a=rtpmap:100 VP8/90000\r\n\
+a=rtpmap:101 rtx/90000\r\n\
+a=fmtp:101 apt=100\r\n\
+a=rtpmap:102 AV1/90000\r\n\
+a=rtpmap:103 rtx/90000\r\n\
+a=fmtp:103 apt=102\r\n";
+
+#[test]
+fn test_add_transceiver_sdp_pairs_rtx_with_primary_codec() -> Result<()> {
+    let mut media_config = new_media_config_with_default_codecs()?;
+    media_config.register_codec(
+        RTCRtpCodecParameters {
+            capability: RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_RTX.to_owned(),
+                clock_rate: 90000,
+                channels: 0,
+                sdp_fmtp_line: "apt=96".to_owned(),
+                rtcp_feedbacks: vec![],
+            },
+            payload_type: 97,
+            ..Default::default()
+        },
+        RTPCodecType::Video,
+    )?;
+    let session_config =
+        new_session_config_with(|server_config| server_config.with_media_config(media_config));
+    let offer = parse_sdp(RTX_SDP);
+
+    let mut transceiver = new_sendonly_transceiver("0", vec![]);
+    transceiver.direction = RTCRtpTransceiverDirection::Recvonly;
+    let media = add_transceiver_sdp_with(
+        &session_config,
+        &[],
+        &MediaSection {
+            mid: "0".to_string(),
+            offered_direction: Some(RTCRtpTransceiverDirection::Sendonly),
+            offered_codecs: codecs_from_media_description(&offer.media_descriptions[0])?,
+            ..Default::default()
+        },
+        &transceiver,
+        Some(RTCRtpTransceiverDirection::Sendonly),
+    )?;
+
+    // RTX of AV1 is not answered, since only VP8 has RTX locally
+    let codecs: Vec<(u8, String, String)> = codecs_from_media_description(&media)?
+        .into_iter()
+        .map(|c| {
+            (
+                c.payload_type,
+                c.capability.mime_type.to_lowercase(),
+                c.capability.sdp_fmtp_line,
+            )
+        })
+        .collect();
+    assert_eq!(
+        codecs,
+        vec![
+            (100, "video/vp8".to_string(), "".to_string()),
+            (102, "video/av1".to_string(), "profile-id=0".to_string()),
+            (101, "video/rtx".to_string(), "apt=100".to_string()),
+        ]
+    );
+
+    Ok(())
+}
+
+const RED_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 109 63\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:0\r\n\
+a=sendonly\r\n\
+a=rtpmap:109 opus/48000/2\r\n\
+a=fmtp:109 minptime=10;useinbandfec=1\r\n\
+a=rtpmap:63 red/48000/2\r\n\
+a=fmtp:63 109/109\r\n";
+
+fn new_red_media(offered_codecs: Vec<RTCRtpCodecParameters>) -> Result<MediaDescription> {
+    let mut media_config = new_media_config_with_default_codecs()?;
+    media_config.register_codec(
+        RTCRtpCodecParameters {
+            capability: RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_RED.to_owned(),
+                clock_rate: 48000,
+                channels: 2,
+                sdp_fmtp_line: "111/111".to_owned(),
+                rtcp_feedbacks: vec![],
+            },
+            payload_type: 63,
+            ..Default::default()
+        },
+        RTPCodecType::Audio,
+    )?;
+    let session_config =
+        new_session_config_with(|server_config| server_config.with_media_config(media_config));
+
+    let offered_direction = if offered_codecs.is_empty() {
+        None
+    } else {
+        Some(RTCRtpTransceiverDirection::Sendonly)
+    };
+    let mut transceiver = new_sendonly_transceiver("0", vec![]);
+    transceiver.kind = RTPCodecType::Audio;
+    if offered_direction.is_some() {
+        transceiver.direction = RTCRtpTransceiverDirection::Recvonly;
+    }
+    add_transceiver_sdp_with(
+        &session_config,
+        &[],
+        &MediaSection {
+            mid: "0".to_string(),
+            offered_direction,
+            offered_codecs,
+            ..Default::default()
+        },
+        &transceiver,
+        offered_direction,
+    )
+}
+
+#[test]
+fn test_add_transceiver_sdp_negotiates_red_over_opus() -> Result<()> {
+    // offering, RED references the local opus payload type
+    let media = new_red_media(vec![])?;
+    let values: Vec<&str> = media
+        .attributes
+        .iter()
+        .filter_map(|a| a.value.as_deref())
+        .collect();
+    assert!(values.contains(&"63 red/48000/2"));
+    assert!(values.contains(&"63 111/111"));
+
+    // answering, RED references the offered opus payload type
+    let offer = parse_sdp(RED_SDP);
+    let media = new_red_media(codecs_from_media_description(&offer.media_descriptions[0])?)?;
+    assert_eq!(
+        media.media_name.formats,
+        vec!["109".to_string(), "63".to_string()]
+    );
+    let values: Vec<&str> = media
+        .attributes
+        .iter()
+        .filter_map(|a| a.value.as_deref())
+        .collect();
+    assert!(values.contains(&"63 red/48000/2"));
+    assert!(values.contains(&"63 109/109"));
+
+    // without opus in the offer, RED is not negotiated either
+    let offered_codecs: Vec<RTCRtpCodecParameters> =
+        codecs_from_media_description(&offer.media_descriptions[0])?
+            .into_iter()
+            .filter(|c| c.payload_type != 109)
+            .collect();
+    let media = new_red_media(offered_codecs)?;
+    assert!(!media
+        .attributes
+        .iter()
+        .any(|a| a.value.as_deref() == Some("63 red/48000/2")));
+
+    Ok(())
+}
+
+const UNMATCHED_CODEC_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 45\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:0\r\n\
+a=sendonly\r\n\
+a=rtpmap:45 AV1X/90000\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:1\r\n\
+a=sendonly\r\n\
+a=rtpmap:96 VP8/90000\r\n";
+
+#[test]
+fn test_populate_sdp_rejects_only_media_section_without_codecs() -> Result<()> {
+    let session_config = new_session_config_with_default_codecs()?;
+    let offer = parse_sdp(UNMATCHED_CODEC_SDP);
+
+    let mut transceivers = HashMap::new();
+    let mut media_sections = vec![];
+    for (i, offered_media) in offer.media_descriptions.iter().enumerate() {
+        let mid = i.to_string();
+        let mut transceiver = new_sendonly_transceiver(&mid, vec![]);
+        transceiver.direction = RTCRtpTransceiverDirection::Recvonly;
+        transceivers.insert(mid.clone(), transceiver);
+        media_sections.push(MediaSection {
+            mid,
+            offered_direction: Some(RTCRtpTransceiverDirection::Sendonly),
+            offered_codecs: codecs_from_media_description(offered_media)?,
+            ..Default::default()
+        });
+    }
+
+    let answer = populate_sdp(
+        SessionDescription::new_jsep_session_description(false),
+        &[],
+        &session_config,
+        &RTCIceParameters::default(),
+        ConnectionRole::Passive,
+        &media_sections,
+        &transceivers,
+        true,
+        None,
+        false,
+        &[],
+    )?;
+
+    // the section without any codec in common is rejected with port zero and kept out of BUNDLE
+    let rejected = &answer.media_descriptions[0];
+    assert_eq!(rejected.media_name.port.value, 0);
+    assert_eq!(rejected.media_name.formats, vec!["45".to_string()]);
+    assert!(rejected.attribute("inactive").is_some());
+    assert_eq!(answer.attribute(ATTR_KEY_GROUP), Some("BUNDLE 1"));
+
+    // the other section still produces a valid media line
+    let accepted = &answer.media_descriptions[1];
+    assert_eq!(accepted.media_name.port.value, 9);
+    assert_eq!(accepted.media_name.formats, vec!["96".to_string()]);
+    assert_eq!(
+        codecs_from_media_description(accepted)?[0]
+            .capability
+            .mime_type
+            .to_lowercase(),
+        "video/vp8"
+    );
+
+    // the answer is still parsable as a whole
+    let mut reader = Cursor::new(answer.marshal().into_bytes());
+    assert!(SessionDescription::unmarshal(&mut reader).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_add_transceiver_sdp_msid_without_stream() -> Result<()> {
+    let mut transceiver = new_sendonly_transceiver("0", vec![]);
+    if let Some(sender) = transceiver.sender.as_mut() {
+        sender.msid.stream_id = String::new();
+        sender.ssrcs = vec![1];
+    }
+    let media = add_transceiver_sdp_for_test(
+        &MediaSection {
+            mid: "0".to_string(),
+            ..Default::default()
+        },
+        &transceiver,
+    )?;
+
+    assert!(media.attribute("msid:- track").is_some());
+    let ssrc_values: Vec<&str> = media
+        .attributes
+        .iter()
+        .filter(|a| a.key == "ssrc")
+        .filter_map(|a| a.value.as_deref())
+        .collect();
+    assert!(ssrc_values.contains(&"1 msid:- track"));
+
+    Ok(())
+}
+
+#[test]
+fn test_get_msid_without_stream() {
+    let sdp = parse_sdp(
+        "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+a=msid:- track\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+a=ssrc:1 msid:stream track\r\n",
+    );
+
+    let msid = get_msid(&sdp.media_descriptions[0]).unwrap();
+    assert!(msid.stream_id.is_empty());
+    assert_eq!(msid.track_id, "track");
+    assert_eq!(msid.msid_id(), "-");
+
+    let msid = get_msid(&sdp.media_descriptions[1]).unwrap();
+    assert_eq!(msid.stream_id, "stream");
+    assert_eq!(msid.msid_id(), "stream");
+}
+
+/// offer_sections_with_candidates returns mids and ICE credentials of media sections of an offer
+/// with video, video, audio and data media sections, which have ICE credentials and candidates
+fn offer_sections_with_candidates(
+    bundle_policy: RTCBundlePolicy,
+) -> Result<Vec<(String, RTCIceParameters)>> {
+    let session_config =
+        new_session_config_with(|server_config| server_config.with_bundle_policy(bundle_policy));
+    let ice_params = RTCIceParameters {
+        username_fragment: "ufrag".to_string(),
+        password: "password".to_string(),
+    };
+
+    let mut transceivers = HashMap::new();
+    let mut media_sections = vec![];
+    for (mid, kind) in [
+        ("0", RTPCodecType::Video),
+        ("1", RTPCodecType::Video),
+        ("2", RTPCodecType::Audio),
+    ] {
+        let mut transceiver = new_sendonly_transceiver(mid, vec![]);
+        transceiver.kind = kind;
+        transceivers.insert(mid.to_string(), transceiver);
+        media_sections.push(MediaSection {
+            mid: mid.to_string(),
+            ..Default::default()
+        });
+    }
+    media_sections.push(MediaSection {
+        mid: "3".to_string(),
+        data: true,
+        ..Default::default()
+    });
+
+    let offer = populate_sdp(
+        SessionDescription::default(),
+        &[],
+        &session_config,
+        &ice_params,
+        ConnectionRole::Actpass,
+        &media_sections,
+        &transceivers,
+        true,
+        None,
+        false,
+        &[],
+    )?;
+    // all media sections are still offered in one BUNDLE group
+    assert_eq!(offer.attribute(ATTR_KEY_GROUP), Some("BUNDLE 0 1 2 3"));
+
+    Ok(offer
+        .media_descriptions
+        .iter()
+        .filter(|media| media.attribute("candidate").is_some())
+        .filter_map(|media| {
+            Some((
+                media.attribute(ATTR_KEY_MID).flatten()?.to_string(),
+                RTCIceParameters {
+                    username_fragment: media.attribute("ice-ufrag").flatten()?.to_string(),
+                    password: media.attribute("ice-pwd").flatten()?.to_string(),
+                },
+            ))
+        })
+        .collect())
+}
+
+fn offer_mids_with_candidates(bundle_policy: RTCBundlePolicy) -> Result<Vec<String>> {
+    Ok(offer_sections_with_candidates(bundle_policy)?
+        .into_iter()
+        .map(|(mid, _)| mid)
+        .collect())
+}
+
+#[test]
+fn test_populate_sdp_with_bundle_policy() -> Result<()> {
+    assert_eq!(
+        offer_mids_with_candidates(RTCBundlePolicy::MaxBundle)?,
+        vec!["0"]
+    );
+    assert_eq!(
+        offer_mids_with_candidates(RTCBundlePolicy::Balanced)?,
+        vec!["0", "2", "3"]
+    );
+    assert_eq!(
+        offer_mids_with_candidates(RTCBundlePolicy::MaxCompat)?,
+        vec!["0", "1", "2", "3"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_populate_sdp_with_media_ice_credentials() -> Result<()> {
+    let session_ice_params = RTCIceParameters {
+        username_fragment: "ufrag".to_string(),
+        password: "password".to_string(),
+    };
+
+    // bundled media sections share the session-level ICE credentials
+    for (_, ice_params) in offer_sections_with_candidates(RTCBundlePolicy::MaxBundle)? {
+        assert_eq!(ice_params, session_ice_params);
+    }
+
+    // under balanced and max-compat, the first media section keeps the session-level ICE
+    // credentials, and each of the others with its own transport carries its own ones
+    for bundle_policy in [RTCBundlePolicy::Balanced, RTCBundlePolicy::MaxCompat] {
+        assert_media_ice_credentials(
+            &offer_sections_with_candidates(bundle_policy)?,
+            &session_ice_params,
+        );
+    }
+
+    Ok(())
+}
+
+/// assert_media_ice_credentials asserts that the first section has the session-level ICE
+/// credentials, and the others have distinct ones derived per mid
+fn assert_media_ice_credentials(
+    sections: &[(String, RTCIceParameters)],
+    session_ice_params: &RTCIceParameters,
+) {
+    assert_eq!(&sections[0].1, session_ice_params);
+    for (i, (mid, ice_params)) in sections.iter().enumerate() {
+        for (other_mid, other_ice_params) in &sections[i + 1..] {
+            assert_ne!(
+                ice_params.username_fragment, other_ice_params.username_fragment,
+                "ice-ufrag of {mid} and {other_mid}"
+            );
+            assert_ne!(
+                ice_params.password, other_ice_params.password,
+                "ice-pwd of {mid} and {other_mid}"
+            );
+        }
+        if i > 0 {
+            assert_eq!(ice_params, &session_ice_params.for_media(mid));
+            assert!(ice_params.username_fragment.len() >= 4);
+            assert!(ice_params.password.len() >= 22);
+        }
+    }
+}
+
+#[test]
+fn test_get_ssrc_attributes() -> Result<()> {
+    let sdp = parse_sdp(
+        "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+a=ssrc:12345 cname:foo\r\n\
+a=ssrc:12345 msid:stream track\r\n\
+a=ssrc:67890 cname:bar\r\n\
+a=ssrc:67890 label:track\r\n",
+    );
+
+    let ssrc_attributes = get_ssrc_attributes(&sdp.media_descriptions[0])?;
+    assert_eq!(ssrc_attributes.len(), 2);
+    let attributes = &ssrc_attributes[&12345];
+    assert_eq!(attributes.cname.as_deref(), Some("foo"));
+    assert_eq!(
+        attributes.msid.as_ref().map(|msid| msid.track_id.as_str()),
+        Some("track")
+    );
+    let attributes = &ssrc_attributes[&67890];
+    assert_eq!(attributes.cname.as_deref(), Some("bar"));
+    assert!(attributes.msid.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_add_transceiver_sdp_echoes_ssrc_cname() -> Result<()> {
+    let mut transceiver = new_sendonly_transceiver("0", vec![]);
+    if let Some(sender) = transceiver.sender.as_mut() {
+        sender.ssrcs = vec![1, 2];
+        sender.ssrc_attributes.insert(
+            2,
+            SsrcAttributes {
+                cname: Some("other".to_string()),
+                msid: None,
+            },
+        );
+    }
+
+    let mut cnames = vec![];
+    for echo_ssrc_attributes in [false, true] {
+        let session_config = new_session_config_with(|server_config| {
+            server_config.with_echo_ssrc_attributes(echo_ssrc_attributes)
+        });
+        let media = add_transceiver_sdp_with(
+            &session_config,
+            &[],
+            &MediaSection {
+                mid: "0".to_string(),
+                ..Default::default()
+            },
+            &transceiver,
+            None,
+        )?;
+        let ssrc_attributes = get_ssrc_attributes(&media)?;
+        cnames.push((
+            ssrc_attributes[&1].cname.clone(),
+            ssrc_attributes[&2].cname.clone(),
+        ));
+    }
+
+    assert_eq!(
+        cnames,
+        vec![
+            (Some("cname".to_string()), Some("cname".to_string())),
+            (Some("cname".to_string()), Some("other".to_string())),
+        ]
+    );
+
+    Ok(())
+}
+
+fn new_session_config_with_header_extension(
+    allowed_direction: Option<RTCRtpTransceiverDirection>,
+) -> Result<SessionConfig> {
+    let mut media_config = new_media_config_with_default_codecs()?;
+    media_config.register_header_extension(
+        crate::description::rtp_codec::RTCRtpHeaderExtensionCapability {
+            uri: sdp::extmap::ABS_SEND_TIME_URI.to_owned(),
+        },
+        RTPCodecType::Video,
+        allowed_direction,
+    )?;
+    Ok(new_session_config_with(|server_config| {
+        server_config.with_media_config(media_config)
+    }))
+}
+
+/// get_extmaps returns the extmaps of the extension registered by
+/// new_session_config_with_header_extension, besides the default ones
+fn get_extmaps(media: &MediaDescription) -> Vec<String> {
+    media
+        .attributes
+        .iter()
+        .filter(|a| a.key == ATTR_KEY_EXT_MAP)
+        .filter_map(|a| a.value.clone())
+        .filter(|value| value.ends_with(sdp::extmap::ABS_SEND_TIME_URI))
+        .collect()
+}
+
+#[test]
+fn test_add_transceiver_sdp_emits_extmap_direction() -> Result<()> {
+    let session_config =
+        new_session_config_with_header_extension(Some(RTCRtpTransceiverDirection::Sendonly))?;
+
+    let mut extmaps = vec![];
+    for direction in [
+        RTCRtpTransceiverDirection::Sendonly,
+        RTCRtpTransceiverDirection::Recvonly,
+    ] {
+        let mut transceiver = new_sendonly_transceiver("0", vec![]);
+        transceiver.direction = direction;
+        let media = add_transceiver_sdp_with(
+            &session_config,
+            &[],
+            &MediaSection {
+                mid: "0".to_string(),
+                ..Default::default()
+            },
+            &transceiver,
+            None,
+        )?;
+        extmaps.push(get_extmaps(&media));
+    }
+
+    // the send-only extension is only offered on the sending section
+    assert_eq!(extmaps[0].len(), 1);
+    assert!(extmaps[0][0].contains("/sendonly "));
+    assert!(extmaps[0][0].ends_with(sdp::extmap::ABS_SEND_TIME_URI));
+    assert!(extmaps[1].is_empty());
+
+    Ok(())
+}
+
+const EXTMAP_DIRECTION_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:0\r\n\
+a=extmap:2/sendonly http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time\r\n\
+a=sendrecv\r\n\
+a=rtpmap:96 VP8/90000\r\n";
+
+#[test]
+fn test_add_transceiver_sdp_answers_extmap_direction() -> Result<()> {
+    let offer = parse_sdp(EXTMAP_DIRECTION_SDP);
+    let offered_media = &offer.media_descriptions[0];
+    let offered_extensions = rtp_extensions_from_media_description(offered_media)?;
+    assert_eq!(
+        offered_extensions[0].direction,
+        RTCRtpTransceiverDirection::Sendonly
+    );
+
+    let mut extmaps = vec![];
+    for allowed_direction in [None, Some(RTCRtpTransceiverDirection::Sendonly)] {
+        let session_config = new_session_config_with_header_extension(allowed_direction)?;
+        let mut transceiver = new_sendonly_transceiver("0", vec![]);
+        transceiver.direction = RTCRtpTransceiverDirection::Sendrecv;
+        let media = add_transceiver_sdp_with(
+            &session_config,
+            &[],
+            &MediaSection {
+                mid: "0".to_string(),
+                offered_direction: Some(RTCRtpTransceiverDirection::Sendrecv),
+                offered_codecs: codecs_from_media_description(offered_media)?,
+                offered_header_extensions: offered_extensions.clone(),
+                ..Default::default()
+            },
+            &transceiver,
+            Some(RTCRtpTransceiverDirection::Sendrecv),
+        )?;
+        extmaps.push(get_extmaps(&media));
+    }
+
+    // an extension offered as sendonly is answered as recvonly
+    assert_eq!(extmaps[0].len(), 1);
+    assert!(extmaps[0][0].contains("/recvonly "));
+    // and not answered at all when it is only used for sending locally
+    assert!(extmaps[1].is_empty());
+
+    Ok(())
+}
+
+fn get_answer_direction(
+    transceiver: &RTCRtpTransceiver,
+    offered_direction: RTCRtpTransceiverDirection,
+) -> Result<RTCRtpTransceiverDirection> {
+    let media = add_transceiver_sdp_with(
+        &new_session_config_with_default_codecs()?,
+        &[],
+        &MediaSection {
+            mid: transceiver.mid.clone(),
+            offered_direction: Some(offered_direction),
+            ..Default::default()
+        },
+        transceiver,
+        Some(offered_direction),
+    )?;
+    Ok(media
+        .attributes
+        .iter()
+        .map(|a| RTCRtpTransceiverDirection::from(a.key.as_str()))
+        .find(|direction| *direction != RTCRtpTransceiverDirection::Unspecified)
+        .unwrap_or_default())
+}
+
+#[test]
+fn test_add_transceiver_sdp_applies_direction_override() -> Result<()> {
+    let mut transceiver = new_sendonly_transceiver("0", vec![]);
+    transceiver.direction = RTCRtpTransceiverDirection::Sendrecv;
+    assert_eq!(
+        get_answer_direction(&transceiver, RTCRtpTransceiverDirection::Sendrecv)?,
+        RTCRtpTransceiverDirection::Sendrecv
+    );
+
+    transceiver.direction_override = Some(RTCRtpTransceiverDirection::Recvonly);
+    assert_eq!(
+        get_answer_direction(&transceiver, RTCRtpTransceiverDirection::Sendrecv)?,
+        RTCRtpTransceiverDirection::Recvonly
+    );
+    // the override is still intersected with the offered direction
+    assert_eq!(
+        get_answer_direction(&transceiver, RTCRtpTransceiverDirection::Recvonly)?,
+        RTCRtpTransceiverDirection::Inactive
+    );
+
+    Ok(())
+}
+
+/// populate_ten_media_sections answers 4 audio, 5 video and a data media sections without BUNDLE
+/// under max-compat, so that every media section has its own fingerprints, ICE parameters and
+/// candidates of two host addresses
+fn populate_ten_media_sections(session_config: &SessionConfig) -> Result<SessionDescription> {
+    let mut media_sections = vec![];
+    let mut transceivers = HashMap::new();
+    for i in 0..10 {
+        let mid = i.to_string();
+        if i == 9 {
+            media_sections.push(MediaSection {
+                mid,
+                data: true,
+                ..Default::default()
+            });
+            continue;
+        }
+        let mut transceiver = new_sendonly_transceiver(&mid, vec![]);
+        transceiver.kind = if i < 4 {
+            RTPCodecType::Audio
+        } else {
+            RTPCodecType::Video
+        };
+        transceivers.insert(mid.clone(), transceiver);
+        media_sections.push(MediaSection {
+            mid,
+            offered_direction: Some(RTCRtpTransceiverDirection::Recvonly),
+            ..Default::default()
+        });
+    }
+
+    populate_sdp(
+        SessionDescription::default(),
+        &[
+            RTCDtlsFingerprint {
+                algorithm: "sha-256".to_owned(),
+                value: "ab:cd:ef".to_owned(),
+            },
+            RTCDtlsFingerprint {
+                algorithm: "sha-512".to_owned(),
+                value: "01:23:45".to_owned(),
+            },
+        ],
+        session_config,
+        &RTCIceParameters {
+            username_fragment: "ufrag".to_owned(),
+            password: "pwd".to_owned(),
+        },
+        ConnectionRole::Passive,
+        &media_sections,
+        &transceivers,
+        true,
+        None,
+        false,
+        &[],
+    )
+}
+
+fn new_ten_media_sections_session_config() -> SessionConfig {
+    let local_addr: SocketAddr = "10.0.0.1:3478".parse().unwrap();
+    new_session_config_on(local_addr, |server_config| {
+        server_config
+            .with_bundle_policy(RTCBundlePolicy::MaxCompat)
+            .with_host_addrs(
+                local_addr,
+                vec![local_addr, "[2001:db8::1]:3478".parse().unwrap()],
+            )
+    })
+}
+
+#[test]
+fn test_populate_sdp_ten_media_sections() -> Result<()> {
+    let session_config = new_ten_media_sections_session_config();
+    let d = populate_ten_media_sections(&session_config)?;
+    // the description is the same as the one serializing fingerprints and candidates per media
+    // section, with ICE parameters of all but the first media sections derived per mid under
+    // max-compat
+    assert_eq!(
+        d.marshal().replace("\r\n", "\n"),
+        include_str!("testdata/ten_media_sections.sdp")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_populate_sdp_serializes_shared_attributes_once() -> Result<()> {
+    let session_config = new_ten_media_sections_session_config();
+    SERIALIZED_ATTRIBUTES.with(|count| count.set(0));
+    let d = populate_ten_media_sections(&session_config)?;
+
+    // each media section has its own transport under max-compat, and carries all of them
+    let shared_attributes: usize = d
+        .media_descriptions
+        .iter()
+        .map(|media| {
+            media
+                .attributes
+                .iter()
+                .filter(|a| a.key == "fingerprint" || a.key == "candidate")
+                .count()
+        })
+        .sum();
+    assert_eq!(shared_attributes, 10 * (2 + 2));
+    // while the 2 fingerprints and RTP and RTCP components of the 2 candidates are serialized
+    // once per description
+    assert_eq!(SERIALIZED_ATTRIBUTES.with(|count| count.get()), 2 + 2 * 2);
+    Ok(())
+}
@@ -1,11 +1,14 @@
 pub(crate) mod generic;
 pub(crate) mod h264;
+pub(crate) mod opus;
+pub(crate) mod red;
+pub(crate) mod vendor;
 
 use std::any::Any;
 use std::collections::HashMap;
 use std::fmt;
 
-use crate::description::fmtp::{generic::GenericFmtp, h264::H264Fmtp};
+use crate::description::fmtp::{generic::GenericFmtp, h264::H264Fmtp, opus::OpusFmtp};
 
 /// Fmtp interface for implementing custom
 /// Fmtp parsers based on mime_type
@@ -48,6 +51,8 @@ pub(crate) fn parse(mime_type: &str, line: &str) -> Box<dyn Fmtp> {
 
     if mime_type.to_uppercase() == "video/h264".to_uppercase() {
         Box::new(H264Fmtp { parameters })
+    } else if mime_type.to_uppercase() == "audio/opus".to_uppercase() {
+        Box::new(OpusFmtp { parameters })
     } else {
         Box::new(GenericFmtp {
             mime_type: mime_type.to_owned(),
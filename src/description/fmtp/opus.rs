@@ -0,0 +1,92 @@
+use super::*;
+
+/// OPUS_MIN_PTIME is the fmtp parameter for the minimum packetization time in milliseconds
+pub(crate) const OPUS_MIN_PTIME: &str = "minptime";
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct OpusFmtp {
+    pub(crate) parameters: HashMap<String, String>,
+}
+
+impl OpusFmtp {
+    /// min_ptime returns the minimum packetization time, if any
+    pub(crate) fn min_ptime(&self) -> Option<u32> {
+        self.parameters
+            .get(OPUS_MIN_PTIME)
+            .and_then(|v| v.parse::<u32>().ok())
+    }
+}
+
+impl Fmtp for OpusFmtp {
+    fn mime_type(&self) -> &str {
+        "audio/opus"
+    }
+
+    /// Match returns true if o and b are compatible fmtp descriptions
+    /// Based on RFC7587 Section 7.1, minptime is a receiver property which
+    /// each side declares independently, so it doesn't need to be symmetric.
+    fn match_fmtp(&self, f: &dyn Fmtp) -> bool {
+        if let Some(c) = f.as_any().downcast_ref::<OpusFmtp>() {
+            self.parameters
+                .iter()
+                .filter(|(k, _)| k.as_str() != OPUS_MIN_PTIME)
+                .all(|(k, v)| {
+                    c.parameters
+                        .get(k)
+                        .is_none_or(|vc| vc.to_uppercase() == v.to_uppercase())
+                })
+        } else {
+            false
+        }
+    }
+
+    fn parameter(&self, key: &str) -> Option<&String> {
+        self.parameters.get(key)
+    }
+
+    fn equal(&self, other: &dyn Fmtp) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<OpusFmtp>()
+            .is_some_and(|a| self == a)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// intersect_min_ptime returns the local fmtp line with minptime raised to the remote
+/// minptime, so the answer doesn't violate the constraint of either side
+pub(crate) fn intersect_min_ptime(local_line: &str, remote_line: &str) -> String {
+    let remote_min_ptime = match parse("audio/opus", remote_line)
+        .as_any()
+        .downcast_ref::<OpusFmtp>()
+        .and_then(|f| f.min_ptime())
+    {
+        Some(remote_min_ptime) => remote_min_ptime,
+        None => return local_line.to_owned(),
+    };
+
+    let mut has_min_ptime = false;
+    let mut parameters: Vec<String> = vec![];
+    for p in local_line.split(';').filter(|p| !p.trim().is_empty()) {
+        let pp: Vec<&str> = p.trim().splitn(2, '=').collect();
+        if pp[0].to_lowercase() == OPUS_MIN_PTIME {
+            has_min_ptime = true;
+            let local_min_ptime = pp.get(1).and_then(|v| v.parse::<u32>().ok()).unwrap_or(0);
+            parameters.push(format!(
+                "{}={}",
+                OPUS_MIN_PTIME,
+                local_min_ptime.max(remote_min_ptime)
+            ));
+        } else {
+            parameters.push(p.trim().to_owned());
+        }
+    }
+    if !has_min_ptime {
+        parameters.insert(0, format!("{}={}", OPUS_MIN_PTIME, remote_min_ptime));
+    }
+
+    parameters.join(";")
+}
@@ -0,0 +1,30 @@
+use crate::description::rtp_transceiver::PayloadType;
+
+/// redundant_payload_types returns the payload types of the redundant encodings in a RED fmtp line,
+/// i.e., `<primary>/<secondary>/...`, or None if the line is not of this form
+/// <https://datatracker.ietf.org/doc/html/rfc2198#section-5>
+pub(crate) fn redundant_payload_types(line: &str) -> Option<Vec<PayloadType>> {
+    line.trim()
+        .split('/')
+        .map(|payload_type| payload_type.trim().parse::<PayloadType>().ok())
+        .collect()
+}
+
+/// remap_redundant_payload_types returns the RED fmtp line with each payload type translated by map,
+/// or None if the line is invalid or any payload type can't be translated
+pub(crate) fn remap_redundant_payload_types(
+    line: &str,
+    map: impl Fn(PayloadType) -> Option<PayloadType>,
+) -> Option<String> {
+    let payload_types = redundant_payload_types(line)?
+        .into_iter()
+        .map(map)
+        .collect::<Option<Vec<PayloadType>>>()?;
+    Some(
+        payload_types
+            .iter()
+            .map(|payload_type| payload_type.to_string())
+            .collect::<Vec<String>>()
+            .join("/"),
+    )
+}
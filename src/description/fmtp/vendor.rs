@@ -0,0 +1,60 @@
+use super::*;
+
+/// X_GOOGLE_MIN_BITRATE is the vendor fmtp parameter hinting the minimum bitrate in kbps
+pub(crate) const X_GOOGLE_MIN_BITRATE: &str = "x-google-min-bitrate";
+/// X_GOOGLE_MAX_BITRATE is the vendor fmtp parameter hinting the maximum bitrate in kbps
+pub(crate) const X_GOOGLE_MAX_BITRATE: &str = "x-google-max-bitrate";
+/// VENDOR_PARAMETER_PREFIX is the prefix of vendor specific fmtp parameters
+const VENDOR_PARAMETER_PREFIX: &str = "x-";
+
+/// BitrateHints are the bitrate bounds in bits per second hinted by vendor fmtp parameters
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BitrateHints {
+    pub min_bitrate: Option<u64>,
+    pub max_bitrate: Option<u64>,
+}
+
+impl BitrateHints {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.min_bitrate.is_none() && self.max_bitrate.is_none()
+    }
+}
+
+/// bitrate_hints returns the bitrate bounds of x-google-min-bitrate and x-google-max-bitrate
+pub(crate) fn bitrate_hints(mime_type: &str, line: &str) -> BitrateHints {
+    let fmtp = parse(mime_type, line);
+    let kbps = |key: &str| {
+        fmtp.parameter(key)
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(|kbps| kbps.saturating_mul(1000))
+    };
+    BitrateHints {
+        min_bitrate: kbps(X_GOOGLE_MIN_BITRATE),
+        max_bitrate: kbps(X_GOOGLE_MAX_BITRATE),
+    }
+}
+
+/// with_vendor_parameters returns the local fmtp line followed by the vendor parameters of the
+/// remote fmtp line which the local one doesn't have, kept verbatim in their original order
+pub(crate) fn with_vendor_parameters(local_line: &str, remote_line: &str) -> String {
+    let key = |p: &str| {
+        p.trim()
+            .split_once('=')
+            .map_or(p.trim(), |(key, _)| key)
+            .to_lowercase()
+    };
+    let local_keys: Vec<String> = local_line.split(';').map(key).collect();
+
+    let mut parameters: Vec<&str> = local_line
+        .split(';')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect();
+    for p in remote_line.split(';').map(str::trim) {
+        let k = key(p);
+        if k.starts_with(VENDOR_PARAMETER_PREFIX) && !local_keys.contains(&k) {
+            parameters.push(p);
+        }
+    }
+    parameters.join(";")
+}
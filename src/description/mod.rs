@@ -1,14 +1,29 @@
+pub(crate) mod bundle_policy;
 pub(crate) mod fmtp;
+pub(crate) mod payload_type_allocator;
+pub(crate) mod rtcp_rsize_policy;
 pub(crate) mod rtp_codec;
 pub(crate) mod rtp_transceiver;
 pub(crate) mod rtp_transceiver_direction;
 pub(crate) mod sdp_type;
+pub(crate) mod signaling_state;
 
+#[cfg(test)]
+mod description_test;
+#[cfg(test)]
+mod payload_type_allocator_test;
+
+use crate::configs::media_config::{MIME_TYPE_OPUS, MIME_TYPE_RED, MIME_TYPE_RTX};
 use crate::configs::session_config::SessionConfig;
+use crate::description::bundle_policy::RTCBundlePolicy;
 use crate::description::{
-    rtp_codec::{RTCRtpCodecCapability, RTCRtpCodecParameters, RTCRtpHeaderExtensionParameters},
+    rtp_codec::{
+        codec_parameters_fuzzy_search, CodecMatch, RTCRtpCodecCapability, RTCRtpCodecParameters,
+        RTCRtpHeaderExtensionParameters, RTPCodecType,
+    },
     rtp_transceiver::{
-        MediaStreamId, PayloadType, RTCPFeedback, RTCRtpTransceiver, SsrcGroup, SSRC,
+        MediaStreamId, PayloadType, RTCPFeedback, RTCRtpTransceiver, SsrcAttributes, SsrcGroup,
+        RTCP_FB_CCM_TMMBR, SSRC, TYPE_RTCP_FB_CCM,
     },
     rtp_transceiver_direction::RTCRtpTransceiverDirection,
     sdp_type::RTCSdpType,
@@ -16,24 +31,43 @@ use crate::description::{
 use crate::endpoint::candidate::RTCIceParameters;
 use crate::server::certificate::RTCDtlsFingerprint;
 use crate::types::Mid;
-use sdp::description::common::{Address, ConnectionInformation};
+use sdp::description::common::{Address, Attribute, Bandwidth, ConnectionInformation};
 use sdp::description::media::{MediaName, RangedPort};
 use sdp::description::session::{
     Origin, ATTR_KEY_CONNECTION_SETUP, ATTR_KEY_EXT_MAP, ATTR_KEY_GROUP, ATTR_KEY_ICELITE,
     ATTR_KEY_MID, ATTR_KEY_RTCPMUX, ATTR_KEY_RTCPRSIZE,
 };
+use sdp::direction::Direction;
 use sdp::extmap::ExtMap;
 use sdp::util::ConnectionRole;
 use sdp::{MediaDescription, SessionDescription};
 use serde::{Deserialize, Serialize};
 use shared::error::{Error, Result};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{BufReader, Cursor};
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use url::Url;
 
 pub(crate) const UNSPECIFIED_STR: &str = "Unspecified";
+/// ATTR_KEY_EXTMAP_ALLOW_MIXED allows one-byte and two-byte header extensions in the same stream
+/// <https://datatracker.ietf.org/doc/html/rfc8285#section-6>
+pub(crate) const ATTR_KEY_EXTMAP_ALLOW_MIXED: &str = "extmap-allow-mixed";
+/// ATTR_KEY_END_OF_CANDIDATES indicates that no more candidates are trickled for the media section
+/// <https://datatracker.ietf.org/doc/html/rfc8840#section-4.1>
+pub(crate) const ATTR_KEY_END_OF_CANDIDATES: &str = "end-of-candidates";
+
+/// END_OF_CANDIDATES is the attribute line signaled out-of-band after trickled candidates once
+/// gathering completes <https://datatracker.ietf.org/doc/html/rfc8838#section-8.2>
+pub const END_OF_CANDIDATES: &str = "a=end-of-candidates";
 pub(crate) const SDP_ATTRIBUTE_RID: &str = "rid";
+pub(crate) const ATTR_KEY_SCTP_PORT: &str = "sctp-port";
+pub(crate) const ATTR_KEY_MAX_MESSAGE_SIZE: &str = "max-message-size";
+/// ATTR_KEY_RTCP is the media-level attribute of the RTCP address without rtcp-mux
+/// <https://datatracker.ietf.org/doc/html/rfc3605#section-2.1>
+const ATTR_KEY_RTCP: &str = "rtcp";
+/// DEFAULT_MAX_MESSAGE_SIZE is the max message size the remote can receive without a=max-message-size
+/// <https://datatracker.ietf.org/doc/html/rfc8841#section-6.1>
+pub(crate) const DEFAULT_MAX_MESSAGE_SIZE: usize = 65536;
 
 /// RTCSessionDescription is used to expose local and remote session descriptions.
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -95,6 +129,15 @@ impl RTCSessionDescription {
         Ok(desc)
     }
 
+    /// Given no SDP, create an RTCSessionDescription to roll back the pending offer
+    pub fn rollback() -> RTCSessionDescription {
+        RTCSessionDescription {
+            sdp: String::new(),
+            sdp_type: RTCSdpType::Rollback,
+            parsed: None,
+        }
+    }
+
     /// Unmarshal is a helper to deserialize the sdp
     pub fn unmarshal(&self) -> Result<SessionDescription> {
         let mut reader = Cursor::new(self.sdp.as_bytes());
@@ -106,6 +149,11 @@ impl RTCSessionDescription {
 
 pub(crate) const MEDIA_SECTION_APPLICATION: &str = "application";
 
+/// SDES_REPAIRED_RTP_STREAM_ID_URI is the header extension of RTX streams carrying the rid of
+/// the repaired stream <https://datatracker.ietf.org/doc/html/rfc8852#section-3.2>
+pub(crate) const SDES_REPAIRED_RTP_STREAM_ID_URI: &str =
+    "urn:ietf:params:rtp-hdrext:sdes:repaired-rtp-stream-id";
+
 pub(crate) fn get_rids(media: &MediaDescription) -> HashMap<String, String> {
     let mut rids = HashMap::new();
     for attr in &media.attributes {
@@ -139,54 +187,265 @@ pub enum RTCIceGatheringState {
     Complete,
 }
 
-fn append_candidate_if_new(
-    c: &SocketAddr,
-    component: u16,
-    m: MediaDescription,
-) -> MediaDescription {
-    let marshaled = format!("1 {} UDP 1 {} {} typ host", component, c.ip(), c.port());
-    for a in &m.attributes {
-        if let Some(value) = &a.value {
-            if &marshaled == value {
-                return m;
-            }
+/// HOST_TYPE_PREFERENCE and SRFLX_TYPE_PREFERENCE are the recommended type preferences
+/// <https://datatracker.ietf.org/doc/html/rfc8445#section-5.1.2.2>
+const HOST_TYPE_PREFERENCE: u32 = 126;
+const SRFLX_TYPE_PREFERENCE: u32 = 100;
+/// IPV6_LOCAL_PREFERENCE is higher than IPV4_LOCAL_PREFERENCE to prefer IPv6 on dual-stack hosts
+/// <https://datatracker.ietf.org/doc/html/rfc8421#section-4>
+const IPV6_LOCAL_PREFERENCE: u32 = 65535;
+const IPV4_LOCAL_PREFERENCE: u32 = 65534;
+
+/// LocalCandidate is a candidate put into local descriptions, related_addr is the host address
+/// a server reflexive candidate is mapped from, None for a host candidate
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct LocalCandidate {
+    pub(crate) addr: SocketAddr,
+    pub(crate) related_addr: Option<SocketAddr>,
+}
+
+impl LocalCandidate {
+    pub(crate) fn host(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            related_addr: None,
+        }
+    }
+
+    /// foundation is the same for candidates of the same type and base IP address, and differs
+    /// otherwise, it is the FNV-1a hash of both, and a server reflexive candidate also hashes its
+    /// mapped address, since its base may be masked
+    /// <https://datatracker.ietf.org/doc/html/rfc8445#section-5.1.1.3>
+    fn foundation(&self) -> u32 {
+        let mut key = match self.related_addr {
+            None => format!("host {}", self.addr.ip()),
+            Some(related_addr) => format!("srflx {} {}", related_addr.ip(), self.addr.ip()),
+        };
+        key.push_str(" udp");
+        key.bytes().fold(0x811c9dc5, |hash: u32, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x01000193)
+        })
+    }
+
+    /// priority of the candidate of the component
+    /// <https://datatracker.ietf.org/doc/html/rfc8445#section-5.1.2.1>
+    fn priority(&self, component: u16) -> u32 {
+        let type_preference = if self.related_addr.is_none() {
+            HOST_TYPE_PREFERENCE
+        } else {
+            SRFLX_TYPE_PREFERENCE
+        };
+        let local_preference = if self.addr.is_ipv6() {
+            IPV6_LOCAL_PREFERENCE
+        } else {
+            IPV4_LOCAL_PREFERENCE
+        };
+        (type_preference << 24) + (local_preference << 8) + (256 - component as u32)
+    }
+
+    /// marshal returns the candidate attribute value of the component
+    pub(crate) fn marshal(&self, component: u16) -> String {
+        #[cfg(test)]
+        SERIALIZED_ATTRIBUTES.with(|count| count.set(count.get() + 1));
+        let candidate = format!(
+            "{} {} UDP {} {} {}",
+            self.foundation(),
+            component,
+            self.priority(component),
+            self.addr.ip(),
+            self.addr.port()
+        );
+        match self.related_addr {
+            None => format!("{} typ host", candidate),
+            Some(related_addr) => format!(
+                "{} typ srflx raddr {} rport {}",
+                candidate,
+                related_addr.ip(),
+                related_addr.port()
+            ),
         }
     }
-    m.with_value_attribute("candidate".to_owned(), marshaled)
 }
 
-pub(crate) fn add_candidate_to_media_descriptions(
-    candidate: &SocketAddr,
-    mut m: MediaDescription,
-    ice_gathering_state: RTCIceGatheringState,
-) -> Result<MediaDescription> {
-    m = append_candidate_if_new(candidate, 1, m); // 1: RTP
+/// gather_candidates returns the local candidates to put into a description, host candidates
+/// and server reflexive ones of static mappings are all known without gathering, so descriptions
+/// are always emitted with Complete ICEGatheringState.
+pub(crate) fn gather_candidates(session_config: &SessionConfig) -> Vec<LocalCandidate> {
+    let server_config = &session_config.server_config;
+    let mut host_addrs = vec![session_config.local_addr];
+    for &host_addr in server_config
+        .host_addrs
+        .get(&session_config.local_addr)
+        .into_iter()
+        .flatten()
+    {
+        if !host_addrs.contains(&host_addr) {
+            host_addrs.push(host_addr);
+        }
+    }
 
-    //TODO: m = append_candidate_if_new(candidate, 2, m); // 2: RTCP
+    let mut candidates: Vec<LocalCandidate> = host_addrs
+        .iter()
+        .copied()
+        .map(LocalCandidate::host)
+        .collect();
+    // server reflexive address of a static mapping, e.g., 1:1 NAT, is known without gathering,
+    // and its related address is masked in privacy mode to hide the host address
+    for host_addr in host_addrs {
+        let Some(&addr) = server_config.server_reflexive_addrs.get(&host_addr) else {
+            continue;
+        };
+        let related_addr = if server_config.mask_related_addr {
+            let unspecified_ip = if addr.is_ipv4() {
+                IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+            } else {
+                IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+            };
+            SocketAddr::new(unspecified_ip, 0)
+        } else {
+            host_addr
+        };
+        candidates.push(LocalCandidate {
+            addr,
+            related_addr: Some(related_addr),
+        });
+    }
+
+    candidates
+}
 
-    if ice_gathering_state != RTCIceGatheringState::Complete {
-        return Ok(m);
+/// new_connection_information returns the placeholder connection line of the address family of
+/// local_addr, since the connection addresses are only given by candidates
+/// <https://datatracker.ietf.org/doc/html/rfc8839#section-4.2.1.1>
+pub(crate) fn new_connection_information(local_addr: &SocketAddr) -> ConnectionInformation {
+    let (address_type, address) = if local_addr.is_ipv6() {
+        ("IP6", Ipv6Addr::UNSPECIFIED.to_string())
+    } else {
+        ("IP4", Ipv4Addr::UNSPECIFIED.to_string())
+    };
+    ConnectionInformation {
+        network_type: "IN".to_owned(),
+        address_type: address_type.to_owned(),
+        address: Some(Address {
+            address,
+            ttl: None,
+            range: None,
+        }),
     }
-    for a in &m.attributes {
-        if &a.key == "end-of-candidates" {
-            return Ok(m);
+}
+
+/// rtcp_attribute returns the value of a=rtcp with the port, network type and address of addr
+fn rtcp_attribute(addr: &SocketAddr) -> String {
+    let address_type = if addr.is_ipv6() { "IP6" } else { "IP4" };
+    format!("{} IN {} {}", addr.port(), address_type, addr.ip())
+}
+
+#[cfg(test)]
+thread_local! {
+    /// SERIALIZED_ATTRIBUTES counts the fingerprint and candidate attribute values serialized on
+    /// the thread, by which tests check that the ones shared by media sections are serialized once
+    pub(crate) static SERIALIZED_ATTRIBUTES: std::cell::Cell<usize> =
+        const { std::cell::Cell::new(0) };
+}
+
+/// SharedMediaAttributes are the fingerprints and candidates shared by media sections of a
+/// description, which are serialized once and cloned into each media section carrying them
+#[derive(Default, Debug, Clone)]
+pub(crate) struct SharedMediaAttributes {
+    fingerprints: Vec<Attribute>,
+    // candidate attributes of RTP and RTCP components of each candidate
+    candidates: Vec<(Attribute, Attribute)>,
+    // a=rtcp value of the first candidate for media sections without rtcp-mux
+    rtcp: Option<String>,
+    ice_gathering_state: RTCIceGatheringState,
+}
+
+impl SharedMediaAttributes {
+    pub(crate) fn new(
+        dtls_fingerprints: &[RTCDtlsFingerprint],
+        local_candidates: &[LocalCandidate],
+        ice_gathering_state: RTCIceGatheringState,
+    ) -> Self {
+        let fingerprints = dtls_fingerprints
+            .iter()
+            .map(|fingerprint| {
+                #[cfg(test)]
+                SERIALIZED_ATTRIBUTES.with(|count| count.set(count.get() + 1));
+                Attribute::new(
+                    "fingerprint".to_owned(),
+                    Some(format!(
+                        "{} {}",
+                        fingerprint.algorithm,
+                        fingerprint.value.to_uppercase()
+                    )),
+                )
+            })
+            .collect();
+
+        let mut candidates: Vec<(Attribute, Attribute)> = vec![];
+        for candidate in local_candidates {
+            let rtp_candidate = Attribute::new("candidate".to_owned(), Some(candidate.marshal(1))); // 1: RTP
+            if !candidates
+                .iter()
+                .any(|(c, _)| c.value == rtp_candidate.value)
+            {
+                let rtcp_candidate =
+                    Attribute::new("candidate".to_owned(), Some(candidate.marshal(2))); // 2: RTCP
+                candidates.push((rtp_candidate, rtcp_candidate));
+            }
         }
+
+        Self {
+            fingerprints,
+            candidates,
+            rtcp: local_candidates
+                .first()
+                .map(|candidate| rtcp_attribute(&candidate.addr)),
+            ice_gathering_state,
+        }
+    }
+
+    fn add_fingerprints(&self, mut m: MediaDescription) -> MediaDescription {
+        m.attributes.extend(self.fingerprints.iter().cloned());
+        m
     }
 
-    Ok(m.with_property_attribute("end-of-candidates".to_owned()))
+    /// add_candidates appends the candidates of RTP component, and the ones of RTCP component as
+    /// well without rtcp-mux, followed by end-of-candidates if gathering completes
+    pub(crate) fn add_candidates(
+        &self,
+        mut m: MediaDescription,
+        rtcp_mux: bool,
+    ) -> MediaDescription {
+        if self.candidates.is_empty() {
+            return m;
+        }
+        for (rtp_candidate, rtcp_candidate) in &self.candidates {
+            m.attributes.push(rtp_candidate.clone());
+            if !rtcp_mux {
+                m.attributes.push(rtcp_candidate.clone());
+            }
+        }
+
+        if self.ice_gathering_state != RTCIceGatheringState::Complete {
+            return m;
+        }
+        m.with_property_attribute(ATTR_KEY_END_OF_CANDIDATES.to_owned())
+    }
 }
 
 pub(crate) struct AddDataMediaSectionParams {
-    should_add_candidates: bool,
+    has_candidates: bool,
     mid_value: String,
     ice_params: RTCIceParameters,
     dtls_role: ConnectionRole,
-    ice_gathering_state: RTCIceGatheringState,
+    sctp_port: u16,
+    max_message_size: u32,
 }
 
 pub(crate) fn add_data_media_section(
     d: SessionDescription,
-    dtls_fingerprints: &[RTCDtlsFingerprint],
+    shared_attributes: &SharedMediaAttributes,
     session_config: &SessionConfig,
     params: AddDataMediaSectionParams,
 ) -> Result<SessionDescription> {
@@ -201,15 +460,7 @@ pub(crate) fn add_data_media_section(
             formats: vec!["webrtc-datachannel".to_owned()],
         },
         media_title: None,
-        connection_information: Some(ConnectionInformation {
-            network_type: "IN".to_owned(),
-            address_type: "IP4".to_owned(),
-            address: Some(Address {
-                address: "0.0.0.0".to_owned(),
-                ttl: None,
-                range: None,
-            }),
-        }),
+        connection_information: Some(new_connection_information(&session_config.local_addr)),
         bandwidth: vec![],
         encryption_key: None,
         attributes: vec![],
@@ -220,67 +471,43 @@ pub(crate) fn add_data_media_section(
     )
     .with_value_attribute(ATTR_KEY_MID.to_owned(), params.mid_value)
     .with_property_attribute(RTCRtpTransceiverDirection::Sendrecv.to_string())
+    .with_value_attribute(ATTR_KEY_SCTP_PORT.to_owned(), params.sctp_port.to_string())
     .with_value_attribute(
-        "sctp-port".to_owned(),
-        session_config
-            .server_config
-            .sctp_server_config
-            .transport
-            .sctp_port()
-            .to_string(),
-    )
-    .with_value_attribute(
-        "max-message-size".to_owned(),
-        session_config
-            .server_config
-            .sctp_server_config
-            .transport
-            .max_message_size()
-            .to_string(),
+        ATTR_KEY_MAX_MESSAGE_SIZE.to_owned(),
+        params.max_message_size.to_string(),
     )
     .with_ice_credentials(
         params.ice_params.username_fragment,
         params.ice_params.password,
     );
 
-    for f in dtls_fingerprints {
-        media = media.with_fingerprint(f.algorithm.clone(), f.value.to_uppercase());
-    }
-
-    if params.should_add_candidates {
-        media = add_candidate_to_media_descriptions(
-            &session_config.local_addr,
-            media,
-            params.ice_gathering_state,
-        )?;
+    media = shared_attributes.add_fingerprints(media);
+    if params.has_candidates {
+        // data channels never carry RTCP
+        media = shared_attributes.add_candidates(media, true);
     }
 
     Ok(d.with_media(media))
 }
 
 pub(crate) struct AddTransceiverSdpParams {
-    should_add_candidates: bool,
+    has_candidates: bool,
     mid_value: String,
     dtls_role: ConnectionRole,
-    ice_gathering_state: RTCIceGatheringState,
     offered_direction: Option<RTCRtpTransceiverDirection>,
 }
 
 pub(crate) fn add_transceiver_sdp(
     d: SessionDescription,
-    dtls_fingerprints: &[RTCDtlsFingerprint],
+    shared_attributes: &SharedMediaAttributes,
     ice_params: &RTCIceParameters,
     session_config: &SessionConfig,
     media_section: &MediaSection,
     transceiver: &RTCRtpTransceiver,
     params: AddTransceiverSdpParams,
 ) -> Result<(SessionDescription, bool)> {
-    let (should_add_candidates, mid_value, dtls_role, ice_gathering_state) = (
-        params.should_add_candidates,
-        params.mid_value,
-        params.dtls_role,
-        params.ice_gathering_state,
-    );
+    let (has_candidates, mid_value, dtls_role) =
+        (params.has_candidates, params.mid_value, params.dtls_role);
 
     let mut media =
         MediaDescription::new_jsep_media_description(transceiver.kind.to_string(), vec![])
@@ -289,45 +516,115 @@ pub(crate) fn add_transceiver_sdp(
             .with_ice_credentials(
                 ice_params.username_fragment.clone(),
                 ice_params.password.clone(),
-            )
-            .with_property_attribute(ATTR_KEY_RTCPMUX.to_owned())
-            .with_property_attribute(ATTR_KEY_RTCPRSIZE.to_owned());
-
-    for fingerprint in dtls_fingerprints {
-        media = media.with_fingerprint(
-            fingerprint.algorithm.to_owned(),
-            fingerprint.value.to_uppercase(),
-        );
+            );
+    media.connection_information = Some(new_connection_information(&session_config.local_addr));
+    // b=AS is in kilobits per second, while b=TIAS is in bits per second without RTP overhead
+    // <https://datatracker.ietf.org/doc/html/rfc3890#section-6.2>
+    if let Some(max_bitrate_kbps) = media_section.max_bitrate_kbps {
+        media.bandwidth = vec![
+            Bandwidth {
+                experimental: false,
+                bandwidth_type: "AS".to_owned(),
+                bandwidth: max_bitrate_kbps,
+            },
+            Bandwidth {
+                experimental: false,
+                bandwidth_type: "TIAS".to_owned(),
+                bandwidth: max_bitrate_kbps * 1000,
+            },
+        ];
+    }
+    // rtcp-mux can only be answered if offered, otherwise RTCP falls back to its own port
+    let rtcp_mux = media_section.offered_rtcp_mux != Some(false);
+    if rtcp_mux {
+        media = media.with_property_attribute(ATTR_KEY_RTCPMUX.to_owned());
+    } else if let Some(rtcp) = shared_attributes.rtcp.as_ref().filter(|_| has_candidates) {
+        // RTCP is received on the same address as RTP, which is given explicitly in its family
+        media = media.with_value_attribute(ATTR_KEY_RTCP.to_owned(), rtcp.clone());
+    }
+    // rtcp-rsize can only be answered if offered, and is advertised per kind by the policy
+    if media_section.offered_rtcp_rsize != Some(false)
+        && session_config
+            .server_config
+            .rtcp_rsize_policy
+            .allows(transceiver.kind)
+    {
+        media = media.with_property_attribute(ATTR_KEY_RTCPRSIZE.to_owned());
     }
 
-    if should_add_candidates {
-        media = add_candidate_to_media_descriptions(
-            &session_config.local_addr,
-            media,
-            ice_gathering_state,
-        )?;
+    media = shared_attributes.add_fingerprints(media);
+    if has_candidates {
+        media = shared_attributes.add_candidates(media, rtcp_mux);
     }
 
-    let codecs = session_config
-        .server_config
-        .media_config
-        .get_codecs_by_kind(transceiver.kind);
-    for codec in codecs {
+    let media_config = &session_config.server_config.media_config;
+    let disabled_codecs = &session_config.disabled_codecs;
+    // payload types are allocated only when offering, since an answer uses the offered ones
+    let codecs = if media_section.offered_codecs.is_empty() {
+        without_disabled_codecs(
+            &media_config.get_offered_codecs_by_kind(transceiver.kind)?,
+            disabled_codecs,
+        )
+    } else {
+        reconcile_payload_types(
+            &without_disabled_codecs(
+                media_config.get_codecs_by_kind(transceiver.kind),
+                disabled_codecs,
+            ),
+            &media_section.offered_codecs,
+        )
+    };
+    if codecs.is_empty() {
+        if params.offered_direction.is_none() {
+            return Err(Error::Other(format!(
+                "ErrSDPNoCodecsForMediaSection {}",
+                mid_value
+            )));
+        }
+        // a media section without any codec in common is rejected alone, rather than
+        // emitting a media line without formats which invalidates the whole answer
+        return Ok((
+            d.with_media(new_rejected_media_description(
+                transceiver.kind,
+                mid_value,
+                &media_section.offered_codecs,
+            )),
+            false,
+        ));
+    }
+    for codec in &codecs {
         let name = codec
             .capability
             .mime_type
             .trim_start_matches("audio/")
             .trim_start_matches("video/")
             .to_owned();
+        let sdp_fmtp_line = if params.offered_direction.is_some() {
+            get_answer_sdp_fmtp_line(
+                transceiver,
+                codec,
+                session_config.server_config.echo_vendor_fmtp_parameters,
+            )
+        } else {
+            codec.capability.sdp_fmtp_line.clone()
+        };
         media = media.with_codec(
             codec.payload_type,
             name,
             codec.capability.clock_rate,
             codec.capability.channels,
-            codec.capability.sdp_fmtp_line.clone(),
+            sdp_fmtp_line,
         );
 
         for feedback in &codec.capability.rtcp_feedbacks {
+            // "ccm tmmbr" is only answered when it is offered for the same codec
+            if params.offered_direction.is_some()
+                && feedback.typ == TYPE_RTCP_FB_CCM
+                && feedback.parameter == RTCP_FB_CCM_TMMBR
+                && !is_rtcp_feedback_offered(transceiver, codec, feedback)
+            {
+                continue;
+            }
             media = media.with_value_attribute(
                 "rtcp-fb".to_owned(),
                 format!(
@@ -343,33 +640,40 @@ pub(crate) fn add_transceiver_sdp(
         .media_config
         .get_rtp_parameters_by_kind(transceiver.kind, transceiver.direction);
     for rtp_extension in parameters.header_extensions {
+        // only the offered extensions which are also supported locally are answered,
+        // with the offered ids
+        let (id, direction) = if params.offered_direction.is_some() {
+            let Some(offered_extension) = media_section
+                .offered_header_extensions
+                .iter()
+                .find(|offered| offered.uri == rtp_extension.uri)
+            else {
+                continue;
+            };
+            match get_answer_extmap_direction(offered_extension, &rtp_extension) {
+                Some(direction) => (offered_extension.id, direction),
+                None => continue,
+            }
+        } else {
+            (rtp_extension.id, rtp_extension.direction)
+        };
         let ext_url = Url::parse(rtp_extension.uri.as_str())?;
-        media = media.with_extmap(ExtMap {
-            value: rtp_extension.id,
+        // keep extmap as a key-value attribute, so it can be read back from the parsed description
+        let ext_map = ExtMap {
+            value: id,
+            direction: Direction::new(&direction.to_string()),
             uri: Some(ext_url),
             ..Default::default()
-        });
-    }
-
-    if !media_section.rid_map.is_empty() {
-        let mut recv_rids: Vec<String> = vec![];
-
-        for rid in media_section.rid_map.keys() {
-            media =
-                media.with_value_attribute(SDP_ATTRIBUTE_RID.to_owned(), rid.to_owned() + " recv");
-            recv_rids.push(rid.to_owned());
-        }
-        // Simulcast
-        media = media.with_value_attribute(
-            "simulcast".to_owned(),
-            "recv ".to_owned() + recv_rids.join(";").as_str(),
-        );
+        };
+        media = media.with_value_attribute(ATTR_KEY_EXT_MAP.to_owned(), ext_map.to_string());
     }
 
     let direction = match params.offered_direction {
         Some(offered_direction) => {
             use RTCRtpTransceiverDirection::*;
-            let transceiver_direction = transceiver.direction;
+            let transceiver_direction = transceiver
+                .direction_override
+                .unwrap_or(transceiver.direction);
 
             match offered_direction {
                 Sendonly | Recvonly => {
@@ -406,11 +710,43 @@ pub(crate) fn add_transceiver_sdp(
     };
     media = media.with_property_attribute(direction.to_string());
 
+    let mut simulcast = vec![];
+    if direction.has_send() {
+        if let Some(sender) = transceiver.sender.as_ref() {
+            if !sender.rids.is_empty() {
+                for rid in &sender.rids {
+                    media = media.with_value_attribute(
+                        SDP_ATTRIBUTE_RID.to_owned(),
+                        rid.to_owned() + " send",
+                    );
+                }
+                simulcast.push("send ".to_owned() + sender.rids.join(";").as_str());
+            }
+        }
+    }
+
+    if !media_section.rid_map.is_empty() {
+        let mut recv_rids: Vec<String> = vec![];
+
+        for rid in media_section.rid_map.keys() {
+            media =
+                media.with_value_attribute(SDP_ATTRIBUTE_RID.to_owned(), rid.to_owned() + " recv");
+            recv_rids.push(rid.to_owned());
+        }
+        simulcast.push("recv ".to_owned() + recv_rids.join(";").as_str());
+    }
+
+    // Simulcast
+    if !simulcast.is_empty() {
+        media = media.with_value_attribute("simulcast".to_owned(), simulcast.join(" "));
+    }
+
     if direction == RTCRtpTransceiverDirection::Sendonly {
         if let Some(sender) = transceiver.sender.as_ref() {
             media = media.with_property_attribute(format!(
                 "msid:{} {}",
-                sender.msid.stream_id, sender.msid.track_id
+                sender.msid.msid_id(),
+                sender.msid.track_id
             ));
 
             for ssrc_group in &sender.ssrc_groups {
@@ -426,11 +762,22 @@ pub(crate) fn add_transceiver_sdp(
                 ));
             }
 
+            let echo_ssrc_attributes = session_config.server_config.echo_ssrc_attributes;
             for ssrc in &sender.ssrcs {
+                // echo the cname which the publisher uses for the ssrc, if it differs per ssrc
+                let ssrc_cname = if echo_ssrc_attributes {
+                    sender
+                        .ssrc_attributes
+                        .get(ssrc)
+                        .and_then(|attributes| attributes.cname.as_ref())
+                } else {
+                    None
+                };
+                let cname = ssrc_cname.unwrap_or(&sender.cname);
                 media = media.with_media_source(
                     *ssrc,
-                    sender.cname.clone(),
-                    sender.msid.stream_id.clone(),
+                    cname.clone(),
+                    sender.msid.msid_id().to_string(),
                     sender.msid.track_id.clone(),
                 );
             }
@@ -444,12 +791,276 @@ pub(crate) fn add_transceiver_sdp(
     Ok((d.with_media(media), true))
 }
 
+/// new_rejected_media_description creates a media section with port zero and inactive direction,
+/// listing the offered formats, to reject the offered media section
+/// <https://datatracker.ietf.org/doc/html/rfc8829#section-5.3.1>
+fn new_rejected_media_description(
+    kind: RTPCodecType,
+    mid_value: String,
+    offered_codecs: &[RTCRtpCodecParameters],
+) -> MediaDescription {
+    let mut media = MediaDescription::new_jsep_media_description(kind.to_string(), vec![])
+        .with_value_attribute(ATTR_KEY_MID.to_owned(), mid_value)
+        .with_property_attribute(RTCRtpTransceiverDirection::Inactive.to_string());
+    media.media_name.port.value = 0;
+    media.media_name.formats = offered_codecs
+        .iter()
+        .map(|codec| codec.payload_type.to_string())
+        .collect();
+    media
+}
+
+/// new_rejected_data_media_description returns the application media section with port 0,
+/// which rejects it without affecting the other media sections
+fn new_rejected_data_media_description(mid_value: String) -> MediaDescription {
+    MediaDescription {
+        media_name: MediaName {
+            media: MEDIA_SECTION_APPLICATION.to_owned(),
+            port: RangedPort {
+                value: 0,
+                range: None,
+            },
+            protos: vec!["UDP".to_owned(), "DTLS".to_owned(), "SCTP".to_owned()],
+            formats: vec!["webrtc-datachannel".to_owned()],
+        },
+        media_title: None,
+        connection_information: None,
+        bandwidth: vec![],
+        encryption_key: None,
+        attributes: vec![],
+    }
+    .with_value_attribute(ATTR_KEY_MID.to_owned(), mid_value)
+}
+
+/// reconcile_payload_types keeps the local codecs matching the offered media section,
+/// each with payload type the offer uses for it, since payload types are per section
+/// and the offer may reuse or renumber dynamic ones. Without offered codecs, local codecs are returned.
+fn reconcile_payload_types(
+    codecs: &[RTCRtpCodecParameters],
+    offered_codecs: &[RTCRtpCodecParameters],
+) -> Vec<RTCRtpCodecParameters> {
+    if offered_codecs.is_empty() {
+        return codecs.to_vec();
+    }
+
+    let mut reconciled: Vec<RTCRtpCodecParameters> = vec![];
+    let mut reconciled_payload_types: HashMap<PayloadType, PayloadType> = HashMap::new();
+    let is_rtx = |codec: &RTCRtpCodecParameters| {
+        codec
+            .capability
+            .mime_type
+            .eq_ignore_ascii_case(MIME_TYPE_RTX)
+    };
+    for codec in codecs.iter().filter(|codec| !is_rtx(codec)) {
+        let offered_codec = offered_codecs.iter().find(|offered_codec| {
+            !reconciled
+                .iter()
+                .any(|c| c.payload_type == offered_codec.payload_type)
+                && matches!(
+                    codec_parameters_fuzzy_search(offered_codec, std::slice::from_ref(codec)),
+                    (_, CodecMatch::Exact)
+                )
+        });
+        if let Some(offered_codec) = offered_codec {
+            reconciled_payload_types
+                .entry(codec.payload_type)
+                .or_insert(offered_codec.payload_type);
+            reconciled.push(RTCRtpCodecParameters {
+                payload_type: offered_codec.payload_type,
+                ..codec.clone()
+            });
+        }
+    }
+
+    // RED references the payload types of its redundant encodings, which follow the offer too,
+    // and RED is dropped if any of them is not negotiated
+    reconciled.retain_mut(|codec| {
+        if !codec
+            .capability
+            .mime_type
+            .eq_ignore_ascii_case(MIME_TYPE_RED)
+        {
+            return true;
+        }
+        match fmtp::red::remap_redundant_payload_types(&codec.capability.sdp_fmtp_line, |pt| {
+            reconciled_payload_types.get(&pt).copied()
+        }) {
+            Some(sdp_fmtp_line) => {
+                codec.capability.sdp_fmtp_line = sdp_fmtp_line;
+                true
+            }
+            None => false,
+        }
+    });
+
+    // RTX is paired with the offered RTX whose apt is the offered payload type of its primary
+    // codec, so unlike other codecs it is matched after primary codecs
+    for codec in codecs.iter().filter(|codec| is_rtx(codec)) {
+        let Some(&primary_payload_type) = get_apt(&codec.capability.sdp_fmtp_line)
+            .and_then(|apt| reconciled_payload_types.get(&apt))
+        else {
+            continue;
+        };
+        let offered_codec = offered_codecs.iter().find(|offered_codec| {
+            is_rtx(offered_codec)
+                && offered_codec.capability.clock_rate == codec.capability.clock_rate
+                && get_apt(&offered_codec.capability.sdp_fmtp_line) == Some(primary_payload_type)
+                && !reconciled
+                    .iter()
+                    .any(|c| c.payload_type == offered_codec.payload_type)
+        });
+        if let Some(offered_codec) = offered_codec {
+            reconciled.push(RTCRtpCodecParameters {
+                payload_type: offered_codec.payload_type,
+                capability: RTCRtpCodecCapability {
+                    sdp_fmtp_line: format!("apt={}", primary_payload_type),
+                    ..codec.capability.clone()
+                },
+                ..codec.clone()
+            });
+        }
+    }
+    reconciled
+}
+
+/// get_apt returns the associated payload type of RTX fmtp line
+/// <https://datatracker.ietf.org/doc/html/rfc4588#section-8.1>
+pub(crate) fn get_apt(sdp_fmtp_line: &str) -> Option<PayloadType> {
+    sdp_fmtp_line
+        .split(';')
+        .find_map(|parameter| parameter.trim().strip_prefix("apt="))
+        .and_then(|apt| apt.parse::<PayloadType>().ok())
+}
+
+/// without_disabled_codecs returns the codecs except the ones whose lowercase mime type is disabled,
+/// together with RTX and RED codecs referencing payload types of the removed ones
+fn without_disabled_codecs(
+    codecs: &[RTCRtpCodecParameters],
+    disabled_codecs: &HashSet<String>,
+) -> Vec<RTCRtpCodecParameters> {
+    if disabled_codecs.is_empty() {
+        return codecs.to_vec();
+    }
+
+    let mut enabled: Vec<RTCRtpCodecParameters> = codecs
+        .iter()
+        .filter(|codec| !disabled_codecs.contains(&codec.capability.mime_type.to_lowercase()))
+        .cloned()
+        .collect();
+    let payload_types: HashSet<PayloadType> =
+        enabled.iter().map(|codec| codec.payload_type).collect();
+    enabled.retain(|codec| {
+        let mime_type = &codec.capability.mime_type;
+        if mime_type.eq_ignore_ascii_case(MIME_TYPE_RTX) {
+            codec
+                .capability
+                .sdp_fmtp_line
+                .split(';')
+                .filter_map(|parameter| parameter.trim().strip_prefix("apt="))
+                .all(|apt| {
+                    apt.parse::<PayloadType>()
+                        .is_ok_and(|apt| payload_types.contains(&apt))
+                })
+        } else if mime_type.eq_ignore_ascii_case(MIME_TYPE_RED) {
+            fmtp::red::redundant_payload_types(&codec.capability.sdp_fmtp_line).is_none_or(
+                |redundant_payload_types| {
+                    redundant_payload_types
+                        .iter()
+                        .all(|payload_type| payload_types.contains(payload_type))
+                },
+            )
+        } else {
+            true
+        }
+    });
+    enabled
+}
+
+/// get_answer_sdp_fmtp_line returns the fmtp line of the local codec, made consistent with
+/// the constraints of the same codec in the remote offer, and followed by the offered vendor
+/// parameters, e.g., x-google-max-bitrate, if echo_vendor_parameters
+fn get_answer_sdp_fmtp_line(
+    transceiver: &RTCRtpTransceiver,
+    codec: &RTCRtpCodecParameters,
+    echo_vendor_parameters: bool,
+) -> String {
+    let is_same_codec = |remote_codec: &&RTCRtpCodecParameters| {
+        remote_codec
+            .capability
+            .mime_type
+            .eq_ignore_ascii_case(&codec.capability.mime_type)
+    };
+    let Some(remote_codec) = transceiver
+        .rtp_params
+        .codecs
+        .iter()
+        .filter(is_same_codec)
+        .find(|remote_codec| remote_codec.payload_type == codec.payload_type)
+        .or_else(|| transceiver.rtp_params.codecs.iter().find(is_same_codec))
+    else {
+        return codec.capability.sdp_fmtp_line.clone();
+    };
+
+    let sdp_fmtp_line = if codec
+        .capability
+        .mime_type
+        .eq_ignore_ascii_case(MIME_TYPE_OPUS)
+    {
+        fmtp::opus::intersect_min_ptime(
+            &codec.capability.sdp_fmtp_line,
+            &remote_codec.capability.sdp_fmtp_line,
+        )
+    } else {
+        codec.capability.sdp_fmtp_line.clone()
+    };
+    if echo_vendor_parameters {
+        fmtp::vendor::with_vendor_parameters(&sdp_fmtp_line, &remote_codec.capability.sdp_fmtp_line)
+    } else {
+        sdp_fmtp_line
+    }
+}
+
+/// is_rtcp_feedback_offered returns true if remote offered the feedback for the codec
+fn is_rtcp_feedback_offered(
+    transceiver: &RTCRtpTransceiver,
+    codec: &RTCRtpCodecParameters,
+    feedback: &RTCPFeedback,
+) -> bool {
+    transceiver.rtp_params.codecs.iter().any(|remote_codec| {
+        remote_codec
+            .capability
+            .mime_type
+            .eq_ignore_ascii_case(&codec.capability.mime_type)
+            && remote_codec.capability.rtcp_feedbacks.contains(feedback)
+    })
+}
+
 #[derive(Default)]
 pub(crate) struct MediaSection {
     pub(crate) mid: Mid,
     pub(crate) data: bool,
     pub(crate) rid_map: HashMap<String, String>,
     pub(crate) offered_direction: Option<RTCRtpTransceiverDirection>,
+    /// codecs of the offered media section with their payload types, empty if we are offering
+    pub(crate) offered_codecs: Vec<RTCRtpCodecParameters>,
+    /// header extensions of the offered media section with their ids, empty if we are offering
+    pub(crate) offered_header_extensions: Vec<RTCRtpHeaderExtensionParameters>,
+    /// sctp-port of the offered application media section, None if we are offering
+    pub(crate) offered_sctp_port: Option<u16>,
+    /// max-message-size of the offered application media section, None if absent or we are offering
+    pub(crate) offered_max_message_size: Option<u32>,
+    /// whether the offered media section multiplexes RTP and RTCP, None if we are offering
+    pub(crate) offered_rtcp_mux: Option<bool>,
+    /// whether the offered media section uses reduced-size RTCP, None if we are offering
+    pub(crate) offered_rtcp_rsize: Option<bool>,
+    /// bitrate cap of the media section advertised as b=AS and b=TIAS, None if uncapped
+    pub(crate) max_bitrate_kbps: Option<u64>,
+    /// rejected media section is answered with port 0, e.g., an extra application media section,
+    /// a media section without rtcp-mux when it is required, or with an unsupported protocol
+    pub(crate) rejected: bool,
+    /// transport protocol of the offered media section echoed when rejecting it, empty to
+    /// answer with the default one
+    pub(crate) offered_protos: Vec<String>,
 }
 
 /// populate_sdp serializes a PeerConnections state into an SDP
@@ -463,19 +1074,25 @@ pub(crate) fn populate_sdp(
     media_sections: &[MediaSection],
     transceivers: &HashMap<Mid, RTCRtpTransceiver>,
     media_description_fingerprint: bool,
+    offered_bundle_group: Option<&[Mid]>,
+    extmap_allow_mixed: bool,
+    trickled_candidates: &[LocalCandidate],
 ) -> Result<SessionDescription> {
-    let media_dtls_fingerprints = if media_description_fingerprint {
-        dtls_fingerprints.to_vec()
-    } else {
-        vec![]
-    };
+    // fingerprints and candidates are serialized once for all media sections
+    let mut candidates = gather_candidates(session_config);
+    candidates.extend_from_slice(trickled_candidates);
+    let shared_attributes = SharedMediaAttributes::new(
+        if media_description_fingerprint {
+            dtls_fingerprints
+        } else {
+            &[]
+        },
+        &candidates,
+        RTCIceGatheringState::Complete,
+    );
 
-    let mut bundle_value = "BUNDLE".to_owned();
-    let mut bundle_count = 0;
-    let append_bundle = |mid_value: &str, value: &mut String, count: &mut i32| {
-        *value = value.clone() + " " + mid_value;
-        *count += 1;
-    };
+    let mut bundle_mids: Vec<&Mid> = vec![];
+    let mut kinds_with_candidates: HashSet<String> = HashSet::new();
 
     for (i, m) in media_sections.iter().enumerate() {
         if m.data && transceivers.get(&m.mid).is_some() {
@@ -484,30 +1101,92 @@ pub(crate) fn populate_sdp(
             ));
         }
 
-        let should_add_candidates = i == 0;
+        // when answering a BUNDLE group, candidates are only added to its first media section,
+        // otherwise the bundle policy decides which media sections have their own transports
+        let has_candidates = match (
+            offered_bundle_group,
+            session_config.server_config.bundle_policy,
+        ) {
+            (None, RTCBundlePolicy::MaxCompat) => true,
+            (None, RTCBundlePolicy::Balanced) => {
+                let kind = if m.data {
+                    MEDIA_SECTION_APPLICATION.to_owned()
+                } else {
+                    transceivers
+                        .get(&m.mid)
+                        .map(|transceiver| transceiver.kind.to_string())
+                        .unwrap_or_default()
+                };
+                kinds_with_candidates.insert(kind)
+            }
+            _ => i == 0,
+        };
+        // media sections with their own transports override the session-level ICE parameters,
+        // except the first one, i.e., the BUNDLE transport
+        let media_ice_params = if i > 0 && has_candidates {
+            ice_params.for_media(&m.mid)
+        } else {
+            ice_params.clone()
+        };
 
-        let should_add_id = if m.data {
+        let should_add_id = if m.rejected {
+            let mut media = if m.data {
+                new_rejected_data_media_description(m.mid.clone())
+            } else {
+                let kind = transceivers
+                    .get(&m.mid)
+                    .ok_or(Error::Other("ErrSDPZeroTransceivers".to_string()))?
+                    .kind;
+                new_rejected_media_description(kind, m.mid.clone(), &m.offered_codecs)
+            };
+            if !m.offered_protos.is_empty() {
+                media.media_name.protos.clone_from(&m.offered_protos);
+            }
+            d = d.with_media(media);
+            false
+        } else if m.data {
+            let local_max_message_size = session_config
+                .server_config
+                .sctp_server_config
+                .transport
+                .max_message_size();
             let params = AddDataMediaSectionParams {
-                should_add_candidates,
+                has_candidates,
                 mid_value: m.mid.clone(),
-                ice_params: ice_params.clone(),
+                ice_params: media_ice_params,
                 dtls_role: connection_role,
-                ice_gathering_state: RTCIceGatheringState::Complete,
+                // echo the offered port, since SCTP association replies to the ports of remote INIT
+                sctp_port: m
+                    .offered_sctp_port
+                    .or(session_config.sctp_port)
+                    .unwrap_or_else(|| {
+                        session_config
+                            .server_config
+                            .sctp_server_config
+                            .transport
+                            .sctp_port()
+                    }),
+                // don't advertise more than the remote's limit, where 0 means no limit
+                max_message_size: match m.offered_max_message_size {
+                    Some(remote_max_message_size) if remote_max_message_size > 0 => {
+                        local_max_message_size.min(remote_max_message_size)
+                    }
+                    _ => local_max_message_size,
+                },
             };
-            d = add_data_media_section(d, &media_dtls_fingerprints, session_config, params)?;
+            d = add_data_media_section(d, &shared_attributes, session_config, params)?;
             true
         } else {
             let params = AddTransceiverSdpParams {
-                should_add_candidates,
+                has_candidates,
                 mid_value: m.mid.clone(),
                 dtls_role: connection_role,
-                ice_gathering_state: RTCIceGatheringState::Complete,
                 offered_direction: m.offered_direction,
             };
             let (d1, should_add_id) = add_transceiver_sdp(
                 d,
-                &media_dtls_fingerprints,
-                ice_params,
+                &shared_attributes,
+                &media_ice_params,
                 session_config,
                 m,
                 transceivers
@@ -520,10 +1199,24 @@ pub(crate) fn populate_sdp(
         };
 
         if should_add_id {
-            append_bundle(&m.mid, &mut bundle_value, &mut bundle_count);
+            bundle_mids.push(&m.mid);
         }
     }
 
+    // when answering, bundled mids must stay in the offered BUNDLE group with the same order,
+    // so that the offerer tagged mid is still the first one
+    // <https://datatracker.ietf.org/doc/html/rfc8843#section-7.3>
+    if let Some(offered_bundle_group) = offered_bundle_group {
+        bundle_mids = offered_bundle_group
+            .iter()
+            .filter(|mid| bundle_mids.contains(mid))
+            .collect();
+    }
+    let mut bundle_value = "BUNDLE".to_owned();
+    for mid in bundle_mids {
+        bundle_value = bundle_value + " " + mid;
+    }
+
     if !media_description_fingerprint {
         for fingerprint in dtls_fingerprints {
             d = d.with_fingerprint(
@@ -537,9 +1230,111 @@ pub(crate) fn populate_sdp(
     // RFC 5245 S15.3
     d = d.with_property_attribute(ATTR_KEY_ICELITE.to_owned());
 
+    // forwarded packets keep the header extension profile of the publisher
+    if extmap_allow_mixed {
+        d = d.with_property_attribute(ATTR_KEY_EXTMAP_ALLOW_MIXED.to_owned());
+    }
+
     Ok(d.with_value_attribute(ATTR_KEY_GROUP.to_owned(), bundle_value))
 }
 
+/// SUPPORTED_RTP_PROTOS are the secure transport protocols accepted for audio and video sections
+const SUPPORTED_RTP_PROTOS: &[&str] = &["UDP/TLS/RTP/SAVPF", "UDP/TLS/RTP/SAVP"];
+/// SUPPORTED_DATA_PROTOS are the transport protocols accepted for application sections
+const SUPPORTED_DATA_PROTOS: &[&str] = &["UDP/DTLS/SCTP", "DTLS/SCTP"];
+
+/// validate_media_protos checks the media section is offered with a transport protocol we support,
+/// i.e., DTLS over UDP, since neither TCP candidates nor unencrypted RTP are supported.
+pub(crate) fn validate_media_protos(media: &MediaDescription) -> Result<()> {
+    let protos = media.media_name.protos.join("/");
+    let supported = if media.media_name.media == MEDIA_SECTION_APPLICATION {
+        SUPPORTED_DATA_PROTOS
+    } else {
+        SUPPORTED_RTP_PROTOS
+    };
+
+    if supported
+        .iter()
+        .any(|supported_protos| supported_protos.eq_ignore_ascii_case(&protos))
+    {
+        Ok(())
+    } else {
+        Err(Error::Other(format!(
+            "ErrSDPUnsupportedTransportProtocol {} for media {}",
+            protos, media.media_name.media
+        )))
+    }
+}
+
+/// get_bundle_group returns mids of the BUNDLE group in the description, if any.
+/// Only one BUNDLE group is supported, since all media sections share one transport.
+pub(crate) fn get_bundle_group(desc: &SessionDescription) -> Result<Option<Vec<Mid>>> {
+    let mut bundle_group = None;
+    for a in &desc.attributes {
+        if a.key != ATTR_KEY_GROUP {
+            continue;
+        }
+        let Some(value) = a.value.as_ref() else {
+            continue;
+        };
+        let mut fields = value.split_whitespace();
+        if fields.next() != Some("BUNDLE") {
+            continue;
+        }
+        if bundle_group.is_some() {
+            return Err(Error::Other(
+                "ErrSDPMultipleBundleGroupsNotSupported".to_string(),
+            ));
+        }
+
+        let mids: Vec<Mid> = fields.map(|mid| mid.to_owned()).collect();
+        for mid in &mids {
+            if !desc
+                .media_descriptions
+                .iter()
+                .any(|media| get_mid_value(media) == Some(mid))
+            {
+                return Err(Error::Other(format!("ErrSDPBundleGroupUnknownMid {}", mid)));
+            }
+        }
+        bundle_group = Some(mids);
+    }
+
+    Ok(bundle_group)
+}
+
+/// with_index_mids assigns mids by m-line index to the offered media sections, if none of them
+/// has a=mid without BUNDLE, e.g., of legacy clients, while mids are required under BUNDLE
+/// <https://datatracker.ietf.org/doc/html/rfc8843#section-9.1>
+pub(crate) fn with_index_mids(desc: &mut SessionDescription) -> Result<()> {
+    if desc
+        .media_descriptions
+        .iter()
+        .all(|media| get_mid_value(media).is_some())
+    {
+        return Ok(());
+    }
+    if get_bundle_group(desc)?.is_some() {
+        return Err(Error::Other(
+            "ErrPeerConnRemoteDescriptionWithoutMidValue under BUNDLE".to_string(),
+        ));
+    }
+    if desc
+        .media_descriptions
+        .iter()
+        .any(|media| get_mid_value(media).is_some())
+    {
+        return Ok(());
+    }
+
+    for (i, media) in desc.media_descriptions.iter_mut().enumerate() {
+        media
+            .attributes
+            .push(Attribute::new(ATTR_KEY_MID.to_owned(), Some(i.to_string())));
+    }
+    Ok(())
+}
+
 pub(crate) fn get_mid_value(media: &MediaDescription) -> Option<&String> {
     for attr in &media.attributes {
         if attr.key == "mid" {
@@ -549,8 +1344,72 @@ pub(crate) fn get_mid_value(media: &MediaDescription) -> Option<&String> {
     None
 }
 
-pub(crate) fn get_peer_direction(media: &MediaDescription) -> RTCRtpTransceiverDirection {
-    for a in &media.attributes {
+/// get_sctp_port returns the value of a=sctp-port of the media section, if any
+pub(crate) fn get_sctp_port(media: &MediaDescription) -> Option<u16> {
+    media
+        .attributes
+        .iter()
+        .find(|attr| attr.key == ATTR_KEY_SCTP_PORT)
+        .and_then(|attr| attr.value.as_ref())
+        .and_then(|value| value.trim().parse::<u16>().ok())
+}
+
+/// has_rtcp_mux returns true if the media section has a=rtcp-mux, i.e., RTP and RTCP share the port
+/// <https://datatracker.ietf.org/doc/html/rfc5761#section-5.1.1>
+pub(crate) fn has_rtcp_mux(media: &MediaDescription) -> bool {
+    media.attribute(ATTR_KEY_RTCPMUX).is_some()
+}
+
+pub(crate) fn has_rtcp_rsize(media: &MediaDescription) -> bool {
+    media.attribute(ATTR_KEY_RTCPRSIZE).is_some()
+}
+
+/// is_ice_lite returns true if the description advertises a=ice-lite at session level
+/// <https://datatracker.ietf.org/doc/html/rfc8839#section-5.3>
+pub(crate) fn is_ice_lite(desc: &SessionDescription) -> bool {
+    desc.attributes
+        .iter()
+        .any(|attribute| attribute.key == ATTR_KEY_ICELITE)
+}
+
+/// has_extmap_allow_mixed returns true if the description advertises a=extmap-allow-mixed, at
+/// session level or in any media section
+pub(crate) fn has_extmap_allow_mixed(desc: &SessionDescription) -> bool {
+    desc.attributes
+        .iter()
+        .any(|attribute| attribute.key == ATTR_KEY_EXTMAP_ALLOW_MIXED)
+        || desc
+            .media_descriptions
+            .iter()
+            .any(|media| media.attribute(ATTR_KEY_EXTMAP_ALLOW_MIXED).is_some())
+}
+
+/// get_max_message_size returns the max message size the remote can receive, advertised by
+/// a=max-message-size of the application media section, or DEFAULT_MAX_MESSAGE_SIZE if it is
+/// absent or invalid, None if there is no limit, i.e., the value is 0 or no data channel is negotiated
+pub(crate) fn get_max_message_size(desc: &SessionDescription) -> Option<usize> {
+    let media = desc
+        .media_descriptions
+        .iter()
+        .find(|media| media.media_name.media == MEDIA_SECTION_APPLICATION)?;
+    match get_media_max_message_size(media) {
+        Some(0) => None,
+        Some(max_message_size) => Some(max_message_size as usize),
+        None => Some(DEFAULT_MAX_MESSAGE_SIZE),
+    }
+}
+
+/// get_media_max_message_size returns the value of a=max-message-size of the media section, if any
+pub(crate) fn get_media_max_message_size(media: &MediaDescription) -> Option<u32> {
+    media
+        .attribute(ATTR_KEY_MAX_MESSAGE_SIZE)
+        .flatten()
+        .and_then(|value| value.trim().parse::<u32>().ok())
+}
+
+/// get_session_direction returns the direction attribute set at session level, if any
+pub(crate) fn get_session_direction(desc: &SessionDescription) -> RTCRtpTransceiverDirection {
+    for a in &desc.attributes {
         let direction = RTCRtpTransceiverDirection::from(a.key.as_str());
         if direction != RTCRtpTransceiverDirection::Unspecified {
             return direction;
@@ -559,6 +1418,28 @@ pub(crate) fn get_peer_direction(media: &MediaDescription) -> RTCRtpTransceiverD
     RTCRtpTransceiverDirection::Unspecified
 }
 
+/// get_peer_direction returns the direction of a media section. A media section without
+/// direction attribute inherits the session-level one, or sendrecv if neither is present.
+/// <https://datatracker.ietf.org/doc/html/rfc3264#section-5.1>
+pub(crate) fn get_peer_direction(
+    desc: &SessionDescription,
+    media: &MediaDescription,
+) -> RTCRtpTransceiverDirection {
+    for a in &media.attributes {
+        let direction = RTCRtpTransceiverDirection::from(a.key.as_str());
+        if direction != RTCRtpTransceiverDirection::Unspecified {
+            return direction;
+        }
+    }
+
+    let direction = get_session_direction(desc);
+    if direction != RTCRtpTransceiverDirection::Unspecified {
+        direction
+    } else {
+        RTCRtpTransceiverDirection::Sendrecv
+    }
+}
+
 pub(crate) fn get_cname(media: &MediaDescription) -> Option<String> {
     for a in &media.attributes {
         if a.key == "ssrc" {
@@ -581,10 +1462,7 @@ pub(crate) fn get_msid(media: &MediaDescription) -> Option<MediaStreamId> {
             if let Some(value) = a.value.as_ref() {
                 let fields: Vec<&str> = value.split_whitespace().collect();
                 if fields.len() == 2 {
-                    return Some(MediaStreamId {
-                        stream_id: fields[0].to_string(),
-                        track_id: fields[1].to_string(),
-                    });
+                    return Some(MediaStreamId::parse(fields[0], fields[1]));
                 }
             }
         } else if a.key == "ssrc" {
@@ -594,10 +1472,7 @@ pub(crate) fn get_msid(media: &MediaDescription) -> Option<MediaStreamId> {
                     if let Some(msid) = fields_msid.last() {
                         let fields: Vec<&str> = msid.split_whitespace().collect();
                         if fields.len() == 2 {
-                            return Some(MediaStreamId {
-                                stream_id: fields[0].to_string(),
-                                track_id: fields[1].to_string(),
-                            });
+                            return Some(MediaStreamId::parse(fields[0], fields[1]));
                         }
                     }
                 }
@@ -632,6 +1507,40 @@ pub(crate) fn get_ssrc_groups(media: &MediaDescription) -> Result<Vec<SsrcGroup>
     Ok(ssrc_groups)
 }
 
+/// get_ssrc_attributes returns cname and msid of each ssrc from `a=ssrc:<ssrc> <attribute>:<value>`
+pub(crate) fn get_ssrc_attributes(
+    media: &MediaDescription,
+) -> Result<HashMap<SSRC, SsrcAttributes>> {
+    let mut ssrc_attributes: HashMap<SSRC, SsrcAttributes> = HashMap::new();
+    for a in &media.attributes {
+        if a.key == "ssrc" {
+            if let Some(value) = a.value.as_ref() {
+                let Some((ssrc, attribute)) = value.split_once(' ') else {
+                    continue;
+                };
+                let ssrc = ssrc.parse::<u32>()?;
+                let Some((name, value)) = attribute.split_once(':') else {
+                    continue;
+                };
+                match name {
+                    "cname" => {
+                        ssrc_attributes.entry(ssrc).or_default().cname = Some(value.to_string());
+                    }
+                    "msid" => {
+                        let fields: Vec<&str> = value.split_whitespace().collect();
+                        if fields.len() == 2 {
+                            ssrc_attributes.entry(ssrc).or_default().msid =
+                                Some(MediaStreamId::parse(fields[0], fields[1]));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(ssrc_attributes)
+}
+
 pub(crate) fn get_ssrcs(media: &MediaDescription) -> Result<Vec<SSRC>> {
     let mut ssrcs = Vec::new();
     for a in &media.attributes {
@@ -839,6 +1748,30 @@ pub(crate) fn codecs_from_media_description(
     Ok(out)
 }
 
+/// get_answer_extmap_direction returns the direction qualifier answering the offered extension,
+/// or None if the offered direction can't be accepted
+/// <https://datatracker.ietf.org/doc/html/rfc8285#section-6>
+fn get_answer_extmap_direction(
+    offered_extension: &RTCRtpHeaderExtensionParameters,
+    rtp_extension: &RTCRtpHeaderExtensionParameters,
+) -> Option<RTCRtpTransceiverDirection> {
+    let offered_direction = offered_extension.direction;
+    if offered_direction == RTCRtpTransceiverDirection::Unspecified {
+        return Some(rtp_extension.direction);
+    }
+
+    let local_direction = if rtp_extension.direction == RTCRtpTransceiverDirection::Unspecified {
+        RTCRtpTransceiverDirection::Sendrecv
+    } else {
+        rtp_extension.direction
+    };
+    // an extension offered as sendonly is answered as recvonly, and vice versa
+    match offered_direction.reverse().intersect(local_direction) {
+        RTCRtpTransceiverDirection::Inactive => None,
+        direction => Some(direction),
+    }
+}
+
 pub(crate) fn rtp_extensions_from_media_description(
     m: &MediaDescription,
 ) -> Result<Vec<RTCRtpHeaderExtensionParameters>> {
@@ -855,6 +1788,7 @@ pub(crate) fn rtp_extensions_from_media_description(
                 out.push(RTCRtpHeaderExtensionParameters {
                     uri: uri.to_string(),
                     id: e.value,
+                    direction: RTCRtpTransceiverDirection::from(e.direction.to_string().as_str()),
                 });
             }
         }
@@ -883,9 +1817,14 @@ pub(crate) fn update_sdp_origin(origin: &mut Origin, d: &mut SessionDescription)
             }
         }*/
         d.origin.session_id = origin.session_id;
+        // the rest of origin is kept as well, since some clients key on the whole o= line
+        d.origin.username.clone_from(&origin.username);
+        d.origin.network_type.clone_from(&origin.network_type);
+        d.origin.address_type.clone_from(&origin.address_type);
+        d.origin.unicast_address.clone_from(&origin.unicast_address);
 
         //d.Origin.SessionVersion = atomic.AddUint64(&origin.SessionVersion, 1)
         origin.session_version += 1;
-        d.origin.session_version += 1;
+        d.origin.session_version = origin.session_version;
     }
 }
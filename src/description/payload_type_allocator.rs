@@ -0,0 +1,117 @@
+use crate::configs::media_config::{MIME_TYPE_RED, MIME_TYPE_RTX};
+use crate::description::{fmtp, rtp_codec::RTCRtpCodecParameters, rtp_transceiver::PayloadType};
+use shared::error::{Error, Result};
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+
+/// STATIC_PAYLOAD_TYPES are assigned to codecs by RFC 3551, which are never reallocated
+const STATIC_PAYLOAD_TYPES: RangeInclusive<PayloadType> = 0..=34;
+/// RTCP_CONFLICT_PAYLOAD_TYPES are ambiguous with RTCP packet types when RTP and RTCP are multiplexed
+/// <https://datatracker.ietf.org/doc/html/rfc5761#section-4>
+const RTCP_CONFLICT_PAYLOAD_TYPES: RangeInclusive<PayloadType> = 64..=95;
+
+/// validate_dynamic_payload_type_range checks the range only has payload types usable for
+/// dynamic assignment when RTP and RTCP are multiplexed
+pub(crate) fn validate_dynamic_payload_type_range(
+    range: &RangeInclusive<PayloadType>,
+) -> Result<()> {
+    if range.is_empty()
+        || *range.start() <= *STATIC_PAYLOAD_TYPES.end()
+        || *range.end() > 127
+        || (*range.start() <= *RTCP_CONFLICT_PAYLOAD_TYPES.end()
+            && *range.end() >= *RTCP_CONFLICT_PAYLOAD_TYPES.start())
+    {
+        return Err(Error::Other(format!(
+            "ErrInvalidPayloadTypeRange {:?}",
+            range
+        )));
+    }
+    Ok(())
+}
+
+/// PayloadTypeAllocator assigns distinct dynamic payload types to the codecs of a media section
+/// within the configured range, keeping the registered payload type whenever it is in range and free
+pub(crate) struct PayloadTypeAllocator {
+    range: RangeInclusive<PayloadType>,
+    used: HashSet<PayloadType>,
+}
+
+impl PayloadTypeAllocator {
+    pub(crate) fn new(range: RangeInclusive<PayloadType>) -> Self {
+        Self {
+            range,
+            used: HashSet::new(),
+        }
+    }
+
+    /// allocate returns the payload type for the codec registered with preferred one
+    pub(crate) fn allocate(&mut self, preferred: PayloadType) -> Result<PayloadType> {
+        let payload_type = if STATIC_PAYLOAD_TYPES.contains(&preferred)
+            || (self.range.contains(&preferred) && !self.used.contains(&preferred))
+        {
+            preferred
+        } else {
+            self.range
+                .clone()
+                .find(|payload_type| !self.used.contains(payload_type))
+                .ok_or(Error::Other(format!(
+                    "ErrPayloadTypeRangeExhausted {:?}",
+                    self.range
+                )))?
+        };
+        self.used.insert(payload_type);
+        Ok(payload_type)
+    }
+
+    /// allocate_codecs returns the codecs with allocated payload types, where the associated
+    /// payload types of RTX codecs, i.e., apt, and the redundant encodings of RED follow their reallocated codecs
+    pub(crate) fn allocate_codecs(
+        &mut self,
+        codecs: &[RTCRtpCodecParameters],
+    ) -> Result<Vec<RTCRtpCodecParameters>> {
+        let mut allocated = Vec::with_capacity(codecs.len());
+        let mut reallocated: HashMap<PayloadType, PayloadType> = HashMap::new();
+        for codec in codecs {
+            let payload_type = self.allocate(codec.payload_type)?;
+            reallocated
+                .entry(codec.payload_type)
+                .or_insert(payload_type);
+            allocated.push(RTCRtpCodecParameters {
+                payload_type,
+                ..codec.clone()
+            });
+        }
+
+        for codec in allocated.iter_mut() {
+            let mime_type = &codec.capability.mime_type;
+            if mime_type.eq_ignore_ascii_case(MIME_TYPE_RTX) {
+                codec.capability.sdp_fmtp_line = codec
+                    .capability
+                    .sdp_fmtp_line
+                    .split(';')
+                    .map(|parameter| match parameter.trim().strip_prefix("apt=") {
+                        Some(apt) => match apt
+                            .parse::<PayloadType>()
+                            .ok()
+                            .and_then(|apt| reallocated.get(&apt))
+                        {
+                            Some(apt) => format!("apt={}", apt),
+                            None => parameter.to_string(),
+                        },
+                        None => parameter.to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(";");
+            } else if mime_type.eq_ignore_ascii_case(MIME_TYPE_RED) {
+                if let Some(sdp_fmtp_line) = fmtp::red::remap_redundant_payload_types(
+                    &codec.capability.sdp_fmtp_line,
+                    |payload_type| Some(*reallocated.get(&payload_type).unwrap_or(&payload_type)),
+                ) {
+                    codec.capability.sdp_fmtp_line = sdp_fmtp_line;
+                }
+            }
+        }
+
+        Ok(allocated)
+    }
+}
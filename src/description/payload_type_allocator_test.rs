@@ -0,0 +1,80 @@
+use super::payload_type_allocator::*;
+use super::rtp_codec::{RTCRtpCodecCapability, RTCRtpCodecParameters};
+use crate::configs::media_config::{MIME_TYPE_OPUS, MIME_TYPE_PCMU, MIME_TYPE_RTX, MIME_TYPE_VP8};
+use shared::error::Result;
+use std::collections::HashSet;
+
+fn new_codec(mime_type: &str, payload_type: u8, sdp_fmtp_line: &str) -> RTCRtpCodecParameters {
+    RTCRtpCodecParameters {
+        capability: RTCRtpCodecCapability {
+            mime_type: mime_type.to_string(),
+            sdp_fmtp_line: sdp_fmtp_line.to_string(),
+            ..Default::default()
+        },
+        payload_type,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_payload_type_allocator_allocates_distinct_payload_types_in_range() -> Result<()> {
+    let range = 100..=110;
+    let codecs = vec![
+        new_codec(MIME_TYPE_VP8, 96, ""),
+        new_codec(MIME_TYPE_RTX, 97, "apt=96"),
+        new_codec(MIME_TYPE_OPUS, 111, "minptime=10;useinbandfec=1"),
+        // colliding with the VP8's allocated payload type
+        new_codec(MIME_TYPE_OPUS, 100, "stereo=1"),
+        new_codec(MIME_TYPE_PCMU, 0, ""),
+    ];
+
+    let allocated = PayloadTypeAllocator::new(range.clone()).allocate_codecs(&codecs)?;
+    let payload_types: Vec<u8> = allocated.iter().map(|c| c.payload_type).collect();
+    assert_eq!(
+        payload_types.iter().collect::<HashSet<_>>().len(),
+        codecs.len()
+    );
+    for &payload_type in &payload_types[..4] {
+        assert!(range.contains(&payload_type));
+    }
+    // static payload types are kept
+    assert_eq!(payload_types[4], 0);
+
+    // RTX follows the reallocated payload type of VP8
+    assert_eq!(
+        allocated[1].capability.sdp_fmtp_line,
+        format!("apt={}", payload_types[0])
+    );
+    assert_eq!(
+        allocated[2].capability.sdp_fmtp_line,
+        "minptime=10;useinbandfec=1"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_payload_type_allocator_keeps_registered_payload_types_in_range() -> Result<()> {
+    let mut allocator = PayloadTypeAllocator::new(96..=127);
+    assert_eq!(allocator.allocate(111)?, 111);
+    assert_eq!(allocator.allocate(96)?, 96);
+    assert_eq!(allocator.allocate(96)?, 97);
+
+    let mut allocator = PayloadTypeAllocator::new(96..=96);
+    assert_eq!(allocator.allocate(111)?, 96);
+    assert!(allocator.allocate(112).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_dynamic_payload_type_range() {
+    assert!(validate_dynamic_payload_type_range(&(96..=127)).is_ok());
+    assert!(validate_dynamic_payload_type_range(&(35..=63)).is_ok());
+    assert!(validate_dynamic_payload_type_range(&(60..=100)).is_err());
+    assert!(validate_dynamic_payload_type_range(&(0..=20)).is_err());
+    assert!(validate_dynamic_payload_type_range(&(120..=130)).is_err());
+    #[allow(clippy::reversed_empty_ranges)]
+    let empty = 110..=100;
+    assert!(validate_dynamic_payload_type_range(&empty).is_err());
+}
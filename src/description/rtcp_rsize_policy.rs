@@ -0,0 +1,32 @@
+use crate::description::rtp_codec::RTPCodecType;
+
+/// RtcpRsizePolicy affects which media kinds advertise reduced-size RTCP with a=rtcp-rsize.
+/// In an answer, a=rtcp-rsize is still only included if it is offered.
+/// <https://datatracker.ietf.org/doc/html/rfc5506#section-5>
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RtcpRsizePolicy {
+    /// indicates to advertise reduced-size RTCP for both audio and video media sections
+    #[default]
+    All,
+
+    /// indicates to advertise reduced-size RTCP for video media sections only
+    VideoOnly,
+
+    /// indicates to advertise reduced-size RTCP for audio media sections only
+    AudioOnly,
+
+    /// indicates to never advertise reduced-size RTCP
+    Disabled,
+}
+
+impl RtcpRsizePolicy {
+    /// allows returns true if reduced-size RTCP is advertised for media sections of the kind
+    pub(crate) fn allows(&self, kind: RTPCodecType) -> bool {
+        match *self {
+            RtcpRsizePolicy::All => true,
+            RtcpRsizePolicy::VideoOnly => kind == RTPCodecType::Video,
+            RtcpRsizePolicy::AudioOnly => kind == RTPCodecType::Audio,
+            RtcpRsizePolicy::Disabled => false,
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use crate::configs::media_config::*;
 use crate::description::{
     fmtp,
-    rtp_transceiver::{PayloadType, RTCPFeedback},
+    rtp_transceiver::{PayloadType, RTCPFeedback, RTCP_FB_NACK_PLI, TYPE_RTCP_FB_NACK},
+    rtp_transceiver_direction::RTCRtpTransceiverDirection,
 };
 use shared::error::{Error, Result};
 
@@ -61,6 +62,25 @@ pub struct RTCRtpCodecCapability {
 }
 
 impl RTCRtpCodecCapability {
+    /// has_generic_nack returns true if the codec signals "nack" without parameter, i.e.,
+    /// lost packets can be requested for retransmission
+    pub fn has_generic_nack(&self) -> bool {
+        self.has_rtcp_feedback(TYPE_RTCP_FB_NACK, "")
+    }
+
+    /// has_nack_pli returns true if the codec signals "nack pli", i.e., keyframes can be requested
+    /// by PLI, which is independent of generic NACK
+    pub fn has_nack_pli(&self) -> bool {
+        self.has_rtcp_feedback(TYPE_RTCP_FB_NACK, RTCP_FB_NACK_PLI)
+    }
+
+    fn has_rtcp_feedback(&self, typ: &str, parameter: &str) -> bool {
+        self.rtcp_feedbacks.iter().any(|feedback| {
+            feedback.typ.eq_ignore_ascii_case(typ)
+                && feedback.parameter.eq_ignore_ascii_case(parameter)
+        })
+    }
+
     /// Turn codec capability into a `packetizer::Payloader`
     pub fn payloader_for_codec(&self) -> Result<Box<dyn rtp::packetizer::Payloader>> {
         let mime_type = self.mime_type.to_lowercase();
@@ -101,6 +121,8 @@ pub struct RTCRtpHeaderExtensionCapability {
 pub struct RTCRtpHeaderExtensionParameters {
     pub uri: String,
     pub id: isize,
+    /// direction qualifier of `a=extmap:<id>/<direction>`, Unspecified if absent
+    pub direction: RTCRtpTransceiverDirection,
 }
 
 /// RTPCodecParameters is a sequence containing the media codecs that an RtpSender
@@ -1,5 +1,7 @@
+use std::collections::HashMap;
+
 use crate::description::{
-    rtp_codec::{RTCRtpParameters, RTPCodecType},
+    rtp_codec::{RTCRtpHeaderExtensionParameters, RTCRtpParameters, RTPCodecType},
     rtp_transceiver_direction::RTCRtpTransceiverDirection,
 };
 
@@ -29,9 +31,16 @@ pub const TYPE_RTCP_FB_ACK: &str = "ack";
 /// TYPE_RTCP_FB_CCM ..
 pub const TYPE_RTCP_FB_CCM: &str = "ccm";
 
+/// RTCP_FB_CCM_TMMBR is the parameter of "ccm tmmbr" feedback
+pub const RTCP_FB_CCM_TMMBR: &str = "tmmbr";
+
 /// TYPE_RTCP_FB_NACK ..
 pub const TYPE_RTCP_FB_NACK: &str = "nack";
 
+/// RTCP_FB_NACK_PLI is the parameter of "nack pli" feedback, while "nack" without parameter
+/// is generic NACK for retransmission <https://datatracker.ietf.org/doc/html/rfc4585#section-4.2>
+pub const RTCP_FB_NACK_PLI: &str = "pli";
+
 /// rtcpfeedback signals the connection to use additional RTCP packet types.
 /// <https://draft.ortc.org/#dom-rtcrtcpfeedback>
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
@@ -46,24 +55,117 @@ pub struct RTCPFeedback {
     pub parameter: String,
 }
 
+/// MSID_NO_STREAM is the msid id of a track which belongs to no MediaStream
+/// <https://datatracker.ietf.org/doc/html/rfc8829#section-5.2.1>
+pub(crate) const MSID_NO_STREAM: &str = "-";
+
 #[derive(Debug, Clone)]
 pub(crate) struct MediaStreamId {
+    /// empty if the track belongs to no MediaStream
     pub(crate) stream_id: String,
     pub(crate) track_id: String,
 }
 
+impl MediaStreamId {
+    /// parse creates MediaStreamId from msid id and appdata, with "-" as no stream
+    pub(crate) fn parse(id: &str, appdata: &str) -> Self {
+        Self {
+            stream_id: if id == MSID_NO_STREAM {
+                String::new()
+            } else {
+                id.to_string()
+            },
+            track_id: appdata.to_string(),
+        }
+    }
+
+    /// msid_id returns the id of msid attribute, "-" if the track belongs to no MediaStream
+    pub(crate) fn msid_id(&self) -> &str {
+        if self.stream_id.is_empty() {
+            MSID_NO_STREAM
+        } else {
+            &self.stream_id
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct SsrcGroup {
     pub(crate) name: String,
     pub(crate) ssrcs: Vec<SSRC>,
 }
 
+/// SsrcAttributes are the source-level attributes of a remote SSRC
+/// <https://datatracker.ietf.org/doc/html/rfc5576#section-4.1>
+#[derive(Default, Debug, Clone)]
+pub(crate) struct SsrcAttributes {
+    pub(crate) cname: Option<String>,
+    pub(crate) msid: Option<MediaStreamId>,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct RTCRtpSender {
     pub(crate) cname: String,
     pub(crate) msid: MediaStreamId,
     pub(crate) ssrcs: Vec<SSRC>,
+    /// source-level attributes of each ssrc, as `a=ssrc:<ssrc> <attribute>:<value>`
+    pub(crate) ssrc_attributes: HashMap<SSRC, SsrcAttributes>,
     pub(crate) ssrc_groups: Vec<SsrcGroup>,
+    /// rids of simulcast layers sent by this sender, advertised as `a=rid:<rid> send`
+    pub(crate) rids: Vec<String>,
+}
+
+impl RTCRtpSender {
+    /// map_ssrcs returns the sender with all its ssrcs, including ones in groups, translated by map
+    pub(crate) fn map_ssrcs(&self, mut map: impl FnMut(SSRC) -> SSRC) -> Self {
+        Self {
+            ssrcs: self.ssrcs.iter().map(|&ssrc| map(ssrc)).collect(),
+            ssrc_attributes: self
+                .ssrc_attributes
+                .iter()
+                .map(|(&ssrc, attributes)| (map(ssrc), attributes.clone()))
+                .collect(),
+            ssrc_groups: self
+                .ssrc_groups
+                .iter()
+                .map(|group| SsrcGroup {
+                    name: group.name.clone(),
+                    ssrcs: group.ssrcs.iter().map(|&ssrc| map(ssrc)).collect(),
+                })
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+/// SimulcastLayer is a simulcast layer named by the rids of libwebrtc's convention, i.e., "q", "h"
+/// and "f" for quarter, half and full resolution
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SimulcastLayer {
+    Low,
+    Mid,
+    High,
+}
+
+impl SimulcastLayer {
+    /// rid returns the rid of the layer
+    pub fn rid(&self) -> &'static str {
+        match self {
+            SimulcastLayer::Low => "q",
+            SimulcastLayer::Mid => "h",
+            SimulcastLayer::High => "f",
+        }
+    }
+
+    /// from_rid returns the layer of rid, or None if rid isn't of the convention
+    pub fn from_rid(rid: &str) -> Option<SimulcastLayer> {
+        match rid {
+            "q" => Some(SimulcastLayer::Low),
+            "h" => Some(SimulcastLayer::Mid),
+            "f" => Some(SimulcastLayer::High),
+            _ => None,
+        }
+    }
 }
 
 /// RTPTransceiver represents a combination of an RTPSender and an RTPReceiver that share a common mid.
@@ -75,10 +177,19 @@ pub struct RTCRtpTransceiver {
 
     pub(crate) direction: RTCRtpTransceiverDirection,
     pub(crate) current_direction: RTCRtpTransceiverDirection,
+    /// direction set by the application to answer with instead of direction, still
+    /// intersected with the offered one
+    pub(crate) direction_override: Option<RTCRtpTransceiverDirection>,
 
     pub(crate) rtp_params: RTCRtpParameters,
 
     pub(crate) kind: RTPCodecType,
+
+    /// header extensions agreed by both sides once the answer is applied
+    pub(crate) negotiated_header_extensions: Vec<RTCRtpHeaderExtensionParameters>,
+
+    /// rid of the simulcast layer forwarded to a subscriber's transceiver, all layers if None
+    pub(crate) selected_rid: Option<String>,
 }
 
 impl RTCRtpTransceiver {
@@ -90,4 +201,19 @@ impl RTCRtpTransceiver {
     pub(crate) fn set_current_direction(&mut self, d: RTCRtpTransceiverDirection) {
         self.current_direction = d;
     }
+
+    /// negotiated_header_extensions returns the header extension id to uri map as negotiated.
+    pub(crate) fn negotiated_header_extensions(&self) -> HashMap<isize, String> {
+        self.negotiated_header_extensions
+            .iter()
+            .map(|ext| (ext.id, ext.uri.clone()))
+            .collect()
+    }
+
+    pub(crate) fn set_negotiated_header_extensions(
+        &mut self,
+        header_extensions: Vec<RTCRtpHeaderExtensionParameters>,
+    ) {
+        self.negotiated_header_extensions = header_extensions;
+    }
 }
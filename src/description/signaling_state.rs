@@ -0,0 +1,37 @@
+use crate::description::UNSPECIFIED_STR;
+use std::fmt;
+
+/// SignalingState indicates the signaling state of the offer/answer process.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RTCSignalingState {
+    Unspecified = 0,
+
+    /// indicates there is no offer/answer exchange in progress.
+    #[default]
+    Stable,
+
+    /// indicates that a local description, of type "offer", has been successfully applied.
+    HaveLocalOffer,
+
+    /// indicates that a remote description, of type "offer", has been successfully applied.
+    HaveRemoteOffer,
+}
+
+const SIGNALING_STATE_STABLE_STR: &str = "stable";
+const SIGNALING_STATE_HAVE_LOCAL_OFFER_STR: &str = "have-local-offer";
+const SIGNALING_STATE_HAVE_REMOTE_OFFER_STR: &str = "have-remote-offer";
+
+impl fmt::Display for RTCSignalingState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RTCSignalingState::Stable => write!(f, "{SIGNALING_STATE_STABLE_STR}"),
+            RTCSignalingState::HaveLocalOffer => {
+                write!(f, "{SIGNALING_STATE_HAVE_LOCAL_OFFER_STR}")
+            }
+            RTCSignalingState::HaveRemoteOffer => {
+                write!(f, "{SIGNALING_STATE_HAVE_REMOTE_OFFER_STR}")
+            }
+            _ => write!(f, "{}", UNSPECIFIED_STR),
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use rtp::extension::abs_send_time_extension::AbsSendTimeExtension;
+use shared::marshal::Unmarshal;
+
+/// get_abs_send_time returns the 24-bit absolute send time of the RTP packet, in 6.18 fixed point
+/// seconds, if it carries the abs-send-time extension with the negotiated id
+/// <http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time>
+pub(crate) fn get_abs_send_time(rtp_packet: &rtp::packet::Packet, id: u8) -> Option<u32> {
+    let mut extension = rtp_packet.header.get_extension(id)?;
+    AbsSendTimeExtension::unmarshal(&mut extension)
+        .ok()
+        .map(|abs_send_time| abs_send_time.timestamp as u32)
+}
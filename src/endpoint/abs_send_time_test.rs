@@ -0,0 +1,25 @@
+use super::abs_send_time::*;
+use crate::messages::RTPMessageEvent;
+use bytes::Bytes;
+use shared::error::Result;
+
+#[test]
+fn test_get_abs_send_time() -> Result<()> {
+    let mut rtp_packet = rtp::packet::Packet::default();
+    rtp_packet
+        .header
+        .set_extension(3, Bytes::from_static(&[0x12, 0x34, 0x56]))?;
+    assert_eq!(get_abs_send_time(&rtp_packet, 3), Some(0x123456));
+    assert_eq!(get_abs_send_time(&rtp_packet, 4), None);
+
+    let event = RTPMessageEvent::Rtp(rtp_packet);
+    assert_eq!(event.abs_send_time(3), Some(0x123456));
+
+    // truncated extension
+    let mut rtp_packet = rtp::packet::Packet::default();
+    rtp_packet
+        .header
+        .set_extension(3, Bytes::from_static(&[0x12, 0x34]))?;
+    assert_eq!(get_abs_send_time(&rtp_packet, 3), None);
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::description::{RTCSessionDescription, UNSPECIFIED_STR};
+use crate::description::{get_mid_value, RTCSessionDescription, UNSPECIFIED_STR};
 use crate::server::certificate::RTCDtlsFingerprint;
 use crate::types::{EndpointId, SessionId, UserName};
 use base64::{prelude::BASE64_STANDARD, Engine};
@@ -6,6 +6,7 @@ use ring::rand::{SecureRandom, SystemRandom};
 use sdp::util::ConnectionRole;
 use sdp::SessionDescription;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use shared::error::{Error, Result};
 use std::fmt;
 use std::time::Instant;
@@ -92,6 +93,47 @@ impl DTLSRole {
     }
 }
 
+/// IceRole indicates the role of the ICE agent, the controlling agent nominates candidate pairs
+/// and an ICE-lite agent is always controlled
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum IceRole {
+    Controlling,
+    Controlled,
+}
+
+/// RoleConflict is the resolution of a binding request of the remote agent with the same role
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum RoleConflict {
+    /// the remote agent has the opposite role
+    None,
+    /// the local agent switches to the opposite role and handles the request
+    SwitchRole,
+    /// the local agent keeps its role and responds with 487 Role Conflict error
+    Reject,
+}
+
+/// resolve_role_conflict compares the tie-breakers if both agents claim the same role, an ICE-lite
+/// agent can't switch to controlling so it always keeps its role
+/// <https://datatracker.ietf.org/doc/html/rfc8445#section-7.3.1.1>
+pub(crate) fn resolve_role_conflict(
+    local_role: IceRole,
+    local_tie_breaker: u64,
+    is_lite: bool,
+    remote_role: IceRole,
+    remote_tie_breaker: u64,
+) -> RoleConflict {
+    if local_role != remote_role {
+        return RoleConflict::None;
+    }
+    match local_role {
+        IceRole::Controlling if local_tie_breaker >= remote_tie_breaker => RoleConflict::Reject,
+        IceRole::Controlled if is_lite || local_tie_breaker < remote_tie_breaker => {
+            RoleConflict::Reject
+        }
+        _ => RoleConflict::SwitchRole,
+    }
+}
+
 /// ICEParameters includes the ICE username fragment
 /// and password and other ICE-related parameters.
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -100,6 +142,40 @@ pub(crate) struct RTCIceParameters {
     pub(crate) password: String,
 }
 
+impl RTCIceParameters {
+    /// generate returns random ICE parameters, e.g., for a new candidate or an ICE restart
+    pub(crate) fn generate() -> RTCIceParameters {
+        let rng = SystemRandom::new();
+
+        let mut user = [0u8; 9];
+        let _ = rng.fill(&mut user);
+        let mut password = [0u8; 18];
+        let _ = rng.fill(&mut password);
+
+        RTCIceParameters {
+            username_fragment: BASE64_STANDARD.encode(&user[..]),
+            password: BASE64_STANDARD.encode(&password[..]),
+        }
+    }
+
+    /// for_media derives the ICE parameters of a media section with its own transport, which
+    /// are distinct per mid and have the same length as the generated ones
+    pub(crate) fn for_media(&self, mid: &str) -> RTCIceParameters {
+        let derive = |value: &str, len: usize| {
+            let mut hasher = Sha256::new();
+            hasher.update(value.as_bytes());
+            hasher.update([0u8]);
+            hasher.update(mid.as_bytes());
+            BASE64_STANDARD.encode(&hasher.finalize()[..len])
+        };
+
+        RTCIceParameters {
+            username_fragment: derive(&self.username_fragment, 9),
+            password: derive(&self.password, 18),
+        }
+    }
+}
+
 /// DTLSParameters holds information relating to DTLS configuration.
 #[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub(crate) struct DTLSParameters {
@@ -113,27 +189,31 @@ pub(crate) struct ConnectionCredentials {
     pub(crate) dtls_params: DTLSParameters,
 }
 
-impl ConnectionCredentials {
-    pub(crate) fn new(fingerprints: Vec<RTCDtlsFingerprint>, remote_role: DTLSRole) -> Self {
-        let rng = SystemRandom::new();
-
-        let mut user = [0u8; 9];
-        let _ = rng.fill(&mut user);
-        let mut password = [0u8; 18];
-        let _ = rng.fill(&mut password);
+impl DTLSRole {
+    /// answer_role returns the local role answering the remote role, i.e., setup:passive to
+    /// setup:active, setup:active to setup:passive, and setup:actpass to setup:active as
+    /// RECOMMENDED if the DTLS client role is supported, otherwise to setup:passive
+    pub(crate) fn answer_role(remote_role: DTLSRole, client_supported: bool) -> DTLSRole {
+        match remote_role {
+            DTLSRole::Server => DTLSRole::Client,
+            DTLSRole::Client => DTLSRole::Server,
+            _ if client_supported => DEFAULT_DTLS_ROLE_ANSWER,
+            _ => DTLSRole::Server,
+        }
+    }
+}
 
+impl ConnectionCredentials {
+    pub(crate) fn new(
+        fingerprints: Vec<RTCDtlsFingerprint>,
+        remote_role: DTLSRole,
+        client_supported: bool,
+    ) -> Self {
         Self {
-            ice_params: RTCIceParameters {
-                username_fragment: BASE64_STANDARD.encode(&user[..]),
-                password: BASE64_STANDARD.encode(&password[..]),
-            },
+            ice_params: RTCIceParameters::generate(),
             dtls_params: DTLSParameters {
                 fingerprints,
-                role: if remote_role == DTLSRole::Server {
-                    DTLSRole::Client
-                } else {
-                    DTLSRole::Server
-                },
+                role: DTLSRole::answer_role(remote_role, client_supported),
             },
         }
     }
@@ -193,6 +273,9 @@ pub(crate) struct Candidate {
     local_conn_cred: ConnectionCredentials,
     remote_description: RTCSessionDescription,
     local_description: RTCSessionDescription,
+    /// ICE parameters of local media sections with their own transports, each paired with the
+    /// remote username fragment of the same mid
+    local_media_ice_params: Vec<(RTCIceParameters, String)>,
     expired_time: Instant,
 }
 
@@ -206,6 +289,44 @@ impl Candidate {
         local_description: RTCSessionDescription,
         expired_time: Instant,
     ) -> Self {
+        // media sections with their own transports may override the session-level ICE parameters,
+        // and are checked with the remote username fragment of the same m-line, if any
+        let mut local_media_ice_params = vec![];
+        if let Some(parsed) = local_description.parsed.as_ref() {
+            for media in &parsed.media_descriptions {
+                if let (Some(Some(username_fragment)), Some(Some(password))) =
+                    (media.attribute("ice-ufrag"), media.attribute("ice-pwd"))
+                {
+                    let ice_params = RTCIceParameters {
+                        username_fragment: username_fragment.to_string(),
+                        password: password.to_string(),
+                    };
+                    let mid = get_mid_value(media);
+                    let remote_username_fragment = remote_description
+                        .parsed
+                        .as_ref()
+                        .and_then(|remote| {
+                            remote
+                                .media_descriptions
+                                .iter()
+                                .find(|remote_media| {
+                                    mid.is_some() && get_mid_value(remote_media) == mid
+                                })
+                                .and_then(|remote_media| remote_media.attribute("ice-ufrag"))
+                                .flatten()
+                        })
+                        .unwrap_or(&remote_conn_cred.ice_params.username_fragment)
+                        .to_string();
+                    let media_ice_params = (ice_params, remote_username_fragment);
+                    if media_ice_params.0 != local_conn_cred.ice_params
+                        && !local_media_ice_params.contains(&media_ice_params)
+                    {
+                        local_media_ice_params.push(media_ice_params);
+                    }
+                }
+            }
+        }
+
         Self {
             session_id,
             endpoint_id,
@@ -213,6 +334,7 @@ impl Candidate {
             remote_conn_cred,
             remote_description,
             local_description,
+            local_media_ice_params,
             expired_time,
         }
     }
@@ -235,6 +357,27 @@ impl Candidate {
         &self.local_conn_cred.ice_params
     }
 
+    /// get_local_parameters_by_username returns the local's ICE parameters of the media section
+    /// whose username fragments are used by the username, either session-level or media-level
+    pub(crate) fn get_local_parameters_by_username(
+        &self,
+        username: &str,
+    ) -> Option<&RTCIceParameters> {
+        let (local_username_fragment, remote_username_fragment) = username.split_once(':')?;
+        if local_username_fragment == self.local_conn_cred.ice_params.username_fragment
+            && remote_username_fragment == self.remote_conn_cred.ice_params.username_fragment
+        {
+            return Some(&self.local_conn_cred.ice_params);
+        }
+        self.local_media_ice_params
+            .iter()
+            .find(|(ice_params, remote)| {
+                ice_params.username_fragment == local_username_fragment
+                    && remote == remote_username_fragment
+            })
+            .map(|(ice_params, _)| ice_params)
+    }
+
     pub(crate) fn session_id(&self) -> SessionId {
         self.session_id
     }
@@ -251,6 +394,19 @@ impl Candidate {
         )
     }
 
+    /// usernames returns the username of session-level ICE parameters, followed by the ones of
+    /// media-level ICE parameters with the remote username fragments of their m-lines, if any
+    pub(crate) fn usernames(&self) -> Vec<UserName> {
+        let mut usernames = vec![self.username()];
+        for (ice_params, remote_username_fragment) in &self.local_media_ice_params {
+            usernames.push(format!(
+                "{}:{}",
+                ice_params.username_fragment, remote_username_fragment
+            ));
+        }
+        usernames
+    }
+
     pub(crate) fn remote_description(&self) -> &RTCSessionDescription {
         &self.remote_description
     }
@@ -0,0 +1,181 @@
+use super::candidate::*;
+use crate::description::RTCSessionDescription;
+use shared::error::Result;
+use std::time::Instant;
+
+fn new_connection_credentials(username_fragment: &str, password: &str) -> ConnectionCredentials {
+    ConnectionCredentials {
+        ice_params: RTCIceParameters {
+            username_fragment: username_fragment.to_string(),
+            password: password.to_string(),
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_candidate_with_media_ice_credentials() -> Result<()> {
+    let local_conn_cred = new_connection_credentials("local", "local-password");
+    let media_ice_params = local_conn_cred.ice_params.for_media("1");
+    let sdp = format!(
+        "v=0\r\n\
+o=- 0 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+a=mid:0\r\n\
+a=ice-ufrag:local\r\n\
+a=ice-pwd:local-password\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+a=mid:1\r\n\
+a=ice-ufrag:{}\r\n\
+a=ice-pwd:{}\r\n",
+        media_ice_params.username_fragment, media_ice_params.password
+    );
+    let answer = RTCSessionDescription::answer(sdp)?;
+    // the remote offer has its own ICE credentials for the second media section, too
+    let offer = RTCSessionDescription::offer(
+        "v=0\r\n\
+o=- 0 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+a=mid:0\r\n\
+a=ice-ufrag:remote\r\n\
+a=ice-pwd:remote-password\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+a=mid:1\r\n\
+a=ice-ufrag:remote-1\r\n\
+a=ice-pwd:remote-1-password\r\n"
+            .to_string(),
+    )?;
+    let candidate = Candidate::new(
+        1,
+        2,
+        new_connection_credentials("remote", "remote-password"),
+        local_conn_cred.clone(),
+        offer,
+        answer.clone(),
+        Instant::now(),
+    );
+
+    // the media-level username pairs the ufrags of the same m-line
+    let media_username = format!("{}:remote-1", media_ice_params.username_fragment);
+    assert_eq!(
+        candidate.usernames(),
+        vec!["local:remote".to_string(), media_username.clone()]
+    );
+    assert_eq!(
+        candidate.get_local_parameters_by_username(&format!(
+            "{}:remote",
+            media_ice_params.username_fragment
+        )),
+        None
+    );
+    assert_eq!(
+        candidate.get_local_parameters_by_username("local:remote-1"),
+        None
+    );
+    assert_eq!(
+        candidate
+            .get_local_parameters_by_username("local:remote")
+            .map(|ice_params| ice_params.password.as_str()),
+        Some("local-password")
+    );
+    assert_eq!(
+        candidate.get_local_parameters_by_username(&media_username),
+        Some(&media_ice_params)
+    );
+    assert_eq!(
+        candidate.get_local_parameters_by_username("local:other"),
+        None
+    );
+
+    // without remote media-level ICE credentials, the session-level remote ufrag is paired
+    let candidate = Candidate::new(
+        1,
+        2,
+        new_connection_credentials("remote", "remote-password"),
+        local_conn_cred,
+        RTCSessionDescription::default(),
+        answer,
+        Instant::now(),
+    );
+    let media_username = format!("{}:remote", media_ice_params.username_fragment);
+    assert_eq!(
+        candidate.usernames(),
+        vec!["local:remote".to_string(), media_username.clone()]
+    );
+    assert_eq!(
+        candidate.get_local_parameters_by_username(&media_username),
+        Some(&media_ice_params)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_resolve_role_conflict() {
+    use IceRole::{Controlled, Controlling};
+
+    // no conflict with the opposite role
+    assert_eq!(
+        resolve_role_conflict(Controlling, 1, false, Controlled, 2),
+        RoleConflict::None
+    );
+    assert_eq!(
+        resolve_role_conflict(Controlled, 1, true, Controlling, 2),
+        RoleConflict::None
+    );
+
+    // both controlling, the larger tie-breaker keeps controlling
+    assert_eq!(
+        resolve_role_conflict(Controlling, 2, false, Controlling, 1),
+        RoleConflict::Reject
+    );
+    assert_eq!(
+        resolve_role_conflict(Controlling, 1, false, Controlling, 1),
+        RoleConflict::Reject
+    );
+    assert_eq!(
+        resolve_role_conflict(Controlling, 1, false, Controlling, 2),
+        RoleConflict::SwitchRole
+    );
+
+    // both controlled, the larger tie-breaker switches to controlling
+    assert_eq!(
+        resolve_role_conflict(Controlled, 2, false, Controlled, 1),
+        RoleConflict::SwitchRole
+    );
+    assert_eq!(
+        resolve_role_conflict(Controlled, 1, false, Controlled, 2),
+        RoleConflict::Reject
+    );
+    // unless the local agent is ICE-lite
+    assert_eq!(
+        resolve_role_conflict(Controlled, u64::MAX, true, Controlled, 1),
+        RoleConflict::Reject
+    );
+}
+
+#[test]
+fn test_dtls_answer_role() {
+    // actpass resolves to active only if the DTLS client role is supported
+    assert_eq!(
+        DTLSRole::answer_role(DTLSRole::Auto, true),
+        DTLSRole::Client
+    );
+    assert_eq!(
+        DTLSRole::answer_role(DTLSRole::Auto, false),
+        DTLSRole::Server
+    );
+
+    assert_eq!(
+        DTLSRole::answer_role(DTLSRole::Client, true),
+        DTLSRole::Server
+    );
+    assert_eq!(
+        DTLSRole::answer_role(DTLSRole::Server, false),
+        DTLSRole::Client
+    );
+}
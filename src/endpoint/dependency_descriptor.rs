@@ -0,0 +1,139 @@
+use shared::error::{Error, Result};
+
+/// DEPENDENCY_DESCRIPTOR_URI is the header extension describing frame dependencies of SVC streams
+/// <https://aomediacodec.github.io/av1-rtp-spec/#dependency-descriptor-rtp-header-extension>
+pub(crate) const DEPENDENCY_DESCRIPTOR_URI: &str =
+    "https://aomediacodec.github.io/av1-rtp-spec/#dependency-descriptor-rtp-header-extension";
+
+/// MaxSvcLayers caps the spatial and temporal layers of SVC streams forwarded to a subscriber,
+/// layers are counted from 0 for the base layer
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MaxSvcLayers {
+    pub max_spatial_layer: u8,
+    pub max_temporal_layer: u8,
+}
+
+impl MaxSvcLayers {
+    /// exceeds returns true if the layer is above the cap, since in SVC a layer only depends on
+    /// layers not higher than itself, the packets within the cap remain decodable
+    pub(crate) fn exceeds(&self, layer: SvcLayer) -> bool {
+        layer.spatial_id > self.max_spatial_layer || layer.temporal_id > self.max_temporal_layer
+    }
+}
+
+/// SvcLayer identifies the spatial and temporal layer of a frame
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct SvcLayer {
+    pub(crate) spatial_id: u8,
+    pub(crate) temporal_id: u8,
+}
+
+/// FrameDependencyStructure keeps the layers of the frame dependency templates,
+/// which are sent on key frames and referenced by template id afterwards
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct FrameDependencyStructure {
+    pub(crate) template_id_offset: u8,
+    pub(crate) template_layers: Vec<SvcLayer>,
+}
+
+impl FrameDependencyStructure {
+    /// layer returns the layer of the frame using the template id
+    pub(crate) fn layer(&self, frame_dependency_template_id: u8) -> Option<SvcLayer> {
+        let index =
+            (frame_dependency_template_id as usize + 64 - self.template_id_offset as usize) % 64;
+        self.template_layers.get(index).copied()
+    }
+}
+
+/// DependencyDescriptor is the part of the dependency descriptor needed to select layers,
+/// i.e., the mandatory fields and the template layers of the dependency structure, if attached
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct DependencyDescriptor {
+    pub(crate) start_of_frame: bool,
+    pub(crate) end_of_frame: bool,
+    pub(crate) frame_dependency_template_id: u8,
+    pub(crate) frame_number: u16,
+    pub(crate) structure: Option<FrameDependencyStructure>,
+}
+
+impl DependencyDescriptor {
+    pub(crate) fn unmarshal(buf: &[u8]) -> Result<Self> {
+        let mut reader = BitReader { buf, pos: 0 };
+        let start_of_frame = reader.read_bits(1)? == 1;
+        let end_of_frame = reader.read_bits(1)? == 1;
+        let frame_dependency_template_id = reader.read_bits(6)? as u8;
+        let frame_number = reader.read_bits(16)? as u16;
+
+        let mut structure = None;
+        if buf.len() > 3 {
+            let template_dependency_structure_present = reader.read_bits(1)? == 1;
+            // active_decode_targets_present, custom_dtis, custom_fdiffs and custom_chains flags
+            reader.read_bits(4)?;
+            if template_dependency_structure_present {
+                let template_id_offset = reader.read_bits(6)? as u8;
+                // dt_cnt_minus_one
+                reader.read_bits(5)?;
+                structure = Some(FrameDependencyStructure {
+                    template_id_offset,
+                    template_layers: reader.read_template_layers()?,
+                });
+            }
+        }
+
+        Ok(Self {
+            start_of_frame,
+            end_of_frame,
+            frame_dependency_template_id,
+            frame_number,
+            structure,
+        })
+    }
+}
+
+struct BitReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn read_bits(&mut self, n: usize) -> Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..n {
+            let byte = self
+                .buf
+                .get(self.pos / 8)
+                .ok_or(Error::Other("ErrDependencyDescriptorTooShort".to_string()))?;
+            value = (value << 1) | ((byte >> (7 - self.pos % 8)) & 1) as u32;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    /// read_template_layers reads templates in the order of increasing spatial and temporal ids,
+    /// where next_layer_idc tells whether the next template is of the same layer, the next
+    /// temporal layer, the next spatial layer, or there are no more templates
+    fn read_template_layers(&mut self) -> Result<Vec<SvcLayer>> {
+        let (mut spatial_id, mut temporal_id) = (0u8, 0u8);
+        let mut template_layers = vec![];
+        loop {
+            template_layers.push(SvcLayer {
+                spatial_id,
+                temporal_id,
+            });
+            if template_layers.len() > 64 {
+                return Err(Error::Other(
+                    "ErrDependencyDescriptorTooManyTemplates".to_string(),
+                ));
+            }
+            match self.read_bits(2)? {
+                0 => {}
+                1 => temporal_id += 1,
+                2 => {
+                    temporal_id = 0;
+                    spatial_id += 1;
+                }
+                _ => return Ok(template_layers),
+            }
+        }
+    }
+}
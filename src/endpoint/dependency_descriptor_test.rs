@@ -0,0 +1,139 @@
+use super::dependency_descriptor::*;
+use super::marker_rewriter::MarkerRewriter;
+
+/// new_dependency_descriptor encodes the mandatory fields, followed by the template layers of
+/// L2T1 dependency structure if has_structure, i.e., S0T0 as template 0 and S1T0 as template 1
+fn new_dependency_descriptor(template_id: u8, frame_number: u16, has_structure: bool) -> Vec<u8> {
+    let mut fields: Vec<(u32, usize)> = vec![
+        (1, 1),
+        (1, 1),
+        (template_id as u32, 6),
+        (frame_number as u32, 16),
+    ];
+    if has_structure {
+        // template_dependency_structure_present followed by 4 unset flags
+        fields.extend([(1, 1), (0, 4)]);
+        // template_id_offset, dt_cnt_minus_one, next_layer_idc of S1T0 then the end
+        fields.extend([(0, 6), (1, 5), (2, 2), (3, 2)]);
+    }
+
+    let mut buf = vec![];
+    let mut pos = 0;
+    for (value, width) in fields {
+        for i in (0..width).rev() {
+            if pos % 8 == 0 {
+                buf.push(0u8);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *buf.last_mut().unwrap() |= bit << (7 - pos % 8);
+            pos += 1;
+        }
+    }
+    buf
+}
+
+fn new_svc_packet(
+    sequence_number: u16,
+    timestamp: u32,
+    marker: bool,
+    spatial_id: u8,
+) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            ssrc: 1111,
+            sequence_number,
+            timestamp,
+            marker,
+            ..Default::default()
+        },
+        payload: vec![spatial_id].into(),
+    }
+}
+
+#[test]
+fn test_dependency_descriptor_unmarshal() {
+    let dependency_descriptor =
+        DependencyDescriptor::unmarshal(&new_dependency_descriptor(1, 7, false)).unwrap();
+    assert_eq!(
+        dependency_descriptor,
+        DependencyDescriptor {
+            start_of_frame: true,
+            end_of_frame: true,
+            frame_dependency_template_id: 1,
+            frame_number: 7,
+            structure: None,
+        }
+    );
+
+    let dependency_descriptor =
+        DependencyDescriptor::unmarshal(&new_dependency_descriptor(0, 8, true)).unwrap();
+    let structure = dependency_descriptor.structure.unwrap();
+    assert_eq!(
+        structure.layer(0),
+        Some(SvcLayer {
+            spatial_id: 0,
+            temporal_id: 0
+        })
+    );
+    assert_eq!(
+        structure.layer(1),
+        Some(SvcLayer {
+            spatial_id: 1,
+            temporal_id: 0
+        })
+    );
+    assert_eq!(structure.layer(2), None);
+
+    assert!(DependencyDescriptor::unmarshal(&[0x80, 0x00]).is_err());
+}
+
+#[test]
+fn test_max_svc_layers_forwards_base_layer_with_sequence_continuity() {
+    let max_svc_layers = MaxSvcLayers {
+        max_spatial_layer: 0,
+        max_temporal_layer: 2,
+    };
+    let mut structure = None;
+    let mut marker_rewriter = MarkerRewriter::default();
+    let mut forwarded = vec![];
+    // two L2T1 superframes, each of a S0 frame followed by a S1 frame ending the superframe
+    for (sequence_number, timestamp, marker, template_id) in [
+        (100, 3000, false, 0),
+        (101, 3000, false, 0),
+        (102, 3000, true, 1),
+        (103, 6000, false, 0),
+        (104, 6000, true, 1),
+    ] {
+        let dependency_descriptor = DependencyDescriptor::unmarshal(&new_dependency_descriptor(
+            template_id,
+            sequence_number,
+            sequence_number == 100,
+        ))
+        .unwrap();
+        if dependency_descriptor.structure.is_some() {
+            structure = dependency_descriptor.structure;
+        }
+        let layer = structure
+            .as_ref()
+            .and_then(|structure| {
+                structure.layer(dependency_descriptor.frame_dependency_template_id)
+            })
+            .unwrap();
+
+        let rtp_packet = new_svc_packet(sequence_number, timestamp, marker, layer.spatial_id);
+        if max_svc_layers.exceeds(layer) {
+            forwarded.extend(marker_rewriter.skip(&rtp_packet));
+        } else {
+            forwarded.extend(marker_rewriter.forward(rtp_packet));
+        }
+    }
+
+    let forwarded: Vec<(u16, bool, u8)> = forwarded
+        .iter()
+        .map(|p| (p.header.sequence_number, p.header.marker, p.payload[0]))
+        .collect();
+    assert_eq!(
+        forwarded,
+        vec![(100, false, 0), (101, true, 0), (102, true, 0)]
+    );
+}
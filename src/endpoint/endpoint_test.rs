@@ -0,0 +1,565 @@
+use super::*;
+use crate::configs::{server_config::ServerConfig, session_config::SessionConfig};
+use crate::description::rtp_codec::{RTCRtpCodecCapability, RTCRtpParameters};
+use crate::description::rtp_transceiver::{MediaStreamId, RTCPFeedback, RTCRtpSender, SsrcGroup};
+use crate::description::rtp_transceiver_direction::RTCRtpTransceiverDirection;
+use crate::description::RTCSessionDescription;
+use crate::endpoint::candidate::{Candidate, ConnectionCredentials};
+use crate::handlers::srtp_test::new_srtp_context;
+use crate::interceptors::Registry;
+use crate::session::Session;
+use bytes::Bytes;
+use shared::error::Result;
+use shared::marshal::Marshal;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+fn new_transport(four_tuple: FourTuple) -> Transport {
+    let candidate = Rc::new(Candidate::new(
+        1,
+        1,
+        ConnectionCredentials::default(),
+        ConnectionCredentials::default(),
+        RTCSessionDescription::default(),
+        RTCSessionDescription::default(),
+        Instant::now(),
+    ));
+    Transport::new(
+        four_tuple,
+        candidate,
+        Arc::new(dtls::config::HandshakeConfig::default()),
+        Arc::new(sctp::EndpointConfig::default()),
+        Arc::new(sctp::ServerConfig::default()),
+    )
+}
+
+fn new_established_transport(four_tuple: FourTuple) -> Result<Transport> {
+    let mut transport = new_transport(four_tuple);
+    transport.set_local_srtp_context(new_srtp_context()?);
+    transport.set_remote_srtp_context(new_srtp_context()?);
+    Ok(transport)
+}
+
+fn new_rtp_packet(sequence_number: u16) -> Result<Bytes> {
+    let packet = rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            sequence_number,
+            ssrc: 1234,
+            ..Default::default()
+        },
+        payload: Bytes::from_static(&[0xAA; 10]),
+    };
+    Ok(packet.marshal()?.freeze())
+}
+
+#[test]
+fn test_endpoint_migrate_preserves_srtp_state() -> Result<()> {
+    let old_four_tuple = FourTuple {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "192.168.1.2:5000".parse().unwrap(),
+    };
+    let new_four_tuple = FourTuple {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "10.0.0.2:6000".parse().unwrap(),
+    };
+
+    let mut endpoint = Endpoint::new(1, Registry::new().build(""));
+    endpoint.add_transport(new_established_transport(old_four_tuple)?);
+
+    let mut remote_srtp_context = new_srtp_context()?;
+    let encrypted = remote_srtp_context.encrypt_rtp(&new_rtp_packet(1)?)?;
+    endpoint
+        .get_mut_transports()
+        .get_mut(&old_four_tuple)
+        .unwrap()
+        .remote_srtp_context()
+        .unwrap()
+        .decrypt_rtp(&encrypted)?;
+
+    assert_eq!(endpoint.migrate(new_four_tuple), Some(old_four_tuple));
+    assert!(!endpoint.has_transport(&old_four_tuple));
+    assert!(endpoint.has_transport(&new_four_tuple));
+
+    let transport = endpoint
+        .get_mut_transports()
+        .get_mut(&new_four_tuple)
+        .unwrap();
+    assert_eq!(transport.four_tuple(), &new_four_tuple);
+    assert_eq!(transport.initial_peer_addr(), old_four_tuple.peer_addr);
+
+    // replay protection still knows about sequence number 1
+    let context = transport.remote_srtp_context().unwrap();
+    assert!(context.decrypt_rtp(&encrypted).is_err());
+    let encrypted = remote_srtp_context.encrypt_rtp(&new_rtp_packet(2)?)?;
+    context.decrypt_rtp(&encrypted)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_endpoint_migrate_requires_established_transport() -> Result<()> {
+    let old_four_tuple = FourTuple {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "192.168.1.2:5000".parse().unwrap(),
+    };
+    // DTLS handshake is not done yet
+    let transport = new_transport(old_four_tuple);
+
+    let mut endpoint = Endpoint::new(1, Registry::new().build(""));
+    endpoint.add_transport(transport);
+
+    assert_eq!(
+        endpoint.migrate(FourTuple {
+            local_addr: "127.0.0.1:3478".parse().unwrap(),
+            peer_addr: "10.0.0.2:6000".parse().unwrap(),
+        }),
+        None
+    );
+
+    Ok(())
+}
+
+fn new_endpoint_with_codecs(endpoint_id: EndpointId, codecs: &[(&str, u8, &str)]) -> Endpoint {
+    let mut endpoint = Endpoint::new(endpoint_id, Registry::new().build(""));
+    endpoint.get_mut_transceivers().insert(
+        "0".to_string(),
+        RTCRtpTransceiver {
+            mid: "0".to_string(),
+            sender: None,
+            direction: RTCRtpTransceiverDirection::Sendrecv,
+            current_direction: RTCRtpTransceiverDirection::Unspecified,
+            direction_override: None,
+            rtp_params: RTCRtpParameters {
+                header_extensions: vec![],
+                codecs: codecs
+                    .iter()
+                    .map(
+                        |&(mime_type, payload_type, sdp_fmtp_line)| RTCRtpCodecParameters {
+                            capability: RTCRtpCodecCapability {
+                                mime_type: mime_type.to_string(),
+                                clock_rate: 90000,
+                                sdp_fmtp_line: sdp_fmtp_line.to_string(),
+                                ..Default::default()
+                            },
+                            payload_type,
+                            ..Default::default()
+                        },
+                    )
+                    .collect(),
+            },
+            kind: RTPCodecType::Video,
+            negotiated_header_extensions: vec![],
+            selected_rid: None,
+        },
+    );
+    endpoint
+}
+
+#[test]
+fn test_endpoint_remap_payload_type() {
+    let publisher = new_endpoint_with_codecs(
+        1,
+        &[("video/VP8", 96, ""), ("video/VP9", 98, "profile-id=0")],
+    );
+    let subscriber = new_endpoint_with_codecs(2, &[("video/vp8", 100, "")]);
+
+    assert_eq!(publisher.remap_payload_type(96, &subscriber), Some(100));
+    // subscriber didn't negotiate VP9
+    assert_eq!(publisher.remap_payload_type(98, &subscriber), None);
+    // publisher didn't negotiate payload type 111
+    assert_eq!(publisher.remap_payload_type(111, &subscriber), None);
+}
+
+#[test]
+fn test_endpoint_failover_to_backup_pair() -> Result<()> {
+    let primary = FourTuple {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "192.168.1.2:5000".parse().unwrap(),
+    };
+    let backup = FourTuple {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "10.0.0.2:6000".parse().unwrap(),
+    };
+    let consent_timeout = Duration::from_secs(30);
+    let now = Instant::now();
+
+    let mut endpoint = Endpoint::new(1, Registry::new().build(""));
+    endpoint.add_transport(new_established_transport(primary)?);
+    endpoint.add_backup_four_tuple(backup, now);
+    // the selected pair itself is not a backup
+    endpoint.add_backup_four_tuple(primary, now);
+
+    let mut remote_srtp_context = new_srtp_context()?;
+    let encrypted = remote_srtp_context.encrypt_rtp(&new_rtp_packet(1)?)?;
+    endpoint
+        .get_mut_transports()
+        .get_mut(&primary)
+        .unwrap()
+        .remote_srtp_context()
+        .unwrap()
+        .decrypt_rtp(&encrypted)?;
+
+    let now = now + Duration::from_secs(10);
+    assert_eq!(
+        endpoint.failover(&primary, now, consent_timeout),
+        Some(backup)
+    );
+    assert!(!endpoint.has_transport(&primary));
+
+    // media continues over the backup pair with the same SRTP state
+    let transport = endpoint.get_mut_transports().get_mut(&backup).unwrap();
+    assert_eq!(transport.four_tuple(), &backup);
+    let encrypted = remote_srtp_context.encrypt_rtp(&new_rtp_packet(2)?)?;
+    transport
+        .remote_srtp_context()
+        .unwrap()
+        .decrypt_rtp(&encrypted)?;
+
+    // no more backup pairs to fail over to
+    assert_eq!(endpoint.failover(&backup, now, consent_timeout), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_endpoint_failover_ignores_stale_backup_pair() -> Result<()> {
+    let primary = FourTuple {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "192.168.1.2:5000".parse().unwrap(),
+    };
+    let backup = FourTuple {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "10.0.0.2:6000".parse().unwrap(),
+    };
+    let now = Instant::now();
+
+    let mut endpoint = Endpoint::new(1, Registry::new().build(""));
+    endpoint.add_transport(new_established_transport(primary)?);
+    endpoint.add_backup_four_tuple(backup, now);
+
+    assert_eq!(
+        endpoint.failover(
+            &primary,
+            now + Duration::from_secs(31),
+            Duration::from_secs(30)
+        ),
+        None
+    );
+    assert!(endpoint.has_transport(&primary));
+
+    Ok(())
+}
+
+fn get_pli_media_ssrcs(endpoint: &mut Endpoint) -> Vec<u32> {
+    endpoint
+        .take_pending_rtcp_packets()
+        .iter()
+        .filter_map(|packet| {
+            packet
+                .as_any()
+                .downcast_ref::<PictureLossIndication>()
+                .map(|pli| pli.media_ssrc)
+        })
+        .collect()
+}
+
+#[test]
+fn test_endpoint_force_keyframe_bypasses_suppression() -> Result<()> {
+    let mut endpoint = new_endpoint_with_codecs(1, &[("video/VP8", 96, "")]);
+    endpoint.get_mut_transceivers().get_mut("0").unwrap().sender = Some(RTCRtpSender {
+        cname: "cname".to_string(),
+        msid: MediaStreamId::parse("stream", "track"),
+        ssrcs: vec![1111, 2222],
+        ssrc_attributes: HashMap::new(),
+        // the RTX stream is not requested for keyframes
+        ssrc_groups: vec![SsrcGroup {
+            name: "FID".to_string(),
+            ssrcs: vec![1111, 2222],
+        }],
+        rids: vec![],
+    });
+
+    let now = Instant::now();
+    assert!(endpoint.request_keyframe("0", now)?);
+    assert_eq!(get_pli_media_ssrcs(&mut endpoint), vec![1111]);
+
+    // a repeated request within the suppression window is not sent
+    let later = now + Duration::from_millis(100);
+    assert!(!endpoint.request_keyframe("0", later)?);
+    assert!(get_pli_media_ssrcs(&mut endpoint).is_empty());
+
+    // but a forced one is
+    endpoint.force_keyframe("0", later)?;
+    assert_eq!(get_pli_media_ssrcs(&mut endpoint), vec![1111]);
+
+    // and it restarts the suppression window
+    assert!(!endpoint.request_keyframe("0", now + KEYFRAME_REQUEST_INTERVAL)?);
+    assert!(endpoint.request_keyframe("0", later + KEYFRAME_REQUEST_INTERVAL)?);
+
+    assert!(endpoint.force_keyframe("1", later).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_endpoint_take_pending_rtcp_message() -> Result<()> {
+    let mut endpoint = new_endpoint_with_codecs(1, &[("video/VP8", 96, "")]);
+    endpoint.get_mut_transceivers().get_mut("0").unwrap().sender = Some(RTCRtpSender {
+        cname: "cname".to_string(),
+        msid: MediaStreamId::parse("stream", "track"),
+        ssrcs: vec![1111],
+        ssrc_attributes: HashMap::new(),
+        ssrc_groups: vec![],
+        rids: vec![],
+    });
+    let now = Instant::now();
+    assert!(endpoint.take_pending_rtcp_message(now).is_none());
+
+    // requests are dropped without a transport ready to send them
+    endpoint.force_keyframe("0", now)?;
+    assert!(endpoint.has_pending_rtcp_packets());
+    assert!(endpoint.take_pending_rtcp_message(now).is_none());
+    assert!(!endpoint.has_pending_rtcp_packets());
+
+    let four_tuple = FourTuple {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "192.168.1.2:5000".parse().unwrap(),
+    };
+    endpoint.add_transport(new_established_transport(four_tuple)?);
+    endpoint.force_keyframe("0", now)?;
+    let message = endpoint.take_pending_rtcp_message(now).unwrap();
+    assert_eq!(FourTuple::from(&message.transport), four_tuple);
+    assert!(matches!(
+        message.message,
+        MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)) if rtcp_packets.len() == 1
+    ));
+    assert!(!endpoint.has_pending_rtcp_packets());
+
+    Ok(())
+}
+
+#[test]
+fn test_endpoint_generic_nack_without_pli_skips_keyframe_requests() -> Result<()> {
+    let mut endpoint = new_endpoint_with_codecs(1, &[("video/VP8", 96, "")]);
+    let transceiver = endpoint.get_mut_transceivers().get_mut("0").unwrap();
+    transceiver.sender = Some(RTCRtpSender {
+        cname: "cname".to_string(),
+        msid: MediaStreamId::parse("stream", "track"),
+        ssrcs: vec![1111],
+        ssrc_attributes: HashMap::new(),
+        ssrc_groups: vec![],
+        rids: vec![],
+    });
+    // a=rtcp-fb:96 nack
+    let capability = &mut transceiver.rtp_params.codecs[0].capability;
+    capability.rtcp_feedbacks = vec![RTCPFeedback {
+        typ: "nack".to_string(),
+        parameter: String::new(),
+    }];
+    assert!(capability.has_generic_nack());
+    assert!(!capability.has_nack_pli());
+
+    let now = Instant::now();
+    assert!(!endpoint.request_keyframe("0", now)?);
+    endpoint.force_keyframe("0", now)?;
+    assert!(get_pli_media_ssrcs(&mut endpoint).is_empty());
+
+    // a=rtcp-fb:96 nack pli
+    let capability = &mut endpoint
+        .get_mut_transceivers()
+        .get_mut("0")
+        .unwrap()
+        .rtp_params
+        .codecs[0]
+        .capability;
+    capability.rtcp_feedbacks.push(RTCPFeedback {
+        typ: "nack".to_string(),
+        parameter: "pli".to_string(),
+    });
+    assert!(capability.has_nack_pli());
+    assert!(endpoint.request_keyframe("0", now)?);
+    assert_eq!(get_pli_media_ssrcs(&mut endpoint), vec![1111]);
+
+    Ok(())
+}
+
+/// subscribe subscribes the endpoint to a video stream of each of the publishers by
+/// Session::subscribe, and returns how many times the endpoint's transceiver storage grows
+fn subscribe(endpoint: Endpoint, publishers: u64) -> usize {
+    let subscriber = endpoint.endpoint_id();
+    let mut session = Session::new(
+        SessionConfig::new(
+            Arc::new(ServerConfig::new(vec![])),
+            "127.0.0.1:3478".parse().unwrap(),
+        ),
+        1,
+    );
+    session.get_mut_endpoints().insert(subscriber, endpoint);
+
+    let mut reallocations = 0;
+    for publisher in 100..100 + publishers {
+        let mut publisher_endpoint = Endpoint::new(publisher, Registry::new().build(""));
+        publisher_endpoint.get_mut_mids().push("0".to_string());
+        publisher_endpoint.get_mut_transceivers().insert(
+            "0".to_string(),
+            RTCRtpTransceiver {
+                mid: "0".to_string(),
+                sender: None,
+                direction: RTCRtpTransceiverDirection::Recvonly,
+                current_direction: RTCRtpTransceiverDirection::Unspecified,
+                direction_override: None,
+                rtp_params: RTCRtpParameters::default(),
+                kind: RTPCodecType::Video,
+                negotiated_header_extensions: vec![],
+                selected_rid: None,
+            },
+        );
+        session
+            .get_mut_endpoints()
+            .insert(publisher, publisher_endpoint);
+
+        let endpoint = session.get_endpoint(&subscriber).unwrap();
+        let capacity = (endpoint.mids.capacity(), endpoint.transceivers.capacity());
+        assert!(session.subscribe(subscriber, publisher).unwrap());
+        let endpoint = session.get_endpoint(&subscriber).unwrap();
+        if capacity != (endpoint.mids.capacity(), endpoint.transceivers.capacity()) {
+            reallocations += 1;
+        }
+    }
+    assert_eq!(
+        session
+            .get_endpoint(&subscriber)
+            .unwrap()
+            .get_transceivers()
+            .len(),
+        publishers as usize
+    );
+    reallocations
+}
+
+#[test]
+fn test_endpoint_reserve_transceivers() {
+    let cold = Endpoint::new(1, Registry::new().build(""));
+    assert!(subscribe(cold, 64) > 0);
+
+    let mut warm = Endpoint::new(2, Registry::new().build(""));
+    warm.reserve_transceivers(64);
+    assert_eq!(subscribe(warm, 64), 0);
+}
+
+#[test]
+fn test_endpoint_forwards_old_svc_layers_within_grace_period() {
+    use crate::endpoint::dependency_descriptor::{MaxSvcLayers, SvcLayer};
+    use crate::endpoint::marker_rewriter::MarkerRewriter;
+
+    let grace_period = Duration::from_millis(100);
+    let now = Instant::now();
+    let (s0, s1, s2) = (
+        SvcLayer {
+            spatial_id: 0,
+            temporal_id: 0,
+        },
+        SvcLayer {
+            spatial_id: 1,
+            temporal_id: 0,
+        },
+        SvcLayer {
+            spatial_id: 2,
+            temporal_id: 0,
+        },
+    );
+    let mut endpoint = Endpoint::new(1, Registry::new().build(""));
+    endpoint.set_max_svc_layers(
+        "video/VP9",
+        Some(MaxSvcLayers {
+            max_spatial_layer: 1,
+            max_temporal_layer: 0,
+        }),
+        now,
+    );
+    // switch from S1 down to S0 after the first grace period
+    let switched_at = now + Duration::from_secs(1);
+    endpoint.set_max_svc_layers(
+        "video/vp9",
+        Some(MaxSvcLayers {
+            max_spatial_layer: 0,
+            max_temporal_layer: 0,
+        }),
+        switched_at,
+    );
+
+    let exceeds = |layer, elapsed| {
+        endpoint.exceeds_max_svc_layers("video/VP9", layer, switched_at + elapsed, grace_period)
+    };
+    assert!(!exceeds(s0, Duration::ZERO));
+    // in-flight packets of the old layer are forwarded within the grace period
+    assert!(!exceeds(s1, Duration::from_millis(50)));
+    // but not the layers above the old cap
+    assert!(exceeds(s2, Duration::from_millis(50)));
+    // and they are dropped after it
+    assert!(exceeds(s1, grace_period));
+    assert!(!exceeds(s0, grace_period));
+
+    // forwarded old layer packets are rewritten in sequence with the ones of the new layer
+    let mut marker_rewriter = MarkerRewriter::default();
+    let mut forwarded = vec![];
+    for (sequence_number, layer, elapsed) in [
+        (100, s0, 0),
+        (101, s1, 50),
+        (102, s0, 150),
+        (103, s1, 150),
+        (104, s0, 200),
+    ] {
+        let rtp_packet = rtp::packet::Packet {
+            header: rtp::header::Header {
+                sequence_number,
+                marker: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        if exceeds(layer, Duration::from_millis(elapsed)) {
+            forwarded.extend(marker_rewriter.skip(&rtp_packet));
+        } else {
+            forwarded.extend(marker_rewriter.forward(rtp_packet));
+        }
+    }
+    let sequence_numbers: Vec<u16> = forwarded.iter().map(|p| p.header.sequence_number).collect();
+    assert_eq!(sequence_numbers, vec![100, 101, 102, 103]);
+}
+
+#[test]
+fn test_transport_maps_rtx_to_repaired_simulcast_layer() {
+    let mut transport = new_transport(FourTuple {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "127.0.0.1:5000".parse().unwrap(),
+    });
+    // RTX packet of the low layer arriving before any of its media packets
+    transport.set_simulcast_rid(3, None, Some("l".to_string()));
+    assert_eq!(transport.get_simulcast_rid(3), Some("l"));
+    assert_eq!(transport.get_repaired_ssrc(3), None);
+
+    transport.set_simulcast_rid(1, Some("h".to_string()), None);
+    transport.set_simulcast_rid(2, Some("l".to_string()), None);
+    transport.set_simulcast_rid(4, None, Some("h".to_string()));
+    assert_eq!(transport.get_repaired_ssrc(3), Some(2));
+    assert_eq!(transport.get_repaired_ssrc(4), Some(1));
+    // media streams are not RTX streams
+    assert_eq!(transport.get_repaired_ssrc(1), None);
+
+    for (ssrc, sequence_number) in [(2, 1), (3, 1)] {
+        transport.get_mut_loss_monitor(ssrc).update(sequence_number);
+    }
+    let mut stats = transport.get_inbound_rtp_stats();
+    stats.sort_by_key(|stats| stats.ssrc);
+    let rids: Vec<(SSRC, Option<String>)> = stats
+        .into_iter()
+        .map(|stats| (stats.ssrc, stats.rid))
+        .collect();
+    assert_eq!(
+        rids,
+        vec![(2, Some("l".to_string())), (3, Some("l".to_string()))]
+    );
+}
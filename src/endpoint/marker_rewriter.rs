@@ -0,0 +1,58 @@
+/// MarkerRewriter keeps the marker bit of a forwarded video stream at the end of each frame.
+///
+/// For video the marker bit is set on the last packet of a frame (RFC 3551 section 4.1).
+/// A packet without marker is held until the next packet of the stream tells whether its
+/// frame continues, so if the marker-bearing packet is not forwarded, the held packet is
+/// released as the last one of its frame with the marker set.
+///
+/// Packets of SVC layers above the subscriber's cap are skipped rather than dropped, so the
+/// sequence numbers of the forwarded packets stay continuous.
+#[derive(Debug, Default)]
+pub(crate) struct MarkerRewriter {
+    pending: Option<rtp::packet::Packet>,
+    // number of packets skipped so far, subtracted from sequence numbers of forwarded packets
+    skipped: u16,
+}
+
+impl MarkerRewriter {
+    /// forward returns the packets to send once rtp_packet is forwarded
+    pub(crate) fn forward(
+        &mut self,
+        mut rtp_packet: rtp::packet::Packet,
+    ) -> Vec<rtp::packet::Packet> {
+        rtp_packet.header.sequence_number =
+            rtp_packet.header.sequence_number.wrapping_sub(self.skipped);
+        let mut rtp_packets = self.release(rtp_packet.header.timestamp, false);
+        if rtp_packet.header.marker {
+            rtp_packets.push(rtp_packet);
+        } else {
+            self.pending = Some(rtp_packet);
+        }
+        rtp_packets
+    }
+
+    /// drop returns the packets to send once rtp_packet is not forwarded
+    pub(crate) fn drop(&mut self, rtp_packet: &rtp::packet::Packet) -> Vec<rtp::packet::Packet> {
+        self.release(rtp_packet.header.timestamp, rtp_packet.header.marker)
+    }
+
+    /// skip returns the packets to send once rtp_packet is intentionally not forwarded,
+    /// the following packets are renumbered to close the gap it leaves
+    pub(crate) fn skip(&mut self, rtp_packet: &rtp::packet::Packet) -> Vec<rtp::packet::Packet> {
+        self.skipped = self.skipped.wrapping_add(1);
+        self.drop(rtp_packet)
+    }
+
+    /// release returns the held packet if its frame ends, either by a packet of another frame
+    /// or by the dropped marker-bearing packet of its own frame
+    fn release(&mut self, timestamp: u32, end_of_frame: bool) -> Vec<rtp::packet::Packet> {
+        let Some(mut pending) = self.pending.take() else {
+            return vec![];
+        };
+
+        if pending.header.timestamp != timestamp || end_of_frame {
+            pending.header.marker = true;
+        }
+        vec![pending]
+    }
+}
@@ -0,0 +1,68 @@
+use super::marker_rewriter::*;
+
+fn new_video_packet(sequence_number: u16, timestamp: u32, marker: bool) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            ssrc: 1111,
+            sequence_number,
+            timestamp,
+            marker,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_marker_rewriter_keeps_frames_intact() {
+    let mut marker_rewriter = MarkerRewriter::default();
+    let mut forwarded = vec![];
+    for (sequence_number, timestamp, marker) in [(1, 3000, false), (2, 3000, true), (3, 6000, true)]
+    {
+        forwarded.extend(marker_rewriter.forward(new_video_packet(
+            sequence_number,
+            timestamp,
+            marker,
+        )));
+    }
+
+    let forwarded: Vec<(u16, bool)> = forwarded
+        .iter()
+        .map(|p| (p.header.sequence_number, p.header.marker))
+        .collect();
+    assert_eq!(forwarded, vec![(1, false), (2, true), (3, true)]);
+}
+
+#[test]
+fn test_marker_rewriter_marks_last_forwarded_packet_of_frame() {
+    let mut marker_rewriter = MarkerRewriter::default();
+    let mut forwarded = vec![];
+    forwarded.extend(marker_rewriter.forward(new_video_packet(1, 3000, false)));
+    forwarded.extend(marker_rewriter.forward(new_video_packet(2, 3000, false)));
+    // the final packet of the frame is dropped
+    forwarded.extend(marker_rewriter.drop(&new_video_packet(3, 3000, true)));
+
+    let last = forwarded.last().unwrap();
+    assert_eq!(last.header.sequence_number, 2);
+    assert!(last.header.marker);
+    assert!(!forwarded[0].header.marker);
+
+    // a dropped marker-less packet of the next frame also ends the held frame
+    forwarded.clear();
+    forwarded.extend(marker_rewriter.forward(new_video_packet(4, 6000, false)));
+    assert!(forwarded.is_empty());
+    forwarded.extend(marker_rewriter.drop(&new_video_packet(5, 6000, true)));
+    assert_eq!(forwarded.len(), 1);
+    assert_eq!(forwarded[0].header.sequence_number, 4);
+    assert!(forwarded[0].header.marker);
+
+    // next frame starts while the marker-bearing packet of the previous one was never seen
+    forwarded.clear();
+    forwarded.extend(marker_rewriter.forward(new_video_packet(6, 9000, false)));
+    forwarded.extend(marker_rewriter.forward(new_video_packet(8, 12000, true)));
+    let forwarded: Vec<(u16, bool)> = forwarded
+        .iter()
+        .map(|p| (p.header.sequence_number, p.header.marker))
+        .collect();
+    assert_eq!(forwarded, vec![(6, true), (8, true)]);
+}
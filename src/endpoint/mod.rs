@@ -1,11 +1,48 @@
+pub(crate) mod abs_send_time;
 pub(crate) mod candidate;
+pub(crate) mod dependency_descriptor;
+pub(crate) mod marker_rewriter;
+pub(crate) mod simulcast_rewriter;
+pub(crate) mod ssrc_allocator;
 pub(crate) mod transport;
 
-use crate::description::{rtp_transceiver::RTCRtpTransceiver, RTCSessionDescription};
+#[cfg(test)]
+mod abs_send_time_test;
+#[cfg(test)]
+mod candidate_test;
+#[cfg(test)]
+mod dependency_descriptor_test;
+#[cfg(test)]
+mod endpoint_test;
+#[cfg(test)]
+mod marker_rewriter_test;
+#[cfg(test)]
+mod simulcast_rewriter_test;
+#[cfg(test)]
+mod ssrc_allocator_test;
+
+use crate::description::{
+    fmtp::vendor::{bitrate_hints, BitrateHints},
+    rtp_codec::{codec_parameters_fuzzy_search, CodecMatch, RTCRtpCodecParameters, RTPCodecType},
+    rtp_transceiver::{PayloadType, RTCRtpTransceiver, SSRC},
+    signaling_state::RTCSignalingState,
+    LocalCandidate, RTCSessionDescription,
+};
+use crate::endpoint::candidate::RTCIceParameters;
+use crate::endpoint::dependency_descriptor::{MaxSvcLayers, SvcLayer};
+use crate::endpoint::ssrc_allocator::SsrcAllocator;
 use crate::endpoint::transport::Transport;
 use crate::interceptors::Interceptor;
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
 use crate::types::{EndpointId, FourTuple, Mid};
+use retty::transport::TransportContext;
+use rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use shared::error::{Error, Result};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// KEYFRAME_REQUEST_INTERVAL is the window in which repeated keyframe requests of a stream are suppressed
+pub(crate) const KEYFRAME_REQUEST_INTERVAL: Duration = Duration::from_millis(500);
 
 pub(crate) struct Endpoint {
     endpoint_id: EndpointId,
@@ -14,11 +51,39 @@ pub(crate) struct Endpoint {
     is_renegotiation_needed: bool,
     remote_description: Option<RTCSessionDescription>,
     local_description: Option<RTCSessionDescription>,
+    // local description applied before the pending local offer, restored on rollback
+    stable_local_description: Option<RTCSessionDescription>,
+    signaling_state: RTCSignalingState,
+    // whether the remote advertises a=ice-lite, which can't reach the ice-lite SFU
+    remote_is_lite: bool,
+    // whether the remote advertises a=extmap-allow-mixed, i.e., accepts two-byte header extensions
+    // mixed with one-byte ones
+    extmap_allow_mixed: bool,
+    // ICE parameters of a pending ICE restart, which the next local description advertises
+    ice_restart_params: Option<RTCIceParameters>,
+    // local candidates added after gathering, which later descriptions advertise as well
+    trickled_candidates: Vec<LocalCandidate>,
+    // a=candidate lines of trickled candidates not yet signaled out-of-band
+    pending_candidate_lines: Vec<String>,
 
     transports: HashMap<FourTuple, Transport>,
+    // validated candidate pairs with the time of their last connectivity check,
+    // which the transport can fail over to under BUNDLE
+    backup_four_tuples: HashMap<FourTuple, Instant>,
 
     mids: Vec<Mid>,
     transceivers: HashMap<Mid, RTCRtpTransceiver>,
+    // outbound ssrcs of the streams forwarded from other endpoints
+    ssrc_allocator: SsrcAllocator,
+    // caps of SVC layers forwarded to this endpoint keyed by lowercase codec mime type
+    max_svc_layers: HashMap<String, MaxSvcLayers>,
+    // previous caps of SVC layers and the time they were switched, keyed as max_svc_layers
+    max_svc_layers_switches: HashMap<String, (Option<MaxSvcLayers>, Instant)>,
+
+    // time of the last keyframe request sent upstream for each remote ssrc
+    last_keyframe_requests: HashMap<SSRC, Instant>,
+    // RTCP packets generated outside of interceptors, sent on next timeout
+    pending_rtcp_packets: Vec<Box<dyn rtcp::packet::Packet>>,
 }
 
 impl Endpoint {
@@ -30,11 +95,25 @@ impl Endpoint {
             is_renegotiation_needed: false,
             remote_description: None,
             local_description: None,
+            stable_local_description: None,
+            signaling_state: RTCSignalingState::Stable,
+            remote_is_lite: false,
+            extmap_allow_mixed: false,
+            ice_restart_params: None,
+            trickled_candidates: vec![],
+            pending_candidate_lines: vec![],
 
             transports: HashMap::new(),
+            backup_four_tuples: HashMap::new(),
 
             mids: vec![],
             transceivers: HashMap::new(),
+            ssrc_allocator: SsrcAllocator::new(endpoint_id),
+            max_svc_layers: HashMap::new(),
+            max_svc_layers_switches: HashMap::new(),
+
+            last_keyframe_requests: HashMap::new(),
+            pending_rtcp_packets: vec![],
         }
     }
 
@@ -50,6 +129,80 @@ impl Endpoint {
         self.transports.remove(four_tuple)
     }
 
+    /// migrate moves the most recently active established transport to new_four_tuple,
+    /// preserving its DTLS, SCTP and SRTP states, and returns its previous four_tuple
+    pub(crate) fn migrate(&mut self, new_four_tuple: FourTuple) -> Option<FourTuple> {
+        if self.transports.contains_key(&new_four_tuple) {
+            return None;
+        }
+
+        let old_four_tuple = self
+            .transports
+            .values()
+            .filter(|transport| {
+                transport.is_established()
+                    && transport.four_tuple().local_addr == new_four_tuple.local_addr
+            })
+            .max_by_key(|transport| transport.last_activity())
+            .map(|transport| *transport.four_tuple())?;
+
+        self.move_transport(old_four_tuple, new_four_tuple)?;
+        Some(old_four_tuple)
+    }
+
+    /// add_backup_four_tuple records a validated candidate pair other than the selected one
+    pub(crate) fn add_backup_four_tuple(&mut self, four_tuple: FourTuple, now: Instant) {
+        if !self.transports.contains_key(&four_tuple) {
+            self.backup_four_tuples.insert(four_tuple, now);
+        }
+    }
+
+    /// failover moves the established transport of failed_four_tuple to the most recently
+    /// validated backup pair within consent_timeout, so all bundled media re-routes to it,
+    /// and returns the new four_tuple
+    pub(crate) fn failover(
+        &mut self,
+        failed_four_tuple: &FourTuple,
+        now: Instant,
+        consent_timeout: Duration,
+    ) -> Option<FourTuple> {
+        self.backup_four_tuples
+            .retain(|_, last_consent| now.duration_since(*last_consent) < consent_timeout);
+
+        if !self
+            .transports
+            .get(failed_four_tuple)
+            .is_some_and(|transport| transport.is_established())
+        {
+            return None;
+        }
+
+        let new_four_tuple = self
+            .backup_four_tuples
+            .iter()
+            .filter(|(four_tuple, _)| {
+                four_tuple.local_addr == failed_four_tuple.local_addr
+                    && !self.transports.contains_key(four_tuple)
+            })
+            .max_by_key(|(_, &last_consent)| last_consent)
+            .map(|(&four_tuple, _)| four_tuple)?;
+        self.move_transport(*failed_four_tuple, new_four_tuple)?;
+        Some(new_four_tuple)
+    }
+
+    fn move_transport(
+        &mut self,
+        old_four_tuple: FourTuple,
+        new_four_tuple: FourTuple,
+    ) -> Option<()> {
+        let mut transport = self.transports.remove(&old_four_tuple)?;
+        self.backup_four_tuples.remove(&new_four_tuple);
+        transport.set_four_tuple(new_four_tuple);
+        transport.keep_alive();
+        self.transports.insert(new_four_tuple, transport);
+        Some(())
+    }
+
     pub(crate) fn has_transport(&self, four_tuple: &FourTuple) -> bool {
         self.transports.contains_key(four_tuple)
     }
@@ -66,6 +219,18 @@ impl Endpoint {
         &mut self.interceptor
     }
 
+    /// update_interceptor_clock_rates passes the clock rates of the negotiated codecs of all
+    /// transceivers to the interceptor once the endpoint negotiates, keyed by payload type
+    pub(crate) fn update_interceptor_clock_rates(&mut self) {
+        let clock_rates: HashMap<PayloadType, u32> = self
+            .transceivers
+            .values()
+            .flat_map(|transceiver| transceiver.rtp_params.codecs.iter())
+            .map(|codec| (codec.payload_type, codec.capability.clock_rate))
+            .collect();
+        self.interceptor.set_clock_rates(&clock_rates);
+    }
+
     pub(crate) fn get_mids(&self) -> &Vec<Mid> {
         &self.mids
     }
@@ -74,6 +239,14 @@ impl Endpoint {
         &mut self.mids
     }
 
+    /// reserve_transceivers pre-allocates storage for at least additional more transceivers and
+    /// their outbound SSRCs, so subscribing to other endpoints' streams reuses it until exhausted
+    pub(crate) fn reserve_transceivers(&mut self, additional: usize) {
+        self.mids.reserve(additional);
+        self.transceivers.reserve(additional);
+        self.ssrc_allocator.reserve_capacity(additional);
+    }
+
     pub(crate) fn get_transceivers(&self) -> &HashMap<Mid, RTCRtpTransceiver> {
         &self.transceivers
     }
@@ -82,12 +255,308 @@ impl Endpoint {
         &mut self.transceivers
     }
 
+    /// get_bitrate_hints returns the bitrate bounds hinted by vendor fmtp parameters of the first
+    /// codec with any in the transceiver with mid, e.g., x-google-max-bitrate of the remote offer
+    pub(crate) fn get_bitrate_hints(&self, mid: &str) -> Option<BitrateHints> {
+        self.transceivers
+            .get(mid)?
+            .rtp_params
+            .codecs
+            .iter()
+            .map(|codec| {
+                bitrate_hints(&codec.capability.mime_type, &codec.capability.sdp_fmtp_line)
+            })
+            .find(|bitrate_hints| !bitrate_hints.is_empty())
+    }
+
+    /// get_stream_id returns the id of the MediaStream which the track of the transceiver with mid
+    /// belongs to by its msid, either received from the remote or forwarded from a publisher
+    pub(crate) fn get_stream_id(&self, mid: &str) -> Option<&str> {
+        let sender = self.transceivers.get(mid)?.sender.as_ref()?;
+        (!sender.msid.stream_id.is_empty()).then_some(sender.msid.stream_id.as_str())
+    }
+
+    /// get_negotiated_header_extensions returns the negotiated header extension id to uri map of the transceiver with mid
+    pub(crate) fn get_negotiated_header_extensions(
+        &self,
+        mid: &str,
+    ) -> Option<HashMap<isize, String>> {
+        self.transceivers
+            .get(mid)
+            .map(|transceiver| transceiver.negotiated_header_extensions())
+    }
+
+    pub(crate) fn get_ssrc_allocator(&self) -> &SsrcAllocator {
+        &self.ssrc_allocator
+    }
+
+    pub(crate) fn get_mut_ssrc_allocator(&mut self) -> &mut SsrcAllocator {
+        &mut self.ssrc_allocator
+    }
+
+    /// set_max_svc_layers caps the SVC layers of the codec forwarded to this endpoint,
+    /// None removes the cap, the previous cap is kept to forward in-flight packets
+    pub(crate) fn set_max_svc_layers(
+        &mut self,
+        mime_type: &str,
+        max_svc_layers: Option<MaxSvcLayers>,
+        now: Instant,
+    ) {
+        let mime_type = mime_type.to_lowercase();
+        let previous = if let Some(max_svc_layers) = max_svc_layers {
+            self.max_svc_layers
+                .insert(mime_type.clone(), max_svc_layers)
+        } else {
+            self.max_svc_layers.remove(&mime_type)
+        };
+        if previous != max_svc_layers {
+            self.max_svc_layers_switches
+                .insert(mime_type, (previous, now));
+        }
+    }
+
+    pub(crate) fn get_max_svc_layers(&self, mime_type: &str) -> Option<MaxSvcLayers> {
+        self.max_svc_layers.get(&mime_type.to_lowercase()).copied()
+    }
+
+    /// exceeds_max_svc_layers returns true if the layer is above the cap of the codec, while
+    /// within grace_period after a switch, layers still within the previous cap are not
+    /// exceeding, so that packets of the old layers in flight don't leave a gap
+    pub(crate) fn exceeds_max_svc_layers(
+        &self,
+        mime_type: &str,
+        layer: SvcLayer,
+        now: Instant,
+        grace_period: Duration,
+    ) -> bool {
+        let mime_type = mime_type.to_lowercase();
+        if !self
+            .max_svc_layers
+            .get(&mime_type)
+            .is_some_and(|max_svc_layers| max_svc_layers.exceeds(layer))
+        {
+            return false;
+        }
+
+        match self.max_svc_layers_switches.get(&mime_type) {
+            Some((previous, switched_at)) if now < *switched_at + grace_period => {
+                previous.is_some_and(|max_svc_layers| max_svc_layers.exceeds(layer))
+            }
+            _ => true,
+        }
+    }
+
+    /// is_subscribed_to returns true if any stream of the publisher is forwarded to this endpoint
+    pub(crate) fn is_subscribed_to(&self, publisher: EndpointId) -> bool {
+        let prefix = format!("{}-", publisher);
+        self.transceivers.keys().any(|mid| mid.starts_with(&prefix))
+    }
+
     pub(crate) fn get_mut_mids_and_transceivers(
         &mut self,
     ) -> (&mut Vec<Mid>, &mut HashMap<Mid, RTCRtpTransceiver>) {
         (&mut self.mids, &mut self.transceivers)
     }
 
+    /// get_codec_by_payload_type returns the codec negotiated with the payload type
+    pub(crate) fn get_codec_by_payload_type(
+        &self,
+        payload_type: PayloadType,
+    ) -> Option<(&RTCRtpCodecParameters, RTPCodecType)> {
+        self.transceivers.values().find_map(|transceiver| {
+            transceiver
+                .rtp_params
+                .codecs
+                .iter()
+                .find(|codec| codec.payload_type == payload_type)
+                .map(|codec| (codec, transceiver.kind))
+        })
+    }
+
+    /// get_negotiated_header_extension_id returns the id negotiated for the header extension uri
+    /// by any transceiver of the kind
+    pub(crate) fn get_negotiated_header_extension_id(
+        &self,
+        kind: RTPCodecType,
+        uri: &str,
+    ) -> Option<u8> {
+        self.transceivers
+            .values()
+            .filter(|transceiver| transceiver.kind == kind)
+            .flat_map(|transceiver| transceiver.negotiated_header_extensions.iter())
+            .find(|ext| ext.uri == uri)
+            .map(|ext| ext.id as u8)
+    }
+
+    /// remap_payload_type translates the payload type negotiated by this endpoint to the payload type
+    /// which other endpoint negotiated for the matching codec, returns None if there is no matching codec
+    pub(crate) fn remap_payload_type(
+        &self,
+        payload_type: PayloadType,
+        other: &Endpoint,
+    ) -> Option<PayloadType> {
+        let (codec, kind) = self.get_codec_by_payload_type(payload_type)?;
+        let other_codecs: Vec<RTCRtpCodecParameters> = other
+            .transceivers
+            .values()
+            .filter(|transceiver| transceiver.kind == kind)
+            .flat_map(|transceiver| transceiver.rtp_params.codecs.iter().cloned())
+            .collect();
+
+        match codec_parameters_fuzzy_search(codec, &other_codecs) {
+            (other_codec, CodecMatch::Exact | CodecMatch::Partial) => {
+                Some(other_codec.payload_type)
+            }
+            (_, CodecMatch::None) => None,
+        }
+    }
+
+    /// request_keyframe sends PLI for the remote streams of the transceiver with mid,
+    /// except ones already requested within KEYFRAME_REQUEST_INTERVAL,
+    /// returns true if any request is sent
+    pub(crate) fn request_keyframe(&mut self, mid: &str, now: Instant) -> Result<bool> {
+        let mut requested = false;
+        let ssrcs = self.get_remote_media_ssrcs(mid)?;
+        if !self.supports_pli(mid) {
+            return Ok(requested);
+        }
+        for ssrc in ssrcs {
+            if self
+                .last_keyframe_requests
+                .get(&ssrc)
+                .is_none_or(|&last| now.duration_since(last) >= KEYFRAME_REQUEST_INTERVAL)
+            {
+                self.queue_keyframe_request(ssrc, now);
+                requested = true;
+            }
+        }
+        Ok(requested)
+    }
+
+    /// force_keyframe sends PLI for the remote streams of the transceiver with mid immediately,
+    /// regardless of KEYFRAME_REQUEST_INTERVAL, e.g., when a recording starts
+    pub(crate) fn force_keyframe(&mut self, mid: &str, now: Instant) -> Result<()> {
+        let ssrcs = self.get_remote_media_ssrcs(mid)?;
+        if !self.supports_pli(mid) {
+            return Ok(());
+        }
+        for ssrc in ssrcs {
+            self.queue_keyframe_request(ssrc, now);
+        }
+        Ok(())
+    }
+
+    /// force_layer_keyframe sends PLI for the remote streams of the simulcast layer of rid of the
+    /// transceiver with mid immediately, e.g., when a subscriber switches to the layer, or for all
+    /// its signaled streams if no packet of the layer was received yet
+    pub(crate) fn force_layer_keyframe(&mut self, mid: &str, rid: &str, now: Instant) {
+        if !self.supports_pli(mid) {
+            return;
+        }
+        let mut ssrcs: Vec<SSRC> = self
+            .transports
+            .values()
+            .flat_map(|transport| transport.get_simulcast_ssrcs(rid))
+            .collect();
+        if ssrcs.is_empty() {
+            ssrcs = self.get_remote_media_ssrcs(mid).unwrap_or_default();
+        }
+        for ssrc in ssrcs {
+            self.queue_keyframe_request(ssrc, now);
+        }
+    }
+
+    /// supports_pli returns false if the remote signals RTCP feedback of the transceiver's codecs
+    /// without "nack pli", e.g., only generic NACK, since PLI is not negotiated then. Codecs
+    /// without any RTCP feedback, e.g., as answered by default MediaConfig, still accept PLI
+    fn supports_pli(&self, mid: &str) -> bool {
+        let Some(transceiver) = self.transceivers.get(mid) else {
+            return false;
+        };
+        let codecs = &transceiver.rtp_params.codecs;
+        codecs
+            .iter()
+            .all(|codec| codec.capability.rtcp_feedbacks.is_empty())
+            || codecs.iter().any(|codec| codec.capability.has_nack_pli())
+    }
+
+    /// take_pending_rtcp_packets returns RTCP packets waiting to be sent to this endpoint
+    pub(crate) fn take_pending_rtcp_packets(&mut self) -> Vec<Box<dyn rtcp::packet::Packet>> {
+        std::mem::take(&mut self.pending_rtcp_packets)
+    }
+
+    /// has_pending_rtcp_packets returns true if RTCP packets are waiting to be sent to this endpoint
+    pub(crate) fn has_pending_rtcp_packets(&self) -> bool {
+        !self.pending_rtcp_packets.is_empty()
+    }
+
+    /// take_pending_rtcp_message returns the RTCP packets waiting to be sent to this endpoint, e.g.,
+    /// keyframe requests, as a message over the most recently active transport, None if there are
+    /// none, or no transport is ready to send them, in which case they are dropped
+    pub(crate) fn take_pending_rtcp_message(&mut self, now: Instant) -> Option<TaggedMessageEvent> {
+        let rtcp_packets = self.take_pending_rtcp_packets();
+        if rtcp_packets.is_empty() {
+            return None;
+        }
+        let Some(transport) = self
+            .transports
+            .values()
+            .filter(|transport| transport.is_local_srtp_context_ready())
+            .max_by_key(|transport| transport.last_activity())
+        else {
+            log::debug!(
+                "drop {} rtcp packets for endpoint {} without transport",
+                rtcp_packets.len(),
+                self.endpoint_id
+            );
+            return None;
+        };
+        let four_tuple = transport.four_tuple();
+        Some(TaggedMessageEvent {
+            now,
+            transport: TransportContext {
+                local_addr: four_tuple.local_addr,
+                peer_addr: four_tuple.peer_addr,
+                ecn: None,
+            },
+            message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)),
+        })
+    }
+
+    fn queue_keyframe_request(&mut self, ssrc: SSRC, now: Instant) {
+        self.pending_rtcp_packets
+            .push(Box::new(PictureLossIndication {
+                sender_ssrc: 0,
+                media_ssrc: ssrc,
+            }));
+        self.last_keyframe_requests.insert(ssrc, now);
+    }
+
+    /// get_remote_media_ssrcs returns ssrcs the remote sends for the transceiver with mid,
+    /// without the repair streams, e.g., RTX or FEC, in ssrc groups
+    fn get_remote_media_ssrcs(&self, mid: &str) -> Result<Vec<SSRC>> {
+        let sender = self
+            .transceivers
+            .get(mid)
+            .and_then(|transceiver| transceiver.sender.as_ref())
+            .ok_or(Error::Other(format!(
+                "can't find remote sender of transceiver with mid {}",
+                mid
+            )))?;
+
+        Ok(sender
+            .ssrcs
+            .iter()
+            .copied()
+            .filter(|ssrc| {
+                !sender
+                    .ssrc_groups
+                    .iter()
+                    .any(|group| group.ssrcs.iter().skip(1).any(|s| s == ssrc))
+            })
+            .collect())
+    }
+
     pub(crate) fn remote_description(&self) -> Option<&RTCSessionDescription> {
         self.remote_description.as_ref()
     }
@@ -104,6 +573,82 @@ impl Endpoint {
         self.local_description = Some(description);
     }
 
+    /// set_pending_local_description applies the local offer, and keeps the current local
+    /// description until the offer is answered or rolled back
+    pub(crate) fn set_pending_local_description(&mut self, description: RTCSessionDescription) {
+        if self.signaling_state == RTCSignalingState::Stable {
+            self.stable_local_description = self.local_description.take();
+        }
+        self.local_description = Some(description);
+        self.signaling_state = RTCSignalingState::HaveLocalOffer;
+    }
+
+    /// rollback discards the pending local offer and restores the local description before it
+    pub(crate) fn rollback(&mut self) -> Result<()> {
+        if self.signaling_state != RTCSignalingState::HaveLocalOffer {
+            return Err(Error::Other("ErrNoPendingDescription".to_string()));
+        }
+        self.local_description = self.stable_local_description.take();
+        self.signaling_state = RTCSignalingState::Stable;
+        // the changes the offer was to negotiate are still pending
+        self.is_renegotiation_needed = true;
+        Ok(())
+    }
+
+    pub(crate) fn signaling_state(&self) -> RTCSignalingState {
+        self.signaling_state
+    }
+
+    pub(crate) fn set_signaling_state(&mut self, signaling_state: RTCSignalingState) {
+        self.signaling_state = signaling_state;
+    }
+
+    pub(crate) fn remote_is_lite(&self) -> bool {
+        self.remote_is_lite
+    }
+
+    pub(crate) fn set_remote_is_lite(&mut self, remote_is_lite: bool) {
+        self.remote_is_lite = remote_is_lite;
+    }
+
+    pub(crate) fn extmap_allow_mixed(&self) -> bool {
+        self.extmap_allow_mixed
+    }
+
+    pub(crate) fn set_extmap_allow_mixed(&mut self, extmap_allow_mixed: bool) {
+        self.extmap_allow_mixed = extmap_allow_mixed;
+    }
+
+    pub(crate) fn ice_restart_params(&self) -> Option<&RTCIceParameters> {
+        self.ice_restart_params.as_ref()
+    }
+
+    pub(crate) fn set_ice_restart_params(&mut self, ice_restart_params: Option<RTCIceParameters>) {
+        self.ice_restart_params = ice_restart_params;
+    }
+
+    pub(crate) fn trickled_candidates(&self) -> &[LocalCandidate] {
+        &self.trickled_candidates
+    }
+
+    /// add_trickled_candidate keeps the local candidate with its a=candidate line to signal,
+    /// and returns false if it was added already
+    pub(crate) fn add_trickled_candidate(&mut self, candidate: LocalCandidate) -> bool {
+        if self.trickled_candidates.contains(&candidate) {
+            return false;
+        }
+        self.pending_candidate_lines
+            .push(format!("a=candidate:{}", candidate.marshal(1))); // 1: RTP
+        self.trickled_candidates.push(candidate);
+        true
+    }
+
+    /// take_pending_candidate_lines returns the a=candidate lines of the candidates trickled
+    /// since the last call
+    pub(crate) fn take_pending_candidate_lines(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.pending_candidate_lines)
+    }
+
     pub(crate) fn is_renegotiation_needed(&self) -> bool {
         self.is_renegotiation_needed
     }
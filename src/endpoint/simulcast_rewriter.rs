@@ -0,0 +1,125 @@
+use crate::description::rtp_transceiver::SSRC;
+use std::time::{Duration, Instant};
+
+/// VIDEO_CLOCK_RATE is the RTP clock rate of video codecs
+const VIDEO_CLOCK_RATE: u64 = 90000;
+
+/// SimulcastRewriter forwards the selected simulcast layer of a publisher's video as a single
+/// stream to the subscriber.
+///
+/// Each simulcast layer is a separate RTP stream, so forwarded packets are rewritten with the ssrc
+/// of the first forwarded layer, and with sequence numbers and timestamps continuing across layer
+/// switches. A newly selected layer takes over at the start of its next frame, until then, and at
+/// most for the grace period after the switch, the previous layer is still forwarded.
+#[derive(Debug)]
+pub(crate) struct SimulcastRewriter {
+    ssrc: SSRC,
+    // inbound ssrc of the forwarded layer, and offsets of its packets into the stream
+    source_ssrc: Option<SSRC>,
+    sequence_number_offset: u16,
+    timestamp_offset: u32,
+    // latest forwarded sequence number and timestamp, and when it was forwarded
+    last: Option<(u16, u32, Instant)>,
+    // ssrc and first seen timestamp of the newly selected layer waiting for its next frame
+    pending: Option<(SSRC, u32)>,
+    // when the forwarded layer was found no longer selected
+    switched_at: Option<Instant>,
+}
+
+impl SimulcastRewriter {
+    pub(crate) fn new(ssrc: SSRC) -> Self {
+        Self {
+            ssrc,
+            source_ssrc: None,
+            sequence_number_offset: 0,
+            timestamp_offset: 0,
+            last: None,
+            pending: None,
+            switched_at: None,
+        }
+    }
+
+    /// is_forwarding returns true if the inbound stream of ssrc is the forwarded layer
+    pub(crate) fn is_forwarding(&self, ssrc: SSRC) -> bool {
+        self.source_ssrc == Some(ssrc)
+    }
+
+    /// rewrite returns the packet rewritten into the subscriber's stream, or None if it is not
+    /// forwarded, selected is true if the packet is of the layer selected by the subscriber
+    pub(crate) fn rewrite(
+        &mut self,
+        now: Instant,
+        rtp_packet: &rtp::packet::Packet,
+        selected: bool,
+        grace_period: Duration,
+    ) -> Option<rtp::packet::Packet> {
+        let header = &rtp_packet.header;
+        if self.source_ssrc == Some(header.ssrc) {
+            if selected {
+                self.switched_at = None;
+            } else if now.duration_since(*self.switched_at.get_or_insert(now)) >= grace_period {
+                return None;
+            }
+        } else {
+            if !selected {
+                return None;
+            }
+            if self.source_ssrc.is_some() {
+                match self.pending {
+                    Some((ssrc, timestamp)) if ssrc == header.ssrc => {
+                        if timestamp == header.timestamp {
+                            return None;
+                        }
+                    }
+                    _ => {
+                        self.pending = Some((header.ssrc, header.timestamp));
+                        return None;
+                    }
+                }
+            }
+            self.take_over(now, header);
+        }
+
+        let mut rtp_packet = rtp_packet.clone();
+        rtp_packet.header.ssrc = self.ssrc;
+        rtp_packet.header.sequence_number = rtp_packet
+            .header
+            .sequence_number
+            .wrapping_add(self.sequence_number_offset);
+        rtp_packet.header.timestamp = rtp_packet
+            .header
+            .timestamp
+            .wrapping_add(self.timestamp_offset);
+        let (sequence_number, timestamp) = (
+            rtp_packet.header.sequence_number,
+            rtp_packet.header.timestamp,
+        );
+        if self
+            .last
+            .is_none_or(|(last, _, _)| (sequence_number.wrapping_sub(last) as i16) > 0)
+        {
+            self.last = Some((sequence_number, timestamp, now));
+        }
+        Some(rtp_packet)
+    }
+
+    /// take_over switches the forwarded layer to the one of the header, whose packets continue
+    /// the stream, the timestamp advances by wall clock since the last forwarded packet
+    fn take_over(&mut self, now: Instant, header: &rtp::header::Header) {
+        let (sequence_number, timestamp) = match self.last {
+            Some((sequence_number, timestamp, last_sent)) => (
+                sequence_number.wrapping_add(1),
+                timestamp.wrapping_add(
+                    (now.duration_since(last_sent).as_millis() as u64 * VIDEO_CLOCK_RATE / 1000)
+                        .max(1) as u32,
+                ),
+            ),
+            None => (header.sequence_number, header.timestamp),
+        };
+        self.sequence_number_offset = sequence_number.wrapping_sub(header.sequence_number);
+        self.timestamp_offset = timestamp.wrapping_sub(header.timestamp);
+        self.source_ssrc = Some(header.ssrc);
+        self.pending = None;
+        self.switched_at = None;
+    }
+}
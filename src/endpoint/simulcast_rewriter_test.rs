@@ -0,0 +1,106 @@
+use super::simulcast_rewriter::*;
+use std::time::{Duration, Instant};
+
+fn new_layer_packet(ssrc: u32, sequence_number: u16, timestamp: u32) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            ssrc,
+            sequence_number,
+            timestamp,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_simulcast_rewriter_continues_stream_across_layers() {
+    let now = Instant::now();
+    let mut simulcast_rewriter = SimulcastRewriter::new(5555);
+    let mut rewrite = |at: Duration, ssrc, sequence_number, timestamp, selected| {
+        simulcast_rewriter
+            .rewrite(
+                now + at,
+                &new_layer_packet(ssrc, sequence_number, timestamp),
+                selected,
+                Duration::ZERO,
+            )
+            .map(|rtp_packet| {
+                (
+                    rtp_packet.header.ssrc,
+                    rtp_packet.header.sequence_number,
+                    rtp_packet.header.timestamp,
+                )
+            })
+    };
+
+    // the first selected layer is forwarded as is with the stream's ssrc
+    assert_eq!(
+        rewrite(Duration::ZERO, 1111, 100, 3000, true),
+        Some((5555, 100, 3000))
+    );
+    assert_eq!(rewrite(Duration::ZERO, 2222, 700, 9000, false), None);
+
+    // the newly selected layer waits for the start of its next frame
+    assert_eq!(
+        rewrite(Duration::from_millis(10), 2222, 701, 9000, true),
+        None
+    );
+    assert_eq!(
+        rewrite(Duration::from_millis(10), 1111, 101, 6000, false),
+        None
+    );
+    assert_eq!(
+        rewrite(Duration::from_millis(33), 2222, 702, 12000, true),
+        Some((5555, 101, 3000 + 33 * 90))
+    );
+    assert_eq!(
+        rewrite(Duration::from_millis(66), 2222, 703, 15000, true),
+        Some((5555, 102, 3000 + 33 * 90 + 3000))
+    );
+    // the previous layer is no longer forwarded once the new one takes over
+    assert_eq!(
+        rewrite(Duration::from_millis(66), 1111, 102, 9000, false),
+        None
+    );
+}
+
+#[test]
+fn test_simulcast_rewriter_forwards_previous_layer_within_grace_period() {
+    let now = Instant::now();
+    let grace_period = Duration::from_millis(100);
+    let mut simulcast_rewriter = SimulcastRewriter::new(5555);
+    let mut rewrite = |at: Duration, ssrc, sequence_number, timestamp, selected| {
+        simulcast_rewriter
+            .rewrite(
+                now + at,
+                &new_layer_packet(ssrc, sequence_number, timestamp),
+                selected,
+                grace_period,
+            )
+            .map(|rtp_packet| rtp_packet.header.sequence_number)
+    };
+
+    assert_eq!(rewrite(Duration::ZERO, 1111, 100, 3000, true), Some(100));
+    // the layer is switched, but the new one doesn't start a frame yet
+    assert_eq!(
+        rewrite(Duration::from_millis(10), 2222, 700, 9000, true),
+        None
+    );
+    assert_eq!(
+        rewrite(Duration::from_millis(20), 1111, 101, 6000, false),
+        Some(101)
+    );
+    assert_eq!(
+        rewrite(Duration::from_millis(110), 1111, 102, 9000, false),
+        Some(102)
+    );
+    assert_eq!(
+        rewrite(Duration::from_millis(130), 1111, 103, 12000, false),
+        None
+    );
+    assert_eq!(
+        rewrite(Duration::from_millis(140), 2222, 701, 12000, true),
+        Some(103)
+    );
+}
@@ -0,0 +1,92 @@
+use crate::description::rtp_transceiver::SSRC;
+use crate::types::EndpointId;
+use std::collections::{HashMap, HashSet};
+
+/// SsrcAllocator assigns outbound SSRCs of the streams forwarded to a subscriber endpoint.
+///
+/// An outbound SSRC is derived from the subscriber, the publisher and the publisher's SSRC,
+/// so the same stream gets the same SSRC whenever it is allocated, and it is probed further
+/// to avoid the subscriber's own SSRCs and the SSRCs allocated for other streams.
+#[derive(Debug, Default)]
+pub(crate) struct SsrcAllocator {
+    endpoint_id: EndpointId,
+    allocated: HashMap<(EndpointId, SSRC), SSRC>,
+    // publisher and its SSRC of each allocated outbound SSRC, e.g., to map feedback back
+    sources: HashMap<SSRC, (EndpointId, SSRC)>,
+    used: HashSet<SSRC>,
+}
+
+impl SsrcAllocator {
+    pub(crate) fn new(endpoint_id: EndpointId) -> Self {
+        Self {
+            endpoint_id,
+            ..Default::default()
+        }
+    }
+
+    /// reserve marks SSRCs of the subscriber's own streams as used
+    pub(crate) fn reserve(&mut self, ssrcs: &[SSRC]) {
+        self.used.extend(ssrcs);
+    }
+
+    /// reserve_capacity pre-allocates storage for at least additional more outbound SSRCs
+    pub(crate) fn reserve_capacity(&mut self, additional: usize) {
+        self.allocated.reserve(additional);
+        self.sources.reserve(additional);
+        self.used.reserve(additional);
+    }
+
+    /// get returns the outbound SSRC already allocated for the publisher's SSRC
+    pub(crate) fn get(&self, publisher: EndpointId, ssrc: SSRC) -> Option<SSRC> {
+        self.allocated.get(&(publisher, ssrc)).copied()
+    }
+
+    /// source returns the publisher and its SSRC which the outbound SSRC is allocated for
+    pub(crate) fn source(&self, outbound_ssrc: SSRC) -> Option<(EndpointId, SSRC)> {
+        self.sources.get(&outbound_ssrc).copied()
+    }
+
+    /// allocate returns the outbound SSRC for the publisher's SSRC, allocating it if needed
+    pub(crate) fn allocate(&mut self, publisher: EndpointId, ssrc: SSRC) -> SSRC {
+        if let Some(outbound_ssrc) = self.get(publisher, ssrc) {
+            return outbound_ssrc;
+        }
+
+        let mut seed = self.endpoint_id ^ publisher.rotate_left(32) ^ ((ssrc as u64) << 16);
+        let outbound_ssrc = loop {
+            let candidate = split_mix64(&mut seed) as SSRC;
+            if candidate != 0 && !self.used.contains(&candidate) {
+                break candidate;
+            }
+        };
+
+        self.used.insert(outbound_ssrc);
+        self.allocated.insert((publisher, ssrc), outbound_ssrc);
+        self.sources.insert(outbound_ssrc, (publisher, ssrc));
+        outbound_ssrc
+    }
+
+    /// release frees the outbound SSRCs allocated for the publisher's streams
+    pub(crate) fn release(&mut self, publisher: EndpointId) {
+        let (used, sources) = (&mut self.used, &mut self.sources);
+        self.allocated.retain(|&(endpoint_id, _), outbound_ssrc| {
+            if endpoint_id == publisher {
+                used.remove(outbound_ssrc);
+                sources.remove(outbound_ssrc);
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+/// split_mix64 is a deterministic pseudo random generator, which advances the state
+/// <https://prng.di.unimi.it/splitmix64.c>
+fn split_mix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
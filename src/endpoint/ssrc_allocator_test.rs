@@ -0,0 +1,57 @@
+use super::ssrc_allocator::*;
+use std::collections::HashSet;
+
+#[test]
+fn test_ssrc_allocator_is_deterministic() {
+    let mut first = SsrcAllocator::new(2);
+    let mut second = SsrcAllocator::new(2);
+    for ssrc in [1111, 2222, 3333] {
+        let outbound_ssrc = first.allocate(1, ssrc);
+        assert_ne!(outbound_ssrc, 0);
+        assert_eq!(outbound_ssrc, second.allocate(1, ssrc));
+        assert_eq!(outbound_ssrc, first.allocate(1, ssrc));
+        assert_eq!(Some(outbound_ssrc), first.get(1, ssrc));
+        assert_eq!(Some((1, ssrc)), first.source(outbound_ssrc));
+    }
+}
+
+#[test]
+fn test_ssrc_allocator_gives_subscribers_distinct_ssrcs() {
+    let publisher = 1;
+    // primary, RTX and FEC streams of the publisher
+    let source_ssrcs = [1111, 1112, 1113];
+
+    let mut subscriber2 = SsrcAllocator::new(2);
+    let mut subscriber3 = SsrcAllocator::new(3);
+    let ssrcs2: Vec<u32> = source_ssrcs
+        .iter()
+        .map(|&ssrc| subscriber2.allocate(publisher, ssrc))
+        .collect();
+    let ssrcs3: Vec<u32> = source_ssrcs
+        .iter()
+        .map(|&ssrc| subscriber3.allocate(publisher, ssrc))
+        .collect();
+
+    let mut all: HashSet<u32> = HashSet::new();
+    all.extend(&ssrcs2);
+    all.extend(&ssrcs3);
+    assert_eq!(all.len(), 2 * source_ssrcs.len());
+}
+
+#[test]
+fn test_ssrc_allocator_avoids_reserved_ssrcs() {
+    let mut ssrc_allocator = SsrcAllocator::new(2);
+    let outbound_ssrc = SsrcAllocator::new(2).allocate(1, 1111);
+
+    // the subscriber already sends with the ssrc which would be derived
+    ssrc_allocator.reserve(&[outbound_ssrc]);
+    let reallocated_ssrc = ssrc_allocator.allocate(1, 1111);
+    assert_ne!(reallocated_ssrc, outbound_ssrc);
+    assert_ne!(reallocated_ssrc, 0);
+
+    // released ssrcs can be derived again
+    ssrc_allocator.release(1);
+    assert_eq!(ssrc_allocator.get(1, 1111), None);
+    assert_eq!(ssrc_allocator.source(reallocated_ssrc), None);
+    assert_eq!(ssrc_allocator.allocate(1, 1111), reallocated_ssrc);
+}
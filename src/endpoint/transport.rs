@@ -1,14 +1,25 @@
+use crate::description::rtp_transceiver::SSRC;
 use crate::endpoint::candidate::Candidate;
-use crate::types::FourTuple;
+use crate::endpoint::dependency_descriptor::{
+    DependencyDescriptor, FrameDependencyStructure, SvcLayer,
+};
+use crate::endpoint::marker_rewriter::MarkerRewriter;
+use crate::endpoint::simulcast_rewriter::SimulcastRewriter;
+use crate::metrics::loss_monitor::{InboundRtpStats, LossMonitor};
+use crate::types::{FourTuple, Mid};
 use sctp::{Association, AssociationHandle};
 use srtp::context::Context;
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::time::Instant;
 
 pub(crate) struct Transport {
     four_tuple: FourTuple,
+    // DTLS and SCTP states are keyed by the peer address of the very first four_tuple,
+    // which stays the same after the transport is migrated to a new four_tuple
+    initial_peer_addr: SocketAddr,
     last_activity: Instant,
 
     // ICE
@@ -28,6 +39,20 @@ pub(crate) struct Transport {
     // SRTP
     local_srtp_context: Option<Context>,
     remote_srtp_context: Option<Context>,
+
+    // RTP forwarding of video streams keyed by outbound ssrc
+    marker_rewriters: HashMap<SSRC, MarkerRewriter>,
+    // RTP forwarding of selected simulcast layers keyed by mid of the subscriber's transceiver
+    simulcast_rewriters: HashMap<Mid, SimulcastRewriter>,
+    // sequence number gaps of inbound RTP streams keyed by ssrc
+    loss_monitors: HashMap<SSRC, LossMonitor>,
+    // latest absolute send times of inbound RTP streams keyed by ssrc
+    abs_send_times: HashMap<SSRC, u32>,
+    // latest dependency structures of inbound SVC streams keyed by ssrc
+    dependency_structures: HashMap<SSRC, FrameDependencyStructure>,
+    // rids of inbound simulcast streams keyed by ssrc, and repaired rids of their RTX streams
+    simulcast_rids: HashMap<SSRC, String>,
+    repaired_rids: HashMap<SSRC, String>,
 }
 
 impl Transport {
@@ -40,6 +65,7 @@ impl Transport {
     ) -> Self {
         Self {
             four_tuple,
+            initial_peer_addr: four_tuple.peer_addr,
             last_activity: Instant::now(),
 
             candidate,
@@ -54,6 +80,14 @@ impl Transport {
 
             local_srtp_context: None,
             remote_srtp_context: None,
+
+            marker_rewriters: HashMap::new(),
+            simulcast_rewriters: HashMap::new(),
+            loss_monitors: HashMap::new(),
+            abs_send_times: HashMap::new(),
+            dependency_structures: HashMap::new(),
+            simulcast_rids: HashMap::new(),
+            repaired_rids: HashMap::new(),
         }
     }
 
@@ -61,6 +95,19 @@ impl Transport {
         &self.four_tuple
     }
 
+    pub(crate) fn set_four_tuple(&mut self, four_tuple: FourTuple) {
+        self.four_tuple = four_tuple;
+    }
+
+    pub(crate) fn initial_peer_addr(&self) -> SocketAddr {
+        self.initial_peer_addr
+    }
+
+    /// is_established returns true once DTLS handshake is done and SRTP contexts are set
+    pub(crate) fn is_established(&self) -> bool {
+        self.local_srtp_context.is_some() && self.remote_srtp_context.is_some()
+    }
+
     pub(crate) fn candidate(&self) -> &Rc<Candidate> {
         &self.candidate
     }
@@ -129,6 +176,107 @@ impl Transport {
         (self.association_handle, self.stream_id)
     }
 
+    pub(crate) fn get_mut_marker_rewriter(&mut self, ssrc: SSRC) -> &mut MarkerRewriter {
+        self.marker_rewriters.entry(ssrc).or_default()
+    }
+
+    pub(crate) fn get_mut_simulcast_rewriter(
+        &mut self,
+        mid: &str,
+    ) -> Option<&mut SimulcastRewriter> {
+        self.simulcast_rewriters.get_mut(mid)
+    }
+
+    pub(crate) fn add_simulcast_rewriter(
+        &mut self,
+        mid: Mid,
+        ssrc: SSRC,
+    ) -> &mut SimulcastRewriter {
+        self.simulcast_rewriters
+            .entry(mid)
+            .or_insert_with(|| SimulcastRewriter::new(ssrc))
+    }
+
+    /// get_simulcast_ssrcs returns ssrcs of the inbound media streams of the simulcast layer of rid
+    pub(crate) fn get_simulcast_ssrcs<'a>(
+        &'a self,
+        rid: &'a str,
+    ) -> impl Iterator<Item = SSRC> + 'a {
+        self.simulcast_rids
+            .iter()
+            .filter(move |(_, simulcast_rid)| *simulcast_rid == rid)
+            .map(|(&ssrc, _)| ssrc)
+    }
+
+    pub(crate) fn get_mut_loss_monitor(&mut self, ssrc: SSRC) -> &mut LossMonitor {
+        self.loss_monitors.entry(ssrc).or_default()
+    }
+
+    /// set_abs_send_time records the absolute send time of the latest packet of the inbound stream
+    pub(crate) fn set_abs_send_time(&mut self, ssrc: SSRC, abs_send_time: u32) {
+        self.abs_send_times.insert(ssrc, abs_send_time);
+    }
+
+    /// get_svc_layer returns the layer of the inbound frame described by dependency_descriptor,
+    /// the dependency structure attached to key frames is kept for the following frames
+    pub(crate) fn get_svc_layer(
+        &mut self,
+        ssrc: SSRC,
+        dependency_descriptor: DependencyDescriptor,
+    ) -> Option<SvcLayer> {
+        if let Some(structure) = dependency_descriptor.structure {
+            self.dependency_structures.insert(ssrc, structure);
+        }
+        self.dependency_structures
+            .get(&ssrc)?
+            .layer(dependency_descriptor.frame_dependency_template_id)
+    }
+
+    /// set_simulcast_rid associates the inbound stream with its simulcast layer, by the rid of
+    /// a media stream or by the repaired rid of an RTX stream, which doesn't carry the rid
+    pub(crate) fn set_simulcast_rid(
+        &mut self,
+        ssrc: SSRC,
+        rid: Option<String>,
+        repaired_rid: Option<String>,
+    ) {
+        if let Some(rid) = rid {
+            self.simulcast_rids.insert(ssrc, rid);
+        } else if let Some(repaired_rid) = repaired_rid {
+            self.repaired_rids.insert(ssrc, repaired_rid);
+        }
+    }
+
+    /// get_simulcast_rid returns the rid of the simulcast layer the inbound stream belongs to
+    pub(crate) fn get_simulcast_rid(&self, ssrc: SSRC) -> Option<&str> {
+        self.simulcast_rids
+            .get(&ssrc)
+            .or_else(|| self.repaired_rids.get(&ssrc))
+            .map(String::as_str)
+    }
+
+    /// get_repaired_ssrc returns ssrc of the media stream repaired by the inbound RTX stream,
+    /// once packets of both streams are received
+    pub(crate) fn get_repaired_ssrc(&self, rtx_ssrc: SSRC) -> Option<SSRC> {
+        let repaired_rid = self.repaired_rids.get(&rtx_ssrc)?;
+        self.simulcast_rids
+            .iter()
+            .find(|(_, rid)| *rid == repaired_rid)
+            .map(|(&ssrc, _)| ssrc)
+    }
+
+    /// get_inbound_rtp_stats returns loss statistics of the RTP streams received on this transport
+    pub(crate) fn get_inbound_rtp_stats(&self) -> Vec<InboundRtpStats> {
+        self.loss_monitors
+            .iter()
+            .map(|(&ssrc, loss_monitor)| InboundRtpStats {
+                rid: self.get_simulcast_rid(ssrc).map(str::to_string),
+                abs_send_time: self.abs_send_times.get(&ssrc).copied(),
+                ..loss_monitor.stats(ssrc)
+            })
+            .collect()
+    }
+
     pub(crate) fn is_local_srtp_context_ready(&self) -> bool {
         self.local_srtp_context.is_some()
     }
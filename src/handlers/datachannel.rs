@@ -168,7 +168,12 @@ impl Handler for DataChannelHandler {
     }
 }
 
-fn get_reliability_params(channel_type: ChannelType) -> (bool, ReliabilityType) {
+/// get_reliability_params maps the channel type of DATA_CHANNEL_OPEN to stream reliability params.
+/// They are applied to the SCTP stream when DATA_CHANNEL_ACK is sent, so that the association
+/// abandons outbound messages once retransmitted `reliability_parameter` times (Rexmit) or after
+/// `reliability_parameter` milliseconds since they were first sent (Timed), and tells the remote
+/// to skip them with FORWARD-TSN. DCEP messages themselves are always sent ordered and reliably.
+pub(crate) fn get_reliability_params(channel_type: ChannelType) -> (bool, ReliabilityType) {
     let (unordered, reliability_type) = match channel_type {
         ChannelType::Reliable => (false, ReliabilityType::Reliable),
         ChannelType::ReliableUnordered => (true, ReliabilityType::Reliable),
@@ -0,0 +1,32 @@
+use super::datachannel::*;
+use datachannel::message::message_channel_open::ChannelType;
+use sctp::ReliabilityType;
+
+#[test]
+fn test_get_reliability_params() {
+    for (channel_type, expected) in [
+        (ChannelType::Reliable, (false, ReliabilityType::Reliable)),
+        (
+            ChannelType::ReliableUnordered,
+            (true, ReliabilityType::Reliable),
+        ),
+        (
+            ChannelType::PartialReliableRexmit,
+            (false, ReliabilityType::Rexmit),
+        ),
+        (
+            ChannelType::PartialReliableRexmitUnordered,
+            (true, ReliabilityType::Rexmit),
+        ),
+        (
+            ChannelType::PartialReliableTimed,
+            (false, ReliabilityType::Timed),
+        ),
+        (
+            ChannelType::PartialReliableTimedUnordered,
+            (true, ReliabilityType::Timed),
+        ),
+    ] {
+        assert_eq!(get_reliability_params(channel_type), expected);
+    }
+}
@@ -1,12 +1,25 @@
 use crate::messages::{
     DTLSMessageEvent, MessageEvent, RTPMessageEvent, STUNMessageEvent, TaggedMessageEvent,
 };
-use log::{debug, error};
+use crate::server::rate_limiter::RateLimiter;
+use crate::types::FourTuple;
+use log::{debug, trace};
 use retty::channel::{Context, Handler};
 use retty::transport::TaggedBytesMut;
+use shared::util::is_rtcp;
+use std::time::{Duration, Instant};
+
+/// RATE_LIMITER_CLEANUP_INTERVAL is the interval to drop idle buckets of the packet rate limiter
+const RATE_LIMITER_CLEANUP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Minimum lengths of packets, i.e., their fixed headers
+const STUN_HEADER_LENGTH: usize = 20;
+const ZRTP_HEADER_LENGTH: usize = 12;
+const DTLS_RECORD_HEADER_LENGTH: usize = 13;
+const RTP_HEADER_LENGTH: usize = 12;
 
 /// match_range is a MatchFunc that accepts packets with the first byte in [lower..upper]
-fn match_range(lower: u8, upper: u8, buf: &[u8]) -> bool {
+pub(crate) fn match_range(lower: u8, upper: u8, buf: &[u8]) -> bool {
     if buf.is_empty() {
         return false;
     }
@@ -27,25 +40,149 @@ fn match_range(lower: u8, upper: u8, buf: &[u8]) -> bool {
 ///              |                |
 ///              |    [128..191] -+--> forward to RTP/RTCP
 ///              +----------------+
+/// match_stun is a MatchFunc that accepts packets with the first byte in [0..3]
+/// as defied in RFC7983
+pub(crate) fn match_stun(b: &[u8]) -> bool {
+    match_range(0, 3, b)
+}
+
+/// match_zrtp is a MatchFunc that accepts packets with the first byte in [16..19]
+/// as defied in RFC7983
+fn match_zrtp(b: &[u8]) -> bool {
+    match_range(16, 19, b)
+}
+
 /// match_dtls is a MatchFunc that accepts packets with the first byte in [20..63]
 /// as defied in RFC7983
 fn match_dtls(b: &[u8]) -> bool {
     match_range(20, 63, b)
 }
 
+/// match_turn_channel is a MatchFunc that accepts packets with the first byte in [64..79]
+/// as defied in RFC7983, and with at least the 4-byte header of TURN ChannelData
+pub(crate) fn match_turn_channel(b: &[u8]) -> bool {
+    b.len() >= 4 && match_range(64, 79, b)
+}
+
 /// match_srtp is a MatchFunc that accepts packets with the first byte in [128..191]
 /// as defied in RFC7983
 fn match_srtp(b: &[u8]) -> bool {
     match_range(128, 191, b)
 }
 
+/// PacketKind is the protocol of a packet classified by its first byte as described in RFC7983
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum PacketKind {
+    Stun,
+    Zrtp,
+    Dtls,
+    Turn,
+    Rtp,
+    Rtcp,
+    /// packets out of the ranges or shorter than the header of their protocol
+    Unknown,
+}
+
+/// classify returns the PacketKind of buf, which is Unknown if buf is too short to be parsed
+pub(crate) fn classify(buf: &[u8]) -> PacketKind {
+    if match_stun(buf) && buf.len() >= STUN_HEADER_LENGTH {
+        PacketKind::Stun
+    } else if match_zrtp(buf) && buf.len() >= ZRTP_HEADER_LENGTH {
+        PacketKind::Zrtp
+    } else if match_dtls(buf) && buf.len() >= DTLS_RECORD_HEADER_LENGTH {
+        PacketKind::Dtls
+    } else if match_turn_channel(buf) {
+        PacketKind::Turn
+    } else if match_srtp(buf) && is_rtcp(buf) {
+        PacketKind::Rtcp
+    } else if match_srtp(buf) && buf.len() >= RTP_HEADER_LENGTH {
+        PacketKind::Rtp
+    } else {
+        PacketKind::Unknown
+    }
+}
+
 /// DemuxerHandler implements demuxing of STUN/DTLS/RTP/RTCP Protocol packets
 #[derive(Default)]
-pub struct DemuxerHandler;
+pub struct DemuxerHandler {
+    packet_limiter: Option<RateLimiter<FourTuple>>,
+    next_cleanup: Option<Instant>,
+}
 
 impl DemuxerHandler {
     pub fn new() -> Self {
-        DemuxerHandler
+        DemuxerHandler::default()
+    }
+
+    /// build with max inbound packets per second per four-tuple and burst of packets above it,
+    /// excess packets are dropped before being demuxed
+    pub fn with_packet_rate_limit(mut self, packets_per_second: u32, burst: u32) -> Self {
+        self.packet_limiter = Some(RateLimiter::new(packets_per_second, burst));
+        self
+    }
+
+    /// check_packet_rate_limit returns false if msg exceeds the configured rate of its four-tuple
+    pub(crate) fn check_packet_rate_limit(&mut self, msg: &TaggedBytesMut) -> bool {
+        let Some(packet_limiter) = self.packet_limiter.as_mut() else {
+            return true;
+        };
+
+        if self
+            .next_cleanup
+            .is_none_or(|next_cleanup| next_cleanup <= msg.now)
+        {
+            packet_limiter.remove_idle(msg.now);
+            self.next_cleanup = Some(msg.now + RATE_LIMITER_CLEANUP_INTERVAL);
+        }
+        packet_limiter.allow((&msg.transport).into(), msg.now)
+    }
+
+    /// handle_turn_channel handles TURN ChannelData packets, which are dropped since server-side
+    /// TURN relaying is not supported yet
+    fn handle_turn_channel(&self, msg: TaggedBytesMut) {
+        let channel_number = u16::from_be_bytes([msg.message[0], msg.message[1]]);
+        debug!(
+            "drop turn channel data of channel {:#06x} from {} since turn relaying is not supported",
+            channel_number, msg.transport.peer_addr
+        );
+    }
+
+    /// demux wraps the packet of msg as the message event of its protocol for the next handlers,
+    /// e.g., SRTP/SRTCP packets are forwarded as raw RTP message events, which SrtpHandler
+    /// decrypts with the transport of their four-tuple
+    pub(crate) fn demux(&self, msg: TaggedBytesMut) -> Option<TaggedMessageEvent> {
+        let message = match classify(&msg.message) {
+            PacketKind::Stun => MessageEvent::Stun(STUNMessageEvent::Raw(msg.message)),
+            PacketKind::Dtls => MessageEvent::Dtls(DTLSMessageEvent::Raw(msg.message)),
+            PacketKind::Rtp | PacketKind::Rtcp => {
+                MessageEvent::Rtp(RTPMessageEvent::Raw(msg.message))
+            }
+            PacketKind::Turn => {
+                self.handle_turn_channel(msg);
+                return None;
+            }
+            PacketKind::Zrtp => {
+                debug!(
+                    "drop zrtp packet from {} since zrtp is not supported",
+                    msg.transport.peer_addr
+                );
+                return None;
+            }
+            PacketKind::Unknown => {
+                debug!(
+                    "drop unknown packet of {} bytes from {}",
+                    msg.message.len(),
+                    msg.transport.peer_addr
+                );
+                return None;
+            }
+        };
+
+        Some(TaggedMessageEvent {
+            now: msg.now,
+            transport: msg.transport,
+            message,
+        })
     }
 }
 
@@ -64,26 +201,13 @@ impl Handler for DemuxerHandler {
         ctx: &Context<Self::Rin, Self::Rout, Self::Win, Self::Wout>,
         msg: Self::Rin,
     ) {
-        if msg.message.is_empty() {
-            error!("drop invalid packet due to zero length");
-        } else if match_dtls(&msg.message) {
-            ctx.fire_read(TaggedMessageEvent {
-                now: msg.now,
-                transport: msg.transport,
-                message: MessageEvent::Dtls(DTLSMessageEvent::Raw(msg.message)),
-            });
-        } else if match_srtp(&msg.message) {
-            ctx.fire_read(TaggedMessageEvent {
-                now: msg.now,
-                transport: msg.transport,
-                message: MessageEvent::Rtp(RTPMessageEvent::Raw(msg.message)),
-            });
-        } else {
-            ctx.fire_read(TaggedMessageEvent {
-                now: msg.now,
-                transport: msg.transport,
-                message: MessageEvent::Stun(STUNMessageEvent::Raw(msg.message)),
-            });
+        if !self.check_packet_rate_limit(&msg) {
+            trace!(
+                "drop packet from {} due to rate limit",
+                msg.transport.peer_addr
+            );
+        } else if let Some(msg) = self.demux(msg) {
+            ctx.fire_read(msg);
         }
     }
 
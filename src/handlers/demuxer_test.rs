@@ -0,0 +1,141 @@
+use super::demuxer::*;
+use crate::messages::{MessageEvent, RTPMessageEvent, STUNMessageEvent, TaggedMessageEvent};
+use bytes::BytesMut;
+use retty::transport::{TaggedBytesMut, TransportContext};
+use std::time::{Duration, Instant};
+
+const RTP_PACKET: [u8; 12] = [
+    0x80, 0x60, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+];
+
+fn new_packet(now: Instant, peer_addr: &str) -> TaggedBytesMut {
+    TaggedBytesMut {
+        now,
+        transport: TransportContext {
+            local_addr: "127.0.0.1:3478".parse().unwrap(),
+            peer_addr: peer_addr.parse().unwrap(),
+            ecn: None,
+        },
+        message: BytesMut::from(&RTP_PACKET[..]),
+    }
+}
+
+#[test]
+fn test_packet_rate_limit_per_four_tuple() {
+    let now = Instant::now();
+    let mut demuxer = DemuxerHandler::new().with_packet_rate_limit(10, 5);
+
+    // the flooding source is cut off after its burst
+    let allowed = (0..20)
+        .filter(|_| demuxer.check_packet_rate_limit(&new_packet(now, "127.0.0.1:5000")))
+        .count();
+    assert_eq!(allowed, 5);
+
+    // the flooding source recovers at the configured rate
+    let later = now + Duration::from_millis(100);
+    assert!(demuxer.check_packet_rate_limit(&new_packet(later, "127.0.0.1:5000")));
+    assert!(!demuxer.check_packet_rate_limit(&new_packet(later, "127.0.0.1:5000")));
+
+    // a compliant source sending within the rate is unaffected
+    for i in 0..20 {
+        let now = now + Duration::from_millis(100 * i);
+        assert!(demuxer.check_packet_rate_limit(&new_packet(now, "127.0.0.1:5001")));
+    }
+
+    // no limit without configuration
+    let mut demuxer = DemuxerHandler::new();
+    assert!((0..20).all(|_| demuxer.check_packet_rate_limit(&new_packet(now, "127.0.0.1:5000"))));
+}
+
+#[test]
+fn test_demux_srtp_packet_to_rtp_message() {
+    let now = Instant::now();
+    let demuxer = DemuxerHandler::new();
+
+    let msg = new_packet(now, "127.0.0.1:5000");
+    let transport = msg.transport;
+    let Some(TaggedMessageEvent {
+        transport: demuxed_transport,
+        message: MessageEvent::Rtp(RTPMessageEvent::Raw(message)),
+        ..
+    }) = demuxer.demux(msg)
+    else {
+        panic!("srtp packet is not demuxed as raw rtp message");
+    };
+    assert!(demuxed_transport == transport);
+    assert_eq!(&message[..], &RTP_PACKET[..]);
+
+    // empty packets are dropped
+    let mut msg = new_packet(now, "127.0.0.1:5000");
+    msg.message.clear();
+    assert!(demuxer.demux(msg).is_none());
+}
+
+#[test]
+fn test_match_stun() {
+    assert!(match_range(0, 3, &[0u8, 1]));
+    assert!(match_stun(&[0u8, 1]));
+    assert!(match_stun(&[3u8]));
+    assert!(!match_stun(&[130u8, 1]));
+    assert!(!match_stun(&[]));
+
+    // stun packets are demuxed as raw stun message, and unmatched packets are dropped
+    let now = Instant::now();
+    let demuxer = DemuxerHandler::new();
+    let mut msg = new_packet(now, "127.0.0.1:5000");
+    msg.message = BytesMut::from(&[0x00u8; 20][..]);
+    assert!(matches!(
+        demuxer.demux(msg),
+        Some(TaggedMessageEvent {
+            message: MessageEvent::Stun(STUNMessageEvent::Raw(_)),
+            ..
+        })
+    ));
+    let mut msg = new_packet(now, "127.0.0.1:5000");
+    msg.message = BytesMut::from(&[0xffu8, 0x01][..]);
+    assert!(demuxer.demux(msg).is_none());
+}
+
+#[test]
+fn test_match_turn_channel() {
+    assert!(!match_turn_channel(&[63u8, 0, 0, 0]));
+    assert!(match_turn_channel(&[64u8, 0, 0, 0]));
+    assert!(match_turn_channel(&[79u8, 0xff, 0, 4, 1, 2, 3, 4]));
+    assert!(!match_turn_channel(&[80u8, 0, 0, 0]));
+
+    // shorter than the ChannelData header
+    assert!(!match_turn_channel(&[64u8, 0, 0]));
+
+    // turn channel data is not forwarded
+    let demuxer = DemuxerHandler::new();
+    let mut msg = new_packet(Instant::now(), "127.0.0.1:5000");
+    msg.message = BytesMut::from(&[0x40u8, 0x00, 0x00, 0x00][..]);
+    assert!(demuxer.demux(msg).is_none());
+}
+
+#[test]
+fn test_classify() {
+    assert_eq!(classify(&[0x00u8; 20]), PacketKind::Stun);
+    assert_eq!(classify(&[0x10u8; 12]), PacketKind::Zrtp);
+    assert_eq!(classify(&[0x16u8; 13]), PacketKind::Dtls);
+    assert_eq!(classify(&[0x40u8, 0x00, 0x00, 0x00]), PacketKind::Turn);
+    assert_eq!(classify(&RTP_PACKET), PacketKind::Rtp);
+    // a receiver report without report blocks
+    assert_eq!(
+        classify(&[0x80u8, 0xc9, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01]),
+        PacketKind::Rtcp
+    );
+
+    // out of the ranges, or shorter than the headers
+    assert_eq!(classify(&[]), PacketKind::Unknown);
+    assert_eq!(classify(&[0x80u8, 0x60]), PacketKind::Unknown);
+    assert_eq!(classify(&[0x00u8; 19]), PacketKind::Unknown);
+    assert_eq!(classify(&[0x16u8; 12]), PacketKind::Unknown);
+    assert_eq!(classify(&[0xffu8; 20]), PacketKind::Unknown);
+
+    // unknown packets are dropped
+    let demuxer = DemuxerHandler::new();
+    let mut msg = new_packet(Instant::now(), "127.0.0.1:5000");
+    msg.message = BytesMut::from(&[0x80u8, 0x60][..]);
+    assert!(demuxer.demux(msg).is_none());
+}
@@ -4,9 +4,14 @@ use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::net::SocketAddr;
 use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Instant;
 
+use crate::endpoint::candidate::DTLSRole;
+use crate::endpoint::transport::Transport;
 use crate::messages::{DTLSMessageEvent, MessageEvent, TaggedMessageEvent};
+use crate::server::certificate::RTCDtlsFingerprint;
+use crate::server::certificate_verifier::CertificateVerifier;
 use crate::server::states::ServerStates;
 use dtls::endpoint::EndpointEvent;
 use dtls::extension::extension_use_srtp::SrtpProtectionProfile;
@@ -25,6 +30,54 @@ pub struct DtlsHandler {
 }
 
 impl DtlsHandler {
+    /// initiate_handshake starts DTLS handshake of the transport by sending ClientHello
+    /// if the local DTLS role is resolved as client and the handshake isn't started yet
+    pub(crate) fn initiate_handshake(
+        transport: &mut Transport,
+        dtls_client_handshake_config: &Arc<dtls::config::HandshakeConfig>,
+    ) -> Result<()> {
+        if transport
+            .candidate()
+            .local_connection_credentials()
+            .dtls_params
+            .role
+            != DTLSRole::Client
+        {
+            return Ok(());
+        }
+
+        let initial_peer_addr = transport.initial_peer_addr();
+        let dtls_endpoint = transport.get_mut_dtls_endpoint();
+        if dtls_endpoint
+            .get_connection_state(initial_peer_addr)
+            .is_none()
+        {
+            debug!("initiate dtls handshake as client to {}", initial_peer_addr);
+            dtls_endpoint.connect(
+                initial_peer_addr,
+                Arc::clone(dtls_client_handshake_config),
+                None,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// verify_peer_certificates returns ErrAlertFatalOrClose to close the transport, if the
+    /// certificate verifier rejects the peer certificates of the completed handshake
+    pub(crate) fn verify_peer_certificates(
+        certificate_verifier: &dyn CertificateVerifier,
+        state: &State,
+        remote_fingerprints: &[RTCDtlsFingerprint],
+    ) -> Result<()> {
+        certificate_verifier
+            .verify(&state.peer_certificates, remote_fingerprints)
+            .map_err(|err| {
+                warn!("reject remote dtls certificate: {}", err);
+                Error::ErrAlertFatalOrClose
+            })
+    }
+
     pub fn new(local_addr: SocketAddr, server_states: Rc<RefCell<ServerStates>>) -> Self {
         DtlsHandler {
             local_addr,
@@ -55,6 +108,8 @@ impl Handler for DtlsHandler {
 
             let try_read = || -> Result<Vec<BytesMut>> {
                 let mut server_states = self.server_states.borrow_mut();
+                let certificate_verifier =
+                    Arc::clone(&server_states.server_config().certificate_verifier);
                 let transport = match server_states.get_mut_transport(&four_tuple) {
                     Ok(transport) => transport,
                     Err(err) => {
@@ -66,11 +121,18 @@ impl Handler for DtlsHandler {
                 let mut contexts = vec![];
 
                 {
+                    let initial_peer_addr = transport.initial_peer_addr();
+                    let remote_fingerprints = transport
+                        .candidate()
+                        .remote_connection_credentials()
+                        .dtls_params
+                        .fingerprints
+                        .clone();
                     let dtls_endpoint = transport.get_mut_dtls_endpoint();
 
                     for message in dtls_endpoint.read(
                         msg.now,
-                        msg.transport.peer_addr,
+                        initial_peer_addr,
                         Some(msg.transport.local_addr.ip()),
                         msg.transport.ecn,
                         dtls_message,
@@ -78,9 +140,14 @@ impl Handler for DtlsHandler {
                         match message {
                             EndpointEvent::HandshakeComplete => {
                                 if let Some(state) =
-                                    dtls_endpoint.get_connection_state(msg.transport.peer_addr)
+                                    dtls_endpoint.get_connection_state(initial_peer_addr)
                                 {
                                     debug!("recv dtls handshake complete");
+                                    DtlsHandler::verify_peer_certificates(
+                                        certificate_verifier.as_ref(),
+                                        state,
+                                        &remote_fingerprints,
+                                    )?;
                                     let (local_context, remote_context) =
                                         DtlsHandler::update_srtp_contexts(state)?;
                                     contexts.push((local_context, remote_context));
@@ -103,7 +170,7 @@ impl Handler for DtlsHandler {
                             now: transmit.now,
                             transport: TransportContext {
                                 local_addr: self.local_addr,
-                                peer_addr: transmit.remote,
+                                peer_addr: msg.transport.peer_addr,
                                 ecn: transmit.ecn,
                             },
                             message: MessageEvent::Dtls(DTLSMessageEvent::Raw(transmit.payload)),
@@ -154,9 +221,22 @@ impl Handler for DtlsHandler {
     ) {
         let mut try_timeout = || -> Result<()> {
             let mut server_states = self.server_states.borrow_mut();
+            let dtls_client_handshake_config = server_states
+                .server_config()
+                .dtls_client_handshake_config
+                .clone();
             for session in server_states.get_mut_sessions().values_mut() {
                 for endpoint in session.get_mut_endpoints().values_mut() {
                     for transport in endpoint.get_mut_transports().values_mut() {
+                        if let Some(dtls_client_handshake_config) = &dtls_client_handshake_config {
+                            if let Err(err) = DtlsHandler::initiate_handshake(
+                                transport,
+                                dtls_client_handshake_config,
+                            ) {
+                                error!("initiate_handshake with error {}", err);
+                            }
+                        }
+                        let peer_addr = transport.four_tuple().peer_addr;
                         let dtls_endpoint = transport.get_mut_dtls_endpoint();
                         let remotes: Vec<SocketAddr> =
                             dtls_endpoint.get_connections_keys().copied().collect();
@@ -168,7 +248,7 @@ impl Handler for DtlsHandler {
                                 now: transmit.now,
                                 transport: TransportContext {
                                     local_addr: self.local_addr,
-                                    peer_addr: transmit.remote,
+                                    peer_addr,
                                     ecn: transmit.ecn,
                                 },
                                 message: MessageEvent::Dtls(DTLSMessageEvent::Raw(
@@ -227,15 +307,16 @@ impl Handler for DtlsHandler {
                 let mut try_write = || -> Result<()> {
                     let mut server_states = self.server_states.borrow_mut();
                     let transport = server_states.get_mut_transport(&four_tuple)?;
+                    let initial_peer_addr = transport.initial_peer_addr();
                     let dtls_endpoint = transport.get_mut_dtls_endpoint();
 
-                    dtls_endpoint.write(msg.transport.peer_addr, &dtls_message)?;
+                    dtls_endpoint.write(initial_peer_addr, &dtls_message)?;
                     while let Some(transmit) = dtls_endpoint.poll_transmit() {
                         self.transmits.push_back(TaggedMessageEvent {
                             now: transmit.now,
                             transport: TransportContext {
                                 local_addr: self.local_addr,
-                                peer_addr: transmit.remote,
+                                peer_addr: msg.transport.peer_addr,
                                 ecn: transmit.ecn,
                             },
                             message: MessageEvent::Dtls(DTLSMessageEvent::Raw(transmit.payload)),
@@ -274,6 +355,8 @@ impl DtlsHandler {
                 ProtectionProfile::Aes128CmHmacSha1_80
             }
             SrtpProtectionProfile::Srtp_Aead_Aes_128_Gcm => ProtectionProfile::AeadAes128Gcm,
+            // the auth tag length follows the profile, and srtp has no context for 32-bit tags of
+            // SRTP_AES128_CM_HMAC_SHA1_32, so it must not be offered in HandshakeConfig
             _ => return Err(Error::ErrNoSuchSrtpProfile),
         };
 
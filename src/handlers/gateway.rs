@@ -1,17 +1,34 @@
 use crate::description::{
-    rtp_transceiver_direction::RTCRtpTransceiverDirection, sdp_type::RTCSdpType,
-    RTCSessionDescription,
+    get_apt,
+    rtp_codec::RTPCodecType,
+    rtp_transceiver::{PayloadType, SSRC},
+    sdp_type::RTCSdpType,
+    RTCSessionDescription, SDES_REPAIRED_RTP_STREAM_ID_URI,
+};
+use crate::endpoint::abs_send_time::get_abs_send_time;
+use crate::endpoint::candidate::{resolve_role_conflict, Candidate, IceRole, RoleConflict};
+use crate::endpoint::dependency_descriptor::{
+    DependencyDescriptor, SvcLayer, DEPENDENCY_DESCRIPTOR_URI,
 };
-use crate::endpoint::candidate::Candidate;
 use crate::messages::{
     ApplicationMessage, DTLSMessageEvent, DataChannelEvent, MessageEvent, RTPMessageEvent,
     STUNMessageEvent, TaggedMessageEvent,
 };
+use crate::server::rate_limiter::RateLimiter;
 use crate::server::states::ServerStates;
+use crate::session::dominant_speaker::CSRC_AUDIO_LEVEL_URI;
+use crate::types::{EndpointId, FourTuple, Mid};
 use bytes::BytesMut;
 use log::{debug, info, trace, warn};
 use retty::channel::{Context, Handler};
 use retty::transport::TransportContext;
+use rtcp::goodbye::Goodbye;
+use rtcp::payload_feedbacks::full_intra_request::{FirEntry, FullIntraRequest};
+use rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use rtcp::payload_feedbacks::receiver_estimated_maximum_bitrate::ReceiverEstimatedMaximumBitrate;
+use rtcp::sender_report::SenderReport;
+use rtcp::source_description::{SourceDescription, SourceDescriptionChunk};
+use rtcp::transport_feedbacks::transport_layer_nack::TransportLayerNack;
 use shared::error::{Error, Result};
 use std::cell::RefCell;
 use std::collections::VecDeque;
@@ -23,9 +40,10 @@ use stun::attributes::{
     ATTR_ICE_CONTROLLED, ATTR_ICE_CONTROLLING, ATTR_NETWORK_COST, ATTR_PRIORITY, ATTR_USERNAME,
     ATTR_USE_CANDIDATE,
 };
+use stun::error_code::{ErrorCode, ErrorCodeAttribute, CODE_BAD_REQUEST, CODE_ROLE_CONFLICT};
 use stun::fingerprint::FINGERPRINT;
 use stun::integrity::MessageIntegrity;
-use stun::message::{Setter, TransactionId, BINDING_SUCCESS};
+use stun::message::{Setter, TransactionId, BINDING_ERROR, BINDING_REQUEST, BINDING_SUCCESS};
 use stun::textattrs::TextAttribute;
 use stun::xoraddr::XorMappedAddress;
 
@@ -35,17 +53,27 @@ pub struct GatewayHandler {
     transmits: VecDeque<TaggedMessageEvent>,
     next_timeout: Instant,
     idle_timeout: Duration,
+    stun_binding_limiter: Option<RateLimiter<FourTuple>>,
 }
 
 impl GatewayHandler {
     pub fn new(server_states: Rc<RefCell<ServerStates>>) -> Self {
-        let idle_timeout = server_states.borrow().server_config().idle_timeout;
+        let (idle_timeout, stun_binding_rate_limit) = {
+            let server_states = server_states.borrow();
+            let server_config = server_states.server_config();
+            (
+                server_config.idle_timeout,
+                server_config.stun_binding_rate_limit,
+            )
+        };
 
         GatewayHandler {
             server_states,
             transmits: VecDeque::new(),
             next_timeout: Instant::now().add(idle_timeout),
             idle_timeout,
+            // allow a burst of one second worth of binding requests
+            stun_binding_limiter: stun_binding_rate_limit.map(|rate| RateLimiter::new(rate, rate)),
         }
     }
 }
@@ -82,6 +110,14 @@ impl Handler for GatewayHandler {
         ctx: &Context<Self::Rin, Self::Rout, Self::Win, Self::Wout>,
         msg: Self::Rin,
     ) {
+        if !self.check_stun_binding_rate_limit(&msg) {
+            debug!(
+                "drop STUN binding request from {} due to rate limit",
+                msg.transport.peer_addr
+            );
+            return;
+        }
+
         let try_read = || -> Result<Vec<TaggedMessageEvent>> {
             let mut server_states = self.server_states.borrow_mut();
             match msg.message {
@@ -145,20 +181,50 @@ impl Handler for GatewayHandler {
         // terminate timeout here, no more ctx.fire_handle_timeout(now);
         if self.next_timeout <= now {
             let mut four_tuples = vec![];
+            let mut failovers = vec![];
             let mut server_states = self.server_states.borrow_mut();
             for session in server_states.get_mut_sessions().values_mut() {
+                let session_id = session.session_id();
                 for endpoint in session.get_mut_endpoints().values_mut() {
-                    for transport in endpoint.get_mut_transports().values_mut() {
-                        if transport.last_activity() <= now.sub(self.idle_timeout) {
-                            four_tuples.push(*transport.four_tuple());
+                    let idle_four_tuples: Vec<FourTuple> = endpoint
+                        .get_transports()
+                        .values()
+                        .filter(|transport| transport.last_activity() <= now.sub(self.idle_timeout))
+                        .map(|transport| *transport.four_tuple())
+                        .collect();
+                    for four_tuple in idle_four_tuples {
+                        // consent of the selected pair is lost, switch to a backup pair if any
+                        if let Some(new_four_tuple) =
+                            endpoint.failover(&four_tuple, now, self.idle_timeout)
+                        {
+                            failovers.push((
+                                session_id,
+                                endpoint.endpoint_id(),
+                                four_tuple,
+                                new_four_tuple,
+                            ));
+                        } else {
+                            four_tuples.push(four_tuple);
                         }
                     }
                 }
             }
+            for (session_id, endpoint_id, old_four_tuple, new_four_tuple) in failovers {
+                info!(
+                    "{}/{} fails over from {:?} to {:?}",
+                    session_id, endpoint_id, old_four_tuple, new_four_tuple
+                );
+                server_states.remove_endpoint(&old_four_tuple);
+                server_states.add_endpoint(new_four_tuple, session_id, endpoint_id);
+            }
             for four_tuple in four_tuples {
                 server_states.remove_transport(four_tuple);
             }
 
+            if let Some(stun_binding_limiter) = self.stun_binding_limiter.as_mut() {
+                stun_binding_limiter.remove_idle(now);
+            }
+
             self.next_timeout = self.next_timeout.add(self.idle_timeout);
         }
     }
@@ -186,6 +252,24 @@ impl Handler for GatewayHandler {
 }
 
 impl GatewayHandler {
+    /// check_stun_binding_rate_limit returns false if msg is a STUN binding request
+    /// exceeding the configured rate of its four-tuple
+    pub(crate) fn check_stun_binding_rate_limit(&mut self, msg: &TaggedMessageEvent) -> bool {
+        match (&mut self.stun_binding_limiter, &msg.message) {
+            (Some(stun_binding_limiter), MessageEvent::Stun(STUNMessageEvent::Stun(message)))
+                if message.typ == BINDING_REQUEST =>
+            {
+                stun_binding_limiter.allow((&msg.transport).into(), msg.now)
+            }
+            _ => true,
+        }
+    }
+
+    /// is_padding_only returns true if the packet only carries padding, e.g., a bandwidth probe
+    pub(crate) fn is_padding_only(rtp_packet: &rtp::packet::Packet) -> bool {
+        rtp_packet.header.padding && rtp_packet.payload.is_empty()
+    }
+
     fn handle_stun_message(
         server_states: &mut ServerStates,
         now: Instant,
@@ -203,7 +287,63 @@ impl GatewayHandler {
             }
         };
 
-        GatewayHandler::add_endpoint(server_states, &request, &candidate, &transport_context)?;
+        let Some((remote_role, remote_tie_breaker)) = GatewayHandler::get_ice_role(&request)?
+        else {
+            debug!(
+                "handle_stun_message without ICE role from {}",
+                transport_context.peer_addr
+            );
+            return GatewayHandler::create_error_message_event(
+                now,
+                transport_context,
+                &request,
+                &candidate,
+                CODE_BAD_REQUEST,
+                b"Bad Request",
+            );
+        };
+        // the SFU is an ICE-lite agent in controlled role
+        if resolve_role_conflict(
+            IceRole::Controlled,
+            server_states.ice_tie_breaker(),
+            true,
+            remote_role,
+            remote_tie_breaker,
+        ) == RoleConflict::Reject
+        {
+            debug!(
+                "handle_stun_message role conflict with controlled {}",
+                transport_context.peer_addr
+            );
+            return GatewayHandler::create_error_message_event(
+                now,
+                transport_context,
+                &request,
+                &candidate,
+                CODE_ROLE_CONFLICT,
+                b"Role Conflict",
+            );
+        }
+
+        // only the BUNDLE transport of the session-level ICE credentials is served, so checks of
+        // a media section's own transport are rejected instead of migrating the established
+        // transport back and forth between the four-tuples of both transports
+        if TextAttribute::get_from_as(&request, ATTR_USERNAME)?.text != candidate.username() {
+            debug!(
+                "handle_stun_message of non-BUNDLE transport from {}",
+                transport_context.peer_addr
+            );
+            return GatewayHandler::create_error_message_event(
+                now,
+                transport_context,
+                &request,
+                &candidate,
+                CODE_BAD_REQUEST,
+                b"Bad Request",
+            );
+        }
+
+        GatewayHandler::add_endpoint(server_states, now, &request, &candidate, &transport_context)?;
 
         let mut response = stun::message::Message::new();
         response.build(&[
@@ -214,9 +354,7 @@ impl GatewayHandler {
                 port: transport_context.peer_addr.port(),
             }),
         ])?;
-        let integrity = MessageIntegrity::new_short_term_integrity(
-            candidate.get_local_parameters().password.clone(),
-        );
+        let integrity = GatewayHandler::get_response_integrity(&request, &candidate);
         integrity.add_to(&mut response)?;
         FINGERPRINT.add_to(&mut response)?;
 
@@ -285,19 +423,20 @@ impl GatewayHandler {
                 session_id
             )))?;
 
-        let mut new_transceivers = vec![];
-        let endpoints = session.get_endpoints();
-        for (&other_endpoint_id, other_endpoint) in endpoints.iter() {
-            if other_endpoint_id != endpoint_id {
-                let other_transceivers = other_endpoint.get_transceivers();
-                for (other_mid_value, other_transceiver) in other_transceivers.iter() {
-                    if other_transceiver.direction == RTCRtpTransceiverDirection::Recvonly {
-                        let mut transceiver = other_transceiver.clone();
-                        transceiver.mid = format!("{}-{}", other_endpoint_id, other_mid_value);
-                        transceiver.direction = RTCRtpTransceiverDirection::Sendonly;
-                        new_transceivers.push(transceiver);
-                    }
-                }
+        let publishers: Vec<EndpointId> = session
+            .get_endpoints()
+            .keys()
+            .copied()
+            .filter(|&other_endpoint_id| other_endpoint_id != endpoint_id)
+            .collect();
+        let mut renegotiation_needed = false;
+        for publisher in publishers {
+            match session.subscribe(endpoint_id, publisher) {
+                Ok(subscribed) => renegotiation_needed |= subscribed,
+                Err(err) => warn!(
+                    "{}/{}: can't subscribe to {}: {}",
+                    session_id, endpoint_id, publisher, err
+                ),
             }
         }
 
@@ -320,13 +459,7 @@ impl GatewayHandler {
             endpoint_id,
             transport.four_tuple()
         );
-        endpoint.set_renegotiation_needed(!new_transceivers.is_empty());
-
-        let (mids, transceivers) = endpoint.get_mut_mids_and_transceivers();
-        for transceiver in new_transceivers {
-            mids.push(transceiver.mid.clone());
-            transceivers.insert(transceiver.mid.clone(), transceiver);
-        }
+        endpoint.set_renegotiation_needed(renegotiation_needed);
 
         if endpoint.is_renegotiation_needed() {
             Ok(vec![GatewayHandler::create_offer_message_event(
@@ -424,6 +557,20 @@ impl GatewayHandler {
             }
             RTCSdpType::Answer => {
                 server_states.accept_answer(session_id, endpoint_id, four_tuple, request_sdp)?;
+                // keyframes of the streams the subscriber starts receiving are requested right away
+                Ok(server_states
+                    .get_mut_session(&session_id)
+                    .map(|session| {
+                        session
+                            .get_mut_endpoints()
+                            .values_mut()
+                            .filter_map(|endpoint| endpoint.take_pending_rtcp_message(now))
+                            .collect()
+                    })
+                    .unwrap_or_default())
+            }
+            RTCSdpType::Rollback => {
+                server_states.accept_rollback(session_id, endpoint_id)?;
                 Ok(vec![])
             }
             _ => Err(Error::Other(format!(
@@ -433,7 +580,7 @@ impl GatewayHandler {
         }
     }
 
-    fn handle_rtp_message(
+    pub(crate) fn handle_rtp_message(
         server_states: &mut ServerStates,
         now: Instant,
         transport_context: TransportContext,
@@ -443,24 +590,170 @@ impl GatewayHandler {
         server_states
             .get_mut_transport(&(&transport_context).into())?
             .keep_alive();
+        GatewayHandler::update_abs_send_time(server_states, &transport_context, &rtp_packet);
+
+        // padding-only packets were already counted by inbound stats and interceptors
+        if server_states.server_config().strip_padding_only_packets
+            && GatewayHandler::is_padding_only(&rtp_packet)
+        {
+            trace!(
+                "drop padding-only rtp packet from {}",
+                transport_context.peer_addr
+            );
+            return Ok(vec![]);
+        }
 
         //TODO: Selective Forwarding RTP Packets
         let peers =
             GatewayHandler::get_other_media_transport_contexts(server_states, &transport_context)?;
 
+        let is_dominant_speaker_audio = GatewayHandler::update_dominant_speaker_selector(
+            server_states,
+            now,
+            &transport_context,
+            &rtp_packet,
+        );
+        let is_video = GatewayHandler::is_video(server_states, &transport_context, &rtp_packet);
+        let (svc_layer, retransmitted_packet) = if is_video {
+            GatewayHandler::update_simulcast_rid(server_states, &transport_context, &rtp_packet);
+            (
+                GatewayHandler::get_svc_layer(server_states, &transport_context, &rtp_packet),
+                GatewayHandler::unwrap_simulcast_retransmission(
+                    server_states,
+                    &transport_context,
+                    &rtp_packet,
+                ),
+            )
+        } else {
+            (None, None)
+        };
+
         let mut outgoing_messages = Vec::with_capacity(peers.len());
         for transport in peers {
-            outgoing_messages.push(TaggedMessageEvent {
-                now,
-                transport,
-                message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet.clone())),
-            });
+            let simulcast_selection = if is_video {
+                GatewayHandler::get_simulcast_selection(
+                    server_states,
+                    &transport_context,
+                    &transport,
+                    &rtp_packet,
+                )
+            } else {
+                None
+            };
+
+            let mut rtp_packet = if let Some((mid, selected)) = simulcast_selection {
+                // retransmissions continue the single stream as the packets they repair
+                let Some(rewritten_packet) = GatewayHandler::rewrite_simulcast_layer(
+                    server_states,
+                    now,
+                    &transport_context,
+                    &transport,
+                    &mid,
+                    selected,
+                    retransmitted_packet.as_ref().unwrap_or(&rtp_packet),
+                    retransmitted_packet.is_some(),
+                ) else {
+                    trace!(
+                        "skip unselected simulcast layer of ssrc {} from {} to {}",
+                        rtp_packet.header.ssrc,
+                        transport_context.peer_addr,
+                        transport.peer_addr
+                    );
+                    continue;
+                };
+                rewritten_packet
+            } else if is_dominant_speaker_audio {
+                let ssrc = GatewayHandler::allocate_ssrc(
+                    server_states,
+                    &transport_context,
+                    &transport,
+                    rtp_packet.header.ssrc,
+                )
+                .unwrap_or(rtp_packet.header.ssrc);
+                let Some(speaker_packet) = GatewayHandler::select_dominant_speaker_audio(
+                    server_states,
+                    now,
+                    &transport_context,
+                    &transport,
+                    &rtp_packet,
+                    ssrc,
+                ) else {
+                    continue;
+                };
+                speaker_packet
+            } else {
+                let mut rtp_packet = rtp_packet.clone();
+                rtp_packet.header.ssrc = GatewayHandler::allocate_ssrc(
+                    server_states,
+                    &transport_context,
+                    &transport,
+                    rtp_packet.header.ssrc,
+                )
+                .unwrap_or(rtp_packet.header.ssrc);
+                rtp_packet
+            };
+
+            let payload_type = GatewayHandler::remap_payload_type(
+                server_states,
+                &transport_context,
+                &transport,
+                rtp_packet.header.payload_type,
+            );
+            if payload_type.is_none() {
+                debug!(
+                    "drop rtp packet with unmappable payload type {} from {} to {}",
+                    rtp_packet.header.payload_type,
+                    transport_context.peer_addr,
+                    transport.peer_addr
+                );
+            }
+
+            let rtp_packets = if is_video {
+                let skipped = svc_layer.as_ref().is_some_and(|(mime_type, layer)| {
+                    GatewayHandler::exceeds_max_svc_layers(
+                        server_states,
+                        now,
+                        &transport,
+                        mime_type,
+                        *layer,
+                    )
+                });
+                GatewayHandler::rewrite_marker(
+                    server_states,
+                    &transport,
+                    rtp_packet,
+                    payload_type,
+                    skipped,
+                )
+            } else if let Some(payload_type) = payload_type {
+                rtp_packet.header.payload_type = payload_type;
+                vec![rtp_packet]
+            } else {
+                vec![]
+            };
+
+            let extmap_allow_mixed = server_states
+                .find_endpoint(&(&transport).into())
+                .and_then(|(session_id, endpoint_id)| {
+                    server_states
+                        .get_session(&session_id)?
+                        .get_endpoint(&endpoint_id)
+                })
+                .is_some_and(|endpoint| endpoint.extmap_allow_mixed());
+            for mut rtp_packet in rtp_packets {
+                GatewayHandler::fit_header_extensions(&mut rtp_packet, extmap_allow_mixed);
+                outgoing_messages.push(TaggedMessageEvent {
+                    now,
+                    transport,
+                    message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)),
+                });
+            }
         }
 
         Ok(outgoing_messages)
     }
 
-    fn handle_rtcp_message(
+    pub(crate) fn handle_rtcp_message(
         server_states: &mut ServerStates,
         now: Instant,
         transport_context: TransportContext,
@@ -471,22 +764,226 @@ impl GatewayHandler {
             .get_mut_transport(&(&transport_context).into())?
             .keep_alive();
 
-        //TODO: Selective Forwarding RTCP Packets
         let peers =
             GatewayHandler::get_other_media_transport_contexts(server_states, &transport_context)?;
 
         let mut outgoing_messages = Vec::with_capacity(peers.len());
         for transport in peers {
+            let rtcp_packets = GatewayHandler::map_rtcp_packets(
+                server_states,
+                &transport_context,
+                &transport,
+                &rtcp_packets,
+            );
+            if rtcp_packets.is_empty() {
+                continue;
+            }
             outgoing_messages.push(TaggedMessageEvent {
                 now,
                 transport,
-                message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets.clone())),
+                message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)),
             });
         }
 
         Ok(outgoing_messages)
     }
 
+    /// map_rtcp_packets translates SSRCs of the RTCP packets from the sender endpoint to the ones
+    /// known by the peer endpoint. SSRCs of the sender's own streams, e.g., of SR, SDES and BYE,
+    /// are mapped to the ones allocated for the peer, while media SSRCs of feedback, e.g., PLI, FIR,
+    /// NACK and REMB, are mapped from the ones allocated for the sender back to the publisher's,
+    /// so feedback only reaches the publisher. Packets without SSRCs known by the peer are dropped.
+    pub(crate) fn map_rtcp_packets(
+        server_states: &ServerStates,
+        sender: &TransportContext,
+        peer: &TransportContext,
+        rtcp_packets: &[Box<dyn rtcp::packet::Packet>],
+    ) -> Vec<Box<dyn rtcp::packet::Packet>> {
+        let Some((session_id, sender_endpoint_id)) = server_states.find_endpoint(&sender.into())
+        else {
+            return vec![];
+        };
+        let Some((_, peer_endpoint_id)) = server_states.find_endpoint(&peer.into()) else {
+            return vec![];
+        };
+        let Some((sender_endpoint, peer_endpoint)) =
+            server_states.get_session(&session_id).and_then(|session| {
+                Some((
+                    session.get_endpoint(&sender_endpoint_id)?,
+                    session.get_endpoint(&peer_endpoint_id)?,
+                ))
+            })
+        else {
+            return vec![];
+        };
+
+        // the sender's stream as forwarded to the peer
+        let to_peer = |ssrc: SSRC| {
+            peer_endpoint
+                .get_ssrc_allocator()
+                .get(sender_endpoint_id, ssrc)
+        };
+        // the peer's stream which is forwarded to the sender, e.g., the one of feedback
+        let to_publisher = |ssrc: SSRC| match sender_endpoint.get_ssrc_allocator().source(ssrc) {
+            Some((publisher, ssrc)) if publisher == peer_endpoint_id => Some(ssrc),
+            _ => None,
+        };
+
+        let mut mapped: Vec<Box<dyn rtcp::packet::Packet>> = vec![];
+        for rtcp_packet in rtcp_packets {
+            let packet = rtcp_packet.as_any();
+            if let Some(sr) = packet.downcast_ref::<SenderReport>() {
+                if let Some(ssrc) = to_peer(sr.ssrc) {
+                    // reception reports are about the sender's inbound streams from the SFU
+                    mapped.push(Box::new(SenderReport {
+                        ssrc,
+                        reports: vec![],
+                        ..sr.clone()
+                    }));
+                }
+            } else if let Some(sdes) = packet.downcast_ref::<SourceDescription>() {
+                let chunks: Vec<SourceDescriptionChunk> = sdes
+                    .chunks
+                    .iter()
+                    .filter_map(|chunk| {
+                        Some(SourceDescriptionChunk {
+                            source: to_peer(chunk.source)?,
+                            items: chunk.items.clone(),
+                        })
+                    })
+                    .collect();
+                if !chunks.is_empty() {
+                    mapped.push(Box::new(SourceDescription { chunks }));
+                }
+            } else if let Some(bye) = packet.downcast_ref::<Goodbye>() {
+                let sources: Vec<SSRC> = bye.sources.iter().filter_map(|&s| to_peer(s)).collect();
+                if !sources.is_empty() {
+                    mapped.push(Box::new(Goodbye {
+                        sources,
+                        reason: bye.reason.clone(),
+                    }));
+                }
+            } else if let Some(pli) = packet.downcast_ref::<PictureLossIndication>() {
+                if let Some(media_ssrc) = to_publisher(pli.media_ssrc) {
+                    mapped.push(Box::new(PictureLossIndication {
+                        sender_ssrc: pli.sender_ssrc,
+                        media_ssrc,
+                    }));
+                }
+            } else if let Some(fir) = packet.downcast_ref::<FullIntraRequest>() {
+                let entries: Vec<FirEntry> = fir
+                    .fir
+                    .iter()
+                    .filter_map(|entry| {
+                        Some(FirEntry {
+                            ssrc: to_publisher(entry.ssrc)?,
+                            sequence_number: entry.sequence_number,
+                        })
+                    })
+                    .collect();
+                if !entries.is_empty() {
+                    mapped.push(Box::new(FullIntraRequest {
+                        sender_ssrc: fir.sender_ssrc,
+                        // media ssrc is unused and set to 0 by RFC 5104
+                        media_ssrc: to_publisher(fir.media_ssrc).unwrap_or(0),
+                        fir: entries,
+                    }));
+                }
+            } else if let Some(nack) = packet.downcast_ref::<TransportLayerNack>() {
+                if let Some(media_ssrc) = to_publisher(nack.media_ssrc) {
+                    mapped.push(Box::new(TransportLayerNack {
+                        sender_ssrc: nack.sender_ssrc,
+                        media_ssrc,
+                        nacks: nack.nacks.clone(),
+                    }));
+                }
+            } else if let Some(remb) = packet.downcast_ref::<ReceiverEstimatedMaximumBitrate>() {
+                let ssrcs: Vec<SSRC> = remb
+                    .ssrcs
+                    .iter()
+                    .filter_map(|&ssrc| to_publisher(ssrc))
+                    .collect();
+                if !ssrcs.is_empty() {
+                    mapped.push(Box::new(ReceiverEstimatedMaximumBitrate {
+                        sender_ssrc: remb.sender_ssrc,
+                        bitrate: remb.bitrate,
+                        ssrcs,
+                    }));
+                }
+            } else {
+                trace!(
+                    "drop rtcp packet {:?} without ssrc mapping from {} to {}",
+                    rtcp_packet.header().packet_type,
+                    sender.peer_addr,
+                    peer.peer_addr
+                );
+            }
+        }
+        mapped
+    }
+
+    /// get_response_integrity returns the integrity of responses with the password of the media
+    /// section whose username fragment is requested
+    fn get_response_integrity(
+        request: &stun::message::Message,
+        candidate: &Candidate,
+    ) -> MessageIntegrity {
+        let password = TextAttribute::get_from_as(request, ATTR_USERNAME)
+            .ok()
+            .and_then(|username| candidate.get_local_parameters_by_username(&username.text))
+            .unwrap_or_else(|| candidate.get_local_parameters())
+            .password
+            .clone();
+        MessageIntegrity::new_short_term_integrity(password)
+    }
+
+    /// get_ice_role returns the role and the tie-breaker of the agent sending the binding request,
+    /// which carries either ICE-CONTROLLING or ICE-CONTROLLED attribute of 64-bit tie-breaker,
+    /// or None if it carries neither
+    pub(crate) fn get_ice_role(request: &stun::message::Message) -> Result<Option<(IceRole, u64)>> {
+        let (role, attribute) = if request.contains(ATTR_ICE_CONTROLLING) {
+            (IceRole::Controlling, ATTR_ICE_CONTROLLING)
+        } else if request.contains(ATTR_ICE_CONTROLLED) {
+            (IceRole::Controlled, ATTR_ICE_CONTROLLED)
+        } else {
+            return Ok(None);
+        };
+        let tie_breaker: [u8; 8] = request
+            .get(attribute)?
+            .try_into()
+            .map_err(|_| Error::Other(format!("invalid tie-breaker of {}", attribute)))?;
+        Ok(Some((role, u64::from_be_bytes(tie_breaker))))
+    }
+
+    /// create_error_message_event returns the binding error response of code to the
+    /// authenticated request
+    fn create_error_message_event(
+        now: Instant,
+        transport_context: TransportContext,
+        request: &stun::message::Message,
+        candidate: &Candidate,
+        code: ErrorCode,
+        reason: &[u8],
+    ) -> Result<Vec<TaggedMessageEvent>> {
+        let mut response = stun::message::Message::new();
+        response.build(&[
+            Box::new(BINDING_ERROR),
+            Box::new(request.transaction_id),
+            Box::new(ErrorCodeAttribute {
+                code,
+                reason: reason.to_vec(),
+            }),
+        ])?;
+        GatewayHandler::get_response_integrity(request, candidate).add_to(&mut response)?;
+        FINGERPRINT.add_to(&mut response)?;
+
+        Ok(vec![TaggedMessageEvent {
+            now,
+            transport: transport_context,
+            message: MessageEvent::Stun(STUNMessageEvent::Stun(response)),
+        }])
+    }
+
     fn check_stun_message(
         server_states: &ServerStates,
         request: &mut stun::message::Message,
@@ -503,19 +1000,21 @@ impl GatewayHandler {
                     if request.contains(ATTR_ICE_CONTROLLED) {
                         return Err(Error::Other("invalid STUN message with both ATTR_ICE_CONTROLLING and ATTR_ICE_CONTROLLED".to_string()));
                     }
-                } else if request.contains(ATTR_ICE_CONTROLLED) {
-                    if request.contains(ATTR_USE_CANDIDATE) {
-                        return Err(Error::Other("invalid STUN message with both ATTR_USE_CANDIDATE and ATTR_ICE_CONTROLLED".to_string()));
-                    }
-                } else {
+                } else if request.contains(ATTR_ICE_CONTROLLED)
+                    && request.contains(ATTR_USE_CANDIDATE)
+                {
                     return Err(Error::Other(
-                        "invalid STUN message without ATTR_ICE_CONTROLLING or ATTR_ICE_CONTROLLED"
+                        "invalid STUN message with both ATTR_USE_CANDIDATE and ATTR_ICE_CONTROLLED"
                             .to_string(),
                     ));
                 }
 
                 if let Some(candidate) = server_states.find_candidate(&username.text) {
-                    let password = candidate.get_local_parameters().password.clone();
+                    let password = candidate
+                        .get_local_parameters_by_username(&username.text)
+                        .ok_or(Error::Other("username not found".to_string()))?
+                        .password
+                        .clone();
                     let integrity = MessageIntegrity::new_short_term_integrity(password);
                     integrity.check(request)?;
                     Ok(Some(candidate.clone()))
@@ -631,6 +1130,458 @@ impl GatewayHandler {
         Ok(peers)
     }
 
+    /// remap_payload_type translates the payload type negotiated by the publisher endpoint to the one
+    /// negotiated by the subscriber endpoint for the same codec
+    fn remap_payload_type(
+        server_states: &ServerStates,
+        publisher: &TransportContext,
+        subscriber: &TransportContext,
+        payload_type: PayloadType,
+    ) -> Option<PayloadType> {
+        let (session_id, publisher_endpoint_id) = server_states.find_endpoint(&publisher.into())?;
+        let (_, subscriber_endpoint_id) = server_states.find_endpoint(&subscriber.into())?;
+        let session = server_states.get_session(&session_id)?;
+        let publisher_endpoint = session.get_endpoint(&publisher_endpoint_id)?;
+        let subscriber_endpoint = session.get_endpoint(&subscriber_endpoint_id)?;
+
+        publisher_endpoint.remap_payload_type(payload_type, subscriber_endpoint)
+    }
+
+    /// allocate_ssrc translates the publisher's ssrc to the one forwarded to the subscriber,
+    /// unsignaled ssrcs, e.g., simulcast layers, are allocated on their first packet
+    fn allocate_ssrc(
+        server_states: &mut ServerStates,
+        publisher: &TransportContext,
+        subscriber: &TransportContext,
+        ssrc: SSRC,
+    ) -> Option<SSRC> {
+        let (session_id, publisher_endpoint_id) = server_states.find_endpoint(&publisher.into())?;
+        let (_, subscriber_endpoint_id) = server_states.find_endpoint(&subscriber.into())?;
+        let session = server_states.get_mut_session(&session_id)?;
+        let subscriber_endpoint = session.get_mut_endpoint(&subscriber_endpoint_id)?;
+
+        Some(
+            subscriber_endpoint
+                .get_mut_ssrc_allocator()
+                .allocate(publisher_endpoint_id, ssrc),
+        )
+    }
+
+    /// is_video returns true if the publisher's packet is of a negotiated video codec
+    fn is_video(
+        server_states: &ServerStates,
+        publisher: &TransportContext,
+        rtp_packet: &rtp::packet::Packet,
+    ) -> bool {
+        server_states
+            .find_endpoint(&publisher.into())
+            .and_then(|(session_id, endpoint_id)| {
+                server_states
+                    .get_session(&session_id)?
+                    .get_endpoint(&endpoint_id)?
+                    .get_codec_by_payload_type(rtp_packet.header.payload_type)
+            })
+            .is_some_and(|(_, kind)| kind == RTPCodecType::Video)
+    }
+
+    /// get_rid returns the rid carried by the RTP stream id or repaired RTP stream id extension
+    /// <https://datatracker.ietf.org/doc/html/rfc8852#section-3>
+    pub(crate) fn get_rid(rtp_packet: &rtp::packet::Packet, id: Option<u8>) -> Option<String> {
+        let extension = rtp_packet.header.get_extension(id?)?;
+        String::from_utf8(extension.to_vec())
+            .ok()
+            .filter(|rid| !rid.is_empty())
+    }
+
+    /// fit_header_extensions rewrites two-byte header extensions of the packet forwarded to a
+    /// subscriber which didn't negotiate extmap-allow-mixed into one-byte ones, so that it never
+    /// receives both forms, extensions which don't fit the one-byte form are dropped
+    /// <https://datatracker.ietf.org/doc/html/rfc8285#section-4.3>
+    pub(crate) fn fit_header_extensions(
+        rtp_packet: &mut rtp::packet::Packet,
+        extmap_allow_mixed: bool,
+    ) {
+        let header = &mut rtp_packet.header;
+        if extmap_allow_mixed
+            || !header.extension
+            || header.extension_profile != rtp::header::EXTENSION_PROFILE_TWO_BYTE
+        {
+            return;
+        }
+        header.extension_profile = rtp::header::EXTENSION_PROFILE_ONE_BYTE;
+        header.extensions.retain(|extension| {
+            (1..=14).contains(&extension.id) && (1..=16).contains(&extension.payload.len())
+        });
+        header.extension = !header.extensions.is_empty();
+    }
+
+    /// update_abs_send_time records the absolute send time carried by the publisher's packet with
+    /// the negotiated abs-send-time extension, so that it is reported by the inbound RTP stats
+    fn update_abs_send_time(
+        server_states: &mut ServerStates,
+        publisher: &TransportContext,
+        rtp_packet: &rtp::packet::Packet,
+    ) {
+        let four_tuple = publisher.into();
+        let Some(abs_send_time) = server_states
+            .find_endpoint(&four_tuple)
+            .and_then(|(session_id, endpoint_id)| {
+                let endpoint = server_states
+                    .get_session(&session_id)?
+                    .get_endpoint(&endpoint_id)?;
+                let (_, kind) =
+                    endpoint.get_codec_by_payload_type(rtp_packet.header.payload_type)?;
+                endpoint.get_negotiated_header_extension_id(kind, sdp::extmap::ABS_SEND_TIME_URI)
+            })
+            .and_then(|id| get_abs_send_time(rtp_packet, id))
+        else {
+            return;
+        };
+        if let Ok(transport) = server_states.get_mut_transport(&four_tuple) {
+            transport.set_abs_send_time(rtp_packet.header.ssrc, abs_send_time);
+        }
+    }
+
+    /// update_simulcast_rid associates the publisher's video stream with its simulcast layer,
+    /// so that RTX retransmissions are mapped to the layer of the repaired rid
+    fn update_simulcast_rid(
+        server_states: &mut ServerStates,
+        publisher: &TransportContext,
+        rtp_packet: &rtp::packet::Packet,
+    ) {
+        let four_tuple = publisher.into();
+        let Some(endpoint) =
+            server_states
+                .find_endpoint(&four_tuple)
+                .and_then(|(session_id, endpoint_id)| {
+                    server_states
+                        .get_session(&session_id)?
+                        .get_endpoint(&endpoint_id)
+                })
+        else {
+            return;
+        };
+        let rid = GatewayHandler::get_rid(
+            rtp_packet,
+            endpoint.get_negotiated_header_extension_id(
+                RTPCodecType::Video,
+                sdp::extmap::SDES_RTP_STREAM_ID_URI,
+            ),
+        );
+        let repaired_rid = GatewayHandler::get_rid(
+            rtp_packet,
+            endpoint.get_negotiated_header_extension_id(
+                RTPCodecType::Video,
+                SDES_REPAIRED_RTP_STREAM_ID_URI,
+            ),
+        );
+        if rid.is_none() && repaired_rid.is_none() {
+            return;
+        }
+        if let Ok(transport) = server_states.get_mut_transport(&four_tuple) {
+            transport.set_simulcast_rid(rtp_packet.header.ssrc, rid, repaired_rid);
+        }
+    }
+
+    /// get_simulcast_selection returns the mid of the subscriber's transceiver forwarding the
+    /// publisher's video packet of a simulcast layer, and whether the layer is the one selected by
+    /// the transceiver, or None if the packet is of no simulcast layer or no layer is selected.
+    /// The transceiver is the one of the packet's mid, or the first one forwarded from the
+    /// publisher with a selected layer if the packet carries no mid
+    fn get_simulcast_selection(
+        server_states: &ServerStates,
+        publisher: &TransportContext,
+        subscriber: &TransportContext,
+        rtp_packet: &rtp::packet::Packet,
+    ) -> Option<(Mid, bool)> {
+        let four_tuple: FourTuple = publisher.into();
+        let get_endpoint = |four_tuple: &FourTuple| {
+            server_states
+                .find_endpoint(four_tuple)
+                .and_then(|(session_id, endpoint_id)| {
+                    server_states
+                        .get_session(&session_id)?
+                        .get_endpoint(&endpoint_id)
+                })
+        };
+        let publisher_endpoint = get_endpoint(&four_tuple)?;
+        let subscriber_endpoint = get_endpoint(&subscriber.into())?;
+        let rid = publisher_endpoint
+            .get_transports()
+            .get(&four_tuple)
+            .and_then(|transport| transport.get_simulcast_rid(rtp_packet.header.ssrc))?;
+
+        let publisher_endpoint_id = publisher_endpoint.endpoint_id();
+        let mid = publisher_endpoint
+            .get_negotiated_header_extension_id(RTPCodecType::Video, sdp::extmap::SDES_MID_URI)
+            .and_then(|id| rtp_packet.header.get_extension(id))
+            .filter(|mid| !mid.is_empty());
+        // mid of a forwarded transceiver is prefixed by the publisher's endpoint id
+        subscriber_endpoint
+            .get_transceivers()
+            .iter()
+            .filter(|(_, transceiver)| transceiver.kind == RTPCodecType::Video)
+            .filter(|(other_mid, _)| {
+                other_mid
+                    .split_once('-')
+                    .is_some_and(|(endpoint_id, other_mid)| {
+                        endpoint_id.parse() == Ok(publisher_endpoint_id)
+                            && mid.as_ref().is_none_or(|mid| other_mid.as_bytes() == mid)
+                    })
+            })
+            .find_map(|(other_mid, transceiver)| {
+                let selected_rid = transceiver.selected_rid.as_deref()?;
+                Some((other_mid.clone(), selected_rid == rid))
+            })
+    }
+
+    /// unwrap_simulcast_retransmission returns the original packet carried by the publisher's RTX
+    /// packet of a simulcast layer, with ssrc and payload type of the repaired stream and the
+    /// original sequence number, or None if the packet is no RTX one of a known repaired stream
+    /// <https://datatracker.ietf.org/doc/html/rfc4588#section-4>
+    fn unwrap_simulcast_retransmission(
+        server_states: &ServerStates,
+        publisher: &TransportContext,
+        rtp_packet: &rtp::packet::Packet,
+    ) -> Option<rtp::packet::Packet> {
+        let four_tuple: FourTuple = publisher.into();
+        let (session_id, endpoint_id) = server_states.find_endpoint(&four_tuple)?;
+        let endpoint = server_states
+            .get_session(&session_id)?
+            .get_endpoint(&endpoint_id)?;
+        let repaired_ssrc = endpoint
+            .get_transports()
+            .get(&four_tuple)?
+            .get_repaired_ssrc(rtp_packet.header.ssrc)?;
+        let (codec, _) = endpoint.get_codec_by_payload_type(rtp_packet.header.payload_type)?;
+        let payload_type = get_apt(&codec.capability.sdp_fmtp_line)?;
+        if rtp_packet.payload.len() < 2 {
+            return None;
+        }
+
+        let mut original_packet = rtp_packet.clone();
+        original_packet.header.ssrc = repaired_ssrc;
+        original_packet.header.payload_type = payload_type;
+        original_packet.header.sequence_number =
+            u16::from_be_bytes([rtp_packet.payload[0], rtp_packet.payload[1]]);
+        original_packet.payload = rtp_packet.payload.slice(2..);
+        Some(original_packet)
+    }
+
+    /// rewrite_simulcast_layer returns the publisher's video packet of a simulcast layer rewritten
+    /// into the stream of the subscriber's transceiver with mid, or None if it is not forwarded,
+    /// selected is true if the layer is the one selected by the transceiver, a retransmitted
+    /// packet is only forwarded if its layer is the forwarded one, rather than switching to it
+    #[allow(clippy::too_many_arguments)]
+    fn rewrite_simulcast_layer(
+        server_states: &mut ServerStates,
+        now: Instant,
+        publisher: &TransportContext,
+        subscriber: &TransportContext,
+        mid: &str,
+        selected: bool,
+        rtp_packet: &rtp::packet::Packet,
+        retransmitted: bool,
+    ) -> Option<rtp::packet::Packet> {
+        let grace_period = server_states.server_config().svc_layer_switch_grace_period;
+        let four_tuple = subscriber.into();
+        if let Some(simulcast_rewriter) = server_states
+            .get_mut_transport(&four_tuple)
+            .ok()?
+            .get_mut_simulcast_rewriter(mid)
+        {
+            if retransmitted && !simulcast_rewriter.is_forwarding(rtp_packet.header.ssrc) {
+                return None;
+            }
+        } else {
+            if !selected || retransmitted {
+                return None;
+            }
+            // the stream keeps the ssrc of its first forwarded layer
+            let ssrc = GatewayHandler::allocate_ssrc(
+                server_states,
+                publisher,
+                subscriber,
+                rtp_packet.header.ssrc,
+            )
+            .unwrap_or(rtp_packet.header.ssrc);
+            server_states
+                .get_mut_transport(&four_tuple)
+                .ok()?
+                .add_simulcast_rewriter(mid.to_string(), ssrc);
+        }
+        server_states
+            .get_mut_transport(&four_tuple)
+            .ok()?
+            .get_mut_simulcast_rewriter(mid)?
+            .rewrite(now, rtp_packet, selected, grace_period)
+    }
+
+    /// get_svc_layer returns the codec mime type and the layer of the publisher's video packet,
+    /// or None if the packet has no dependency descriptor
+    fn get_svc_layer(
+        server_states: &mut ServerStates,
+        publisher: &TransportContext,
+        rtp_packet: &rtp::packet::Packet,
+    ) -> Option<(String, SvcLayer)> {
+        let four_tuple = publisher.into();
+        let (session_id, endpoint_id) = server_states.find_endpoint(&four_tuple)?;
+        let endpoint = server_states
+            .get_session(&session_id)?
+            .get_endpoint(&endpoint_id)?;
+        let (codec, _) = endpoint.get_codec_by_payload_type(rtp_packet.header.payload_type)?;
+        let mime_type = codec.capability.mime_type.clone();
+        let extension = endpoint
+            .get_negotiated_header_extension_id(RTPCodecType::Video, DEPENDENCY_DESCRIPTOR_URI)
+            .and_then(|id| rtp_packet.header.get_extension(id))?;
+
+        let dependency_descriptor = match DependencyDescriptor::unmarshal(&extension) {
+            Ok(dependency_descriptor) => dependency_descriptor,
+            Err(err) => {
+                trace!(
+                    "invalid dependency descriptor from {}: {}",
+                    publisher.peer_addr,
+                    err
+                );
+                return None;
+            }
+        };
+        let layer = server_states
+            .get_mut_transport(&four_tuple)
+            .ok()?
+            .get_svc_layer(rtp_packet.header.ssrc, dependency_descriptor)?;
+        Some((mime_type, layer))
+    }
+
+    /// exceeds_max_svc_layers returns true if the layer is above the subscriber's cap of the codec,
+    /// or above its previous cap within the grace period after the cap is switched
+    fn exceeds_max_svc_layers(
+        server_states: &ServerStates,
+        now: Instant,
+        subscriber: &TransportContext,
+        mime_type: &str,
+        layer: SvcLayer,
+    ) -> bool {
+        let grace_period = server_states.server_config().svc_layer_switch_grace_period;
+        server_states
+            .find_endpoint(&subscriber.into())
+            .and_then(|(session_id, endpoint_id)| {
+                server_states
+                    .get_session(&session_id)?
+                    .get_endpoint(&endpoint_id)
+            })
+            .is_some_and(|endpoint| {
+                endpoint.exceeds_max_svc_layers(mime_type, layer, now, grace_period)
+            })
+    }
+
+    /// rewrite_marker returns the video packets to send to the subscriber, so the last forwarded
+    /// packet of a frame keeps the marker bit, payload_type is None if the packet is dropped,
+    /// skipped is true if the packet is above the subscriber's SVC layer cap
+    fn rewrite_marker(
+        server_states: &mut ServerStates,
+        subscriber: &TransportContext,
+        mut rtp_packet: rtp::packet::Packet,
+        payload_type: Option<PayloadType>,
+        skipped: bool,
+    ) -> Vec<rtp::packet::Packet> {
+        let Ok(transport) = server_states.get_mut_transport(&subscriber.into()) else {
+            return vec![];
+        };
+        let marker_rewriter = transport.get_mut_marker_rewriter(rtp_packet.header.ssrc);
+        if skipped {
+            marker_rewriter.skip(&rtp_packet)
+        } else if let Some(payload_type) = payload_type {
+            rtp_packet.header.payload_type = payload_type;
+            marker_rewriter.forward(rtp_packet)
+        } else {
+            marker_rewriter.drop(&rtp_packet)
+        }
+    }
+
+    /// update_dominant_speaker_selector records the audio level of the publisher's audio packet,
+    /// returns true if the packet is audio in a session of dominant speaker mode
+    fn update_dominant_speaker_selector(
+        server_states: &mut ServerStates,
+        now: Instant,
+        publisher: &TransportContext,
+        rtp_packet: &rtp::packet::Packet,
+    ) -> bool {
+        let Some((session_id, endpoint_id)) = server_states.find_endpoint(&publisher.into()) else {
+            return false;
+        };
+        let Some(session) = server_states.get_mut_session(&session_id) else {
+            return false;
+        };
+        let Some(endpoint) = session.get_endpoint(&endpoint_id) else {
+            return false;
+        };
+        let Some(clock_rate) = endpoint
+            .get_codec_by_payload_type(rtp_packet.header.payload_type)
+            .filter(|(_, kind)| *kind == RTPCodecType::Audio)
+            .map(|(codec, _)| codec.capability.clock_rate)
+        else {
+            return false;
+        };
+
+        // ssrc-audio-level has the voice activity flag in MSB and the level in the other 7 bits
+        let audio_level = endpoint
+            .get_negotiated_header_extension_id(RTPCodecType::Audio, sdp::extmap::AUDIO_LEVEL_URI)
+            .and_then(|id| rtp_packet.header.get_extension(id))
+            .and_then(|ext| ext.first().map(|b| b & 0x7F));
+
+        if let Some(dominant_speaker_selector) = session.get_mut_dominant_speaker_selector() {
+            dominant_speaker_selector.update_talker(
+                now,
+                endpoint_id,
+                rtp_packet.header.ssrc,
+                audio_level,
+                clock_rate,
+            );
+            true
+        } else {
+            false
+        }
+    }
+
+    /// select_dominant_speaker_audio returns the audio packet rewritten into subscriber's stream of
+    /// the dominant speaker, or None if the packet is not forwarded to the subscriber, the stream
+    /// carries mixer-to-client audio levels instead of the publisher's client-to-mixer one
+    fn select_dominant_speaker_audio(
+        server_states: &mut ServerStates,
+        now: Instant,
+        publisher: &TransportContext,
+        subscriber: &TransportContext,
+        rtp_packet: &rtp::packet::Packet,
+        ssrc: SSRC,
+    ) -> Option<rtp::packet::Packet> {
+        let (session_id, publisher_endpoint_id) = server_states.find_endpoint(&publisher.into())?;
+        let (_, endpoint_id) = server_states.find_endpoint(&subscriber.into())?;
+        let session = server_states.get_mut_session(&session_id)?;
+        let ssrc_audio_level_id = session
+            .get_endpoint(&publisher_endpoint_id)?
+            .get_negotiated_header_extension_id(RTPCodecType::Audio, sdp::extmap::AUDIO_LEVEL_URI);
+        let csrc_audio_level_id = session
+            .get_endpoint(&endpoint_id)?
+            .get_negotiated_header_extension_id(RTPCodecType::Audio, CSRC_AUDIO_LEVEL_URI);
+
+        let dominant_speaker_selector = session.get_mut_dominant_speaker_selector()?;
+        let mut speaker_packet =
+            dominant_speaker_selector.select(now, endpoint_id, rtp_packet, ssrc)?;
+        if let Err(err) = dominant_speaker_selector.set_csrc_audio_levels(
+            &mut speaker_packet,
+            ssrc_audio_level_id,
+            csrc_audio_level_id,
+        ) {
+            trace!(
+                "can't set csrc audio levels for {}: {}",
+                subscriber.peer_addr,
+                err
+            );
+        }
+        Some(speaker_packet)
+    }
+
     fn create_server_reflective_address_message_event(
         now: Instant,
         transport_context: TransportContext,
@@ -660,6 +1611,7 @@ impl GatewayHandler {
 
     fn add_endpoint(
         server_states: &mut ServerStates,
+        now: Instant,
         request: &stun::message::Message,
         candidate: &Rc<Candidate>,
         transport_context: &TransportContext,
@@ -681,9 +1633,31 @@ impl GatewayHandler {
             false
         };
 
-        if !request.contains(ATTR_USE_CANDIDATE) || has_transport {
+        if has_transport {
             return Ok(is_new_endpoint);
         }
+        if !request.contains(ATTR_USE_CANDIDATE) {
+            // a validated but not nominated pair is kept as backup of the selected one
+            if let Some(endpoint) = session.get_mut_endpoint(&endpoint_id) {
+                endpoint.add_backup_four_tuple(four_tuple, now);
+            }
+            return Ok(is_new_endpoint);
+        }
+
+        // a nominated new path of an established endpoint, e.g., client changes its IP address,
+        // migrates the existing transport instead of creating a new one
+        if let Some(old_four_tuple) = session
+            .get_mut_endpoint(&endpoint_id)
+            .and_then(|endpoint| endpoint.migrate(four_tuple))
+        {
+            info!(
+                "{}/{} is migrated from {:?} to {:?}",
+                session_id, endpoint_id, old_four_tuple, four_tuple
+            );
+            server_states.remove_endpoint(&old_four_tuple);
+            server_states.add_endpoint(four_tuple, session_id, endpoint_id);
+            return Ok(false);
+        }
 
         let is_new_endpoint = session.add_endpoint(candidate, transport_context)?;
 
@@ -734,7 +1708,7 @@ impl GatewayHandler {
 
         let offer = session.create_offer(
             endpoint_id,
-            &remote_description,
+            Some(&remote_description),
             &local_conn_cred.ice_params,
         )?;
         session.set_local_description(endpoint_id, &offer)?;
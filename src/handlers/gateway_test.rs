@@ -0,0 +1,849 @@
+use super::gateway::*;
+use crate::configs::{media_config::MediaConfig, server_config::ServerConfig};
+use crate::description::rtp_codec::{RTCRtpHeaderExtensionCapability, RTPCodecType};
+use crate::description::RTCSessionDescription;
+use crate::endpoint::candidate::{Candidate, ConnectionCredentials, RTCIceParameters};
+use crate::handlers::srtp_test::new_srtp_context;
+use crate::messages::{MessageEvent, RTPMessageEvent, STUNMessageEvent, TaggedMessageEvent};
+use crate::metrics::loss_monitor::LossMonitor;
+use crate::server::certificate::RTCCertificate;
+use crate::server::states::ServerStates;
+use bytes::{Bytes, BytesMut};
+use retty::channel::{InboundPipeline, Pipeline};
+use retty::transport::TransportContext;
+use rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use rtcp::sender_report::SenderReport;
+use shared::error::Result;
+use shared::marshal::{Marshal, Unmarshal};
+use std::cell::RefCell;
+use std::net::SocketAddr;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use stun::message::{Message, TransactionId, BINDING_REQUEST, BINDING_SUCCESS};
+
+fn new_gateway_handler(server_config: ServerConfig) -> Result<GatewayHandler> {
+    let local_addr: SocketAddr = "127.0.0.1:3478".parse().unwrap();
+    let server_states = ServerStates::new(
+        Arc::new(server_config),
+        local_addr,
+        opentelemetry::global::meter("test"),
+    )?;
+    Ok(GatewayHandler::new(Rc::new(RefCell::new(server_states))))
+}
+
+pub(crate) const PUBLISHER_OFFER_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=ice-ufrag:publisher\r\n\
+a=ice-pwd:publisher-password\r\n\
+a=fingerprint:sha-256 00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=sendonly\r\n\
+a=rtcp-mux\r\n\
+a=rtpmap:96 VP8/90000\r\n\
+a=rtcp-fb:96 nack pli\r\n\
+a=msid:stream0 video0\r\n\
+a=ssrc:1111 cname:publisher\r\n";
+
+/// new_connected_server_states returns server states of session 1, where endpoint 1 publishes
+/// with publisher_offer and endpoint 2 subscribes to it before negotiating, both connected with
+/// ready SRTP contexts, along with the transport contexts of the publisher and the subscriber.
+/// Default codecs are registered to the media config of server_config
+pub(crate) fn new_connected_server_states(
+    mut server_config: ServerConfig,
+    publisher_offer: &str,
+) -> Result<(ServerStates, TransportContext, TransportContext)> {
+    server_config.media_config.register_default_codecs()?;
+    server_config.certificates = vec![RTCCertificate::from_key_pair(rcgen::KeyPair::generate(
+        &rcgen::PKCS_ECDSA_P256_SHA256,
+    )?)?];
+    let mut server_states = ServerStates::new(
+        Arc::new(server_config),
+        "127.0.0.1:3478".parse().unwrap(),
+        opentelemetry::global::meter("test"),
+    )?;
+
+    let mut transport_contexts = vec![];
+    for (endpoint_id, offer) in [(2, None), (1, Some(publisher_offer))] {
+        let transport_context = TransportContext {
+            local_addr: "127.0.0.1:3478".parse().unwrap(),
+            peer_addr: format!("127.0.0.1:500{}", endpoint_id).parse().unwrap(),
+            ecn: None,
+        };
+        let four_tuple = (&transport_context).into();
+        let candidate = Rc::new(Candidate::new(
+            1,
+            endpoint_id,
+            ConnectionCredentials::default(),
+            ConnectionCredentials::default(),
+            RTCSessionDescription::default(),
+            RTCSessionDescription::default(),
+            Instant::now(),
+        ));
+        server_states
+            .create_or_get_mut_session(1)
+            .add_endpoint(&candidate, &transport_context)?;
+        server_states.add_endpoint(four_tuple, 1, endpoint_id);
+        server_states
+            .get_mut_transport(&four_tuple)?
+            .set_local_srtp_context(new_srtp_context()?);
+        if let Some(offer) = offer {
+            server_states.accept_offer(
+                1,
+                endpoint_id,
+                Some(four_tuple),
+                RTCSessionDescription::offer(offer.to_string())?,
+            )?;
+        }
+        transport_contexts.push(transport_context);
+    }
+
+    Ok((server_states, transport_contexts[1], transport_contexts[0]))
+}
+
+const SIMULCAST_PUBLISHER_OFFER_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96 97\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=ice-ufrag:publisher\r\n\
+a=ice-pwd:publisher-password\r\n\
+a=fingerprint:sha-256 00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=extmap:1 urn:ietf:params:rtp-hdrext:sdes:mid\r\n\
+a=extmap:4 urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id\r\n\
+a=extmap:5 urn:ietf:params:rtp-hdrext:sdes:repaired-rtp-stream-id\r\n\
+a=sendonly\r\n\
+a=rtcp-mux\r\n\
+a=rtpmap:96 VP8/90000\r\n\
+a=rtcp-fb:96 nack pli\r\n\
+a=rtpmap:97 rtx/90000\r\n\
+a=fmtp:97 apt=96\r\n\
+a=msid:stream0 video0\r\n\
+a=rid:q send\r\n\
+a=rid:h send\r\n\
+a=simulcast:send q;h\r\n";
+
+fn new_stun_message_event(
+    now: Instant,
+    peer_addr: &str,
+    typ: stun::message::MessageType,
+) -> Result<TaggedMessageEvent> {
+    let mut message = Message::new();
+    message.build(&[Box::new(typ), Box::new(TransactionId::new())])?;
+    Ok(TaggedMessageEvent {
+        now,
+        transport: TransportContext {
+            local_addr: "127.0.0.1:3478".parse().unwrap(),
+            peer_addr: peer_addr.parse().unwrap(),
+            ecn: None,
+        },
+        message: MessageEvent::Stun(STUNMessageEvent::Stun(message)),
+    })
+}
+
+#[test]
+fn test_stun_binding_rate_limit() -> Result<()> {
+    let certificate =
+        RTCCertificate::from_key_pair(rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+    let mut handler =
+        new_gateway_handler(ServerConfig::new(vec![certificate]).with_stun_binding_rate_limit(2))?;
+
+    let now = Instant::now();
+    let peer = "127.0.0.1:5000";
+    assert!(
+        handler.check_stun_binding_rate_limit(&new_stun_message_event(now, peer, BINDING_REQUEST)?)
+    );
+    assert!(
+        handler.check_stun_binding_rate_limit(&new_stun_message_event(now, peer, BINDING_REQUEST)?)
+    );
+    // excess binding requests are dropped
+    assert!(
+        !handler.check_stun_binding_rate_limit(&new_stun_message_event(
+            now,
+            peer,
+            BINDING_REQUEST
+        )?)
+    );
+    // other STUN messages and other four-tuples are not limited
+    assert!(
+        handler.check_stun_binding_rate_limit(&new_stun_message_event(now, peer, BINDING_SUCCESS)?)
+    );
+    assert!(
+        handler.check_stun_binding_rate_limit(&new_stun_message_event(
+            now,
+            "127.0.0.1:5001",
+            BINDING_REQUEST
+        )?)
+    );
+    // keepalive traffic at the configured rate passes
+    assert!(
+        handler.check_stun_binding_rate_limit(&new_stun_message_event(
+            now + Duration::from_secs(1),
+            peer,
+            BINDING_REQUEST
+        )?)
+    );
+
+    // the handler only answers binding requests within the rate
+    let certificate =
+        RTCCertificate::from_key_pair(rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+    let pipeline: Pipeline<TaggedMessageEvent, TaggedMessageEvent> = Pipeline::new();
+    pipeline.add_back(new_gateway_handler(
+        ServerConfig::new(vec![certificate]).with_stun_binding_rate_limit(2),
+    )?);
+    let pipeline = pipeline.finalize();
+    let read = |at: Duration, typ| -> Result<usize> {
+        pipeline.read(new_stun_message_event(now + at, peer, typ)?);
+        Ok(std::iter::from_fn(|| pipeline.poll_transmit()).count())
+    };
+    assert_eq!(read(Duration::ZERO, BINDING_REQUEST)?, 1);
+    assert_eq!(read(Duration::ZERO, BINDING_REQUEST)?, 1);
+    assert_eq!(read(Duration::ZERO, BINDING_REQUEST)?, 0);
+    assert_eq!(read(Duration::from_secs(1), BINDING_REQUEST)?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_padding_only_packet() -> Result<()> {
+    // RTP header with P and M bits set, followed by 4 bytes of padding only
+    let mut probe = BytesMut::from(
+        &[
+            0xa0, 0xe0, 0x00, 0x01, // V=2, P=1, M=1, PT=96, sequence number 1
+            0x00, 0x00, 0x00, 0x00, // timestamp
+            0x00, 0x00, 0x04, 0x57, // SSRC 1111
+            0x00, 0x00, 0x00, 0x04, // padding
+        ][..],
+    )
+    .freeze();
+    let probe = rtp::packet::Packet::unmarshal(&mut probe)?;
+    assert!(GatewayHandler::is_padding_only(&probe));
+
+    let mut media = probe.clone();
+    media.header.sequence_number = 2;
+    media.payload = Bytes::from_static(&[1, 2, 3]);
+    assert!(!GatewayHandler::is_padding_only(&media));
+
+    for strip_padding_only_packets in [true, false] {
+        let (mut server_states, publisher, subscriber) = new_connected_server_states(
+            ServerConfig::new(vec![]).with_strip_padding_only_packets(strip_padding_only_packets),
+            PUBLISHER_OFFER_SDP,
+        )?;
+        let mut forward = |rtp_packet: &rtp::packet::Packet| -> Result<usize> {
+            let messages = GatewayHandler::handle_rtp_message(
+                &mut server_states,
+                Instant::now(),
+                publisher,
+                rtp_packet.clone(),
+            )?;
+            Ok(messages
+                .iter()
+                .filter(|message| message.transport == subscriber)
+                .count())
+        };
+
+        // the probe is only forwarded if padding-only packets are not stripped
+        assert_eq!(
+            forward(&probe)?,
+            if strip_padding_only_packets { 0 } else { 1 }
+        );
+        assert_eq!(forward(&media)?, 1);
+    }
+
+    // the stripped probe still counts as received for the publisher's stream
+    let mut loss_monitor = LossMonitor::default();
+    for packet in [&probe, &media] {
+        loss_monitor.update(packet.header.sequence_number);
+    }
+    let stats = loss_monitor.stats(probe.header.ssrc);
+    assert_eq!(stats.packets_received, 2);
+    assert_eq!(stats.packets_lost, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_abs_send_time_is_reported_by_inbound_rtp_stats() -> Result<()> {
+    let mut media_config = MediaConfig::default();
+    media_config.configure_abs_send_time_extension_headers()?;
+    let (mut server_states, publisher, _) = new_connected_server_states(
+        ServerConfig::new(vec![]).with_media_config(media_config),
+        &PUBLISHER_OFFER_SDP.replace(
+            "a=rtcp-mux\r\n",
+            "a=rtcp-mux\r\na=extmap:3 http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time\r\n",
+        ),
+    )?;
+
+    let mut rtp_packet = rtp::packet::Packet::default();
+    rtp_packet.header.payload_type = 96;
+    rtp_packet.header.ssrc = 1111;
+    rtp_packet.header.marker = true;
+    rtp_packet
+        .header
+        .set_extension(3, Bytes::from_static(&[0x12, 0x34, 0x56]))?;
+    // the inbound stream is counted by the SRTP handler before reaching the gateway
+    server_states
+        .get_mut_transport(&(&publisher).into())?
+        .get_mut_loss_monitor(1111)
+        .update(rtp_packet.header.sequence_number);
+    GatewayHandler::handle_rtp_message(&mut server_states, Instant::now(), publisher, rtp_packet)?;
+
+    let stats = server_states.get_inbound_rtp_stats(1, 1);
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].abs_send_time, Some(0x123456));
+
+    Ok(())
+}
+
+#[test]
+fn test_fit_header_extensions() -> Result<()> {
+    let mut rtp_packet = rtp::packet::Packet::default();
+    rtp_packet
+        .header
+        .set_extension(3, Bytes::from_static(&[0x12, 0x34, 0x56]))?;
+    rtp_packet.header.extension_profile = rtp::header::EXTENSION_PROFILE_TWO_BYTE;
+    rtp_packet
+        .header
+        .set_extension(20, Bytes::from_static(&[0x01]))?;
+    rtp_packet
+        .header
+        .set_extension(4, Bytes::from_static(&[0; 17]))?;
+
+    // two-byte extensions are kept for a subscriber allowing mixed ones
+    let mut mixed = rtp_packet.clone();
+    GatewayHandler::fit_header_extensions(&mut mixed, true);
+    assert_eq!(mixed, rtp_packet);
+
+    // and rewritten into one-byte ones otherwise, dropping those not fitting
+    GatewayHandler::fit_header_extensions(&mut rtp_packet, false);
+    assert_eq!(
+        rtp_packet.header.extension_profile,
+        rtp::header::EXTENSION_PROFILE_ONE_BYTE
+    );
+    assert_eq!(
+        rtp_packet.header.get_extension(3),
+        Some(Bytes::from_static(&[0x12, 0x34, 0x56]))
+    );
+    assert_eq!(rtp_packet.header.get_extension(20), None);
+    assert_eq!(rtp_packet.header.get_extension(4), None);
+    let mut raw = rtp_packet.marshal()?.freeze();
+    assert_eq!(rtp::packet::Packet::unmarshal(&mut raw)?, rtp_packet);
+
+    Ok(())
+}
+
+#[test]
+fn test_get_rid() -> Result<()> {
+    let mut rtp_packet = rtp::packet::Packet::default();
+    rtp_packet
+        .header
+        .set_extension(4, Bytes::from_static(b"hi"))?;
+    rtp_packet
+        .header
+        .set_extension(5, Bytes::from_static(b""))?;
+
+    assert_eq!(
+        GatewayHandler::get_rid(&rtp_packet, Some(4)),
+        Some("hi".to_string())
+    );
+    // empty or missing extensions, or extensions not negotiated don't carry a rid
+    assert_eq!(GatewayHandler::get_rid(&rtp_packet, Some(5)), None);
+    assert_eq!(GatewayHandler::get_rid(&rtp_packet, Some(6)), None);
+    assert_eq!(GatewayHandler::get_rid(&rtp_packet, None), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_get_ice_role() -> Result<()> {
+    use crate::endpoint::candidate::IceRole;
+    use stun::attributes::{ATTR_ICE_CONTROLLED, ATTR_ICE_CONTROLLING};
+
+    let new_request = |attribute, tie_breaker: &[u8]| -> Result<Message> {
+        let mut request = Message::new();
+        request.build(&[Box::new(BINDING_REQUEST), Box::new(TransactionId::new())])?;
+        request.add(attribute, tie_breaker);
+        Ok(request)
+    };
+
+    let tie_breaker = 0x0102030405060708u64;
+    assert_eq!(
+        GatewayHandler::get_ice_role(&new_request(
+            ATTR_ICE_CONTROLLING,
+            &tie_breaker.to_be_bytes()
+        )?)?,
+        Some((IceRole::Controlling, tie_breaker))
+    );
+    assert_eq!(
+        GatewayHandler::get_ice_role(&new_request(
+            ATTR_ICE_CONTROLLED,
+            &tie_breaker.to_be_bytes()
+        )?)?,
+        Some((IceRole::Controlled, tie_breaker))
+    );
+    let mut request = Message::new();
+    request.build(&[Box::new(BINDING_REQUEST), Box::new(TransactionId::new())])?;
+    assert_eq!(GatewayHandler::get_ice_role(&request)?, None);
+    // tie-breaker must be 64 bits
+    assert!(
+        GatewayHandler::get_ice_role(&new_request(ATTR_ICE_CONTROLLED, &[1, 2, 3, 4])?).is_err()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_binding_request_without_ice_role_is_answered_with_bad_request() -> Result<()> {
+    use crate::endpoint::candidate::DTLSRole;
+    use stun::attributes::{ATTR_PRIORITY, ATTR_USERNAME};
+    use stun::error_code::{ErrorCodeAttribute, CODE_BAD_REQUEST};
+    use stun::fingerprint::FINGERPRINT;
+    use stun::integrity::MessageIntegrity;
+    use stun::message::{Getter, Setter, BINDING_ERROR};
+    use stun::textattrs::TextAttribute;
+
+    let certificate =
+        RTCCertificate::from_key_pair(rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+    let mut server_states = ServerStates::new(
+        Arc::new(ServerConfig::new(vec![certificate])),
+        "127.0.0.1:3478".parse().unwrap(),
+        opentelemetry::global::meter("test"),
+    )?;
+    let local_conn_cred = ConnectionCredentials::new(vec![], DTLSRole::Client, false);
+    let password = local_conn_cred.ice_params.password.clone();
+    let candidate = Rc::new(Candidate::new(
+        1,
+        1,
+        ConnectionCredentials {
+            ice_params: RTCIceParameters {
+                username_fragment: "publisher".to_string(),
+                password: "publisher-password".to_string(),
+            },
+            ..Default::default()
+        },
+        local_conn_cred,
+        RTCSessionDescription::default(),
+        RTCSessionDescription::default(),
+        Instant::now(),
+    ));
+    let username = candidate.username();
+    server_states.add_candidate(candidate);
+    let pipeline: Pipeline<TaggedMessageEvent, TaggedMessageEvent> = Pipeline::new();
+    pipeline.add_back(GatewayHandler::new(Rc::new(RefCell::new(server_states))));
+    let pipeline = pipeline.finalize();
+
+    // an authenticated check carrying neither ICE-CONTROLLING nor ICE-CONTROLLED
+    let mut request = Message::new();
+    request.build(&[Box::new(BINDING_REQUEST), Box::new(TransactionId::new())])?;
+    TextAttribute::new(ATTR_USERNAME, username).add_to(&mut request)?;
+    request.add(ATTR_PRIORITY, &100u32.to_be_bytes());
+    MessageIntegrity::new_short_term_integrity(password).add_to(&mut request)?;
+    FINGERPRINT.add_to(&mut request)?;
+    let mut event = new_stun_message_event(Instant::now(), "127.0.0.1:5000", BINDING_REQUEST)?;
+    event.message = MessageEvent::Stun(STUNMessageEvent::Stun(request.clone()));
+    pipeline.read(event);
+
+    let responses: Vec<TaggedMessageEvent> =
+        std::iter::from_fn(|| pipeline.poll_transmit()).collect();
+    assert_eq!(responses.len(), 1);
+    let MessageEvent::Stun(STUNMessageEvent::Stun(response)) = &responses[0].message else {
+        panic!("unexpected response {:?}", responses[0].message);
+    };
+    assert_eq!(response.typ, BINDING_ERROR);
+    assert_eq!(response.transaction_id, request.transaction_id);
+    let mut error_code = ErrorCodeAttribute::default();
+    error_code.get_from(response)?;
+    assert!(error_code.code == CODE_BAD_REQUEST);
+
+    Ok(())
+}
+
+#[test]
+fn test_nominations_of_two_transports_do_not_migrate_the_bundle_transport() -> Result<()> {
+    use crate::endpoint::candidate::DTLSRole;
+    use stun::attributes::{
+        ATTR_ICE_CONTROLLING, ATTR_PRIORITY, ATTR_USERNAME, ATTR_USE_CANDIDATE,
+    };
+    use stun::error_code::{ErrorCodeAttribute, CODE_BAD_REQUEST};
+    use stun::fingerprint::FINGERPRINT;
+    use stun::integrity::MessageIntegrity;
+    use stun::message::{Getter, Setter, BINDING_ERROR};
+    use stun::textattrs::TextAttribute;
+
+    let certificate =
+        RTCCertificate::from_key_pair(rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+    let mut server_states = ServerStates::new(
+        Arc::new(ServerConfig::new(vec![certificate])),
+        "127.0.0.1:3478".parse().unwrap(),
+        opentelemetry::global::meter("test"),
+    )?;
+    server_states.create_or_get_mut_session(1);
+    // a local description advertising its own transport for the second media section,
+    // e.g., under max-compat bundle policy
+    let local_conn_cred = ConnectionCredentials::new(vec![], DTLSRole::Client, false);
+    let bundle_ice_params = local_conn_cred.ice_params.clone();
+    let media_ice_params = bundle_ice_params.for_media("1");
+    let local_description = RTCSessionDescription::answer(format!(
+        "v=0\r\n\
+o=- 0 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+a=mid:0\r\n\
+a=ice-ufrag:{}\r\n\
+a=ice-pwd:{}\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+a=mid:1\r\n\
+a=ice-ufrag:{}\r\n\
+a=ice-pwd:{}\r\n",
+        bundle_ice_params.username_fragment,
+        bundle_ice_params.password,
+        media_ice_params.username_fragment,
+        media_ice_params.password
+    ))?;
+    let candidate = Rc::new(Candidate::new(
+        1,
+        1,
+        ConnectionCredentials {
+            ice_params: RTCIceParameters {
+                username_fragment: "publisher".to_string(),
+                password: "publisher-password".to_string(),
+            },
+            ..Default::default()
+        },
+        local_conn_cred,
+        RTCSessionDescription::default(),
+        local_description,
+        Instant::now(),
+    ));
+    let usernames = candidate.usernames();
+    server_states.add_candidate(candidate);
+    let server_states = Rc::new(RefCell::new(server_states));
+    let pipeline: Pipeline<TaggedMessageEvent, TaggedMessageEvent> = Pipeline::new();
+    pipeline.add_back(GatewayHandler::new(server_states.clone()));
+    let pipeline = pipeline.finalize();
+
+    // nominates the pair of peer_addr by a check authenticated with ice_params
+    let nominate = |peer_addr: &str, username: &str, ice_params: &RTCIceParameters| {
+        let mut request = Message::new();
+        request.build(&[Box::new(BINDING_REQUEST), Box::new(TransactionId::new())])?;
+        TextAttribute::new(ATTR_USERNAME, username.to_string()).add_to(&mut request)?;
+        request.add(ATTR_PRIORITY, &100u32.to_be_bytes());
+        request.add(ATTR_ICE_CONTROLLING, &1u64.to_be_bytes());
+        request.add(ATTR_USE_CANDIDATE, &[]);
+        MessageIntegrity::new_short_term_integrity(ice_params.password.clone())
+            .add_to(&mut request)?;
+        FINGERPRINT.add_to(&mut request)?;
+        let mut event = new_stun_message_event(Instant::now(), peer_addr, BINDING_REQUEST)?;
+        event.message = MessageEvent::Stun(STUNMessageEvent::Stun(request));
+        pipeline.read(event);
+
+        let responses: Vec<TaggedMessageEvent> =
+            std::iter::from_fn(|| pipeline.poll_transmit()).collect();
+        assert_eq!(responses.len(), 1);
+        let MessageEvent::Stun(STUNMessageEvent::Stun(response)) = &responses[0].message else {
+            panic!("unexpected response {:?}", responses[0].message);
+        };
+        Ok::<Message, shared::error::Error>(response.clone())
+    };
+    let four_tuple = |peer_addr: &str| {
+        (&TransportContext {
+            local_addr: "127.0.0.1:3478".parse().unwrap(),
+            peer_addr: peer_addr.parse().unwrap(),
+            ecn: None,
+        })
+            .into()
+    };
+
+    // the BUNDLE transport is established on the four-tuple nominated first
+    let response = nominate("127.0.0.1:5000", &usernames[0], &bundle_ice_params)?;
+    assert_eq!(response.typ, BINDING_SUCCESS);
+    assert_eq!(
+        server_states
+            .borrow()
+            .find_endpoint(&four_tuple("127.0.0.1:5000")),
+        Some((1, 1))
+    );
+
+    // the second transport nominates another four-tuple, which is rejected without stealing
+    // the established transport, since only the BUNDLE transport is served
+    for _ in 0..2 {
+        let response = nominate("127.0.0.1:5001", &usernames[1], &media_ice_params)?;
+        assert_eq!(response.typ, BINDING_ERROR);
+        let mut error_code = ErrorCodeAttribute::default();
+        error_code.get_from(&response)?;
+        assert!(error_code.code == CODE_BAD_REQUEST);
+
+        let response = nominate("127.0.0.1:5000", &usernames[0], &bundle_ice_params)?;
+        assert_eq!(response.typ, BINDING_SUCCESS);
+        let server_states = server_states.borrow();
+        assert_eq!(
+            server_states.find_endpoint(&four_tuple("127.0.0.1:5000")),
+            Some((1, 1))
+        );
+        assert_eq!(
+            server_states.find_endpoint(&four_tuple("127.0.0.1:5001")),
+            None
+        );
+        let transports = server_states
+            .get_session(&1)
+            .and_then(|session| session.get_endpoint(&1))
+            .map(|endpoint| endpoint.get_transports().len());
+        assert_eq!(transports, Some(1));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_rtcp_ssrcs_are_mapped_between_publisher_and_subscriber() -> Result<()> {
+    let (mut server_states, publisher, subscriber) =
+        new_connected_server_states(ServerConfig::new(vec![]), PUBLISHER_OFFER_SDP)?;
+    let outbound_ssrc = server_states
+        .get_mut_endpoint(&(&subscriber).into())?
+        .get_ssrc_allocator()
+        .get(1, 1111)
+        .expect("publisher's stream is allocated for the subscriber");
+    assert_ne!(outbound_ssrc, 1111);
+
+    // the subscriber's PLI of the forwarded stream reaches the publisher with its own ssrc
+    let pli: Vec<Box<dyn rtcp::packet::Packet>> = vec![Box::new(PictureLossIndication {
+        sender_ssrc: 2222,
+        media_ssrc: outbound_ssrc,
+    })];
+    let mapped = GatewayHandler::map_rtcp_packets(&server_states, &subscriber, &publisher, &pli);
+    assert_eq!(mapped.len(), 1);
+    let mapped_pli = mapped[0]
+        .as_any()
+        .downcast_ref::<PictureLossIndication>()
+        .unwrap();
+    assert_eq!(mapped_pli.media_ssrc, 1111);
+    assert_eq!(mapped_pli.sender_ssrc, 2222);
+
+    // the publisher's SR reaches the subscriber with the allocated ssrc
+    let sr: Vec<Box<dyn rtcp::packet::Packet>> = vec![Box::new(SenderReport {
+        ssrc: 1111,
+        packet_count: 10,
+        ..Default::default()
+    })];
+    let mapped = GatewayHandler::map_rtcp_packets(&server_states, &publisher, &subscriber, &sr);
+    let mapped_sr = mapped[0].as_any().downcast_ref::<SenderReport>().unwrap();
+    assert_eq!(mapped_sr.ssrc, outbound_ssrc);
+    assert_eq!(mapped_sr.packet_count, 10);
+
+    // ssrcs unknown to the peer are not forwarded
+    let unknown: Vec<Box<dyn rtcp::packet::Packet>> = vec![Box::new(PictureLossIndication {
+        sender_ssrc: 2222,
+        media_ssrc: 3333,
+    })];
+    assert!(
+        GatewayHandler::map_rtcp_packets(&server_states, &subscriber, &publisher, &unknown)
+            .is_empty()
+    );
+
+    // a PLI pushed through the gateway is only forwarded to the publisher as mapped
+    let messages = GatewayHandler::handle_rtcp_message(
+        &mut server_states,
+        Instant::now(),
+        subscriber,
+        vec![Box::new(PictureLossIndication {
+            sender_ssrc: 2222,
+            media_ssrc: outbound_ssrc,
+        })],
+    )?;
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].transport == publisher);
+    let MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)) = &messages[0].message else {
+        panic!("expected rtcp packets");
+    };
+    let forwarded_pli = rtcp_packets[0]
+        .as_any()
+        .downcast_ref::<PictureLossIndication>()
+        .unwrap();
+    assert_eq!(forwarded_pli.media_ssrc, 1111);
+
+    Ok(())
+}
+
+#[test]
+fn test_simulcast_layer_selection_forwards_single_stream() -> Result<()> {
+    // the rid header extensions are registered by default
+    let mut media_config = MediaConfig::default();
+    media_config.register_header_extension(
+        RTCRtpHeaderExtensionCapability {
+            uri: sdp::extmap::SDES_MID_URI.to_owned(),
+        },
+        RTPCodecType::Video,
+        None,
+    )?;
+    let (mut server_states, publisher, subscriber) = new_connected_server_states(
+        ServerConfig::new(vec![]).with_media_config(media_config),
+        SIMULCAST_PUBLISHER_OFFER_SDP,
+    )?;
+
+    let now = Instant::now();
+    let forward_packet = |server_states: &mut ServerStates,
+                          ssrc: u32,
+                          payload_type: u8,
+                          (rid_id, rid): (u8, &'static [u8]),
+                          sequence_number: u16,
+                          timestamp: u32,
+                          payload: &'static [u8]|
+     -> Result<Vec<(u32, u16)>> {
+        let mut rtp_packet = rtp::packet::Packet {
+            header: rtp::header::Header {
+                version: 2,
+                payload_type,
+                ssrc,
+                sequence_number,
+                timestamp,
+                // frames of a single packet are not held by the marker rewriter
+                marker: true,
+                ..Default::default()
+            },
+            payload: Bytes::from_static(payload),
+        };
+        rtp_packet
+            .header
+            .set_extension(1, Bytes::from_static(b"0"))?;
+        rtp_packet
+            .header
+            .set_extension(rid_id, Bytes::from_static(rid))?;
+        let messages =
+            GatewayHandler::handle_rtp_message(server_states, now, publisher, rtp_packet)?;
+        Ok(messages
+            .into_iter()
+            .filter(|message| message.transport == subscriber)
+            .filter_map(|message| match message.message {
+                MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)) => {
+                    Some((rtp_packet.header.ssrc, rtp_packet.header.sequence_number))
+                }
+                _ => None,
+            })
+            .collect())
+    };
+    let forward = |server_states: &mut ServerStates,
+                   ssrc: u32,
+                   rid: &'static [u8],
+                   sequence_number: u16,
+                   timestamp: u32| {
+        forward_packet(
+            server_states,
+            ssrc,
+            96,
+            (4, rid),
+            sequence_number,
+            timestamp,
+            &[1, 2, 3],
+        )
+    };
+    // RTX packets carry the repaired rid and the original sequence number before the payload
+    let retransmit = |server_states: &mut ServerStates,
+                      ssrc: u32,
+                      rid: &'static [u8],
+                      sequence_number: u16,
+                      payload: &'static [u8]| {
+        forward_packet(
+            server_states,
+            ssrc,
+            97,
+            (5, rid),
+            sequence_number,
+            0,
+            payload,
+        )
+    };
+    let take_keyframe_requests = |server_states: &mut ServerStates| -> Result<Vec<u32>> {
+        Ok(server_states
+            .get_mut_endpoint(&(&publisher).into())?
+            .take_pending_rtcp_packets()
+            .iter()
+            .filter_map(|packet| packet.as_any().downcast_ref::<PictureLossIndication>())
+            .map(|pli| pli.media_ssrc)
+            .collect())
+    };
+
+    // both layers are forwarded until one is selected
+    assert_eq!(forward(&mut server_states, 1111, b"q", 100, 3000)?.len(), 1);
+    assert_eq!(forward(&mut server_states, 2222, b"h", 700, 9000)?.len(), 1);
+    take_keyframe_requests(&mut server_states)?;
+
+    // selecting a layer requests its keyframe from the publisher
+    server_states.select_simulcast_layer(1, 2, "1-0", Some("h"))?;
+    assert_eq!(take_keyframe_requests(&mut server_states)?, vec![2222]);
+    assert!(forward(&mut server_states, 1111, b"q", 101, 6000)?.is_empty());
+    let forwarded = forward(&mut server_states, 2222, b"h", 701, 12000)?;
+    assert_eq!(forwarded.len(), 1);
+    let (ssrc, sequence_number) = forwarded[0];
+
+    // the switched layer continues the stream at its next frame
+    server_states.select_simulcast_layer(1, 2, "1-0", Some("q"))?;
+    assert_eq!(take_keyframe_requests(&mut server_states)?, vec![1111]);
+    assert!(forward(&mut server_states, 2222, b"h", 702, 15000)?.is_empty());
+    assert!(forward(&mut server_states, 1111, b"q", 102, 9000)?.is_empty());
+    assert_eq!(
+        forward(&mut server_states, 1111, b"q", 103, 12000)?,
+        vec![(ssrc, sequence_number.wrapping_add(1))]
+    );
+    forward(&mut server_states, 2222, b"h", 703, 18000)?;
+
+    // a retransmission of the forwarded layer continues the stream as the packet it repairs,
+    // while one of the other layer is dropped
+    assert_eq!(
+        retransmit(&mut server_states, 3333, b"q", 50, &[0, 103, 1, 2, 3])?,
+        vec![(ssrc, sequence_number.wrapping_add(1))]
+    );
+    assert!(retransmit(&mut server_states, 4444, b"h", 60, &[2, 190, 1, 2, 3])?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_ice_restart_retires_stale_credentials() -> Result<()> {
+    let (mut server_states, publisher, _) =
+        new_connected_server_states(ServerConfig::new(vec![]), PUBLISHER_OFFER_SDP)?;
+    let four_tuple = (&publisher).into();
+    let stale_candidate = server_states
+        .get_mut_transport(&four_tuple)?
+        .candidate()
+        .clone();
+    let stale_username = stale_candidate.username();
+    server_states.add_candidate(stale_candidate);
+
+    server_states.restart_ice(1, 1)?;
+    let answer = server_states.accept_offer(
+        1,
+        1,
+        Some(four_tuple),
+        RTCSessionDescription::offer(PUBLISHER_OFFER_SDP.to_string())?,
+    )?;
+    let ice_ufrag = answer
+        .unmarshal()?
+        .media_descriptions
+        .first()
+        .and_then(|media| media.attribute("ice-ufrag").flatten().map(str::to_string))
+        .unwrap();
+
+    // connectivity checks are only accepted with the restarted credentials
+    assert!(server_states.find_candidate(&stale_username).is_none());
+    let candidate = server_states
+        .find_candidate(&format!("{}:publisher", ice_ufrag))
+        .unwrap();
+    assert_eq!(candidate.endpoint_id(), 1);
+    assert!(server_states
+        .get_session(&1)
+        .and_then(|session| session.get_endpoint(&1))
+        .unwrap()
+        .ice_restart_params()
+        .is_none());
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::interceptors::InterceptorEvent;
+use crate::interceptors::{limit_events, InterceptorEvent};
 use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
 use crate::types::FourTuple;
 use crate::ServerStates;
@@ -45,12 +45,14 @@ impl Handler for InterceptorHandler {
         {
             let mut try_read = || -> Result<Vec<InterceptorEvent>> {
                 let mut server_states = self.server_states.borrow_mut();
+                let max_events = server_states.server_config().max_interceptor_events;
                 let four_tuple = (&msg.transport).into();
                 let endpoint = server_states.get_mut_endpoint(&four_tuple)?;
                 let interceptor = endpoint.get_mut_interceptor();
-                Ok(interceptor.read(&mut msg))
+                Ok(limit_events(interceptor.read(&mut msg), max_events))
             };
 
+            let mut dropped = false;
             match try_read() {
                 Ok(events) => {
                     for event in events {
@@ -66,6 +68,9 @@ impl Handler for InterceptorHandler {
                                 error!("try_read got error {}", err);
                                 ctx.fire_exception(err);
                             }
+                            InterceptorEvent::Drop => {
+                                dropped = true;
+                            }
                         }
                     }
                 }
@@ -75,6 +80,11 @@ impl Handler for InterceptorHandler {
                 }
             };
 
+            if dropped {
+                debug!("interceptor drops read {:?}", msg.transport.peer_addr);
+                return;
+            }
+
             if let MessageEvent::Rtp(RTPMessageEvent::Rtcp(_)) = &msg.message {
                 // RTCP message read must end here in SFU case. If any rtcp packet needs to be forwarded to other Endpoints,
                 // just add a new interceptor to forward it.
@@ -96,6 +106,7 @@ impl Handler for InterceptorHandler {
             let mut interceptor_events = vec![];
 
             let mut server_states = self.server_states.borrow_mut();
+            let max_events = server_states.server_config().max_interceptor_events;
             let sessions = server_states.get_mut_sessions();
             for session in sessions.values_mut() {
                 let endpoints = session.get_mut_endpoints();
@@ -107,8 +118,14 @@ impl Handler for InterceptorHandler {
                         .map(|four_tuple| *four_tuple)
                         .collect();
                     let interceptor = endpoint.get_mut_interceptor();
-                    let mut events = interceptor.handle_timeout(now, &four_tuples);
+                    let mut events =
+                        limit_events(interceptor.handle_timeout(now, &four_tuples), max_events);
                     interceptor_events.append(&mut events);
+
+                    // e.g., keyframe requests, which are sent over the most recently active transport
+                    if let Some(rtcp_message) = endpoint.take_pending_rtcp_message(now) {
+                        interceptor_events.push(InterceptorEvent::Outbound(rtcp_message));
+                    }
                 }
             }
 
@@ -119,8 +136,10 @@ impl Handler for InterceptorHandler {
             Ok(events) => {
                 for event in events {
                     match event {
-                        InterceptorEvent::Inbound(_) => {
-                            error!("unexpected inbound message from try_handle_timeout");
+                        InterceptorEvent::Inbound(inbound) => {
+                            // e.g., RTP packets released by jitter buffer
+                            debug!("interceptor release {:?}", inbound.transport.peer_addr);
+                            ctx.fire_read(inbound);
                         }
                         InterceptorEvent::Outbound(outbound) => {
                             self.transmits.push_back(outbound);
@@ -129,6 +148,9 @@ impl Handler for InterceptorHandler {
                             error!("try_read got error {}", err);
                             ctx.fire_exception(err);
                         }
+                        InterceptorEvent::Drop => {
+                            error!("unexpected drop from try_handle_timeout");
+                        }
                     }
                 }
             }
@@ -152,6 +174,10 @@ impl Handler for InterceptorHandler {
             for session in sessions.values_mut() {
                 let endpoints = session.get_mut_endpoints();
                 for endpoint in endpoints.values_mut() {
+                    // e.g., a keyframe request forced by the application is sent right away
+                    if endpoint.has_pending_rtcp_packets() {
+                        *eto = Instant::now().min(*eto);
+                    }
                     let interceptor = endpoint.get_mut_interceptor();
                     interceptor.poll_timeout(eto)
                 }
@@ -166,15 +192,17 @@ impl Handler for InterceptorHandler {
         ctx: &Context<Self::Rin, Self::Rout, Self::Win, Self::Wout>,
     ) -> Option<Self::Wout> {
         if let Some(mut msg) = ctx.fire_poll_write() {
+            let mut dropped = false;
             if let MessageEvent::Rtp(RTPMessageEvent::Rtp(_))
             | MessageEvent::Rtp(RTPMessageEvent::Rtcp(_)) = &msg.message
             {
                 let mut try_write = || -> Result<Vec<InterceptorEvent>> {
                     let mut server_states = self.server_states.borrow_mut();
+                    let max_events = server_states.server_config().max_interceptor_events;
                     let four_tuple = (&msg.transport).into();
                     let endpoint = server_states.get_mut_endpoint(&four_tuple)?;
                     let interceptor = endpoint.get_mut_interceptor();
-                    Ok(interceptor.write(&mut msg))
+                    Ok(limit_events(interceptor.write(&mut msg), max_events))
                 };
 
                 match try_write() {
@@ -191,6 +219,9 @@ impl Handler for InterceptorHandler {
                                     error!("try_write got error {}", err);
                                     ctx.fire_exception(err);
                                 }
+                                InterceptorEvent::Drop => {
+                                    dropped = true;
+                                }
                             }
                         }
                     }
@@ -201,8 +232,12 @@ impl Handler for InterceptorHandler {
                 };
             }
 
-            debug!("interceptor write {:?}", msg.transport.peer_addr);
-            self.transmits.push_back(msg);
+            if dropped {
+                debug!("interceptor drops write {:?}", msg.transport.peer_addr);
+            } else {
+                debug!("interceptor write {:?}", msg.transport.peer_addr);
+                self.transmits.push_back(msg);
+            }
         }
 
         self.transmits.pop_front()
@@ -7,3 +7,18 @@ pub(crate) mod interceptor;
 pub(crate) mod sctp;
 pub(crate) mod srtp;
 pub(crate) mod stun;
+
+#[cfg(test)]
+mod datachannel_test;
+#[cfg(test)]
+mod demuxer_test;
+#[cfg(test)]
+mod dtls_test;
+#[cfg(test)]
+mod gateway_test;
+#[cfg(test)]
+mod sctp_test;
+#[cfg(test)]
+pub(crate) mod srtp_test;
+#[cfg(test)]
+mod stun_test;
@@ -1,3 +1,4 @@
+use crate::description::{get_max_message_size, RTCSessionDescription};
 use crate::messages::{
     DTLSMessageEvent, DataChannelMessage, DataChannelMessageParams, DataChannelMessageType,
     MessageEvent, TaggedMessageEvent,
@@ -50,6 +51,28 @@ impl SctpHandler {
             transmits: VecDeque::new(),
         }
     }
+
+    /// check_message_size returns ErrOutboundPacketTooLarge if the payload exceeds the min of both
+    /// sides' max message sizes, i.e., the local one of ServerConfig advertised in the answer,
+    /// and the remote one advertised in the remote description
+    pub(crate) fn check_message_size(
+        payload_len: usize,
+        local_max_message_size: usize,
+        remote_description: Option<&RTCSessionDescription>,
+    ) -> Result<()> {
+        let max_message_size = match remote_description
+            .and_then(|description| description.parsed.as_ref())
+            .and_then(get_max_message_size)
+        {
+            Some(remote_max_message_size) => local_max_message_size.min(remote_max_message_size),
+            None => local_max_message_size,
+        };
+        if payload_len > max_message_size {
+            Err(Error::ErrOutboundPacketTooLarge)
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl Handler for SctpHandler {
@@ -74,6 +97,7 @@ impl Handler for SctpHandler {
             let try_read = || -> Result<Vec<SctpMessage>> {
                 let mut server_states = self.server_states.borrow_mut();
                 let transport = server_states.get_mut_transport(&four_tuple)?;
+                let initial_peer_addr = transport.initial_peer_addr();
                 let (sctp_endpoint, sctp_associations) =
                     transport.get_mut_sctp_endpoint_associations();
 
@@ -81,7 +105,7 @@ impl Handler for SctpHandler {
                     HashMap::new();
                 if let Some((ch, event)) = sctp_endpoint.handle(
                     msg.now,
-                    msg.transport.peer_addr,
+                    initial_peer_addr,
                     Some(msg.transport.local_addr.ip()),
                     msg.transport.ecn,
                     dtls_message.freeze(), //TODO: switch API Bytes to BytesMut
@@ -167,7 +191,7 @@ impl Handler for SctpHandler {
                                             now: transmit.now,
                                             transport: TransportContext {
                                                 local_addr: self.local_addr,
-                                                peer_addr: transmit.remote,
+                                                peer_addr: msg.transport.peer_addr,
                                                 ecn: transmit.ecn,
                                             },
                                             message: MessageEvent::Dtls(DTLSMessageEvent::Raw(
@@ -204,6 +228,7 @@ impl Handler for SctpHandler {
             for session in server_states.get_mut_sessions().values_mut() {
                 for endpoint in session.get_mut_endpoints().values_mut() {
                     for transport in endpoint.get_mut_transports().values_mut() {
+                        let peer_addr = transport.four_tuple().peer_addr;
                         let (sctp_endpoint, sctp_associations) =
                             transport.get_mut_sctp_endpoint_associations();
 
@@ -215,7 +240,8 @@ impl Handler for SctpHandler {
                                 endpoint_events.push((*ch, event));
                             }
 
-                            while let Some(x) = conn.poll_transmit(now) {
+                            while let Some(mut x) = conn.poll_transmit(now) {
+                                x.remote = peer_addr;
                                 transmits.extend(split_transmit(x));
                             }
                         }
@@ -306,9 +332,13 @@ impl Handler for SctpHandler {
                             .transport
                             .max_message_size() as usize
                     };
-                    if message.payload.len() > max_message_size {
-                        return Err(Error::ErrOutboundPacketTooLarge);
-                    }
+                    SctpHandler::check_message_size(
+                        message.payload.len(),
+                        max_message_size,
+                        server_states
+                            .get_mut_endpoint(&four_tuple)?
+                            .remote_description(),
+                    )?;
 
                     let transport = server_states.get_mut_transport(&four_tuple)?;
                     let sctp_associations = transport.get_mut_sctp_associations();
@@ -350,7 +380,7 @@ impl Handler for SctpHandler {
                                         now: transmit.now,
                                         transport: TransportContext {
                                             local_addr: self.local_addr,
-                                            peer_addr: transmit.remote,
+                                            peer_addr: msg.transport.peer_addr,
                                             ecn: transmit.ecn,
                                         },
                                         message: MessageEvent::Dtls(DTLSMessageEvent::Raw(
@@ -0,0 +1,70 @@
+use super::sctp::*;
+use crate::description::{RTCSessionDescription, DEFAULT_MAX_MESSAGE_SIZE};
+use shared::error::{Error, Result};
+
+fn new_data_channel_offer(max_message_size: Option<usize>) -> Result<RTCSessionDescription> {
+    let mut sdp = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=sctp-port:5000\r\n"
+        .to_string();
+    if let Some(max_message_size) = max_message_size {
+        sdp += &format!("a=max-message-size:{}\r\n", max_message_size);
+    }
+    RTCSessionDescription::offer(sdp)
+}
+
+#[test]
+fn test_check_message_size_enforces_min_of_both_sides() -> Result<()> {
+    let local_max_message_size = 262144;
+
+    // the remote advertises a smaller limit than the local one
+    let offer = new_data_channel_offer(Some(1024))?;
+    assert_eq!(
+        SctpHandler::check_message_size(1024, local_max_message_size, Some(&offer)),
+        Ok(())
+    );
+    assert_eq!(
+        SctpHandler::check_message_size(1025, local_max_message_size, Some(&offer)),
+        Err(Error::ErrOutboundPacketTooLarge)
+    );
+
+    // the local limit applies if the remote one is larger or unlimited
+    for max_message_size in [1048576, 0] {
+        let offer = new_data_channel_offer(Some(max_message_size))?;
+        assert_eq!(
+            SctpHandler::check_message_size(
+                local_max_message_size,
+                local_max_message_size,
+                Some(&offer)
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            SctpHandler::check_message_size(
+                local_max_message_size + 1,
+                local_max_message_size,
+                Some(&offer)
+            ),
+            Err(Error::ErrOutboundPacketTooLarge)
+        );
+    }
+
+    // the remote can receive messages of the default size without a=max-message-size
+    let offer = new_data_channel_offer(None)?;
+    assert_eq!(
+        SctpHandler::check_message_size(
+            DEFAULT_MAX_MESSAGE_SIZE + 1,
+            local_max_message_size,
+            Some(&offer)
+        ),
+        Err(Error::ErrOutboundPacketTooLarge)
+    );
+
+    Ok(())
+}
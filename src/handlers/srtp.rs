@@ -1,8 +1,10 @@
 use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
 use crate::server::states::ServerStates;
-use bytes::BytesMut;
+use bytes::{Buf, Bytes, BytesMut};
 use log::{debug, error};
 use retty::channel::{Context, Handler};
+use rtcp::header::{Header, PacketType, HEADER_LENGTH};
+use rtcp::raw_packet::RawPacket;
 use shared::{
     error::{Error, Result},
     marshal::{Marshal, Unmarshal},
@@ -12,6 +14,36 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use std::time::Instant;
 
+/// unmarshal_rtcp_packets unmarshals a compound RTCP packet one packet at a time, and keeps
+/// a feedback packet which fails to unmarshal as RawPacket, e.g., an application layer
+/// feedback other than REMB, so that the other packets in the compound are still processed
+pub(crate) fn unmarshal_rtcp_packets(mut raw: Bytes) -> Result<Vec<Box<dyn rtcp::packet::Packet>>> {
+    let mut rtcp_packets: Vec<Box<dyn rtcp::packet::Packet>> = vec![];
+    while raw.has_remaining() {
+        let header = Header::unmarshal(&mut raw.slice(..raw.len().min(HEADER_LENGTH)))?;
+        let length = (header.length as usize + 1) * 4;
+        if length > raw.remaining() {
+            return Err(Error::PacketTooShort);
+        }
+        let packet = raw.split_to(length);
+        match rtcp::packet::unmarshal(&mut packet.clone()) {
+            Ok(mut packets) => rtcp_packets.append(&mut packets),
+            Err(err)
+                if header.packet_type == PacketType::TransportSpecificFeedback
+                    || header.packet_type == PacketType::PayloadSpecificFeedback =>
+            {
+                debug!(
+                    "keep unknown {} with FMT {} as raw packet: {}",
+                    header.packet_type, header.count, err
+                );
+                rtcp_packets.push(Box::new(RawPacket(packet)));
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(rtcp_packets)
+}
+
 /// SrtpHandler implements SRTP/RTP/RTCP Protocols handling
 pub struct SrtpHandler {
     server_states: Rc<RefCell<ServerStates>>,
@@ -48,8 +80,8 @@ impl Handler for SrtpHandler {
                 if is_rtcp(&message) {
                     let mut remote_context = transport.remote_srtp_context();
                     if let Some(context) = remote_context.as_mut() {
-                        let mut decrypted = context.decrypt_rtcp(&message)?;
-                        let rtcp_packets = rtcp::packet::unmarshal(&mut decrypted)?;
+                        let decrypted = context.decrypt_rtcp(&message)?;
+                        let rtcp_packets = unmarshal_rtcp_packets(decrypted.freeze())?;
                         if rtcp_packets.is_empty() {
                             return Err(Error::Other("empty rtcp_packets".to_string()));
                         }
@@ -68,8 +100,14 @@ impl Handler for SrtpHandler {
                 } else {
                     let mut remote_context = transport.remote_srtp_context();
                     if let Some(context) = remote_context.as_mut() {
+                        // srtp::Context guesses the packet index from ROC-1, ROC and ROC+1
+                        // (RFC 3711 Section 3.3.1), so reordered packets around a sequence
+                        // number wrap are still decrypted with the right rollover counter
                         let mut decrypted = context.decrypt_rtp(&message)?;
                         let rtp_packet = rtp::Packet::unmarshal(&mut decrypted)?;
+                        transport
+                            .get_mut_loss_monitor(rtp_packet.header.ssrc)
+                            .update(rtp_packet.header.sequence_number);
 
                         server_states.metrics().record_rtp_packet_in_count(1, &[]);
                         Ok(MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)))
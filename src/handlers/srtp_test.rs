@@ -0,0 +1,145 @@
+use super::srtp::*;
+use crate::configs::server_config::ServerConfig;
+use crate::handlers::gateway_test::{new_connected_server_states, PUBLISHER_OFFER_SDP};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use bytes::{BufMut, Bytes, BytesMut};
+use retty::channel::{InboundPipeline, Pipeline};
+use rtcp::raw_packet::RawPacket;
+use rtcp::receiver_report::ReceiverReport;
+use shared::error::Result;
+use shared::marshal::Marshal;
+use srtp::context::Context;
+use srtp::protection_profile::ProtectionProfile;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Instant;
+
+const MASTER_KEY: [u8; 16] = [1; 16];
+const MASTER_SALT: [u8; 14] = [2; 14];
+
+/// new_srtp_context returns a context of fixed keys, so that contexts of both sides match
+pub(crate) fn new_srtp_context() -> Result<Context> {
+    Context::new(
+        &MASTER_KEY,
+        &MASTER_SALT,
+        ProtectionProfile::Aes128CmHmacSha1_80,
+        Some(srtp::option::srtp_replay_protection(64)),
+        None,
+    )
+}
+
+fn new_rtp_packet(sequence_number: u16) -> Result<Bytes> {
+    let packet = rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            sequence_number,
+            ssrc: 1234,
+            ..Default::default()
+        },
+        payload: Bytes::from(sequence_number.to_be_bytes().to_vec()),
+    };
+    Ok(packet.marshal()?.freeze())
+}
+
+#[test]
+fn test_srtp_handler_reads_reordered_packets_around_sequence_wrap() -> Result<()> {
+    let (mut server_states, publisher, _) =
+        new_connected_server_states(ServerConfig::new(vec![]), PUBLISHER_OFFER_SDP)?;
+    let four_tuple = (&publisher).into();
+    server_states
+        .get_mut_transport(&four_tuple)?
+        .set_remote_srtp_context(new_srtp_context()?);
+    let server_states = Rc::new(RefCell::new(server_states));
+    let pipeline: Pipeline<TaggedMessageEvent, TaggedMessageEvent> = Pipeline::new();
+    pipeline.add_back(SrtpHandler::new(server_states.clone()));
+    let pipeline = pipeline.finalize();
+
+    // the sender's rollover counter goes from 0 to 1 at sequence number 0
+    let mut local_context = new_srtp_context()?;
+    let sequence_numbers = [65533u16, 65534, 65535, 0, 1, 2];
+    let mut encrypted = vec![];
+    for sequence_number in sequence_numbers {
+        encrypted.push(local_context.encrypt_rtp(&new_rtp_packet(sequence_number)?)?);
+    }
+
+    // packets before the wrap arrive after packets after the wrap, and vice versa
+    for i in [0, 1, 3, 2, 4, 5] {
+        pipeline.read(TaggedMessageEvent {
+            now: Instant::now(),
+            transport: publisher,
+            message: MessageEvent::Rtp(RTPMessageEvent::Raw(encrypted[i].clone())),
+        });
+    }
+
+    // all of them are decrypted with the right rollover counter, none is lost
+    let stats = server_states
+        .borrow_mut()
+        .get_mut_transport(&four_tuple)?
+        .get_inbound_rtp_stats()
+        .into_iter()
+        .find(|stats| stats.ssrc == 1234)
+        .unwrap();
+    assert_eq!(stats.packets_received, 6);
+    assert_eq!(stats.packets_lost, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_srtp_decrypt_rejects_truncated_auth_tag() -> Result<()> {
+    let mut local_context = new_srtp_context()?;
+    let mut remote_context = new_srtp_context()?;
+
+    // SHA1_80 tags are 10 bytes, a packet carrying a 4-byte tag as of SHA1_32 is not authenticated
+    let encrypted = local_context.encrypt_rtp(&new_rtp_packet(1)?)?.freeze();
+    let truncated = encrypted.slice(..encrypted.len() - 6);
+    assert!(remote_context.decrypt_rtp(&truncated).is_err());
+    assert!(remote_context.decrypt_rtp(&encrypted).is_ok());
+
+    Ok(())
+}
+
+// application layer feedback (PSFB with FMT=15) which is not REMB
+const UNKNOWN_AFB_PACKET: [u8; 16] = [
+    0x8f, 0xce, 0x00, 0x03, // V=2, FMT=15, PT=206, length=3
+    0x11, 0x22, 0x33, 0x44, // SSRC of packet sender
+    0x00, 0x00, 0x00, 0x00, // SSRC of media source
+    b'A', b'B', b'C', b'D', // unknown identifier
+];
+
+#[test]
+fn test_unmarshal_rtcp_packets_keeps_unknown_feedback() -> Result<()> {
+    let receiver_report = ReceiverReport {
+        ssrc: 0x55667788,
+        ..Default::default()
+    };
+    let mut compound = BytesMut::new();
+    compound.put(receiver_report.marshal()?);
+    compound.put(&UNKNOWN_AFB_PACKET[..]);
+    let compound = compound.freeze();
+
+    // the whole compound fails to unmarshal with the unknown feedback
+    assert!(rtcp::packet::unmarshal(&mut compound.clone()).is_err());
+
+    let rtcp_packets = unmarshal_rtcp_packets(compound)?;
+    assert_eq!(rtcp_packets.len(), 2);
+    assert_eq!(
+        rtcp_packets[0]
+            .as_any()
+            .downcast_ref::<ReceiverReport>()
+            .map(|rr| rr.ssrc),
+        Some(0x55667788)
+    );
+    assert_eq!(
+        rtcp_packets[1]
+            .as_any()
+            .downcast_ref::<RawPacket>()
+            .map(|raw| raw.0.clone()),
+        Some(Bytes::from_static(&UNKNOWN_AFB_PACKET))
+    );
+
+    // a truncated packet still fails the compound
+    assert!(unmarshal_rtcp_packets(Bytes::from_static(&UNKNOWN_AFB_PACKET[..12])).is_err());
+
+    Ok(())
+}
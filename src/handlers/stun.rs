@@ -2,8 +2,8 @@ use crate::messages::{MessageEvent, STUNMessageEvent, TaggedMessageEvent};
 use bytes::BytesMut;
 use log::{debug, warn};
 use retty::channel::{Context, Handler};
-use shared::error::Result;
-use stun::message::Message;
+use shared::error::{Error, Result};
+use stun::message::{Message, MAGIC_COOKIE, MESSAGE_HEADER_SIZE};
 
 /// StunHandler implements STUN Protocol handling
 #[derive(Default)]
@@ -13,6 +13,39 @@ impl StunHandler {
     pub fn new() -> Self {
         StunHandler
     }
+
+    /// validate_message checks STUN header against the datagram before parsing it:
+    /// the two most significant bits must be zeroes, the magic cookie must match, and
+    /// the message length must be a multiple of 4 and equal to the length of the body.
+    pub(crate) fn validate_message(raw: &[u8]) -> Result<()> {
+        if raw.len() < MESSAGE_HEADER_SIZE {
+            return Err(Error::Other(format!(
+                "STUN message of {} bytes is shorter than header",
+                raw.len()
+            )));
+        }
+        if raw[0] & 0xC0 != 0 {
+            return Err(Error::Other(
+                "STUN message has non-zero leading bits".to_string(),
+            ));
+        }
+        let cookie = u32::from_be_bytes([raw[4], raw[5], raw[6], raw[7]]);
+        if cookie != MAGIC_COOKIE {
+            return Err(Error::Other(format!(
+                "{:x} is invalid STUN magic cookie",
+                cookie
+            )));
+        }
+        let length = u16::from_be_bytes([raw[2], raw[3]]) as usize;
+        if !length.is_multiple_of(4) || MESSAGE_HEADER_SIZE + length != raw.len() {
+            return Err(Error::Other(format!(
+                "STUN message length {} is inconsistent with body of {} bytes",
+                length,
+                raw.len() - MESSAGE_HEADER_SIZE
+            )));
+        }
+        Ok(())
+    }
 }
 
 impl Handler for StunHandler {
@@ -31,6 +64,14 @@ impl Handler for StunHandler {
         msg: Self::Rin,
     ) {
         if let MessageEvent::Stun(STUNMessageEvent::Raw(message)) = msg.message {
+            if let Err(err) = StunHandler::validate_message(&message) {
+                debug!(
+                    "drop invalid StunMessage from {}: {}",
+                    msg.transport.peer_addr, err
+                );
+                return;
+            }
+
             let try_read = || -> Result<Message> {
                 let mut stun_message = Message {
                     raw: message.to_vec(),
@@ -0,0 +1,33 @@
+use super::stun::*;
+use shared::error::Result;
+use stun::message::{Message, TransactionId, BINDING_REQUEST};
+
+fn new_binding_request() -> Result<Vec<u8>> {
+    let mut m = Message::new();
+    m.build(&[Box::new(TransactionId::new()), Box::new(BINDING_REQUEST)])?;
+    Ok(m.raw)
+}
+
+#[test]
+fn test_stun_validate_message() -> Result<()> {
+    let raw = new_binding_request()?;
+    assert!(StunHandler::validate_message(&raw).is_ok());
+
+    // claims 8 more bytes of body than present
+    let mut truncated = raw.clone();
+    truncated[3] += 8;
+    assert!(StunHandler::validate_message(&truncated).is_err());
+
+    // trailing bytes beyond the claimed body
+    let mut oversized = raw.clone();
+    oversized.extend_from_slice(&[0; 4]);
+    assert!(StunHandler::validate_message(&oversized).is_err());
+
+    let mut bad_cookie = raw.clone();
+    bad_cookie[4] ^= 0xFF;
+    assert!(StunHandler::validate_message(&bad_cookie).is_err());
+
+    assert!(StunHandler::validate_message(&raw[..12]).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::description::rtp_transceiver::SSRC;
+use crate::interceptors::bitrate_limiter::LimiterBuilder;
+use crate::interceptors::{Interceptor, InterceptorEvent};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use crate::server::rate_limiter::TokenBucket;
+use crate::types::FourTuple;
+use log::debug;
+use shared::marshal::MarshalSize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// MAX_PACKET_SIZE is the smallest bucket size, so that a single packet can always pass
+const MAX_PACKET_SIZE: u32 = 1500;
+
+/// Limiter measures inbound bitrate of each SSRC with a token bucket in bytes,
+/// and drops RTP packets exceeding the max bitrate, so that a misbehaving publisher
+/// can't flood the server and other participants.
+pub(crate) struct Limiter {
+    /// maximum bitrate of each SSRC in bits per second
+    pub(super) max_bitrate: u64,
+    pub(super) burst: Duration,
+    pub(super) buckets: HashMap<SSRC, TokenBucket>,
+    pub(super) next: Option<Box<dyn Interceptor>>,
+}
+
+impl Limiter {
+    pub(crate) fn builder() -> LimiterBuilder {
+        LimiterBuilder::default()
+    }
+
+    /// allow returns true if the RTP packet of size bytes is within the max bitrate of the SSRC
+    fn allow(&mut self, now: Instant, ssrc: SSRC, size: usize) -> bool {
+        let rate = (self.max_bitrate / 8).min(u32::MAX as u64) as u32;
+        let burst = ((rate as f64 * self.burst.as_secs_f64()) as u32).max(MAX_PACKET_SIZE);
+        self.buckets
+            .entry(ssrc)
+            .or_insert_with(|| TokenBucket::new(rate, burst, now))
+            .try_consume_n(now, size as u32)
+    }
+}
+
+impl Interceptor for Limiter {
+    fn chain(mut self: Box<Self>, next: Box<dyn Interceptor>) -> Box<dyn Interceptor> {
+        self.next = Some(next);
+        self
+    }
+
+    fn next(&mut self) -> Option<&mut Box<dyn Interceptor>> {
+        self.next.as_mut()
+    }
+
+    fn read(&mut self, msg: &mut TaggedMessageEvent) -> Vec<InterceptorEvent> {
+        if let MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)) = &msg.message {
+            let size = rtp_packet.header.marshal_size() + rtp_packet.payload.len();
+            if !self.allow(msg.now, rtp_packet.header.ssrc, size) {
+                debug!(
+                    "drop rtp packet of ssrc {} exceeding max bitrate {}",
+                    rtp_packet.header.ssrc, self.max_bitrate
+                );
+                return vec![InterceptorEvent::Drop];
+            }
+        }
+
+        if let Some(next) = self.next() {
+            next.read(msg)
+        } else {
+            vec![]
+        }
+    }
+
+    fn handle_timeout(&mut self, now: Instant, four_tuples: &[FourTuple]) -> Vec<InterceptorEvent> {
+        // a refilled bucket is equivalent to a new one
+        self.buckets.retain(|_, bucket| !bucket.is_full(now));
+
+        if let Some(next) = self.next() {
+            next.handle_timeout(now, four_tuples)
+        } else {
+            vec![]
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use super::limiter::*;
+use super::*;
+use crate::interceptors::InterceptorEvent;
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use bytes::Bytes;
+use retty::transport::TransportContext;
+use shared::marshal::MarshalSize;
+use std::time::Instant;
+
+const MAX_BITRATE: u64 = 1_000_000;
+const PAYLOAD_SIZE: usize = 1000;
+
+fn new_rtp_message(now: Instant, ssrc: u32, sequence_number: u16) -> TaggedMessageEvent {
+    TaggedMessageEvent {
+        now,
+        transport: TransportContext {
+            local_addr: "127.0.0.1:3478".parse().unwrap(),
+            peer_addr: "127.0.0.1:5000".parse().unwrap(),
+            ecn: None,
+        },
+        message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp::packet::Packet {
+            header: rtp::header::Header {
+                version: 2,
+                ssrc,
+                sequence_number,
+                ..Default::default()
+            },
+            payload: Bytes::from(vec![0u8; PAYLOAD_SIZE]),
+        })),
+    }
+}
+
+fn is_dropped(events: &[InterceptorEvent]) -> bool {
+    events
+        .iter()
+        .any(|event| matches!(event, InterceptorEvent::Drop))
+}
+
+#[test]
+fn test_bitrate_limiter_drops_packets_over_max_bitrate() {
+    let mut limiter = Limiter::builder().with_max_bitrate(MAX_BITRATE).build("");
+    let start = Instant::now();
+    let duration = Duration::from_secs(10);
+
+    // publisher of ssrc 1 sends 2 Mbps, while publisher of ssrc 2 sends 0.5 Mbps
+    let (mut sent_bytes, mut received_bytes) = ([0usize; 2], [0usize; 2]);
+    let mut sequence_number = 0u16;
+    let mut elapsed = Duration::ZERO;
+    while elapsed < duration {
+        let now = start + elapsed;
+        for (i, ssrc) in [1u32, 2].into_iter().enumerate() {
+            if ssrc == 2 && !sequence_number.is_multiple_of(4) {
+                continue;
+            }
+            let mut msg = new_rtp_message(now, ssrc, sequence_number);
+            let size = match &msg.message {
+                MessageEvent::Rtp(RTPMessageEvent::Rtp(p)) => {
+                    p.header.marshal_size() + p.payload.len()
+                }
+                _ => unreachable!(),
+            };
+            sent_bytes[i] += size;
+            if !is_dropped(&limiter.read(&mut msg)) {
+                received_bytes[i] += size;
+            }
+        }
+        sequence_number = sequence_number.wrapping_add(1);
+        // ~2 Mbps with 1012 bytes packets
+        elapsed += Duration::from_micros(4048);
+    }
+
+    let secs = duration.as_secs_f64();
+    let received_bitrate = received_bytes[0] as f64 * 8.0 / secs;
+    assert!(sent_bytes[0] > received_bytes[0]);
+    assert!(
+        received_bitrate <= MAX_BITRATE as f64 * 1.1,
+        "received bitrate {received_bitrate}"
+    );
+    assert!(
+        received_bitrate >= MAX_BITRATE as f64 * 0.95,
+        "received bitrate {received_bitrate}"
+    );
+
+    // publisher within max bitrate is not affected
+    assert_eq!(sent_bytes[1], received_bytes[1]);
+}
+
+#[test]
+fn test_bitrate_limiter_passes_single_packet_and_rtcp() {
+    let mut limiter = Limiter::builder().with_max_bitrate(0).build("");
+    let mut msg = new_rtp_message(Instant::now(), 1, 0);
+    // a single packet always fits in the bucket
+    assert!(!is_dropped(&limiter.read(&mut msg)));
+    let mut msg = new_rtp_message(Instant::now(), 1, 1);
+    assert!(is_dropped(&limiter.read(&mut msg)));
+
+    msg.message = MessageEvent::Rtp(RTPMessageEvent::Rtcp(vec![]));
+    assert!(!is_dropped(&limiter.read(&mut msg)));
+}
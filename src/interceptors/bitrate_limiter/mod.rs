@@ -0,0 +1,57 @@
+use crate::interceptors::{Interceptor, InterceptorBuilder};
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub(crate) mod limiter;
+
+#[cfg(test)]
+mod limiter_test;
+
+use limiter::Limiter;
+
+/// DEFAULT_BURST is the default duration of media at max bitrate allowed in a burst
+pub(crate) const DEFAULT_BURST: Duration = Duration::from_millis(500);
+
+/// LimiterBuilder can be used to configure inbound bitrate Limiter Interceptor.
+pub struct LimiterBuilder {
+    max_bitrate: u64,
+    burst: Duration,
+}
+
+impl Default for LimiterBuilder {
+    fn default() -> Self {
+        Self {
+            max_bitrate: u64::MAX,
+            burst: DEFAULT_BURST,
+        }
+    }
+}
+
+impl LimiterBuilder {
+    /// with_max_bitrate sets the maximum inbound bitrate of each SSRC in bits per second.
+    pub fn with_max_bitrate(mut self, max_bitrate: u64) -> LimiterBuilder {
+        self.max_bitrate = max_bitrate;
+        self
+    }
+
+    /// with_burst sets the duration of media at max bitrate which can be received at once.
+    pub fn with_burst(mut self, burst: Duration) -> LimiterBuilder {
+        self.burst = burst;
+        self
+    }
+}
+
+impl InterceptorBuilder for LimiterBuilder {
+    fn name(&self) -> &str {
+        "Limiter"
+    }
+
+    fn build(&self, _id: &str) -> Box<dyn Interceptor> {
+        Box::new(Limiter {
+            max_bitrate: self.max_bitrate,
+            burst: self.burst,
+            buckets: HashMap::new(),
+            next: None,
+        })
+    }
+}
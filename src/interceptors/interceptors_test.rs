@@ -0,0 +1,171 @@
+use super::*;
+use crate::messages::{MessageEvent, RTPMessageEvent};
+use retty::transport::TransportContext;
+
+/// Flood is an Interceptor which produces an outbound message per count for every read
+struct Flood {
+    count: usize,
+}
+
+impl Interceptor for Flood {
+    fn chain(self: Box<Self>, _next: Box<dyn Interceptor>) -> Box<dyn Interceptor> {
+        self
+    }
+
+    fn next(&mut self) -> Option<&mut Box<dyn Interceptor>> {
+        None
+    }
+
+    fn read(&mut self, msg: &mut TaggedMessageEvent) -> Vec<InterceptorEvent> {
+        let mut events: Vec<InterceptorEvent> = (0..self.count)
+            .map(|_| {
+                InterceptorEvent::Outbound(TaggedMessageEvent {
+                    now: msg.now,
+                    transport: msg.transport,
+                    message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(vec![])),
+                })
+            })
+            .collect();
+        events.push(InterceptorEvent::Drop);
+        events
+    }
+}
+
+#[test]
+fn test_limit_events() {
+    let mut interceptor = Box::new(Flood { count: 10_000 }) as Box<dyn Interceptor>;
+    let mut msg = TaggedMessageEvent {
+        now: Instant::now(),
+        transport: TransportContext::default(),
+        message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(vec![])),
+    };
+
+    let events = limit_events(interceptor.read(&mut msg), 100);
+    assert_eq!(events.len(), 101);
+    assert_eq!(
+        events
+            .iter()
+            .filter(|event| matches!(event, InterceptorEvent::Outbound(_)))
+            .count(),
+        100
+    );
+    // the drop of the message being read is kept
+    assert!(matches!(events.last(), Some(InterceptorEvent::Drop)));
+
+    let events = limit_events(interceptor.read(&mut msg), DEFAULT_MAX_INTERCEPTOR_EVENTS);
+    assert_eq!(events.len(), DEFAULT_MAX_INTERCEPTOR_EVENTS + 1);
+}
+
+#[test]
+fn test_build_with_report_interval() {
+    let mut registry = Registry::new();
+    registry.add(Box::new(
+        report::receiver_report::ReceiverReport::builder().with_interval(Duration::from_secs(1)),
+    ));
+
+    // e.g., sessions of different report intervals sharing the registry of MediaConfig
+    for (report_interval, expected_interval) in [
+        (Some(Duration::from_millis(100)), Duration::from_millis(100)),
+        (Some(Duration::from_millis(50)), Duration::from_millis(50)),
+        (None, Duration::from_secs(1)),
+    ] {
+        let mut interceptor = registry.build_with_report_interval("", report_interval);
+        // the first report is due once the interceptor is built
+        let now = Instant::now();
+        for i in 0..3 {
+            let report_time = now + expected_interval * i;
+            interceptor.handle_timeout(report_time, &[]);
+            let mut eto = report_time + Duration::from_secs(10);
+            interceptor.poll_timeout(&mut eto);
+            assert_eq!(eto, report_time + expected_interval);
+        }
+    }
+}
+
+/// Tag is an Interceptor which records its name for every read passing through it
+struct Tag {
+    name: &'static str,
+    trace: std::sync::Arc<std::sync::Mutex<Vec<&'static str>>>,
+    next: Option<Box<dyn Interceptor>>,
+}
+
+impl Interceptor for Tag {
+    fn chain(mut self: Box<Self>, next: Box<dyn Interceptor>) -> Box<dyn Interceptor> {
+        self.next = Some(next);
+        self
+    }
+
+    fn next(&mut self) -> Option<&mut Box<dyn Interceptor>> {
+        self.next.as_mut()
+    }
+
+    fn read(&mut self, msg: &mut TaggedMessageEvent) -> Vec<InterceptorEvent> {
+        self.trace.lock().unwrap().push(self.name);
+        if let Some(next) = self.next() {
+            next.read(msg)
+        } else {
+            vec![]
+        }
+    }
+}
+
+struct TagBuilder {
+    name: &'static str,
+    trace: std::sync::Arc<std::sync::Mutex<Vec<&'static str>>>,
+}
+
+impl InterceptorBuilder for TagBuilder {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn build(&self, _id: &str) -> Box<dyn Interceptor> {
+        Box::new(Tag {
+            name: self.name,
+            trace: self.trace.clone(),
+            next: None,
+        })
+    }
+}
+
+#[test]
+fn test_registry_remove_and_insert_at() {
+    let trace = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let tag = |name: &'static str| {
+        Box::new(TagBuilder {
+            name,
+            trace: trace.clone(),
+        })
+    };
+    let read = |registry: &Registry| {
+        trace.lock().unwrap().clear();
+        let mut msg = TaggedMessageEvent {
+            now: Instant::now(),
+            transport: TransportContext::default(),
+            message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(vec![])),
+        };
+        registry.build("").read(&mut msg);
+        trace.lock().unwrap().clone()
+    };
+
+    let mut registry = Registry::new();
+    registry.add(tag("a"));
+    registry.add(tag("b"));
+    registry.add(tag("c"));
+    assert_eq!(read(&registry), vec!["a", "b", "c"]);
+
+    // reads are no longer routed through the removed interceptor
+    assert_eq!(
+        registry.remove("b").map(|b| b.name().to_owned()),
+        Some("b".to_owned())
+    );
+    assert!(registry.remove("b").is_none());
+    assert_eq!(registry.names(), vec!["a", "c"]);
+    assert_eq!(read(&registry), vec!["a", "c"]);
+
+    registry.insert_at(0, tag("d")).unwrap();
+    registry.insert_at(2, tag("e")).unwrap();
+    registry.insert_at(4, tag("f")).unwrap();
+    assert!(registry.insert_at(6, tag("g")).is_err());
+    assert_eq!(read(&registry), vec!["d", "a", "e", "c", "f"]);
+}
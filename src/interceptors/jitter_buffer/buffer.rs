@@ -0,0 +1,286 @@
+use crate::description::rtp_transceiver::{PayloadType, SSRC};
+use crate::interceptors::jitter_buffer::JitterBufferBuilder;
+use crate::interceptors::{Interceptor, InterceptorEvent};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use crate::types::FourTuple;
+use log::debug;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// JITTER_DELAY_FACTOR is the multiple of interarrival jitter used as delay in adaptive mode
+const JITTER_DELAY_FACTOR: f64 = 3.0;
+/// STREAM_IDLE_TIMEOUT is the time after the last packet of a SSRC when its stream is dropped,
+/// e.g., once the remote stops sending it or its transport is closed
+pub(crate) const STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// JitterBuffer holds inbound RTP packets of each SSRC for a delay, and releases them in the order
+/// of sequence numbers, so that packets reordered within the delay are forwarded in order, while
+/// packets arriving after later ones are released are forwarded at once. Interarrival jitter of
+/// each SSRC is measured in the clock rate of its negotiated codec, or clock_rate if unknown.
+pub(crate) struct JitterBuffer {
+    pub(super) target_delay: Duration,
+    pub(super) max_delay: Duration,
+    pub(super) adaptive: bool,
+    pub(super) clock_rate: u32,
+    pub(super) clock_rates: HashMap<PayloadType, u32>,
+    pub(crate) streams: HashMap<SSRC, JitterBufferStream>,
+    pub(super) next: Option<Box<dyn Interceptor>>,
+}
+
+impl JitterBuffer {
+    pub(crate) fn builder() -> JitterBufferBuilder {
+        JitterBufferBuilder::default()
+    }
+
+    /// release passes due packets of all streams to the next interceptors, and returns them as
+    /// inbound messages unless the next interceptors drop them
+    fn release(&mut self, now: Instant) -> Vec<InterceptorEvent> {
+        let mut interceptor_events = vec![];
+        let mut packets = vec![];
+        for stream in self.streams.values_mut() {
+            packets.extend(stream.pop_due_packets(now));
+        }
+
+        for mut msg in packets {
+            let mut events = if let Some(next) = self.next() {
+                next.read(&mut msg)
+            } else {
+                vec![]
+            };
+            let dropped = events
+                .iter()
+                .any(|event| matches!(event, InterceptorEvent::Drop));
+            events.retain(|event| !matches!(event, InterceptorEvent::Drop));
+            interceptor_events.append(&mut events);
+            if !dropped {
+                interceptor_events.push(InterceptorEvent::Inbound(msg));
+            }
+        }
+        interceptor_events
+    }
+}
+
+impl Interceptor for JitterBuffer {
+    fn chain(mut self: Box<Self>, next: Box<dyn Interceptor>) -> Box<dyn Interceptor> {
+        self.next = Some(next);
+        self
+    }
+
+    fn next(&mut self) -> Option<&mut Box<dyn Interceptor>> {
+        self.next.as_mut()
+    }
+
+    fn read(&mut self, msg: &mut TaggedMessageEvent) -> Vec<InterceptorEvent> {
+        if let MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)) = &msg.message {
+            let (target_delay, max_delay, adaptive) =
+                (self.target_delay, self.max_delay, self.adaptive);
+            let clock_rate = self
+                .clock_rates
+                .get(&rtp_packet.header.payload_type)
+                .copied()
+                .unwrap_or(self.clock_rate);
+            let stream = self
+                .streams
+                .entry(rtp_packet.header.ssrc)
+                .or_insert_with(|| JitterBufferStream::new(target_delay, clock_rate));
+            if stream.push(msg, adaptive, target_delay, max_delay) {
+                let mut interceptor_events = vec![InterceptorEvent::Drop];
+                interceptor_events.append(&mut self.release(msg.now));
+                return interceptor_events;
+            }
+            debug!(
+                "forward late rtp packet {} of ssrc {}",
+                rtp_packet.header.sequence_number, rtp_packet.header.ssrc
+            );
+        }
+
+        if let Some(next) = self.next() {
+            next.read(msg)
+        } else {
+            vec![]
+        }
+    }
+
+    fn handle_timeout(&mut self, now: Instant, four_tuples: &[FourTuple]) -> Vec<InterceptorEvent> {
+        let mut interceptor_events = self.release(now);
+        self.streams.retain(|_, stream| !stream.is_idle(now));
+
+        if let Some(next) = self.next() {
+            let mut events = next.handle_timeout(now, four_tuples);
+            interceptor_events.append(&mut events);
+        }
+        interceptor_events
+    }
+
+    fn set_clock_rates(&mut self, clock_rates: &HashMap<PayloadType, u32>) {
+        self.clock_rates.clone_from(clock_rates);
+
+        if let Some(next) = self.next() {
+            next.set_clock_rates(clock_rates);
+        }
+    }
+
+    fn poll_timeout(&mut self, eto: &mut Instant) {
+        for stream in self.streams.values() {
+            if let Some(release_time) = stream.next_release_time() {
+                if release_time < *eto {
+                    *eto = release_time;
+                }
+            }
+        }
+
+        if let Some(next) = self.next() {
+            next.poll_timeout(eto);
+        }
+    }
+}
+
+/// JitterBufferStream keeps the packets of a SSRC by extended sequence numbers with their release
+/// times, and measures interarrival jitter as RTCP receiver reports do
+pub(crate) struct JitterBufferStream {
+    packets: BTreeMap<u64, (Instant, TaggedMessageEvent)>,
+    cycles: u64,
+    last_sequence_number: Option<u16>,
+    last_released: Option<u64>,
+    last_rtp_timestamp: u32,
+    last_arrival_time: Option<Instant>,
+    // interarrival jitter in RTP timestamp units of clock_rate
+    jitter: f64,
+    clock_rate: u32,
+    delay: Duration,
+}
+
+impl JitterBufferStream {
+    fn new(delay: Duration, clock_rate: u32) -> Self {
+        Self {
+            packets: BTreeMap::new(),
+            cycles: 0,
+            last_sequence_number: None,
+            last_released: None,
+            last_rtp_timestamp: 0,
+            last_arrival_time: None,
+            jitter: 0.0,
+            clock_rate,
+            delay,
+        }
+    }
+
+    /// clock_rate returns the clock rate of the stream's RTP timestamps
+    pub(crate) fn clock_rate(&self) -> u32 {
+        self.clock_rate
+    }
+
+    /// is_idle returns true if no packet is buffered, and none arrived within STREAM_IDLE_TIMEOUT
+    fn is_idle(&self, now: Instant) -> bool {
+        self.packets.is_empty()
+            && self.last_arrival_time.is_none_or(|last_arrival_time| {
+                now.saturating_duration_since(last_arrival_time) >= STREAM_IDLE_TIMEOUT
+            })
+    }
+
+    /// delay returns the current delay of packets, which is updated by each received packet
+    /// in adaptive mode
+    pub(crate) fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// push buffers the RTP packet of msg and returns true, or returns false if a later packet was
+    /// released already, so that it must be forwarded at once
+    fn push(
+        &mut self,
+        msg: &TaggedMessageEvent,
+        adaptive: bool,
+        target_delay: Duration,
+        max_delay: Duration,
+    ) -> bool {
+        let MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)) = &msg.message else {
+            return false;
+        };
+
+        // https://tools.ietf.org/html/rfc3550#page-39
+        if let Some(last_arrival_time) = self.last_arrival_time {
+            let d = msg
+                .now
+                .saturating_duration_since(last_arrival_time)
+                .as_secs_f64()
+                * self.clock_rate as f64
+                - (rtp_packet
+                    .header
+                    .timestamp
+                    .wrapping_sub(self.last_rtp_timestamp) as i32) as f64;
+            self.jitter += (d.abs() - self.jitter) / 16.0;
+        }
+        self.last_rtp_timestamp = rtp_packet.header.timestamp;
+        self.last_arrival_time = Some(msg.now);
+        if adaptive && self.clock_rate > 0 {
+            let jitter_delay =
+                Duration::from_secs_f64(JITTER_DELAY_FACTOR * self.jitter / self.clock_rate as f64);
+            self.delay = jitter_delay.clamp(target_delay, max_delay);
+        }
+
+        let sequence_number = self.extend(rtp_packet.header.sequence_number);
+        if self
+            .last_released
+            .is_some_and(|last_released| sequence_number <= last_released)
+        {
+            return false;
+        }
+
+        self.packets.insert(
+            sequence_number,
+            (
+                msg.now + self.delay,
+                TaggedMessageEvent {
+                    now: msg.now,
+                    transport: msg.transport,
+                    message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet.clone())),
+                },
+            ),
+        );
+        true
+    }
+
+    /// extend returns the sequence number with the count of wraps, where a sequence number close
+    /// to u16::MAX after one close to 0 is reordered from the previous cycle
+    fn extend(&mut self, sequence_number: u16) -> u64 {
+        let last_sequence_number = self.last_sequence_number.unwrap_or(sequence_number);
+        if sequence_number < 0x0fff && last_sequence_number > 0xf000 {
+            self.cycles += 1 << 16;
+            self.last_sequence_number = Some(sequence_number);
+            self.cycles | sequence_number as u64
+        } else if sequence_number > 0xf000 && last_sequence_number < 0x0fff {
+            self.cycles.saturating_sub(1 << 16) | sequence_number as u64
+        } else {
+            if sequence_number > last_sequence_number || self.last_sequence_number.is_none() {
+                self.last_sequence_number = Some(sequence_number);
+            }
+            self.cycles | sequence_number as u64
+        }
+    }
+
+    /// pop_due_packets removes the packets up to the last one whose release time is due, i.e.,
+    /// packets missing before a due packet are given up
+    fn pop_due_packets(&mut self, now: Instant) -> Vec<TaggedMessageEvent> {
+        let Some(sequence_number) = self
+            .packets
+            .iter()
+            .filter(|(_, (release_time, _))| *release_time <= now)
+            .map(|(sequence_number, _)| *sequence_number)
+            .next_back()
+        else {
+            return vec![];
+        };
+
+        let rest = self.packets.split_off(&(sequence_number + 1));
+        let due = std::mem::replace(&mut self.packets, rest);
+        self.last_released = Some(sequence_number);
+        due.into_values().map(|(_, msg)| msg).collect()
+    }
+
+    fn next_release_time(&self) -> Option<Instant> {
+        self.packets
+            .values()
+            .map(|(release_time, _)| *release_time)
+            .min()
+    }
+}
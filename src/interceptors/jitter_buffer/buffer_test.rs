@@ -0,0 +1,151 @@
+use super::buffer::*;
+use crate::interceptors::{Interceptor, InterceptorBuilder, InterceptorEvent};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use retty::transport::TransportContext;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const SSRC: u32 = 1234;
+
+fn new_rtp_message_event(now: Instant, sequence_number: u16, timestamp: u32) -> TaggedMessageEvent {
+    let mut rtp_packet = rtp::packet::Packet::default();
+    rtp_packet.header.ssrc = SSRC;
+    rtp_packet.header.sequence_number = sequence_number;
+    rtp_packet.header.timestamp = timestamp;
+    TaggedMessageEvent {
+        now,
+        transport: TransportContext {
+            local_addr: "127.0.0.1:3478".parse().unwrap(),
+            peer_addr: "127.0.0.1:5000".parse().unwrap(),
+            ecn: None,
+        },
+        message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)),
+    }
+}
+
+fn get_sequence_numbers(events: &[InterceptorEvent]) -> Vec<u16> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            InterceptorEvent::Inbound(TaggedMessageEvent {
+                message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)),
+                ..
+            }) => Some(rtp_packet.header.sequence_number),
+            _ => None,
+        })
+        .collect()
+}
+
+fn new_adaptive_jitter_buffer(target_delay: Duration, max_delay: Duration) -> JitterBuffer {
+    JitterBuffer {
+        target_delay,
+        max_delay,
+        adaptive: true,
+        clock_rate: 90000,
+        clock_rates: HashMap::new(),
+        streams: HashMap::new(),
+        next: None,
+    }
+}
+
+#[test]
+fn test_jitter_buffer_releases_reordered_packets_in_order() {
+    let mut interceptor = JitterBuffer::builder()
+        .with_target_delay(Duration::from_millis(50))
+        .build("");
+
+    let start = Instant::now();
+    for (sequence_number, arrival) in [(1, 0), (3, 10), (2, 20)] {
+        let mut msg =
+            new_rtp_message_event(start + Duration::from_millis(arrival), sequence_number, 0);
+        let events = interceptor.read(&mut msg);
+        assert!(matches!(events[..], [InterceptorEvent::Drop]));
+    }
+
+    let mut eto = start + Duration::from_secs(1);
+    interceptor.poll_timeout(&mut eto);
+    assert_eq!(eto, start + Duration::from_millis(50));
+
+    let events = interceptor.handle_timeout(start + Duration::from_millis(50), &[]);
+    assert_eq!(get_sequence_numbers(&events), vec![1]);
+    // 2 is released with 3 which is due first
+    let events = interceptor.handle_timeout(start + Duration::from_millis(60), &[]);
+    assert_eq!(get_sequence_numbers(&events), vec![2, 3]);
+    let mut eto = start + Duration::from_secs(1);
+    interceptor.poll_timeout(&mut eto);
+    assert_eq!(eto, start + Duration::from_secs(1));
+
+    // a packet arriving after later ones are released is forwarded at once
+    let mut msg = new_rtp_message_event(start + Duration::from_millis(70), 2, 0);
+    assert!(interceptor.read(&mut msg).is_empty());
+}
+
+#[test]
+fn test_jitter_buffer_adapts_delay_to_jitter() {
+    let target_delay = Duration::from_millis(20);
+    let max_delay = Duration::from_millis(80);
+    let mut jitter_buffer = new_adaptive_jitter_buffer(target_delay, max_delay);
+
+    // packets of 40ms apart arrive 30ms early or late alternately
+    let start = Instant::now();
+    let mut delays = vec![];
+    for i in 0..100u16 {
+        let arrival = Duration::from_millis(40 * i as u64 + 30 * (i % 2) as u64);
+        let mut msg = new_rtp_message_event(start + arrival, i, 3600 * i as u32);
+        jitter_buffer.read(&mut msg);
+        delays.push(jitter_buffer.streams[&SSRC].delay());
+    }
+    assert!(delays.windows(2).all(|delay| delay[0] <= delay[1]));
+    assert!(delays[10] > target_delay && delays[10] < max_delay);
+    assert_eq!(delays.last(), Some(&max_delay));
+
+    // the delay shrinks back to the target once packets arrive steadily
+    for i in 100..200u16 {
+        let arrival = Duration::from_millis(40 * i as u64);
+        let mut msg = new_rtp_message_event(start + arrival, i, 3600 * i as u32);
+        jitter_buffer.read(&mut msg);
+        delays.push(jitter_buffer.streams[&SSRC].delay());
+    }
+    assert!(delays[100..].windows(2).all(|delay| delay[0] >= delay[1]));
+    assert!(delays[120] < max_delay);
+    assert_eq!(delays.last(), Some(&target_delay));
+}
+
+#[test]
+fn test_jitter_buffer_fixed_delay_ignores_jitter() {
+    let mut jitter_buffer =
+        new_adaptive_jitter_buffer(Duration::from_millis(20), Duration::from_millis(80));
+    jitter_buffer.adaptive = false;
+
+    let start = Instant::now();
+    for i in 0..100u16 {
+        let arrival = Duration::from_millis(40 * i as u64 + 30 * (i % 2) as u64);
+        let mut msg = new_rtp_message_event(start + arrival, i, 3600 * i as u32);
+        jitter_buffer.read(&mut msg);
+    }
+    assert_eq!(
+        jitter_buffer.streams[&SSRC].delay(),
+        Duration::from_millis(20)
+    );
+}
+
+#[test]
+fn test_jitter_buffer_streams_use_negotiated_clock_rate_and_expire() {
+    let mut jitter_buffer =
+        new_adaptive_jitter_buffer(Duration::from_millis(20), Duration::from_millis(200));
+    jitter_buffer.set_clock_rates(&HashMap::from([(111, 48000)]));
+
+    let start = Instant::now();
+    let mut msg = new_rtp_message_event(start, 1, 0);
+    if let MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)) = &mut msg.message {
+        rtp_packet.header.payload_type = 111;
+    }
+    jitter_buffer.read(&mut msg);
+    assert_eq!(jitter_buffer.streams[&SSRC].clock_rate(), 48000);
+
+    // the stream is kept while packets are buffered or arrived recently
+    jitter_buffer.handle_timeout(start + Duration::from_millis(20), &[]);
+    assert!(jitter_buffer.streams.contains_key(&SSRC));
+    jitter_buffer.handle_timeout(start + STREAM_IDLE_TIMEOUT, &[]);
+    assert!(!jitter_buffer.streams.contains_key(&SSRC));
+}
@@ -0,0 +1,83 @@
+use crate::interceptors::{Interceptor, InterceptorBuilder, DEFAULT_CLOCK_RATE};
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub(crate) mod buffer;
+
+#[cfg(test)]
+mod buffer_test;
+
+use buffer::JitterBuffer;
+
+/// DEFAULT_TARGET_DELAY is the default delay of inbound RTP packets held by JitterBuffer
+pub(crate) const DEFAULT_TARGET_DELAY: Duration = Duration::from_millis(50);
+/// DEFAULT_MAX_DELAY is the default delay which adaptive JitterBuffer grows up to
+pub(crate) const DEFAULT_MAX_DELAY: Duration = Duration::from_millis(500);
+
+/// JitterBufferBuilder can be used to configure inbound JitterBuffer Interceptor.
+///
+/// Interceptors are built per endpoint from the Registry of its interceptor profile, so endpoints
+/// can have different delays by registering profiles of different JitterBufferBuilders, e.g., a
+/// large one for recording and transcoding, and a minimal one for real-time forwarding.
+pub struct JitterBufferBuilder {
+    target_delay: Duration,
+    max_delay: Duration,
+    adaptive: bool,
+    clock_rate: u32,
+}
+
+impl Default for JitterBufferBuilder {
+    fn default() -> Self {
+        Self {
+            target_delay: DEFAULT_TARGET_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            adaptive: false,
+            clock_rate: DEFAULT_CLOCK_RATE,
+        }
+    }
+}
+
+impl JitterBufferBuilder {
+    /// with_target_delay sets the delay of inbound RTP packets, which is the minimum delay in adaptive mode.
+    pub fn with_target_delay(mut self, target_delay: Duration) -> JitterBufferBuilder {
+        self.target_delay = target_delay;
+        self
+    }
+
+    /// with_max_delay sets the maximum delay of inbound RTP packets in adaptive mode.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> JitterBufferBuilder {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// with_adaptive sets whether the delay grows with observed interarrival jitter of each SSRC.
+    pub fn with_adaptive(mut self, adaptive: bool) -> JitterBufferBuilder {
+        self.adaptive = adaptive;
+        self
+    }
+
+    /// with_clock_rate sets the clock rate of RTP timestamps used to measure interarrival jitter of
+    /// streams whose payload types are not of the endpoint's negotiated codecs.
+    pub fn with_clock_rate(mut self, clock_rate: u32) -> JitterBufferBuilder {
+        self.clock_rate = clock_rate;
+        self
+    }
+}
+
+impl InterceptorBuilder for JitterBufferBuilder {
+    fn name(&self) -> &str {
+        "JitterBuffer"
+    }
+
+    fn build(&self, _id: &str) -> Box<dyn Interceptor> {
+        Box::new(JitterBuffer {
+            target_delay: self.target_delay,
+            max_delay: self.max_delay.max(self.target_delay),
+            adaptive: self.adaptive,
+            clock_rate: self.clock_rate,
+            clock_rates: HashMap::new(),
+            streams: HashMap::new(),
+            next: None,
+        })
+    }
+}
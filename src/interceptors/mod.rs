@@ -1,15 +1,34 @@
+use crate::description::rtp_transceiver::PayloadType;
 use crate::messages::TaggedMessageEvent;
 use crate::types::FourTuple;
-use std::time::Instant;
+use shared::error::{Error, Result};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
+pub(crate) mod bitrate_limiter;
+pub(crate) mod jitter_buffer;
 pub(crate) mod nack;
+pub(crate) mod network_simulator;
+pub(crate) mod remb;
 pub(crate) mod report;
+pub(crate) mod tmmbr;
 pub(crate) mod twcc;
 
+#[cfg(test)]
+mod interceptors_test;
+
+/// DEFAULT_MAX_INTERCEPTOR_EVENTS is the default max messages produced by interceptors per call
+pub(crate) const DEFAULT_MAX_INTERCEPTOR_EVENTS: usize = 1024;
+/// DEFAULT_CLOCK_RATE is the default clock rate of RTP timestamps, i.e., the one of video, for
+/// interceptors measuring time in RTP timestamp units of streams whose codecs are not negotiated
+pub(crate) const DEFAULT_CLOCK_RATE: u32 = 90000;
+
 pub enum InterceptorEvent {
     Inbound(TaggedMessageEvent),
     Outbound(TaggedMessageEvent),
     Error(Box<dyn std::error::Error>),
+    /// Drop the message being read or written
+    Drop,
 }
 
 pub trait Interceptor {
@@ -44,11 +63,65 @@ pub trait Interceptor {
             next.poll_timeout(eto);
         }
     }
+
+    /// set_clock_rates passes the clock rates of the endpoint's negotiated codecs keyed by payload
+    /// type once it negotiates, for interceptors measuring time in RTP timestamp units of streams
+    fn set_clock_rates(&mut self, clock_rates: &HashMap<PayloadType, u32>) {
+        if let Some(next) = self.next() {
+            next.set_clock_rates(clock_rates);
+        }
+    }
+}
+
+/// limit_events keeps at most max_events inbound and outbound messages of the events produced by
+/// one interceptor call, excess ones are dropped, while Error and Drop events are always kept
+pub(crate) fn limit_events(
+    events: Vec<InterceptorEvent>,
+    max_events: usize,
+) -> Vec<InterceptorEvent> {
+    let mut messages = 0;
+    let mut dropped = 0;
+    let events: Vec<InterceptorEvent> = events
+        .into_iter()
+        .filter(|event| match event {
+            InterceptorEvent::Inbound(_) | InterceptorEvent::Outbound(_) => {
+                messages += 1;
+                if messages > max_events {
+                    dropped += 1;
+                    false
+                } else {
+                    true
+                }
+            }
+            InterceptorEvent::Error(_) | InterceptorEvent::Drop => true,
+        })
+        .collect();
+    if dropped > 0 {
+        log::warn!(
+            "drop {} interceptor messages exceeding the limit {}",
+            dropped,
+            max_events
+        );
+    }
+    events
 }
 
 /// InterceptorBuilder provides an interface for constructing interceptors
 pub trait InterceptorBuilder {
+    /// name identifies the builder in Registry, e.g., to remove it
+    fn name(&self) -> &str;
+
     fn build(&self, id: &str) -> Box<dyn Interceptor>;
+
+    /// build_with_report_interval builds the interceptor sending RTCP reports at the interval
+    /// instead of the configured one if it is some, other interceptors ignore it
+    fn build_with_report_interval(
+        &self,
+        id: &str,
+        _report_interval: Option<Duration>,
+    ) -> Box<dyn Interceptor> {
+        self.build(id)
+    }
 }
 
 /// Registry is a collector for interceptors.
@@ -67,10 +140,57 @@ impl Registry {
         self.builders.push(builder);
     }
 
+    /// insert_at inserts an InterceptorBuilder at the index of the chain, where interceptors of
+    /// lower indices handle read messages earlier, returns error if index is beyond the end
+    pub fn insert_at(
+        &mut self,
+        index: usize,
+        builder: Box<dyn InterceptorBuilder + Send + Sync>,
+    ) -> Result<()> {
+        if index > self.builders.len() {
+            return Err(Error::Other(format!(
+                "interceptor index {} out of range {}",
+                index,
+                self.builders.len()
+            )));
+        }
+        self.builders.insert(index, builder);
+        Ok(())
+    }
+
+    /// remove the first InterceptorBuilder of the name from the registry, and returns it
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn InterceptorBuilder + Send + Sync>> {
+        let index = self
+            .builders
+            .iter()
+            .position(|builder| builder.name() == name)?;
+        Some(self.builders.remove(index))
+    }
+
+    /// names of InterceptorBuilders in the order of the chain
+    pub fn names(&self) -> Vec<&str> {
+        self.builders.iter().map(|builder| builder.name()).collect()
+    }
+
     /// build a single Interceptor from an InterceptorRegistry
     pub fn build(&self, id: &str) -> Box<dyn Interceptor> {
+        self.build_with_report_interval(id, None)
+    }
+
+    /// build a single Interceptor from an InterceptorRegistry, whose report interceptors send
+    /// RTCP reports at the report interval if it is some, e.g., the one of the session
+    pub fn build_with_report_interval(
+        &self,
+        id: &str,
+        report_interval: Option<Duration>,
+    ) -> Box<dyn Interceptor> {
         let mut next = Box::new(NoOp) as Box<dyn Interceptor>;
-        for interceptor in self.builders.iter().rev().map(|b| b.build(id)) {
+        for interceptor in self
+            .builders
+            .iter()
+            .rev()
+            .map(|b| b.build_with_report_interval(id, report_interval))
+        {
             next = interceptor.chain(next);
         }
         next
@@ -0,0 +1,66 @@
+use crate::interceptors::{Interceptor, InterceptorBuilder};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::time::Duration;
+
+pub(crate) mod simulator;
+
+#[cfg(test)]
+mod simulator_test;
+
+use simulator::NetworkSimulator;
+
+/// NetworkSimulatorBuilder can be used to configure NetworkSimulator Interceptor, which simulates
+/// an adverse network for outbound RTP packets in tests, without an external netem setup.
+#[derive(Default)]
+pub struct NetworkSimulatorBuilder {
+    loss: f64,
+    min_delay: Duration,
+    max_delay: Duration,
+    seed: Option<u64>,
+}
+
+impl NetworkSimulatorBuilder {
+    /// with_loss sets the probability between 0.0 and 1.0 of dropping an outbound RTP packet.
+    pub fn with_loss(mut self, loss: f64) -> NetworkSimulatorBuilder {
+        self.loss = loss.clamp(0.0, 1.0);
+        self
+    }
+
+    /// with_delay sets the range of random delay of outbound RTP packets, no delay by default.
+    pub fn with_delay(
+        mut self,
+        min_delay: Duration,
+        max_delay: Duration,
+    ) -> NetworkSimulatorBuilder {
+        self.min_delay = min_delay.min(max_delay);
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// with_seed sets the seed of random losses and delays, so that they are reproducible.
+    pub fn with_seed(mut self, seed: u64) -> NetworkSimulatorBuilder {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+impl InterceptorBuilder for NetworkSimulatorBuilder {
+    fn name(&self) -> &str {
+        "NetworkSimulator"
+    }
+
+    fn build(&self, _id: &str) -> Box<dyn Interceptor> {
+        Box::new(NetworkSimulator {
+            loss: self.loss,
+            min_delay: self.min_delay,
+            max_delay: self.max_delay,
+            rng: match self.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+            delayed: vec![],
+            next: None,
+        })
+    }
+}
@@ -0,0 +1,112 @@
+use crate::interceptors::network_simulator::NetworkSimulatorBuilder;
+use crate::interceptors::{Interceptor, InterceptorEvent};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use crate::types::FourTuple;
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+/// NetworkSimulator drops outbound RTP packets at the loss probability, and holds the others for
+/// a random delay within the range before they are sent from handle_timeout, so that packets of
+/// different delays are reordered as well.
+pub(crate) struct NetworkSimulator {
+    pub(super) loss: f64,
+    pub(super) min_delay: Duration,
+    pub(super) max_delay: Duration,
+    pub(super) rng: StdRng,
+    // delayed packets with their release times
+    pub(super) delayed: Vec<(Instant, TaggedMessageEvent)>,
+    pub(super) next: Option<Box<dyn Interceptor>>,
+}
+
+impl NetworkSimulator {
+    pub(crate) fn builder() -> NetworkSimulatorBuilder {
+        NetworkSimulatorBuilder::default()
+    }
+}
+
+impl Interceptor for NetworkSimulator {
+    fn chain(mut self: Box<Self>, next: Box<dyn Interceptor>) -> Box<dyn Interceptor> {
+        self.next = Some(next);
+        self
+    }
+
+    fn next(&mut self) -> Option<&mut Box<dyn Interceptor>> {
+        self.next.as_mut()
+    }
+
+    fn write(&mut self, msg: &mut TaggedMessageEvent) -> Vec<InterceptorEvent> {
+        if let MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)) = &msg.message {
+            if self.loss > 0.0 && self.rng.gen_bool(self.loss) {
+                return vec![InterceptorEvent::Drop];
+            }
+
+            if !self.max_delay.is_zero() {
+                let delay = self.rng.gen_range(self.min_delay..=self.max_delay);
+                self.delayed.push((
+                    msg.now + delay,
+                    TaggedMessageEvent {
+                        now: msg.now,
+                        transport: msg.transport,
+                        message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet.clone())),
+                    },
+                ));
+                return vec![InterceptorEvent::Drop];
+            }
+        }
+
+        if let Some(next) = self.next() {
+            next.write(msg)
+        } else {
+            vec![]
+        }
+    }
+
+    fn handle_timeout(&mut self, now: Instant, four_tuples: &[FourTuple]) -> Vec<InterceptorEvent> {
+        let mut interceptor_events = vec![];
+
+        let (mut due, delayed): (Vec<_>, Vec<_>) = std::mem::take(&mut self.delayed)
+            .into_iter()
+            .partition(|(release_time, _)| *release_time <= now);
+        self.delayed = delayed;
+        due.sort_by_key(|(release_time, _)| *release_time);
+        for (_, mut msg) in due {
+            // packets of closed transports are lost
+            if !four_tuples.contains(&(&msg.transport).into()) {
+                continue;
+            }
+            msg.now = now;
+            let mut events = if let Some(next) = self.next() {
+                next.write(&mut msg)
+            } else {
+                vec![]
+            };
+            let dropped = events
+                .iter()
+                .any(|event| matches!(event, InterceptorEvent::Drop));
+            events.retain(|event| !matches!(event, InterceptorEvent::Drop));
+            interceptor_events.append(&mut events);
+            if !dropped {
+                interceptor_events.push(InterceptorEvent::Outbound(msg));
+            }
+        }
+
+        if let Some(next) = self.next() {
+            let mut events = next.handle_timeout(now, four_tuples);
+            interceptor_events.append(&mut events);
+        }
+        interceptor_events
+    }
+
+    fn poll_timeout(&mut self, eto: &mut Instant) {
+        for (release_time, _) in &self.delayed {
+            if *release_time < *eto {
+                *eto = *release_time;
+            }
+        }
+
+        if let Some(next) = self.next() {
+            next.poll_timeout(eto);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::simulator::*;
+use crate::interceptors::{InterceptorBuilder, InterceptorEvent};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use crate::types::FourTuple;
+use retty::transport::TransportContext;
+use std::time::{Duration, Instant};
+
+const SEED: u64 = 42;
+
+fn new_rtp_message_event(now: Instant, sequence_number: u16) -> TaggedMessageEvent {
+    let mut rtp_packet = rtp::packet::Packet::default();
+    rtp_packet.header.sequence_number = sequence_number;
+    TaggedMessageEvent {
+        now,
+        transport: TransportContext {
+            local_addr: "127.0.0.1:3478".parse().unwrap(),
+            peer_addr: "127.0.0.1:5000".parse().unwrap(),
+            ecn: None,
+        },
+        message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)),
+    }
+}
+
+#[test]
+fn test_network_simulator_drops_with_seeded_loss() {
+    let now = Instant::now();
+    let survived = |seed: u64| -> Vec<u16> {
+        let mut interceptor = NetworkSimulator::builder()
+            .with_loss(0.5)
+            .with_seed(seed)
+            .build("");
+        (0..1000u16)
+            .filter(|i| {
+                let mut msg = new_rtp_message_event(now, *i);
+                !matches!(interceptor.write(&mut msg)[..], [InterceptorEvent::Drop])
+            })
+            .collect()
+    };
+
+    // the same seed drops the same packets, another seed drops other ones
+    let expected = survived(SEED);
+    assert_eq!(survived(SEED), expected);
+    assert_ne!(survived(SEED + 1), expected);
+    assert!((450..550).contains(&expected.len()));
+}
+
+#[test]
+fn test_network_simulator_delays_packets() {
+    let mut interceptor = NetworkSimulator::builder()
+        .with_delay(Duration::from_millis(10), Duration::from_millis(50))
+        .with_seed(SEED)
+        .build("");
+    let now = Instant::now();
+    let four_tuple: FourTuple = (&new_rtp_message_event(now, 0).transport).into();
+
+    for i in 0..100u16 {
+        let mut msg = new_rtp_message_event(now, i);
+        assert!(matches!(
+            interceptor.write(&mut msg)[..],
+            [InterceptorEvent::Drop]
+        ));
+    }
+    let mut eto = now + Duration::from_secs(1);
+    interceptor.poll_timeout(&mut eto);
+    assert!(eto >= now + Duration::from_millis(10) && eto <= now + Duration::from_millis(50));
+
+    assert!(interceptor
+        .handle_timeout(now + Duration::from_millis(9), &[four_tuple])
+        .is_empty());
+    let mut released: Vec<u16> = interceptor
+        .handle_timeout(now + Duration::from_millis(30), &[four_tuple])
+        .into_iter()
+        .chain(interceptor.handle_timeout(now + Duration::from_millis(50), &[four_tuple]))
+        .filter_map(|event| match event {
+            InterceptorEvent::Outbound(TaggedMessageEvent {
+                message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)),
+                ..
+            }) => Some(rtp_packet.header.sequence_number),
+            _ => None,
+        })
+        .collect();
+    // random delays reorder packets, while none is lost
+    assert_ne!(released, (0..100).collect::<Vec<u16>>());
+    released.sort();
+    assert_eq!(released, (0..100).collect::<Vec<u16>>());
+}
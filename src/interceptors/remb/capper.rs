@@ -0,0 +1,55 @@
+use crate::interceptors::remb::CapperBuilder;
+use crate::interceptors::{Interceptor, InterceptorEvent};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use rtcp::payload_feedbacks::receiver_estimated_maximum_bitrate::ReceiverEstimatedMaximumBitrate;
+
+/// Capper clamps the bitrate advertised by outbound REMB messages to the max bitrate,
+/// so that senders are told to send no more than the operator allows, regardless of
+/// the receivers' real estimates.
+pub(crate) struct Capper {
+    /// maximum advertised bitrate in bits per second
+    pub(super) max_bitrate: u64,
+    pub(super) next: Option<Box<dyn Interceptor>>,
+}
+
+impl Capper {
+    pub(crate) fn builder() -> CapperBuilder {
+        CapperBuilder::default()
+    }
+}
+
+impl Interceptor for Capper {
+    fn chain(mut self: Box<Self>, next: Box<dyn Interceptor>) -> Box<dyn Interceptor> {
+        self.next = Some(next);
+        self
+    }
+
+    fn next(&mut self) -> Option<&mut Box<dyn Interceptor>> {
+        self.next.as_mut()
+    }
+
+    fn write(&mut self, msg: &mut TaggedMessageEvent) -> Vec<InterceptorEvent> {
+        if let MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)) = &mut msg.message {
+            let max_bitrate = self.max_bitrate as f32;
+            for rtcp_packet in rtcp_packets.iter_mut() {
+                if let Some(remb) = rtcp_packet
+                    .as_any()
+                    .downcast_ref::<ReceiverEstimatedMaximumBitrate>()
+                {
+                    if remb.bitrate > max_bitrate {
+                        *rtcp_packet = Box::new(ReceiverEstimatedMaximumBitrate {
+                            bitrate: max_bitrate,
+                            ..remb.clone()
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some(next) = self.next() {
+            next.write(msg)
+        } else {
+            vec![]
+        }
+    }
+}
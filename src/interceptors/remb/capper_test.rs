@@ -0,0 +1,77 @@
+use super::capper::*;
+use crate::interceptors::Registry;
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use bytes::Bytes;
+use retty::transport::TransportContext;
+use rtcp::payload_feedbacks::receiver_estimated_maximum_bitrate::ReceiverEstimatedMaximumBitrate;
+use shared::error::Result;
+use shared::marshal::Unmarshal;
+use std::time::Instant;
+
+fn new_remb_message(bitrate: f32) -> TaggedMessageEvent {
+    TaggedMessageEvent {
+        now: Instant::now(),
+        transport: TransportContext::default(),
+        message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(vec![
+            Box::new(
+                rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication {
+                    sender_ssrc: 1,
+                    media_ssrc: 2,
+                },
+            ),
+            Box::new(ReceiverEstimatedMaximumBitrate {
+                sender_ssrc: 1,
+                bitrate,
+                ssrcs: vec![2, 3],
+            }),
+        ])),
+    }
+}
+
+fn advertised_bitrates(msg: &TaggedMessageEvent) -> Result<Vec<f32>> {
+    let MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)) = &msg.message else {
+        return Ok(vec![]);
+    };
+    let mut bitrates = vec![];
+    for rtcp_packet in rtcp_packets {
+        if let Some(remb) = rtcp_packet
+            .as_any()
+            .downcast_ref::<ReceiverEstimatedMaximumBitrate>()
+        {
+            assert_eq!(remb.ssrcs, vec![2, 3]);
+            // the advertised bitrate is the one encoded on the wire
+            let mut raw: Bytes = rtcp_packet.marshal()?.freeze();
+            bitrates.push(ReceiverEstimatedMaximumBitrate::unmarshal(&mut raw)?.bitrate);
+        }
+    }
+    Ok(bitrates)
+}
+
+#[test]
+fn test_remb_capper_clamps_advertised_bitrate() -> Result<()> {
+    let max_bitrate = 1_000_000;
+    let mut registry = Registry::new();
+    registry.add(Box::new(Capper::builder().with_max_bitrate(max_bitrate)));
+    let mut interceptor = registry.build("");
+
+    for estimate in [5_000_000.0, 1_000_001.0, 40_000_000_000.0] {
+        let mut msg = new_remb_message(estimate);
+        assert!(interceptor.write(&mut msg).is_empty());
+        let bitrates = advertised_bitrates(&msg)?;
+        assert_eq!(bitrates.len(), 1);
+        assert!(bitrates[0] <= max_bitrate as f32);
+    }
+
+    // estimates below the cap are forwarded as they are
+    let mut msg = new_remb_message(500_000.0);
+    interceptor.write(&mut msg);
+    assert_eq!(advertised_bitrates(&msg)?, vec![500_000.0]);
+
+    // the other packets in the compound are kept
+    let MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)) = &msg.message else {
+        panic!("unexpected message");
+    };
+    assert_eq!(rtcp_packets.len(), 2);
+
+    Ok(())
+}
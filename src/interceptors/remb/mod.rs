@@ -0,0 +1,42 @@
+use crate::interceptors::{Interceptor, InterceptorBuilder};
+
+pub(crate) mod capper;
+
+#[cfg(test)]
+mod capper_test;
+
+use capper::Capper;
+
+/// CapperBuilder can be used to configure REMB Capper Interceptor.
+pub struct CapperBuilder {
+    max_bitrate: u64,
+}
+
+impl Default for CapperBuilder {
+    fn default() -> Self {
+        Self {
+            max_bitrate: u64::MAX,
+        }
+    }
+}
+
+impl CapperBuilder {
+    /// with_max_bitrate sets the maximum bitrate advertised by outbound REMB in bits per second.
+    pub fn with_max_bitrate(mut self, max_bitrate: u64) -> CapperBuilder {
+        self.max_bitrate = max_bitrate;
+        self
+    }
+}
+
+impl InterceptorBuilder for CapperBuilder {
+    fn name(&self) -> &str {
+        "Capper"
+    }
+
+    fn build(&self, _id: &str) -> Box<dyn Interceptor> {
+        Box::new(Capper {
+            max_bitrate: self.max_bitrate,
+            next: None,
+        })
+    }
+}
@@ -1,19 +1,29 @@
-use crate::interceptors::{Interceptor, InterceptorBuilder};
+use crate::interceptors::{Interceptor, InterceptorBuilder, DEFAULT_CLOCK_RATE};
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
 pub(crate) mod receiver_report;
 pub(crate) mod receiver_stream;
 pub(crate) mod sender_report;
+pub(crate) mod sender_stream;
+
+#[cfg(test)]
+mod receiver_report_test;
+#[cfg(test)]
+mod sender_report_test;
 
 use receiver_report::ReceiverReport;
 use sender_report::SenderReport;
 
-/// ReceiverBuilder can be used to configure ReceiverReport Interceptor.
+/// DEFAULT_REPORT_INTERVAL is the default send interval of Sender and Receiver Reports
+pub(crate) const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// ReportBuilder can be used to configure SenderReport and ReceiverReport Interceptors.
 #[derive(Default)]
 pub struct ReportBuilder {
     is_rr: bool,
     interval: Option<Duration>,
+    clock_rate: Option<u32>,
 }
 
 impl ReportBuilder {
@@ -23,30 +33,65 @@ impl ReportBuilder {
         self
     }
 
-    fn build_rr(&self) -> ReceiverReport {
+    /// with_clock_rate sets the clock rate of RTP timestamps, which are extrapolated to the time of
+    /// Sender Reports, and measure interarrival jitter of Receiver Reports, for streams whose
+    /// payload types are not of the endpoint's negotiated codecs.
+    pub fn with_clock_rate(mut self, clock_rate: u32) -> ReportBuilder {
+        self.clock_rate = Some(clock_rate);
+        self
+    }
+
+    fn interval(&self, report_interval: Option<Duration>) -> Duration {
+        report_interval
+            .or(self.interval)
+            .unwrap_or(DEFAULT_REPORT_INTERVAL)
+    }
+
+    fn build_rr(&self, report_interval: Option<Duration>) -> ReceiverReport {
         ReceiverReport {
-            interval: if let Some(interval) = &self.interval {
-                *interval
-            } else {
-                Duration::from_secs(1) //TODO: make it configurable
-            },
+            interval: self.interval(report_interval),
             eto: Instant::now(),
+            clock_rate: self.clock_rate.unwrap_or(DEFAULT_CLOCK_RATE),
+            clock_rates: HashMap::new(),
             streams: HashMap::new(),
             next: None,
         }
     }
 
-    fn build_sr(&self) -> SenderReport {
-        SenderReport { next: None }
+    fn build_sr(&self, report_interval: Option<Duration>) -> SenderReport {
+        SenderReport {
+            interval: self.interval(report_interval),
+            eto: Instant::now(),
+            clock_rate: self.clock_rate.unwrap_or(DEFAULT_CLOCK_RATE),
+            clock_rates: HashMap::new(),
+            streams: HashMap::new(),
+            next: None,
+        }
     }
 }
 
 impl InterceptorBuilder for ReportBuilder {
-    fn build(&self, _id: &str) -> Box<dyn Interceptor> {
+    fn name(&self) -> &str {
+        if self.is_rr {
+            "ReceiverReport"
+        } else {
+            "SenderReport"
+        }
+    }
+
+    fn build(&self, id: &str) -> Box<dyn Interceptor> {
+        self.build_with_report_interval(id, None)
+    }
+
+    fn build_with_report_interval(
+        &self,
+        _id: &str,
+        report_interval: Option<Duration>,
+    ) -> Box<dyn Interceptor> {
         if self.is_rr {
-            Box::new(self.build_rr())
+            Box::new(self.build_rr(report_interval))
         } else {
-            Box::new(self.build_sr())
+            Box::new(self.build_sr(report_interval))
         }
     }
 }
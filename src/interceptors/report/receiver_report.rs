@@ -1,3 +1,4 @@
+use crate::description::rtp_transceiver::PayloadType;
 use crate::interceptors::report::receiver_stream::ReceiverStream;
 use crate::interceptors::report::ReportBuilder;
 use crate::interceptors::{Interceptor, InterceptorEvent};
@@ -7,9 +8,14 @@ use retty::transport::TransportContext;
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
+/// ReceiverReport accumulates reception statistics of each inbound SSRC, and sends them back as
+/// RTCP Receiver Reports at the interval. Interarrival jitter of each SSRC is measured in the clock
+/// rate of its negotiated codec, or clock_rate if unknown
 pub(crate) struct ReceiverReport {
     pub(super) interval: Duration,
     pub(super) eto: Instant,
+    pub(super) clock_rate: u32,
+    pub(super) clock_rates: HashMap<PayloadType, u32>,
     pub(crate) streams: HashMap<u32, ReceiverStream>,
     pub(super) next: Option<Box<dyn Interceptor>>,
 }
@@ -46,9 +52,15 @@ impl Interceptor for ReceiverReport {
                 }
             }
         } else if let MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)) = &msg.message {
-            if let Some(stream) = self.streams.get_mut(&rtp_packet.header.ssrc) {
-                stream.process_rtp(msg.now, rtp_packet);
-            }
+            let clock_rate = self
+                .clock_rates
+                .get(&rtp_packet.header.payload_type)
+                .copied()
+                .unwrap_or(self.clock_rate);
+            self.streams
+                .entry(rtp_packet.header.ssrc)
+                .or_insert_with(|| ReceiverStream::new(rtp_packet.header.ssrc, clock_rate))
+                .process_rtp(msg.now, rtp_packet);
         }
 
         if let Some(next) = self.next() {
@@ -89,6 +101,14 @@ impl Interceptor for ReceiverReport {
         interceptor_events
     }
 
+    fn set_clock_rates(&mut self, clock_rates: &HashMap<PayloadType, u32>) {
+        self.clock_rates.clone_from(clock_rates);
+
+        if let Some(next) = self.next() {
+            next.set_clock_rates(clock_rates);
+        }
+    }
+
     fn poll_timeout(&mut self, eto: &mut Instant) {
         if self.eto < *eto {
             *eto = self.eto
@@ -0,0 +1,132 @@
+use super::receiver_report::*;
+use crate::interceptors::report::sender_stream::ntp_time;
+use crate::interceptors::{InterceptorBuilder, InterceptorEvent};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use crate::types::FourTuple;
+use retty::transport::TransportContext;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+fn get_reception_reports(
+    events: &[InterceptorEvent],
+) -> Vec<rtcp::reception_report::ReceptionReport> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            InterceptorEvent::Outbound(TaggedMessageEvent {
+                message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)),
+                ..
+            }) => Some(rtcp_packets),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|p| {
+            p.as_any()
+                .downcast_ref::<rtcp::receiver_report::ReceiverReport>()
+                .cloned()
+        })
+        .flat_map(|rr| rr.reports)
+        .collect()
+}
+
+#[test]
+fn test_receiver_report_reports_loss_and_sender_report_delay() {
+    let mut interceptor = ReceiverReport::builder()
+        .with_interval(Duration::from_secs(1))
+        .build("");
+    let transport = TransportContext {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "127.0.0.1:5000".parse().unwrap(),
+        ecn: None,
+    };
+    let four_tuple: FourTuple = (&transport).into();
+
+    // packets 20ms apart, one of every 10 is lost
+    let start = Instant::now();
+    for i in (0..100u32).filter(|i| i % 10 != 5) {
+        let mut msg = TaggedMessageEvent {
+            now: start + Duration::from_millis(20 * i as u64),
+            transport,
+            message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp::packet::Packet {
+                header: rtp::header::Header {
+                    ssrc: 1234,
+                    sequence_number: i as u16,
+                    timestamp: 1800 * i,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })),
+        };
+        interceptor.read(&mut msg);
+    }
+
+    let sender_report_time = start + Duration::from_secs(2);
+    let ntp_time = ntp_time(sender_report_time);
+    let mut msg = TaggedMessageEvent {
+        now: sender_report_time,
+        transport,
+        message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(vec![Box::new(
+            rtcp::sender_report::SenderReport {
+                ssrc: 1234,
+                ntp_time,
+                ..Default::default()
+            },
+        )])),
+    };
+    interceptor.read(&mut msg);
+
+    let now = sender_report_time + Duration::from_millis(500);
+    let reports = get_reception_reports(&interceptor.handle_timeout(now, &[four_tuple]));
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!(report.ssrc, 1234);
+    assert_eq!(report.last_sequence_number, 99);
+    assert_eq!(report.total_lost, 10);
+    assert_eq!(report.fraction_lost, (10 * 256 / 100) as u8);
+    assert_eq!(report.jitter, 0);
+    assert_eq!(report.last_sender_report, (ntp_time >> 16) as u32);
+    assert_eq!(report.delay, 65536 / 2);
+}
+
+#[test]
+fn test_receiver_report_measures_jitter_by_negotiated_clock_rate() {
+    let transport = TransportContext {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "127.0.0.1:5000".parse().unwrap(),
+        ecn: None,
+    };
+    let four_tuple: FourTuple = (&transport).into();
+    let get_jitter = |clock_rates: HashMap<u8, u32>| -> u32 {
+        let mut interceptor = ReceiverReport::builder()
+            .with_interval(Duration::from_secs(1))
+            .build("");
+        interceptor.set_clock_rates(&clock_rates);
+        // opus packets of 20ms arriving exactly 20ms apart
+        let start = Instant::now();
+        for i in 0..10u32 {
+            let mut msg = TaggedMessageEvent {
+                now: start + Duration::from_millis(20 * i as u64),
+                transport,
+                message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp::packet::Packet {
+                    header: rtp::header::Header {
+                        ssrc: 1234,
+                        payload_type: 111,
+                        sequence_number: i as u16,
+                        timestamp: 960 * i,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })),
+            };
+            interceptor.read(&mut msg);
+        }
+        let reports = get_reception_reports(
+            &interceptor.handle_timeout(start + Duration::from_millis(200), &[four_tuple]),
+        );
+        reports[0].jitter
+    };
+
+    assert_eq!(get_jitter(HashMap::from([(111, 48000)])), 0);
+    // measured at the default clock rate of video, the stream appears jittery
+    assert_ne!(get_jitter(HashMap::new()), 0);
+}
@@ -40,17 +40,17 @@ impl ReceiverStream {
     }
 
     fn set_received(&mut self, seq: u16) {
-        let pos = (seq as usize) % self.packets.len();
+        let pos = (seq as usize) % (self.packets.len() * 64);
         self.packets[pos / 64] |= 1 << (pos % 64);
     }
 
     fn del_received(&mut self, seq: u16) {
-        let pos = (seq as usize) % self.packets.len();
+        let pos = (seq as usize) % (self.packets.len() * 64);
         self.packets[pos / 64] &= u64::MAX ^ (1u64 << (pos % 64));
     }
 
     fn get_received(&self, seq: u16) -> bool {
-        let pos = (seq as usize) % self.packets.len();
+        let pos = (seq as usize) % (self.packets.len() * 64);
         (self.packets[pos / 64] & (1 << (pos % 64))) != 0
     }
 
@@ -141,10 +141,14 @@ impl ReceiverStream {
                 fraction_lost: ((total_lost_since_report * 256) as f64 / total_since_report as f64)
                     as u8,
                 total_lost: self.total_lost,
-                delay: (now
-                    .duration_since(self.last_sender_report_time)
-                    .as_secs_f64()
-                    * 65536.0) as u32,
+                // delay since last sender report in 1/65536 seconds, 0 if none is received
+                delay: if self.last_sender_report == 0 {
+                    0
+                } else {
+                    (now.saturating_duration_since(self.last_sender_report_time)
+                        .as_secs_f64()
+                        * 65536.0) as u32
+                },
                 jitter: self.jitter as u32,
             }],
             ..Default::default()
@@ -1,9 +1,24 @@
+use crate::description::rtp_transceiver::PayloadType;
+use crate::interceptors::report::sender_stream::SenderStream;
 use crate::interceptors::report::ReportBuilder;
 use crate::interceptors::{Interceptor, InterceptorEvent};
 use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use crate::types::FourTuple;
+use retty::transport::TransportContext;
 use rtcp::header::PacketType;
+use rtcp::transport_feedbacks::transport_layer_nack::TransportLayerNack;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
+/// SenderReport accumulates statistics of each outbound SSRC, and sends RTCP Sender Reports over
+/// the transport of the SSRC at the interval, while it stops inbound hop by hop reports. RTP
+/// timestamps of each SSRC are in the clock rate of its negotiated codec, or clock_rate if unknown
 pub(crate) struct SenderReport {
+    pub(super) interval: Duration,
+    pub(super) eto: Instant,
+    pub(super) clock_rate: u32,
+    pub(super) clock_rates: HashMap<PayloadType, u32>,
+    pub(crate) streams: HashMap<u32, SenderStream>,
     pub(super) next: Option<Box<dyn Interceptor>>,
 }
 
@@ -35,10 +50,15 @@ impl Interceptor for SenderReport {
             for rtcp_packet in rtcp_packets {
                 let packet_type = rtcp_packet.header().packet_type;
                 if packet_type == PacketType::ReceiverReport
-                    || (packet_type == PacketType::TransportSpecificFeedback)
+                    || (packet_type == PacketType::TransportSpecificFeedback
+                        && rtcp_packet
+                            .as_any()
+                            .downcast_ref::<TransportLayerNack>()
+                            .is_none())
                 {
                     // let's not forward ReceiverReport and TransportSpecificFeedback
-                    // since they are hop by hop reports, instead of end to end reports
+                    // since they are hop by hop reports, instead of end to end reports,
+                    // except NACK, which only the publisher can retransmit for
                     continue;
                 } else {
                     inbound_rtcp_packets.push(rtcp_packet.clone());
@@ -60,4 +80,77 @@ impl Interceptor for SenderReport {
         }
         interceptor_events
     }
+
+    fn write(&mut self, msg: &mut TaggedMessageEvent) -> Vec<InterceptorEvent> {
+        if let MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)) = &msg.message {
+            let clock_rate = self
+                .clock_rates
+                .get(&rtp_packet.header.payload_type)
+                .copied()
+                .unwrap_or(self.clock_rate);
+            let four_tuple = (&msg.transport).into();
+            self.streams
+                .entry(rtp_packet.header.ssrc)
+                .or_insert_with(|| {
+                    SenderStream::new(rtp_packet.header.ssrc, clock_rate, four_tuple, msg.now)
+                })
+                .process_rtp(msg.now, rtp_packet);
+        }
+
+        if let Some(next) = self.next() {
+            next.write(msg)
+        } else {
+            vec![]
+        }
+    }
+
+    fn handle_timeout(&mut self, now: Instant, four_tuples: &[FourTuple]) -> Vec<InterceptorEvent> {
+        let mut interceptor_events = vec![];
+
+        if self.eto <= now {
+            self.eto = now + self.interval;
+
+            // streams of closed transports are dropped
+            self.streams
+                .retain(|_, stream| four_tuples.contains(&stream.four_tuple()));
+            for stream in self.streams.values() {
+                let four_tuple = stream.four_tuple();
+                interceptor_events.push(InterceptorEvent::Outbound(TaggedMessageEvent {
+                    now,
+                    transport: TransportContext {
+                        local_addr: four_tuple.local_addr,
+                        peer_addr: four_tuple.peer_addr,
+                        ecn: None,
+                    },
+                    message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(vec![Box::new(
+                        stream.generate_report(now),
+                    )])),
+                }));
+            }
+        }
+
+        if let Some(next) = self.next() {
+            let mut events = next.handle_timeout(now, four_tuples);
+            interceptor_events.append(&mut events);
+        }
+        interceptor_events
+    }
+
+    fn set_clock_rates(&mut self, clock_rates: &HashMap<PayloadType, u32>) {
+        self.clock_rates.clone_from(clock_rates);
+
+        if let Some(next) = self.next() {
+            next.set_clock_rates(clock_rates);
+        }
+    }
+
+    fn poll_timeout(&mut self, eto: &mut Instant) {
+        if self.eto < *eto {
+            *eto = self.eto
+        }
+
+        if let Some(next) = self.next() {
+            next.poll_timeout(eto);
+        }
+    }
 }
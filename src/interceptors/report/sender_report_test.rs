@@ -0,0 +1,125 @@
+use super::sender_report::*;
+use crate::interceptors::{InterceptorBuilder, InterceptorEvent};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use crate::types::FourTuple;
+use bytes::Bytes;
+use retty::transport::TransportContext;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+fn new_transport_context(peer_addr: &str) -> TransportContext {
+    TransportContext {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: peer_addr.parse().unwrap(),
+        ecn: None,
+    }
+}
+
+fn get_sender_reports(events: &[InterceptorEvent]) -> Vec<rtcp::sender_report::SenderReport> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            InterceptorEvent::Outbound(TaggedMessageEvent {
+                message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)),
+                ..
+            }) => Some(rtcp_packets),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|p| {
+            p.as_any()
+                .downcast_ref::<rtcp::sender_report::SenderReport>()
+                .cloned()
+        })
+        .collect()
+}
+
+#[test]
+fn test_sender_report_counts_packets_and_octets() {
+    let mut interceptor = SenderReport::builder()
+        .with_interval(Duration::from_secs(1))
+        .build("");
+    let transport = new_transport_context("127.0.0.1:5000");
+    let four_tuple: FourTuple = (&transport).into();
+
+    let start = Instant::now();
+    let mut octet_count = 0;
+    for i in 0..100u32 {
+        let payload_size = 100 + (i as usize % 10) * 10;
+        octet_count += payload_size as u32;
+        let mut msg = TaggedMessageEvent {
+            now: start + Duration::from_millis(20 * i as u64),
+            transport,
+            message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp::packet::Packet {
+                header: rtp::header::Header {
+                    ssrc: 1234,
+                    sequence_number: i as u16,
+                    timestamp: 1800 * i,
+                    ..Default::default()
+                },
+                payload: Bytes::from(vec![0u8; payload_size]),
+            })),
+        };
+        assert!(interceptor.write(&mut msg).is_empty());
+    }
+
+    // 100ms after the last packet
+    let now = start + Duration::from_millis(2080);
+    let sender_reports = get_sender_reports(&interceptor.handle_timeout(now, &[four_tuple]));
+    assert_eq!(sender_reports.len(), 1);
+    let sender_report = &sender_reports[0];
+    assert_eq!(sender_report.ssrc, 1234);
+    assert_eq!(sender_report.packet_count, 100);
+    assert_eq!(sender_report.octet_count, octet_count);
+    assert_eq!(sender_report.rtp_time, 1800 * 99 + 9000);
+    assert_ne!(sender_report.ntp_time, 0);
+
+    // the next report is due after the interval
+    let mut eto = now + Duration::from_secs(10);
+    interceptor.poll_timeout(&mut eto);
+    assert_eq!(eto, now + Duration::from_secs(1));
+    assert!(get_sender_reports(&interceptor.handle_timeout(now, &[four_tuple])).is_empty());
+
+    // no report is sent once the transport is closed
+    let now = now + Duration::from_secs(1);
+    assert!(get_sender_reports(&interceptor.handle_timeout(now, &[])).is_empty());
+}
+
+#[test]
+fn test_sender_report_extrapolates_rtp_time_by_negotiated_clock_rate() {
+    let mut interceptor = SenderReport::builder()
+        .with_interval(Duration::from_secs(1))
+        .build("");
+    // opus of payload type 111 is negotiated, while payload type 96 is unknown
+    interceptor.set_clock_rates(&HashMap::from([(111, 48000)]));
+    let transport = new_transport_context("127.0.0.1:5000");
+    let four_tuple: FourTuple = (&transport).into();
+
+    let start = Instant::now();
+    for (ssrc, payload_type) in [(1111, 111), (2222, 96)] {
+        let mut msg = TaggedMessageEvent {
+            now: start,
+            transport,
+            message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp::packet::Packet {
+                header: rtp::header::Header {
+                    ssrc,
+                    payload_type,
+                    timestamp: 1000,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })),
+        };
+        interceptor.write(&mut msg);
+    }
+
+    // 100ms after the packets
+    let now = start + Duration::from_millis(100);
+    let mut rtp_times: Vec<(u32, u32)> =
+        get_sender_reports(&interceptor.handle_timeout(now, &[four_tuple]))
+            .iter()
+            .map(|sender_report| (sender_report.ssrc, sender_report.rtp_time))
+            .collect();
+    rtp_times.sort();
+    assert_eq!(rtp_times, vec![(1111, 1000 + 4800), (2222, 1000 + 9000)]);
+}
@@ -0,0 +1,76 @@
+use crate::types::FourTuple;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// NTP_UNIX_OFFSET is the seconds from NTP epoch 1900 to UNIX epoch 1970
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+pub(crate) struct SenderStream {
+    ssrc: u32,
+    clock_rate: f64,
+    four_tuple: FourTuple,
+
+    last_rtp_time_rtp: u32,
+    last_rtp_time_time: Instant,
+    packet_count: u32,
+    octet_count: u32,
+}
+
+impl SenderStream {
+    pub(crate) fn new(ssrc: u32, clock_rate: u32, four_tuple: FourTuple, now: Instant) -> Self {
+        Self {
+            ssrc,
+            clock_rate: clock_rate as f64,
+            four_tuple,
+
+            last_rtp_time_rtp: 0,
+            last_rtp_time_time: now,
+            packet_count: 0,
+            octet_count: 0,
+        }
+    }
+
+    pub(crate) fn four_tuple(&self) -> FourTuple {
+        self.four_tuple
+    }
+
+    pub(crate) fn process_rtp(&mut self, now: Instant, pkt: &rtp::packet::Packet) {
+        self.last_rtp_time_rtp = pkt.header.timestamp;
+        self.last_rtp_time_time = now;
+        // counts wrap around as RFC 3550 allows
+        self.packet_count = self.packet_count.wrapping_add(1);
+        self.octet_count = self.octet_count.wrapping_add(pkt.payload.len() as u32);
+    }
+
+    /// generate_report pairs NTP time of now with the RTP timestamp extrapolated from the last
+    /// sent packet
+    pub(crate) fn generate_report(&self, now: Instant) -> rtcp::sender_report::SenderReport {
+        let elapsed = now.saturating_duration_since(self.last_rtp_time_time);
+        rtcp::sender_report::SenderReport {
+            ssrc: self.ssrc,
+            ntp_time: ntp_time(now),
+            rtp_time: self
+                .last_rtp_time_rtp
+                .wrapping_add((elapsed.as_secs_f64() * self.clock_rate) as u32),
+            packet_count: self.packet_count,
+            octet_count: self.octet_count,
+            ..Default::default()
+        }
+    }
+}
+
+/// ntp_time returns the 64-bit NTP timestamp of the instant
+/// <https://tools.ietf.org/html/rfc3550#section-4>
+pub(crate) fn ntp_time(instant: Instant) -> u64 {
+    let (system_time, now) = (SystemTime::now(), Instant::now());
+    let system_time = if instant >= now {
+        system_time + (instant - now)
+    } else {
+        system_time - (now - instant)
+    };
+    let since_epoch = system_time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO);
+    let seconds = since_epoch.as_secs() + NTP_UNIX_OFFSET;
+    let fraction = ((since_epoch.subsec_nanos() as u64) << 32) / 1_000_000_000;
+    (seconds << 32) | fraction
+}
@@ -0,0 +1,134 @@
+use crate::interceptors::{Interceptor, InterceptorBuilder};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use rtcp::header::{Header, PacketType, HEADER_LENGTH, SSRC_LENGTH};
+use shared::error::{Error, Result};
+use shared::marshal::{Marshal, Unmarshal};
+use std::collections::HashMap;
+
+pub(crate) mod responder;
+
+#[cfg(test)]
+mod responder_test;
+
+use responder::Responder;
+
+/// FORMAT_TMMBR is the FMT of Temporary Maximum Media Stream Bit Rate Request
+pub(crate) const FORMAT_TMMBR: u8 = 3;
+/// FORMAT_TMMBN is the FMT of Temporary Maximum Media Stream Bit Rate Notification
+pub(crate) const FORMAT_TMMBN: u8 = 4;
+
+const TMMB_ITEM_LENGTH: usize = 8;
+const MANTISSA_MAX: u64 = 0x1FFFF;
+
+/// TmmbItem is a FCI entry of TMMBR and TMMBN
+/// <https://datatracker.ietf.org/doc/html/rfc5104#section-4.2.1.1>
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct TmmbItem {
+    pub(crate) ssrc: u32,
+    /// maximum total media bit rate in bits per second
+    pub(crate) bitrate: u64,
+    /// measured overhead in bytes per packet
+    pub(crate) overhead: u16,
+}
+
+/// TemporaryMaximumMediaStreamBitrate is a TMMBR or TMMBN transport layer feedback message
+/// <https://datatracker.ietf.org/doc/html/rfc5104#section-4.2.1>
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct TemporaryMaximumMediaStreamBitrate {
+    pub(crate) is_notification: bool,
+    pub(crate) sender_ssrc: u32,
+    pub(crate) items: Vec<TmmbItem>,
+}
+
+impl TemporaryMaximumMediaStreamBitrate {
+    /// is_tmmbr returns true if the RTCP header is of a TMMBR message
+    pub(crate) fn is_tmmbr(header: &Header) -> bool {
+        header.packet_type == PacketType::TransportSpecificFeedback && header.count == FORMAT_TMMBR
+    }
+
+    pub(crate) fn unmarshal(raw_packet: &Bytes) -> Result<Self> {
+        let mut buf = raw_packet.clone();
+        let header = Header::unmarshal(&mut buf)?;
+        if header.packet_type != PacketType::TransportSpecificFeedback
+            || (header.count != FORMAT_TMMBR && header.count != FORMAT_TMMBN)
+        {
+            return Err(Error::WrongType);
+        }
+        let length = (header.length as usize + 1) * 4;
+        if raw_packet.len() < length || length < HEADER_LENGTH + SSRC_LENGTH * 2 {
+            return Err(Error::PacketTooShort);
+        }
+
+        let mut buf = raw_packet.slice(HEADER_LENGTH..length);
+        let sender_ssrc = buf.get_u32();
+        // media source SSRC is not used and must be set to 0
+        let _ = buf.get_u32();
+
+        let mut items = vec![];
+        while buf.remaining() >= TMMB_ITEM_LENGTH {
+            let ssrc = buf.get_u32();
+            let value = buf.get_u32();
+            let exp = value >> 26;
+            let mantissa = ((value >> 9) as u64) & MANTISSA_MAX;
+            items.push(TmmbItem {
+                ssrc,
+                bitrate: mantissa.checked_shl(exp).unwrap_or(u64::MAX),
+                overhead: (value & 0x1FF) as u16,
+            });
+        }
+
+        Ok(Self {
+            is_notification: header.count == FORMAT_TMMBN,
+            sender_ssrc,
+            items,
+        })
+    }
+
+    pub(crate) fn marshal(&self) -> Result<Bytes> {
+        let length = HEADER_LENGTH + SSRC_LENGTH * 2 + self.items.len() * TMMB_ITEM_LENGTH;
+        let header = Header {
+            padding: false,
+            count: if self.is_notification {
+                FORMAT_TMMBN
+            } else {
+                FORMAT_TMMBR
+            },
+            packet_type: PacketType::TransportSpecificFeedback,
+            length: (length / 4 - 1) as u16,
+        };
+
+        let mut buf = BytesMut::with_capacity(length);
+        buf.extend_from_slice(&header.marshal()?);
+        buf.put_u32(self.sender_ssrc);
+        buf.put_u32(0);
+        for item in &self.items {
+            let mut exp = 0u32;
+            while (item.bitrate >> exp) > MANTISSA_MAX {
+                exp += 1;
+            }
+            let mantissa = (item.bitrate >> exp) as u32;
+            buf.put_u32(item.ssrc);
+            buf.put_u32((exp << 26) | (mantissa << 9) | (item.overhead as u32 & 0x1FF));
+        }
+
+        Ok(buf.freeze())
+    }
+}
+
+/// ResponderBuilder can be used to configure TMMBR Responder Interceptor.
+#[derive(Default)]
+pub struct ResponderBuilder;
+
+impl InterceptorBuilder for ResponderBuilder {
+    fn name(&self) -> &str {
+        "Responder"
+    }
+
+    fn build(&self, _id: &str) -> Box<dyn Interceptor> {
+        Box::new(Responder {
+            sender_ssrc: rand::random::<u32>(),
+            max_bitrates: HashMap::new(),
+            next: None,
+        })
+    }
+}
@@ -0,0 +1,86 @@
+use crate::interceptors::tmmbr::{ResponderBuilder, TemporaryMaximumMediaStreamBitrate, TmmbItem};
+use crate::interceptors::{Interceptor, InterceptorEvent};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use rtcp::raw_packet::RawPacket;
+use std::collections::HashMap;
+
+/// Responder parses TMMBR messages into max bitrates of media SSRCs,
+/// and acknowledges them with TMMBN messages.
+pub(crate) struct Responder {
+    pub(super) sender_ssrc: u32,
+    pub(crate) max_bitrates: HashMap<u32, u64>,
+    pub(super) next: Option<Box<dyn Interceptor>>,
+}
+
+impl Responder {
+    pub(crate) fn builder() -> ResponderBuilder {
+        ResponderBuilder
+    }
+}
+
+impl Interceptor for Responder {
+    fn chain(mut self: Box<Self>, next: Box<dyn Interceptor>) -> Box<dyn Interceptor> {
+        self.next = Some(next);
+        self
+    }
+
+    fn next(&mut self) -> Option<&mut Box<dyn Interceptor>> {
+        self.next.as_mut()
+    }
+
+    fn read(&mut self, msg: &mut TaggedMessageEvent) -> Vec<InterceptorEvent> {
+        let mut interceptor_events = vec![];
+
+        if let MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)) = &msg.message {
+            let mut tmmbn_items = vec![];
+
+            for rtcp_packet in rtcp_packets {
+                if !TemporaryMaximumMediaStreamBitrate::is_tmmbr(&rtcp_packet.header()) {
+                    continue;
+                }
+                let Some(raw_packet) = rtcp_packet.as_any().downcast_ref::<RawPacket>() else {
+                    continue;
+                };
+                match TemporaryMaximumMediaStreamBitrate::unmarshal(&raw_packet.0) {
+                    Ok(tmmbr) => {
+                        for item in tmmbr.items {
+                            self.max_bitrates.insert(item.ssrc, item.bitrate);
+                            // TMMBN lists the owner of the accepted tuple, i.e., the TMMBR sender
+                            tmmbn_items.push(TmmbItem {
+                                ssrc: tmmbr.sender_ssrc,
+                                ..item
+                            });
+                        }
+                    }
+                    Err(err) => interceptor_events.push(InterceptorEvent::Error(Box::new(err))),
+                }
+            }
+
+            if !tmmbn_items.is_empty() {
+                let tmmbn = TemporaryMaximumMediaStreamBitrate {
+                    is_notification: true,
+                    sender_ssrc: self.sender_ssrc,
+                    items: tmmbn_items,
+                };
+                match tmmbn.marshal() {
+                    Ok(raw) => {
+                        interceptor_events.push(InterceptorEvent::Outbound(TaggedMessageEvent {
+                            now: msg.now,
+                            transport: msg.transport,
+                            message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(vec![Box::new(
+                                RawPacket(raw),
+                            )])),
+                        }))
+                    }
+                    Err(err) => interceptor_events.push(InterceptorEvent::Error(Box::new(err))),
+                }
+            }
+        }
+
+        if let Some(next) = self.next() {
+            let mut events = next.read(msg);
+            interceptor_events.append(&mut events);
+        }
+        interceptor_events
+    }
+}
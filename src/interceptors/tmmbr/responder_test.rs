@@ -0,0 +1,87 @@
+use super::responder::*;
+use super::*;
+use crate::interceptors::InterceptorEvent;
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use retty::transport::TransportContext;
+use rtcp::raw_packet::RawPacket;
+use std::time::Instant;
+
+// TMMBR from SSRC 0x11223344 for media SSRC 0x55667788,
+// MxTBR Exp 5, Mantissa 31250 (1_000_000 bps), Measured Overhead 40
+const TMMBR_PACKET: [u8; 20] = [
+    0x83, 0xcd, 0x00, 0x04, // V=2, FMT=3, PT=205, length=4
+    0x11, 0x22, 0x33, 0x44, // SSRC of packet sender
+    0x00, 0x00, 0x00, 0x00, // SSRC of media source
+    0x55, 0x66, 0x77, 0x88, // SSRC
+    0x14, 0xf4, 0x24, 0x28, // MxTBR Exp, Mantissa, Measured Overhead
+];
+
+#[test]
+fn test_tmmbr_unmarshal() -> Result<()> {
+    let tmmbr = TemporaryMaximumMediaStreamBitrate::unmarshal(&Bytes::from_static(&TMMBR_PACKET))?;
+
+    assert!(!tmmbr.is_notification);
+    assert_eq!(tmmbr.sender_ssrc, 0x11223344);
+    assert_eq!(
+        tmmbr.items,
+        vec![TmmbItem {
+            ssrc: 0x55667788,
+            bitrate: 1_000_000,
+            overhead: 40,
+        }]
+    );
+    assert_eq!(
+        TemporaryMaximumMediaStreamBitrate::unmarshal(&tmmbr.marshal()?)?,
+        tmmbr
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_tmmbr_responder() -> Result<()> {
+    let mut responder = Responder {
+        sender_ssrc: 0x99aabbcc,
+        max_bitrates: HashMap::new(),
+        next: None,
+    };
+    let rtcp_packets = rtcp::packet::unmarshal(&mut Bytes::from_static(&TMMBR_PACKET))?;
+    let mut msg = TaggedMessageEvent {
+        now: Instant::now(),
+        transport: TransportContext {
+            local_addr: "127.0.0.1:3478".parse().unwrap(),
+            peer_addr: "127.0.0.1:5000".parse().unwrap(),
+            ecn: None,
+        },
+        message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)),
+    };
+
+    let events = responder.read(&mut msg);
+    assert_eq!(responder.max_bitrates.get(&0x55667788), Some(&1_000_000));
+
+    assert_eq!(events.len(), 1);
+    let InterceptorEvent::Outbound(TaggedMessageEvent {
+        message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)),
+        ..
+    }) = &events[0]
+    else {
+        panic!("expected outbound TMMBN");
+    };
+    let raw_packet = rtcp_packets[0]
+        .as_any()
+        .downcast_ref::<RawPacket>()
+        .unwrap();
+    let tmmbn = TemporaryMaximumMediaStreamBitrate::unmarshal(&raw_packet.0)?;
+    assert!(tmmbn.is_notification);
+    assert_eq!(tmmbn.sender_ssrc, 0x99aabbcc);
+    assert_eq!(
+        tmmbn.items,
+        vec![TmmbItem {
+            ssrc: 0x11223344,
+            bitrate: 1_000_000,
+            overhead: 40,
+        }]
+    );
+
+    Ok(())
+}
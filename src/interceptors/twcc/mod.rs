@@ -1 +1,101 @@
+use crate::interceptors::{Interceptor, InterceptorBuilder};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
+pub(crate) mod receiver;
+pub(crate) mod sender;
+
+#[cfg(test)]
+mod receiver_test;
+#[cfg(test)]
+mod sender_test;
+
+use receiver::Receiver;
+use sender::Sender;
+
+/// SenderBuilder can be used to configure TWCC Sender Interceptor.
+#[derive(Default)]
+pub struct SenderBuilder {
+    header_extension_id: u8,
+    init_sequence_nr: u16,
+}
+
+impl SenderBuilder {
+    /// with_header_extension_id sets the negotiated transport-cc header extension id.
+    pub fn with_header_extension_id(mut self, header_extension_id: u8) -> SenderBuilder {
+        self.header_extension_id = header_extension_id;
+        self
+    }
+
+    /// with_init_sequence_nr sets the first transport-wide sequence number of each transport.
+    pub fn with_init_sequence_nr(mut self, init_sequence_nr: u16) -> SenderBuilder {
+        self.init_sequence_nr = init_sequence_nr;
+        self
+    }
+}
+
+impl InterceptorBuilder for SenderBuilder {
+    fn name(&self) -> &str {
+        "TwccSender"
+    }
+
+    fn build(&self, _id: &str) -> Box<dyn Interceptor> {
+        Box::new(Sender {
+            header_extension_id: self.header_extension_id,
+            init_sequence_nr: self.init_sequence_nr,
+            sequence_numbers: HashMap::new(),
+            next: None,
+        })
+    }
+}
+
+/// ReceiverBuilder can be used to configure TWCC Receiver Interceptor.
+#[derive(Default)]
+pub struct ReceiverBuilder {
+    header_extension_id: u8,
+    interval: Option<Duration>,
+}
+
+impl ReceiverBuilder {
+    /// with_header_extension_id sets the negotiated transport-cc header extension id.
+    pub fn with_header_extension_id(mut self, header_extension_id: u8) -> ReceiverBuilder {
+        self.header_extension_id = header_extension_id;
+        self
+    }
+
+    /// with_interval sets send interval for TWCC feedback, 100ms by default.
+    pub fn with_interval(mut self, interval: Duration) -> ReceiverBuilder {
+        self.interval = Some(interval);
+        self
+    }
+}
+
+impl InterceptorBuilder for ReceiverBuilder {
+    fn name(&self) -> &str {
+        "TwccReceiver"
+    }
+
+    fn build(&self, id: &str) -> Box<dyn Interceptor> {
+        self.build_with_report_interval(id, None)
+    }
+
+    /// build_with_report_interval sends TWCC feedback at the report interval if it is some,
+    /// e.g., the one of the session, instead of the configured one
+    fn build_with_report_interval(
+        &self,
+        _id: &str,
+        report_interval: Option<Duration>,
+    ) -> Box<dyn Interceptor> {
+        let now = Instant::now();
+        Box::new(Receiver {
+            header_extension_id: self.header_extension_id,
+            interval: report_interval
+                .or(self.interval)
+                .unwrap_or(Duration::from_millis(100)),
+            start_time: now,
+            eto: now,
+            recorders: HashMap::new(),
+            next: None,
+        })
+    }
+}
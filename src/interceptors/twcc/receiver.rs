@@ -0,0 +1,359 @@
+use crate::interceptors::twcc::ReceiverBuilder;
+use crate::interceptors::{Interceptor, InterceptorEvent};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use crate::types::FourTuple;
+use retty::transport::TransportContext;
+use rtcp::transport_feedbacks::transport_layer_cc::{
+    PacketStatusChunk, RecvDelta, RunLengthChunk, StatusChunkTypeTcc, StatusVectorChunk,
+    SymbolSizeTypeTcc, SymbolTypeTcc, TransportLayerCc,
+};
+use rtp::extension::transport_cc_extension::TransportCcExtension;
+use shared::marshal::Unmarshal;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Receiver records arrival times of inbound RTP packets by their transport-wide sequence numbers,
+/// and sends them back as TWCC feedback at the interval, one recorder per transport
+/// <https://datatracker.ietf.org/doc/html/draft-holmer-rmcat-transport-wide-cc-extensions-01>
+pub(crate) struct Receiver {
+    pub(super) header_extension_id: u8,
+    pub(super) interval: Duration,
+    pub(super) start_time: Instant,
+    pub(super) eto: Instant,
+    pub(crate) recorders: HashMap<FourTuple, Recorder>,
+    pub(super) next: Option<Box<dyn Interceptor>>,
+}
+
+impl Receiver {
+    pub(crate) fn builder() -> ReceiverBuilder {
+        ReceiverBuilder::default()
+    }
+}
+
+impl Interceptor for Receiver {
+    fn chain(mut self: Box<Self>, next: Box<dyn Interceptor>) -> Box<dyn Interceptor> {
+        self.next = Some(next);
+        self
+    }
+
+    fn next(&mut self) -> Option<&mut Box<dyn Interceptor>> {
+        self.next.as_mut()
+    }
+
+    fn read(&mut self, msg: &mut TaggedMessageEvent) -> Vec<InterceptorEvent> {
+        if self.header_extension_id != 0 {
+            if let MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)) = &msg.message {
+                if let Some(mut payload) = rtp_packet.header.get_extension(self.header_extension_id)
+                {
+                    if let Ok(tcc_ext) = TransportCcExtension::unmarshal(&mut payload) {
+                        let arrival_time = msg
+                            .now
+                            .saturating_duration_since(self.start_time)
+                            .as_micros() as i64;
+                        self.recorders
+                            .entry((&msg.transport).into())
+                            .or_default()
+                            .record(
+                                rtp_packet.header.ssrc,
+                                tcc_ext.transport_sequence,
+                                arrival_time,
+                            );
+                    }
+                }
+            }
+        }
+
+        if let Some(next) = self.next() {
+            next.read(msg)
+        } else {
+            vec![]
+        }
+    }
+
+    fn handle_timeout(&mut self, now: Instant, four_tuples: &[FourTuple]) -> Vec<InterceptorEvent> {
+        let mut interceptor_events = vec![];
+
+        if self.eto <= now {
+            self.eto = now + self.interval;
+
+            // recorders of closed transports are dropped
+            self.recorders
+                .retain(|four_tuple, _| four_tuples.contains(four_tuple));
+            for (four_tuple, recorder) in self.recorders.iter_mut() {
+                let rtcp_packets = recorder.build_feedback_packets();
+                if rtcp_packets.is_empty() {
+                    continue;
+                }
+                interceptor_events.push(InterceptorEvent::Outbound(TaggedMessageEvent {
+                    now,
+                    transport: TransportContext {
+                        local_addr: four_tuple.local_addr,
+                        peer_addr: four_tuple.peer_addr,
+                        ecn: None,
+                    },
+                    message: MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)),
+                }));
+            }
+        }
+
+        if let Some(next) = self.next() {
+            let mut events = next.handle_timeout(now, four_tuples);
+            interceptor_events.append(&mut events);
+        }
+        interceptor_events
+    }
+
+    fn poll_timeout(&mut self, eto: &mut Instant) {
+        if self.eto < *eto {
+            *eto = self.eto
+        }
+
+        if let Some(next) = self.next() {
+            next.poll_timeout(eto);
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+struct PacketInfo {
+    // transport-wide sequence number extended with the count of wraps
+    sequence_number: u32,
+    // microseconds since the start time of Receiver
+    arrival_time: i64,
+}
+
+/// Recorder keeps the packets received on a transport since the last feedback
+#[derive(Default, Debug, Clone)]
+pub(crate) struct Recorder {
+    received_packets: Vec<PacketInfo>,
+    cycles: u32,
+    last_sequence_number: Option<u16>,
+    media_ssrc: u32,
+    fb_pkt_count: u8,
+}
+
+impl Recorder {
+    /// record marks the packet with the transport-wide sequence number as received at arrival_time,
+    /// a sequence number close to 0 after one close to u16::MAX starts a new cycle, while one close
+    /// to u16::MAX after one close to 0 is reordered from the previous cycle
+    pub(crate) fn record(&mut self, media_ssrc: u32, sequence_number: u16, arrival_time: i64) {
+        self.media_ssrc = media_ssrc;
+        let last_sequence_number = self.last_sequence_number.unwrap_or(sequence_number);
+        let sequence_number = if sequence_number < 0x0fff && last_sequence_number > 0xf000 {
+            self.cycles += 1 << 16;
+            self.last_sequence_number = Some(sequence_number);
+            self.cycles | sequence_number as u32
+        } else if sequence_number > 0xf000 && last_sequence_number < 0x0fff {
+            self.cycles.saturating_sub(1 << 16) | sequence_number as u32
+        } else {
+            self.last_sequence_number = Some(sequence_number);
+            self.cycles | sequence_number as u32
+        };
+        self.received_packets.push(PacketInfo {
+            sequence_number,
+            arrival_time,
+        });
+    }
+
+    /// build_feedback_packets reports the recorded packets in the order of sequence numbers, and
+    /// splits them into more than one feedback if a recv delta doesn't fit into 16 bits
+    pub(crate) fn build_feedback_packets(&mut self) -> Vec<Box<dyn rtcp::packet::Packet>> {
+        if self.received_packets.is_empty() {
+            return vec![];
+        }
+
+        self.received_packets
+            .sort_by_key(|packet| packet.sequence_number);
+        // duplicated packets are reported once
+        self.received_packets
+            .dedup_by_key(|packet| packet.sequence_number);
+
+        let mut feedback = Feedback::new(self.media_ssrc, self.next_fb_pkt_count());
+        let first = &self.received_packets[0];
+        feedback.set_base(first.sequence_number as u16, first.arrival_time);
+
+        let mut rtcp_packets: Vec<Box<dyn rtcp::packet::Packet>> = vec![];
+        for i in 0..self.received_packets.len() {
+            let PacketInfo {
+                sequence_number,
+                arrival_time,
+            } = self.received_packets[i];
+            if !feedback.add_received(sequence_number as u16, arrival_time) {
+                rtcp_packets.push(Box::new(feedback.get_rtcp()));
+                feedback = Feedback::new(self.media_ssrc, self.next_fb_pkt_count());
+                feedback.set_base(sequence_number as u16, arrival_time);
+                feedback.add_received(sequence_number as u16, arrival_time);
+            }
+        }
+        self.received_packets.clear();
+        rtcp_packets.push(Box::new(feedback.get_rtcp()));
+        rtcp_packets
+    }
+
+    fn next_fb_pkt_count(&mut self) -> u8 {
+        let fb_pkt_count = self.fb_pkt_count;
+        self.fb_pkt_count = self.fb_pkt_count.wrapping_add(1);
+        fb_pkt_count
+    }
+}
+
+/// Feedback builds a TransportLayerCc from the base sequence number, where packets missing
+/// between the reported ones are marked as not received
+#[derive(Default, Debug, Clone)]
+struct Feedback {
+    rtcp: TransportLayerCc,
+    base_sequence_number: u16,
+    // reference time in multiples of 64ms
+    ref_timestamp_64ms: i64,
+    last_timestamp_us: i64,
+    next_sequence_number: u16,
+    sequence_number_count: u16,
+    last_chunk: Chunk,
+    chunks: Vec<PacketStatusChunk>,
+    deltas: Vec<RecvDelta>,
+}
+
+impl Feedback {
+    fn new(media_ssrc: u32, fb_pkt_count: u8) -> Self {
+        Self {
+            rtcp: TransportLayerCc {
+                sender_ssrc: 0,
+                media_ssrc,
+                fb_pkt_count,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn set_base(&mut self, sequence_number: u16, time_us: i64) {
+        self.base_sequence_number = sequence_number;
+        self.next_sequence_number = sequence_number;
+        self.ref_timestamp_64ms = time_us / 64000;
+        self.last_timestamp_us = self.ref_timestamp_64ms * 64000;
+    }
+
+    fn get_rtcp(&mut self) -> TransportLayerCc {
+        self.rtcp.packet_status_count = self.sequence_number_count;
+        self.rtcp.reference_time = self.ref_timestamp_64ms as u32;
+        self.rtcp.base_sequence_number = self.base_sequence_number;
+        while !self.last_chunk.symbols.is_empty() {
+            self.chunks.push(self.last_chunk.encode());
+        }
+        self.rtcp.packet_chunks = std::mem::take(&mut self.chunks);
+        self.rtcp.recv_deltas = std::mem::take(&mut self.deltas);
+        self.rtcp.clone()
+    }
+
+    /// add_received returns false if the delta to the last received packet doesn't fit into
+    /// 16 bits of 250us, then the packet must be reported by a new feedback
+    fn add_received(&mut self, sequence_number: u16, timestamp_us: i64) -> bool {
+        let delta_us = timestamp_us - self.last_timestamp_us;
+        let delta_250us = delta_us / 250;
+        if delta_250us < i16::MIN as i64 || delta_250us > i16::MAX as i64 {
+            return false;
+        }
+
+        while self.next_sequence_number != sequence_number {
+            self.add_symbol(SymbolTypeTcc::PacketNotReceived);
+            self.next_sequence_number = self.next_sequence_number.wrapping_add(1);
+        }
+
+        let symbol = if (0..=0xff).contains(&delta_250us) {
+            SymbolTypeTcc::PacketReceivedSmallDelta
+        } else {
+            SymbolTypeTcc::PacketReceivedLargeDelta
+        };
+        self.add_symbol(symbol);
+        // the last timestamp advances by the quantized delta, so that rounding errors don't add up
+        self.deltas.push(RecvDelta {
+            type_tcc_packet: symbol,
+            delta: delta_250us * 250,
+        });
+        self.last_timestamp_us += delta_250us * 250;
+        self.next_sequence_number = self.next_sequence_number.wrapping_add(1);
+        true
+    }
+
+    fn add_symbol(&mut self, symbol: SymbolTypeTcc) {
+        if !self.last_chunk.can_add(symbol) {
+            self.chunks.push(self.last_chunk.encode());
+        }
+        self.last_chunk.add(symbol);
+        self.sequence_number_count = self.sequence_number_count.wrapping_add(1);
+    }
+}
+
+// run length is 13 bits
+const MAX_RUN_LENGTH_CAP: usize = 0x1fff;
+const MAX_ONE_BIT_CAP: usize = 14;
+const MAX_TWO_BIT_CAP: usize = 7;
+
+/// Chunk collects packet status symbols until they are encoded as a run length chunk if they are
+/// all the same, or otherwise as a status vector chunk of 1-bit or 2-bit symbols
+#[derive(Default, Debug, Clone)]
+struct Chunk {
+    has_large_delta: bool,
+    has_different_types: bool,
+    symbols: Vec<SymbolTypeTcc>,
+}
+
+impl Chunk {
+    fn can_add(&self, symbol: SymbolTypeTcc) -> bool {
+        if self.symbols.len() < MAX_TWO_BIT_CAP {
+            return true;
+        }
+        if self.symbols.len() < MAX_ONE_BIT_CAP
+            && !self.has_large_delta
+            && symbol != SymbolTypeTcc::PacketReceivedLargeDelta
+        {
+            return true;
+        }
+        self.symbols.len() < MAX_RUN_LENGTH_CAP
+            && !self.has_different_types
+            && symbol == self.symbols[0]
+    }
+
+    fn add(&mut self, symbol: SymbolTypeTcc) {
+        self.symbols.push(symbol);
+        self.has_large_delta |= symbol == SymbolTypeTcc::PacketReceivedLargeDelta;
+        self.has_different_types |= symbol != self.symbols[0];
+    }
+
+    fn encode(&mut self) -> PacketStatusChunk {
+        if !self.has_different_types {
+            let chunk = PacketStatusChunk::RunLengthChunk(RunLengthChunk {
+                type_tcc: StatusChunkTypeTcc::RunLengthChunk,
+                packet_status_symbol: self.symbols[0],
+                run_length: self.symbols.len() as u16,
+            });
+            *self = Chunk::default();
+            return chunk;
+        }
+        if self.symbols.len() == MAX_ONE_BIT_CAP {
+            let chunk = PacketStatusChunk::StatusVectorChunk(StatusVectorChunk {
+                type_tcc: StatusChunkTypeTcc::StatusVectorChunk,
+                symbol_size: SymbolSizeTypeTcc::OneBit,
+                symbol_list: std::mem::take(&mut self.symbols),
+            });
+            *self = Chunk::default();
+            return chunk;
+        }
+
+        // the first symbols up to 2-bit capacity are encoded, and the rest stay in the chunk
+        let count = std::cmp::min(MAX_TWO_BIT_CAP, self.symbols.len());
+        let chunk = PacketStatusChunk::StatusVectorChunk(StatusVectorChunk {
+            type_tcc: StatusChunkTypeTcc::StatusVectorChunk,
+            symbol_size: SymbolSizeTypeTcc::TwoBit,
+            symbol_list: self.symbols.drain(..count).collect(),
+        });
+        self.has_large_delta = self
+            .symbols
+            .contains(&SymbolTypeTcc::PacketReceivedLargeDelta);
+        self.has_different_types = self
+            .symbols
+            .first()
+            .is_some_and(|first| self.symbols.iter().any(|symbol| symbol != first));
+        chunk
+    }
+}
@@ -0,0 +1,325 @@
+use super::receiver::*;
+use super::ReceiverBuilder;
+use crate::interceptors::{Interceptor, InterceptorEvent, Registry};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use crate::types::FourTuple;
+use bytes::Bytes;
+use retty::transport::TransportContext;
+use rtcp::transport_feedbacks::transport_layer_cc::{
+    PacketStatusChunk, RecvDelta, RunLengthChunk, StatusChunkTypeTcc, StatusVectorChunk,
+    SymbolSizeTypeTcc, SymbolTypeTcc, TransportLayerCc,
+};
+use rtp::extension::transport_cc_extension::TransportCcExtension;
+use shared::marshal::Marshal;
+use std::time::{Duration, Instant};
+
+const TRANSPORT_CC_ID: u8 = 5;
+
+fn get_transport_layer_cc(rtcp_packets: &[Box<dyn rtcp::packet::Packet>]) -> Vec<TransportLayerCc> {
+    rtcp_packets
+        .iter()
+        .map(|p| {
+            p.as_any()
+                .downcast_ref::<TransportLayerCc>()
+                .unwrap()
+                .clone()
+        })
+        .collect()
+}
+
+fn recv_delta(type_tcc_packet: SymbolTypeTcc, delta: i64) -> RecvDelta {
+    RecvDelta {
+        type_tcc_packet,
+        delta,
+    }
+}
+
+#[test]
+fn test_twcc_recorder_encodes_recv_deltas() {
+    let mut recorder = Recorder::default();
+    // 64ms reference time plus 1ms, then 2ms, 0.25ms, 70ms and -1ms apart
+    for (sequence_number, arrival_time) in [
+        (10, 65_000),
+        (11, 67_000),
+        (12, 67_250),
+        (13, 137_250),
+        (14, 136_250),
+    ] {
+        recorder.record(1234, sequence_number, arrival_time);
+    }
+
+    let feedbacks = get_transport_layer_cc(&recorder.build_feedback_packets());
+    assert_eq!(feedbacks.len(), 1);
+    let feedback = &feedbacks[0];
+    assert_eq!(feedback.media_ssrc, 1234);
+    assert_eq!(feedback.base_sequence_number, 10);
+    assert_eq!(feedback.packet_status_count, 5);
+    assert_eq!(feedback.reference_time, 1);
+    assert_eq!(feedback.fb_pkt_count, 0);
+    assert_eq!(
+        feedback.recv_deltas,
+        vec![
+            recv_delta(SymbolTypeTcc::PacketReceivedSmallDelta, 1_000),
+            recv_delta(SymbolTypeTcc::PacketReceivedSmallDelta, 2_000),
+            recv_delta(SymbolTypeTcc::PacketReceivedSmallDelta, 250),
+            recv_delta(SymbolTypeTcc::PacketReceivedLargeDelta, 70_000),
+            recv_delta(SymbolTypeTcc::PacketReceivedLargeDelta, -1_000),
+        ]
+    );
+    assert_eq!(
+        feedback.packet_chunks,
+        vec![PacketStatusChunk::StatusVectorChunk(StatusVectorChunk {
+            type_tcc: StatusChunkTypeTcc::StatusVectorChunk,
+            symbol_size: SymbolSizeTypeTcc::TwoBit,
+            symbol_list: vec![
+                SymbolTypeTcc::PacketReceivedSmallDelta,
+                SymbolTypeTcc::PacketReceivedSmallDelta,
+                SymbolTypeTcc::PacketReceivedSmallDelta,
+                SymbolTypeTcc::PacketReceivedLargeDelta,
+                SymbolTypeTcc::PacketReceivedLargeDelta,
+            ],
+        })]
+    );
+    // the feedback is a valid TWCC packet
+    assert!(feedback.marshal().is_ok());
+
+    // recorded packets are reported once
+    assert!(recorder.build_feedback_packets().is_empty());
+}
+
+#[test]
+fn test_twcc_recorder_reports_gap_as_not_received() {
+    let mut recorder = Recorder::default();
+    recorder.record(1234, 1, 0);
+    recorder.record(1234, 2, 1_000);
+    // 3 to 22 are lost
+    recorder.record(1234, 23, 2_000);
+
+    let feedbacks = get_transport_layer_cc(&recorder.build_feedback_packets());
+    assert_eq!(feedbacks.len(), 1);
+    let feedback = &feedbacks[0];
+    assert_eq!(feedback.base_sequence_number, 1);
+    assert_eq!(feedback.packet_status_count, 23);
+    assert_eq!(
+        feedback.packet_chunks,
+        vec![
+            PacketStatusChunk::StatusVectorChunk(StatusVectorChunk {
+                type_tcc: StatusChunkTypeTcc::StatusVectorChunk,
+                symbol_size: SymbolSizeTypeTcc::OneBit,
+                symbol_list: vec![
+                    SymbolTypeTcc::PacketReceivedSmallDelta,
+                    SymbolTypeTcc::PacketReceivedSmallDelta,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                ],
+            }),
+            PacketStatusChunk::StatusVectorChunk(StatusVectorChunk {
+                type_tcc: StatusChunkTypeTcc::StatusVectorChunk,
+                symbol_size: SymbolSizeTypeTcc::TwoBit,
+                symbol_list: vec![
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketNotReceived,
+                ],
+            }),
+            PacketStatusChunk::StatusVectorChunk(StatusVectorChunk {
+                type_tcc: StatusChunkTypeTcc::StatusVectorChunk,
+                symbol_size: SymbolSizeTypeTcc::TwoBit,
+                symbol_list: vec![
+                    SymbolTypeTcc::PacketNotReceived,
+                    SymbolTypeTcc::PacketReceivedSmallDelta,
+                ],
+            }),
+        ]
+    );
+    // only received packets have recv deltas
+    assert_eq!(
+        feedback.recv_deltas,
+        vec![
+            recv_delta(SymbolTypeTcc::PacketReceivedSmallDelta, 0),
+            recv_delta(SymbolTypeTcc::PacketReceivedSmallDelta, 1_000),
+            recv_delta(SymbolTypeTcc::PacketReceivedSmallDelta, 1_000),
+        ]
+    );
+    assert!(feedback.marshal().is_ok());
+}
+
+#[test]
+fn test_twcc_recorder_handles_sequence_wrap() {
+    let mut recorder = Recorder::default();
+    recorder.record(1234, u16::MAX - 1, 0);
+    recorder.record(1234, 1, 3_000);
+    recorder.record(1234, u16::MAX, 1_000);
+    recorder.record(1234, 0, 2_000);
+
+    let feedbacks = get_transport_layer_cc(&recorder.build_feedback_packets());
+    assert_eq!(feedbacks.len(), 1);
+    let feedback = &feedbacks[0];
+    assert_eq!(feedback.base_sequence_number, u16::MAX - 1);
+    assert_eq!(feedback.packet_status_count, 4);
+    assert_eq!(
+        feedback.packet_chunks,
+        vec![PacketStatusChunk::RunLengthChunk(RunLengthChunk {
+            type_tcc: StatusChunkTypeTcc::RunLengthChunk,
+            packet_status_symbol: SymbolTypeTcc::PacketReceivedSmallDelta,
+            run_length: 4,
+        })]
+    );
+    assert_eq!(
+        feedback.recv_deltas,
+        vec![
+            recv_delta(SymbolTypeTcc::PacketReceivedSmallDelta, 0),
+            recv_delta(SymbolTypeTcc::PacketReceivedSmallDelta, 1_000),
+            recv_delta(SymbolTypeTcc::PacketReceivedSmallDelta, 1_000),
+            recv_delta(SymbolTypeTcc::PacketReceivedSmallDelta, 1_000),
+        ]
+    );
+}
+
+#[test]
+fn test_twcc_recorder_splits_feedback_on_long_delta() {
+    let mut recorder = Recorder::default();
+    recorder.record(1234, 1, 0);
+    // more than 8.192s doesn't fit into 16 bits of 250us
+    recorder.record(1234, 2, 10_000_000);
+
+    let feedbacks = get_transport_layer_cc(&recorder.build_feedback_packets());
+    assert_eq!(feedbacks.len(), 2);
+    assert_eq!(feedbacks[0].base_sequence_number, 1);
+    assert_eq!(feedbacks[0].fb_pkt_count, 0);
+    assert_eq!(feedbacks[1].base_sequence_number, 2);
+    assert_eq!(feedbacks[1].fb_pkt_count, 1);
+    assert_eq!(feedbacks[1].reference_time, 10_000_000 / 64_000);
+}
+
+#[test]
+fn test_twcc_receiver_sends_feedback_at_interval() {
+    let start_time = Instant::now();
+    let mut interceptor: Box<dyn Interceptor> = Box::new(Receiver {
+        header_extension_id: TRANSPORT_CC_ID,
+        interval: Duration::from_millis(100),
+        start_time,
+        eto: start_time,
+        recorders: Default::default(),
+        next: None,
+    });
+    let transport = TransportContext {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "127.0.0.1:5000".parse().unwrap(),
+        ecn: None,
+    };
+    let four_tuple: FourTuple = (&transport).into();
+
+    for transport_sequence in 0..3u16 {
+        let mut rtp_packet = rtp::packet::Packet::default();
+        rtp_packet.header.ssrc = 1234;
+        let extension: Bytes = TransportCcExtension { transport_sequence }
+            .marshal()
+            .unwrap()
+            .freeze();
+        rtp_packet
+            .header
+            .set_extension(TRANSPORT_CC_ID, extension)
+            .unwrap();
+        let mut msg = TaggedMessageEvent {
+            now: start_time + Duration::from_millis(transport_sequence as u64),
+            transport,
+            message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)),
+        };
+        assert!(interceptor.read(&mut msg).is_empty());
+    }
+
+    let mut eto = start_time + Duration::from_secs(1);
+    interceptor.poll_timeout(&mut eto);
+    assert_eq!(eto, start_time);
+
+    let now = start_time + Duration::from_millis(10);
+    let events = interceptor.handle_timeout(now, &[four_tuple]);
+    assert_eq!(events.len(), 1);
+    let InterceptorEvent::Outbound(msg) = &events[0] else {
+        panic!("expected outbound feedback");
+    };
+    assert_eq!(msg.transport.peer_addr, transport.peer_addr);
+    let MessageEvent::Rtp(RTPMessageEvent::Rtcp(rtcp_packets)) = &msg.message else {
+        panic!("expected rtcp feedback");
+    };
+    let feedbacks = get_transport_layer_cc(rtcp_packets);
+    assert_eq!(feedbacks[0].media_ssrc, 1234);
+    assert_eq!(feedbacks[0].packet_status_count, 3);
+
+    // the next feedback is due after the interval
+    let mut eto = now + Duration::from_secs(1);
+    interceptor.poll_timeout(&mut eto);
+    assert_eq!(eto, now + Duration::from_millis(100));
+    assert!(interceptor.handle_timeout(now, &[four_tuple]).is_empty());
+}
+
+#[test]
+fn test_twcc_receiver_sends_feedback_at_report_interval() {
+    let mut registry = Registry::new();
+    registry.add(Box::new(
+        ReceiverBuilder::default()
+            .with_header_extension_id(TRANSPORT_CC_ID)
+            .with_interval(Duration::from_secs(1)),
+    ));
+    let transport = TransportContext {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: "127.0.0.1:5000".parse().unwrap(),
+        ecn: None,
+    };
+    let four_tuple: FourTuple = (&transport).into();
+
+    // e.g., sessions of different report intervals sharing the registry of MediaConfig
+    for (report_interval, expected_interval) in [
+        (Some(Duration::from_millis(100)), Duration::from_millis(100)),
+        (Some(Duration::from_millis(50)), Duration::from_millis(50)),
+        (None, Duration::from_secs(1)),
+    ] {
+        let mut interceptor = registry.build_with_report_interval("", report_interval);
+        let start_time = Instant::now();
+        for i in 0..3u32 {
+            let now = start_time + expected_interval * i;
+            let mut rtp_packet = rtp::packet::Packet::default();
+            rtp_packet.header.ssrc = 1234;
+            let extension: Bytes = TransportCcExtension {
+                transport_sequence: i as u16,
+            }
+            .marshal()
+            .unwrap()
+            .freeze();
+            rtp_packet
+                .header
+                .set_extension(TRANSPORT_CC_ID, extension)
+                .unwrap();
+            let mut msg = TaggedMessageEvent {
+                now,
+                transport,
+                message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)),
+            };
+            assert!(interceptor.read(&mut msg).is_empty());
+
+            // each feedback is due one interval after the previous one
+            let mut eto = now + Duration::from_secs(10);
+            interceptor.poll_timeout(&mut eto);
+            assert!(eto <= now);
+            assert_eq!(interceptor.handle_timeout(now, &[four_tuple]).len(), 1);
+            let mut eto = now + Duration::from_secs(10);
+            interceptor.poll_timeout(&mut eto);
+            assert_eq!(eto, now + expected_interval);
+        }
+    }
+}
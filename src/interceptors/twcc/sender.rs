@@ -0,0 +1,69 @@
+use crate::interceptors::twcc::SenderBuilder;
+use crate::interceptors::{Interceptor, InterceptorEvent};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use crate::types::FourTuple;
+use rtp::extension::transport_cc_extension::TransportCcExtension;
+use shared::marshal::Marshal;
+use std::collections::HashMap;
+
+/// Sender writes transport-wide sequence numbers into the transport-cc header extension
+/// of every outbound RTP packet, one monotonic counter per transport.
+pub(crate) struct Sender {
+    pub(super) header_extension_id: u8,
+    pub(super) init_sequence_nr: u16,
+    pub(crate) sequence_numbers: HashMap<FourTuple, u16>,
+    pub(super) next: Option<Box<dyn Interceptor>>,
+}
+
+impl Sender {
+    pub(crate) fn builder() -> SenderBuilder {
+        SenderBuilder::default()
+    }
+
+    fn next_sequence_nr(&mut self, four_tuple: FourTuple) -> u16 {
+        let sequence_nr = self
+            .sequence_numbers
+            .entry(four_tuple)
+            .or_insert(self.init_sequence_nr);
+        let current = *sequence_nr;
+        *sequence_nr = sequence_nr.wrapping_add(1);
+        current
+    }
+}
+
+impl Interceptor for Sender {
+    fn chain(mut self: Box<Self>, next: Box<dyn Interceptor>) -> Box<dyn Interceptor> {
+        self.next = Some(next);
+        self
+    }
+
+    fn next(&mut self) -> Option<&mut Box<dyn Interceptor>> {
+        self.next.as_mut()
+    }
+
+    fn write(&mut self, msg: &mut TaggedMessageEvent) -> Vec<InterceptorEvent> {
+        let mut interceptor_events = vec![];
+
+        if self.header_extension_id != 0 {
+            if let MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)) = &mut msg.message {
+                let transport_sequence = self.next_sequence_nr((&msg.transport).into());
+                let result = TransportCcExtension { transport_sequence }
+                    .marshal()
+                    .and_then(|payload| {
+                        rtp_packet
+                            .header
+                            .set_extension(self.header_extension_id, payload.freeze())
+                    });
+                if let Err(err) = result {
+                    interceptor_events.push(InterceptorEvent::Error(Box::new(err)));
+                }
+            }
+        }
+
+        if let Some(next) = self.next() {
+            let mut events = next.write(msg);
+            interceptor_events.append(&mut events);
+        }
+        interceptor_events
+    }
+}
@@ -0,0 +1,76 @@
+use super::sender::*;
+use crate::interceptors::{Interceptor, InterceptorBuilder};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use retty::transport::TransportContext;
+use rtp::extension::transport_cc_extension::TransportCcExtension;
+use shared::marshal::Unmarshal;
+use std::time::Instant;
+
+const TRANSPORT_CC_ID: u8 = 5;
+
+fn new_rtp_message_event(peer_addr: &str) -> TaggedMessageEvent {
+    TaggedMessageEvent {
+        now: Instant::now(),
+        transport: TransportContext {
+            local_addr: "127.0.0.1:3478".parse().unwrap(),
+            peer_addr: peer_addr.parse().unwrap(),
+            ecn: None,
+        },
+        message: MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp::packet::Packet::default())),
+    }
+}
+
+fn write_transport_sequence(interceptor: &mut Box<dyn Interceptor>, peer_addr: &str) -> u16 {
+    let mut msg = new_rtp_message_event(peer_addr);
+    assert!(interceptor.write(&mut msg).is_empty());
+    if let MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp_packet)) = &msg.message {
+        let mut payload = rtp_packet.header.get_extension(TRANSPORT_CC_ID).unwrap();
+        TransportCcExtension::unmarshal(&mut payload)
+            .unwrap()
+            .transport_sequence
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_twcc_sender_increments_transport_sequence() {
+    let mut interceptor = Sender::builder()
+        .with_header_extension_id(TRANSPORT_CC_ID)
+        .build("");
+
+    assert_eq!(
+        write_transport_sequence(&mut interceptor, "127.0.0.1:5000"),
+        0
+    );
+    assert_eq!(
+        write_transport_sequence(&mut interceptor, "127.0.0.1:5000"),
+        1
+    );
+    assert_eq!(
+        write_transport_sequence(&mut interceptor, "127.0.0.1:5000"),
+        2
+    );
+    // each transport has its own counter
+    assert_eq!(
+        write_transport_sequence(&mut interceptor, "127.0.0.1:5001"),
+        0
+    );
+}
+
+#[test]
+fn test_twcc_sender_transport_sequence_wraps() {
+    let mut interceptor = Sender::builder()
+        .with_header_extension_id(TRANSPORT_CC_ID)
+        .with_init_sequence_nr(u16::MAX)
+        .build("");
+
+    assert_eq!(
+        write_transport_sequence(&mut interceptor, "127.0.0.1:5000"),
+        u16::MAX
+    );
+    assert_eq!(
+        write_transport_sequence(&mut interceptor, "127.0.0.1:5000"),
+        0
+    );
+}
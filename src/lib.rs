@@ -12,11 +12,27 @@ pub(crate) mod server;
 pub(crate) mod session;
 pub(crate) mod types;
 
-pub use configs::{media_config::MediaConfig, server_config::ServerConfig};
-pub use description::RTCSessionDescription;
+pub use configs::{
+    media_config::MediaConfig, server_config::ServerConfig, session_config::AudioForwardingMode,
+};
+pub use description::{
+    bundle_policy::RTCBundlePolicy, fmtp::vendor::BitrateHints, rtcp_rsize_policy::RtcpRsizePolicy,
+    rtp_transceiver::SimulcastLayer, rtp_transceiver_direction::RTCRtpTransceiverDirection,
+    RTCSessionDescription, END_OF_CANDIDATES,
+};
+pub use endpoint::dependency_descriptor::MaxSvcLayers;
 pub use handlers::{
     datachannel::DataChannelHandler, demuxer::DemuxerHandler, dtls::DtlsHandler,
     exception::ExceptionHandler, gateway::GatewayHandler, interceptor::InterceptorHandler,
     sctp::SctpHandler, srtp::SrtpHandler, stun::StunHandler,
 };
-pub use server::{certificate::RTCCertificate, states::ServerStates};
+pub use interceptors::{
+    jitter_buffer::JitterBufferBuilder, network_simulator::NetworkSimulatorBuilder, Interceptor,
+    InterceptorBuilder, InterceptorEvent, Registry,
+};
+pub use metrics::loss_monitor::InboundRtpStats;
+pub use server::{
+    certificate::{RTCCertificate, RTCDtlsFingerprint},
+    certificate_verifier::{CertificateVerifier, FingerprintVerifier},
+    states::ServerStates,
+};
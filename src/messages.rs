@@ -1,3 +1,4 @@
+use crate::endpoint::abs_send_time::get_abs_send_time;
 use bytes::BytesMut;
 use retty::transport::TransportContext;
 use sctp::ReliabilityType;
@@ -61,6 +62,18 @@ pub enum RTPMessageEvent {
     Rtcp(Vec<Box<dyn rtcp::packet::Packet>>),
 }
 
+impl RTPMessageEvent {
+    /// abs_send_time returns the absolute send time of an RTP packet, in 6.18 fixed point seconds,
+    /// carried by the abs-send-time header extension with the negotiated id
+    pub fn abs_send_time(&self, header_extension_id: u8) -> Option<u32> {
+        if let RTPMessageEvent::Rtp(rtp_packet) = self {
+            get_abs_send_time(rtp_packet, header_extension_id)
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum MessageEvent {
     Stun(STUNMessageEvent),
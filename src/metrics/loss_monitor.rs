@@ -0,0 +1,94 @@
+use crate::description::rtp_transceiver::SSRC;
+use std::collections::BTreeSet;
+
+/// MAX_TRACKED_MISSING is the number of missing sequence numbers remembered per stream,
+/// older ones stay counted as lost even if they arrive later
+const MAX_TRACKED_MISSING: usize = 1024;
+
+/// InboundRtpStats are the loss statistics of an inbound RTP stream,
+/// estimated from gaps of the observed sequence numbers
+#[derive(Debug, Clone, PartialEq)]
+pub struct InboundRtpStats {
+    /// SSRC of the stream
+    pub ssrc: SSRC,
+    /// rid of the simulcast layer of the stream, the repaired one for an RTX stream
+    pub rid: Option<String>,
+    /// absolute send time of the latest packet in 6.18 fixed point seconds, if abs-send-time is
+    /// negotiated
+    pub abs_send_time: Option<u32>,
+    /// number of packets received, without duplicates
+    pub packets_received: u64,
+    /// number of packets missing from the received sequence numbers
+    pub packets_lost: u64,
+    /// packets_lost over the number of packets expected, in 0.0..=1.0
+    pub loss_rate: f64,
+}
+
+/// LossMonitor counts packets lost in an inbound RTP stream from sequence number gaps.
+///
+/// Sequence numbers are extended over wraparound, and a reordered packet arriving
+/// after a later one is removed from the lost ones instead of being counted twice.
+#[derive(Debug, Default)]
+pub(crate) struct LossMonitor {
+    base_sequence_number: Option<u64>,
+    highest_sequence_number: u64,
+    packets_received: u64,
+    missing: BTreeSet<u64>,
+    packets_lost: u64,
+}
+
+impl LossMonitor {
+    /// update records the sequence number of a received packet
+    pub(crate) fn update(&mut self, sequence_number: u16) {
+        if self.base_sequence_number.is_none() {
+            // start at the second cycle, so the packets reordered before the first one don't underflow
+            let extended = (1 << 16) + sequence_number as u64;
+            self.base_sequence_number = Some(extended);
+            self.highest_sequence_number = extended;
+            self.packets_received = 1;
+            return;
+        }
+
+        let diff = sequence_number.wrapping_sub(self.highest_sequence_number as u16) as i16;
+        if diff > 0 {
+            for missing in
+                self.highest_sequence_number + 1..self.highest_sequence_number + diff as u64
+            {
+                self.missing.insert(missing);
+            }
+            while self.missing.len() > MAX_TRACKED_MISSING {
+                self.missing.pop_first();
+            }
+            self.packets_lost += diff as u64 - 1;
+            self.highest_sequence_number += diff as u64;
+            self.packets_received += 1;
+        } else if diff < 0 {
+            let extended = self.highest_sequence_number - diff.unsigned_abs() as u64;
+            if self.missing.remove(&extended) {
+                // the packet is reordered rather than lost
+                self.packets_lost -= 1;
+                self.packets_received += 1;
+            }
+        }
+    }
+
+    /// stats returns the loss statistics of the stream with ssrc
+    pub(crate) fn stats(&self, ssrc: SSRC) -> InboundRtpStats {
+        let expected = self
+            .base_sequence_number
+            .map(|base| self.highest_sequence_number - base + 1)
+            .unwrap_or_default();
+        InboundRtpStats {
+            ssrc,
+            rid: None,
+            abs_send_time: None,
+            packets_received: self.packets_received,
+            packets_lost: self.packets_lost,
+            loss_rate: if expected > 0 {
+                self.packets_lost as f64 / expected as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use super::loss_monitor::*;
+
+fn receive(loss_monitor: &mut LossMonitor, sequence_numbers: &[u16]) -> InboundRtpStats {
+    for &sequence_number in sequence_numbers {
+        loss_monitor.update(sequence_number);
+    }
+    loss_monitor.stats(1234)
+}
+
+#[test]
+fn test_loss_monitor_counts_gap_and_uncounts_late_packet() {
+    let mut loss_monitor = LossMonitor::default();
+
+    let stats = receive(&mut loss_monitor, &[1, 2, 4, 5]);
+    assert_eq!(stats.packets_received, 4);
+    assert_eq!(stats.packets_lost, 1);
+    assert_eq!(stats.loss_rate, 0.2);
+
+    // 3 arrives late, so it is reordered rather than lost
+    let stats = receive(&mut loss_monitor, &[3]);
+    assert_eq!(stats.packets_received, 5);
+    assert_eq!(stats.packets_lost, 0);
+    assert_eq!(stats.loss_rate, 0.0);
+
+    // duplicates are neither received nor lost again
+    let stats = receive(&mut loss_monitor, &[3, 5]);
+    assert_eq!(stats.packets_received, 5);
+    assert_eq!(stats.packets_lost, 0);
+}
+
+#[test]
+fn test_loss_monitor_handles_wraparound() {
+    let mut loss_monitor = LossMonitor::default();
+
+    let stats = receive(&mut loss_monitor, &[65533, 65534, 0, 2, 65535]);
+    assert_eq!(stats.packets_received, 5);
+    // only 1 is missing after the wrap
+    assert_eq!(stats.packets_lost, 1);
+    assert_eq!(stats.loss_rate, 1.0 / 6.0);
+}
@@ -1,3 +1,8 @@
+pub(crate) mod loss_monitor;
+
+#[cfg(test)]
+mod loss_monitor_test;
+
 use opentelemetry::{
     metrics::{Counter, Meter, ObservableGauge, Unit},
     KeyValue,
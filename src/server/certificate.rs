@@ -6,7 +6,7 @@ use ring::rand::SystemRandom;
 use ring::rsa;
 use ring::signature::{EcdsaKeyPair, Ed25519KeyPair};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use sha2::{Digest, Sha256, Sha512};
 use shared::error::{Error, Result};
 use std::ops::Add;
 use std::time::{Duration, SystemTime};
@@ -201,7 +201,8 @@ impl RTCCertificate {
         )
     }
 
-    /// get_fingerprints returns a SHA-256 fingerprint of this certificate.
+    /// get_fingerprints returns SHA-256 and SHA-512 fingerprints of this certificate, so that
+    /// the remote can verify it with either algorithm.
     ///
     /// TODO: return a fingerprint computed with the digest algorithm used in the certificate
     /// signature.
@@ -209,15 +210,16 @@ impl RTCCertificate {
         let mut fingerprints = Vec::new();
 
         for c in &self.dtls_certificate.certificate {
-            let mut h = Sha256::new();
-            h.update(c.as_ref());
-            let hashed = h.finalize();
-            let values: Vec<String> = hashed.iter().map(|x| format! {"{x:02x}"}).collect();
-
-            fingerprints.push(RTCDtlsFingerprint {
-                algorithm: "sha-256".to_owned(),
-                value: values.join(":"),
-            });
+            for (algorithm, hashed) in [
+                ("sha-256", Sha256::digest(c.as_ref()).to_vec()),
+                ("sha-512", Sha512::digest(c.as_ref()).to_vec()),
+            ] {
+                let values: Vec<String> = hashed.iter().map(|x| format! {"{x:02x}"}).collect();
+                fingerprints.push(RTCDtlsFingerprint {
+                    algorithm: algorithm.to_owned(),
+                    value: values.join(":"),
+                });
+            }
         }
 
         fingerprints
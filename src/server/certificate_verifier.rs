@@ -0,0 +1,65 @@
+use crate::server::certificate::RTCDtlsFingerprint;
+use sha2::{Digest, Sha224, Sha256, Sha384, Sha512};
+use shared::error::{Error, Result};
+
+/// CertificateVerifier validates the remote DTLS certificate once the handshake completes,
+/// the handshake fails if it returns error, e.g., to enforce key type or validity period.
+///
+/// Peer certificates are DER encoded with the leaf certificate first, and they are empty
+/// unless the DTLS server's HandshakeConfig requests client certificates, e.g., with
+/// ClientAuthType::RequireAnyClientCert, and the default verifier rejects empty ones.
+pub trait CertificateVerifier: Send + Sync {
+    fn verify(
+        &self,
+        peer_certificates: &[Vec<u8>],
+        fingerprints: &[RTCDtlsFingerprint],
+    ) -> Result<()>;
+}
+
+/// FingerprintVerifier is the default CertificateVerifier, which accepts the remote certificate
+/// if it matches any fingerprint of the remote description, and rejects the remote without
+/// certificate if the remote description has fingerprints
+#[derive(Default, Debug, Copy, Clone)]
+pub struct FingerprintVerifier;
+
+impl FingerprintVerifier {
+    fn digest(algorithm: &str, certificate: &[u8]) -> Option<Vec<u8>> {
+        match algorithm.to_lowercase().as_str() {
+            "sha-224" => Some(Sha224::digest(certificate).to_vec()),
+            "sha-256" => Some(Sha256::digest(certificate).to_vec()),
+            "sha-384" => Some(Sha384::digest(certificate).to_vec()),
+            "sha-512" => Some(Sha512::digest(certificate).to_vec()),
+            _ => None,
+        }
+    }
+}
+
+impl CertificateVerifier for FingerprintVerifier {
+    fn verify(
+        &self,
+        peer_certificates: &[Vec<u8>],
+        fingerprints: &[RTCDtlsFingerprint],
+    ) -> Result<()> {
+        // a remote without certificate can't prove the fingerprints of its description
+        let Some(certificate) = peer_certificates.first() else {
+            return if fingerprints.is_empty() {
+                Ok(())
+            } else {
+                Err(Error::Other("ErrFingerprintMismatch".to_string()))
+            };
+        };
+
+        for fingerprint in fingerprints {
+            let Some(digest) = FingerprintVerifier::digest(&fingerprint.algorithm, certificate)
+            else {
+                continue;
+            };
+            let value: Vec<String> = digest.iter().map(|x| format!("{x:02x}")).collect();
+            if value.join(":").eq_ignore_ascii_case(&fingerprint.value) {
+                return Ok(());
+            }
+        }
+
+        Err(Error::Other("ErrFingerprintMismatch".to_string()))
+    }
+}
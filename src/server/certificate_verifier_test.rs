@@ -0,0 +1,68 @@
+use super::certificate::RTCCertificate;
+use super::certificate_verifier::*;
+use shared::error::{Error, Result};
+
+#[test]
+fn test_fingerprint_verifier() -> Result<()> {
+    let certificate =
+        RTCCertificate::from_key_pair(rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+    let other_certificate =
+        RTCCertificate::from_key_pair(rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+    let peer_certificates: Vec<Vec<u8>> = certificate
+        .dtls_certificate
+        .certificate
+        .iter()
+        .map(|c| c.0.clone())
+        .collect();
+
+    let mut fingerprints = certificate.get_fingerprints();
+    assert!(FingerprintVerifier
+        .verify(&peer_certificates, &fingerprints)
+        .is_ok());
+    // fingerprints in the description may be uppercase
+    fingerprints[0].value = fingerprints[0].value.to_uppercase();
+    assert!(FingerprintVerifier
+        .verify(&peer_certificates, &fingerprints)
+        .is_ok());
+    assert_eq!(
+        FingerprintVerifier.verify(&peer_certificates, &other_certificate.get_fingerprints()),
+        Err(Error::Other("ErrFingerprintMismatch".to_string()))
+    );
+
+    // the remote without certificate can't match its fingerprints
+    assert_eq!(
+        FingerprintVerifier.verify(&[], &other_certificate.get_fingerprints()),
+        Err(Error::Other("ErrFingerprintMismatch".to_string()))
+    );
+    assert!(FingerprintVerifier.verify(&[], &[]).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_fingerprint_verifier_with_either_algorithm() -> Result<()> {
+    let certificate =
+        RTCCertificate::from_key_pair(rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+    let peer_certificates: Vec<Vec<u8>> = certificate
+        .dtls_certificate
+        .certificate
+        .iter()
+        .map(|c| c.0.clone())
+        .collect();
+
+    let fingerprints = certificate.get_fingerprints();
+    let algorithms: Vec<&str> = fingerprints
+        .iter()
+        .map(|fingerprint| fingerprint.algorithm.as_str())
+        .collect();
+    assert_eq!(algorithms, vec!["sha-256", "sha-512"]);
+
+    // the remote may select any of the algorithms
+    for fingerprint in fingerprints {
+        assert!(FingerprintVerifier
+            .verify(&peer_certificates, &[fingerprint])
+            .is_ok());
+    }
+
+    Ok(())
+}
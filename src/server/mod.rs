@@ -1,2 +1,9 @@
 pub(crate) mod certificate;
+pub(crate) mod certificate_verifier;
+pub(crate) mod rate_limiter;
 pub(crate) mod states;
+
+#[cfg(test)]
+mod certificate_verifier_test;
+#[cfg(test)]
+mod rate_limiter_test;
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Instant;
+
+/// TokenBucket allows events at `rate` per second on average, with bursts up to `burst` events
+#[derive(Debug, Clone)]
+pub(crate) struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(rate: u32, burst: u32, now: Instant) -> Self {
+        Self {
+            rate: rate as f64,
+            burst: burst.max(1) as f64,
+            tokens: burst.max(1) as f64,
+            last: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if now > self.last {
+            let elapsed = now.duration_since(self.last).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+            self.last = now;
+        }
+    }
+
+    /// try_consume takes one token from the bucket, returns false if the bucket is empty
+    pub(crate) fn try_consume(&mut self, now: Instant) -> bool {
+        self.try_consume_n(now, 1)
+    }
+
+    /// try_consume_n takes n tokens from the bucket, returns false if not enough tokens are left
+    pub(crate) fn try_consume_n(&mut self, now: Instant, n: u32) -> bool {
+        self.refill(now);
+        if self.tokens >= n as f64 {
+            self.tokens -= n as f64;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// is_full returns true when the bucket has been refilled to its burst size
+    pub(crate) fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.burst
+    }
+}
+
+/// RateLimiter keeps a TokenBucket per key, e.g., per FourTuple
+pub(crate) struct RateLimiter<K> {
+    rate: u32,
+    burst: u32,
+    buckets: HashMap<K, TokenBucket>,
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    pub(crate) fn new(rate: u32, burst: u32) -> Self {
+        Self {
+            rate,
+            burst,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// allow returns true if one more event for the key is within the rate limit
+    pub(crate) fn allow(&mut self, key: K, now: Instant) -> bool {
+        let (rate, burst) = (self.rate, self.burst);
+        self.buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(rate, burst, now))
+            .try_consume(now)
+    }
+
+    /// remove_idle drops buckets which have been refilled, since a new bucket is equivalent
+    pub(crate) fn remove_idle(&mut self, now: Instant) {
+        self.buckets.retain(|_, bucket| !bucket.is_full(now));
+    }
+}
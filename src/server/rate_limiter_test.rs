@@ -0,0 +1,31 @@
+use super::rate_limiter::*;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_rate_limiter_drops_excess_per_key() {
+    let now = Instant::now();
+    let mut limiter = RateLimiter::new(2, 2);
+
+    assert!(limiter.allow(1, now));
+    assert!(limiter.allow(1, now));
+    assert!(!limiter.allow(1, now));
+    // other keys have their own bucket
+    assert!(limiter.allow(2, now));
+
+    // bucket refilled at 2 tokens per second
+    assert!(limiter.allow(1, now + Duration::from_millis(500)));
+    assert!(!limiter.allow(1, now + Duration::from_millis(500)));
+}
+
+#[test]
+fn test_rate_limiter_remove_idle() {
+    let now = Instant::now();
+    let mut limiter = RateLimiter::new(1, 1);
+
+    assert!(limiter.allow(1, now));
+    limiter.remove_idle(now);
+    assert!(!limiter.allow(1, now));
+
+    limiter.remove_idle(now + Duration::from_secs(1));
+    assert!(limiter.allow(1, now + Duration::from_secs(1)));
+}
@@ -1,23 +1,28 @@
 use crate::configs::server_config::ServerConfig;
-use crate::configs::session_config::SessionConfig;
-use crate::description::RTCSessionDescription;
+use crate::configs::session_config::{AudioForwardingMode, SessionConfig};
+use crate::description::{
+    fmtp::vendor::BitrateHints, rtp_transceiver_direction::RTCRtpTransceiverDirection,
+    signaling_state::RTCSignalingState, with_index_mids, RTCSessionDescription,
+};
 use crate::endpoint::{
-    candidate::{Candidate, ConnectionCredentials},
+    candidate::{Candidate, ConnectionCredentials, DTLSRole},
+    dependency_descriptor::MaxSvcLayers,
     transport::Transport,
     Endpoint,
 };
-use crate::metrics::Metrics;
+use crate::metrics::{loss_monitor::InboundRtpStats, Metrics};
 use crate::session::Session;
-use crate::types::{EndpointId, FourTuple, SessionId, UserName};
+use crate::types::{EndpointId, FourTuple, Mid, SessionId, UserName};
 use log::{debug, info};
 use opentelemetry::metrics::Meter;
+use ring::rand::{SecureRandom, SystemRandom};
 use shared::error::{Error, Result};
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::rc::Rc;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// ServerStates maintains SFU internal states, such sessions, endpoints, etc.
 pub struct ServerStates {
@@ -28,6 +33,8 @@ pub struct ServerStates {
     sessions: HashMap<SessionId, Session>,
     endpoints: HashMap<FourTuple, (SessionId, EndpointId)>,
     candidates: HashMap<UserName, Rc<Candidate>>,
+    // tie-breaker of ICE role conflicts, generated randomly once
+    ice_tie_breaker: u64,
 }
 
 impl ServerStates {
@@ -52,6 +59,13 @@ impl ServerStates {
             sessions: HashMap::new(),
             endpoints: HashMap::new(),
             candidates: HashMap::new(),
+            ice_tie_breaker: {
+                let mut tie_breaker = [0u8; 8];
+                SystemRandom::new()
+                    .fill(&mut tie_breaker)
+                    .map_err(|_| Error::Other("ErrGenerateIceTieBreaker".to_string()))?;
+                u64::from_be_bytes(tie_breaker)
+            },
         })
     }
 
@@ -63,7 +77,8 @@ impl ServerStates {
         four_tuple: Option<FourTuple>,
         mut offer: RTCSessionDescription,
     ) -> Result<RTCSessionDescription> {
-        let parsed = offer.unmarshal()?;
+        let mut parsed = offer.unmarshal()?;
+        with_index_mids(&mut parsed)?;
         let remote_conn_cred = ConnectionCredentials::from_sdp(&parsed)?;
         offer.parsed = Some(parsed);
 
@@ -94,12 +109,41 @@ impl ServerStates {
             )))?;
             transport.candidate().local_connection_credentials().clone()
         } else {
-            ConnectionCredentials::new(fingerprints, remote_conn_cred.dtls_params.role)
+            ConnectionCredentials::new(
+                fingerprints,
+                remote_conn_cred.dtls_params.role,
+                self.server_config.dtls_client_handshake_config.is_some(),
+            )
         };
+        if local_conn_cred.dtls_params.role == DTLSRole::Client
+            && self.server_config.dtls_client_handshake_config.is_none()
+        {
+            return Err(Error::Other(
+                "ErrNoDtlsClientHandshakeConfig for remote setup:passive".to_string(),
+            ));
+        }
 
-        let answer = session.create_answer(endpoint_id, &offer, &local_conn_cred.ice_params)?;
+        let session = self.create_or_get_mut_session(session_id);
+        let answer = match session.create_answer(
+            endpoint_id,
+            &offer,
+            &local_conn_cred.ice_params,
+            local_conn_cred.dtls_params.role,
+        ) {
+            Ok(answer) => answer,
+            Err(err) => {
+                // the offer that can't be answered is no longer pending
+                if let Some(endpoint) = session.get_mut_endpoint(&endpoint_id) {
+                    endpoint.set_signaling_state(RTCSignalingState::Stable);
+                }
+                return Err(err);
+            }
+        };
         if has_endpoint {
             session.set_local_description(endpoint_id, &answer)?;
+            // the FourTuple of an existing endpoint is checked above
+            let four_tuple = four_tuple.ok_or(Error::Other("missing FourTuple".to_string()))?;
+            self.complete_ice_restart(session_id, endpoint_id, four_tuple, &offer, &answer)?;
         } else {
             self.add_candidate(Rc::new(Candidate::new(
                 session_id,
@@ -115,6 +159,265 @@ impl ServerStates {
         Ok(answer)
     }
 
+    /// set audio forwarding mode of the session, which overrides the one of ServerConfig
+    pub fn set_audio_forwarding_mode(
+        &mut self,
+        session_id: SessionId,
+        audio_forwarding_mode: AudioForwardingMode,
+    ) {
+        self.create_or_get_mut_session(session_id)
+            .set_audio_forwarding_mode(audio_forwarding_mode);
+    }
+
+    /// set max endpoints of the session receiving streams of the same publisher, which overrides
+    /// the one of ServerConfig, None removes the limit
+    pub fn set_max_subscribers_per_publisher(
+        &mut self,
+        session_id: SessionId,
+        max_subscribers_per_publisher: Option<usize>,
+    ) {
+        self.create_or_get_mut_session(session_id)
+            .set_max_subscribers_per_publisher(max_subscribers_per_publisher);
+    }
+
+    /// set interval of RTCP reports and TWCC feedback sent to endpoints of the session, which
+    /// overrides the one configured in MediaConfig for endpoints connected afterwards,
+    /// None restores it
+    pub fn set_report_interval(
+        &mut self,
+        session_id: SessionId,
+        report_interval: Option<Duration>,
+    ) {
+        self.create_or_get_mut_session(session_id)
+            .set_report_interval(report_interval);
+    }
+
+    /// set SCTP port advertised by a=sctp-port in descriptions of the session unless the remote
+    /// offers one, which overrides the port of ServerConfig's SCTP transport, None restores it
+    pub fn set_sctp_port(&mut self, session_id: SessionId, sctp_port: Option<u16>) {
+        self.create_or_get_mut_session(session_id)
+            .set_sctp_port(sctp_port);
+    }
+
+    /// update codec preferences of the session, so the codecs of MediaConfig with disabled_codecs
+    /// mime types, e.g., "video/H264", are removed from later offers and answers, returns the ids
+    /// of endpoints marked for renegotiation since they negotiated a codec whose preference changes
+    pub fn update_codec_preferences(
+        &mut self,
+        session_id: SessionId,
+        disabled_codecs: &[&str],
+    ) -> Vec<EndpointId> {
+        self.create_or_get_mut_session(session_id)
+            .update_codec_preferences(disabled_codecs)
+    }
+
+    /// set named interceptor profile of MediaConfig for the endpoint, which must be set
+    /// before the endpoint is connected, otherwise the default interceptor registry is used
+    pub fn set_interceptor_profile(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        profile: String,
+    ) {
+        self.create_or_get_mut_session(session_id)
+            .set_interceptor_profile(endpoint_id, profile);
+    }
+
+    /// set direction for the transceiver of the endpoint with mid to answer with, regardless of the
+    /// transceiver's own direction, it is still intersected with the offered direction, e.g., a
+    /// recvonly override answers a sendrecv offer with recvonly, None restores the default behavior
+    pub fn set_direction_override(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        mid: &str,
+        direction: Option<RTCRtpTransceiverDirection>,
+    ) {
+        self.create_or_get_mut_session(session_id)
+            .set_direction_override(endpoint_id, mid.to_string(), direction);
+    }
+
+    /// set bitrate cap in kbps of audio and video media sections of the endpoint, advertised as
+    /// b=AS and b=TIAS lines from the next offer or answer to it, None removes the cap
+    pub fn set_max_bitrate_kbps(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        max_bitrate_kbps: Option<u64>,
+    ) {
+        self.create_or_get_mut_session(session_id)
+            .set_max_bitrate_kbps(endpoint_id, max_bitrate_kbps);
+    }
+
+    /// select the simulcast layer by rid forwarded to the transceiver of the endpoint with mid, e.g.,
+    /// SimulcastLayer::Low.rid() for a small viewport, None forwards all layers
+    pub fn select_simulcast_layer(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        mid: &str,
+        rid: Option<&str>,
+    ) -> Result<()> {
+        self.get_mut_session(&session_id)
+            .ok_or(Error::Other(format!(
+                "can't find session id {}",
+                session_id
+            )))?
+            .select_layer(endpoint_id, mid, rid.map(str::to_string))
+    }
+
+    /// restart ICE of the endpoint with fresh ICE credentials, which the next offer or answer to it
+    /// advertises, e.g., when the endpoint roams networks
+    pub fn restart_ice(&mut self, session_id: SessionId, endpoint_id: EndpointId) -> Result<()> {
+        self.get_mut_session(&session_id)
+            .ok_or(Error::Other(format!(
+                "can't find session id {}",
+                session_id
+            )))?
+            .restart_ice(endpoint_id)?;
+        Ok(())
+    }
+
+    /// add a local candidate of addr to the endpoint after its description for trickle ICE, e.g.,
+    /// a new host address, which later descriptions advertise as well
+    pub fn add_local_candidate(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        addr: SocketAddr,
+    ) -> Result<()> {
+        self.get_mut_session(&session_id)
+            .ok_or(Error::Other(format!(
+                "can't find session id {}",
+                session_id
+            )))?
+            .add_local_candidate(endpoint_id, addr)
+    }
+
+    /// take a=candidate lines of the local candidates added to the endpoint since the last call,
+    /// paired with the mid of the media section each applies to, which are signaled out-of-band
+    /// followed by END_OF_CANDIDATES once gathering completes
+    pub fn take_local_candidates(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+    ) -> Result<Vec<(Mid, String)>> {
+        self.get_mut_session(&session_id)
+            .ok_or(Error::Other(format!(
+                "can't find session id {}",
+                session_id
+            )))?
+            .take_local_candidates(endpoint_id)
+    }
+
+    /// set max spatial and temporal layers of the codec with mime_type, e.g., "video/VP9", forwarded
+    /// to the connected endpoint from SVC streams with dependency descriptor, None removes the cap
+    pub fn set_max_svc_layers(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        mime_type: &str,
+        max_svc_layers: Option<MaxSvcLayers>,
+    ) -> Result<()> {
+        self.get_mut_endpoint_by_id(session_id, endpoint_id)?
+            .set_max_svc_layers(mime_type, max_svc_layers, Instant::now());
+        Ok(())
+    }
+
+    /// get negotiated header extension id to uri map of the transceiver with mid
+    pub fn get_negotiated_header_extensions(
+        &self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        mid: &str,
+    ) -> Option<HashMap<isize, String>> {
+        self.get_session(&session_id)?
+            .get_endpoint(&endpoint_id)?
+            .get_negotiated_header_extensions(mid)
+    }
+
+    /// get id of the MediaStream signaled by msid for the track of the transceiver with mid, so that
+    /// subscribers can group the forwarded tracks of each publisher's MediaStream
+    pub fn get_stream_id(
+        &self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        mid: &str,
+    ) -> Option<String> {
+        self.get_session(&session_id)?
+            .get_endpoint(&endpoint_id)?
+            .get_stream_id(mid)
+            .map(str::to_string)
+    }
+
+    /// get bitrate bounds of the transceiver with mid of the connected endpoint, hinted by vendor
+    /// fmtp parameters x-google-min-bitrate and x-google-max-bitrate of its codecs, if any
+    pub fn get_bitrate_hints(
+        &self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        mid: &str,
+    ) -> Option<BitrateHints> {
+        self.get_session(&session_id)?
+            .get_endpoint(&endpoint_id)?
+            .get_bitrate_hints(mid)
+    }
+
+    /// request keyframe from the endpoint for the transceiver with mid, which is suppressed
+    /// if one was requested within KEYFRAME_REQUEST_INTERVAL, returns true if it is sent
+    pub fn request_keyframe(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        mid: &str,
+    ) -> Result<bool> {
+        self.get_mut_endpoint_by_id(session_id, endpoint_id)?
+            .request_keyframe(mid, Instant::now())
+    }
+
+    /// force keyframe request to the endpoint for the transceiver with mid without suppression,
+    /// e.g., for recording start or thumbnail generation
+    pub fn force_keyframe(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        mid: &str,
+    ) -> Result<()> {
+        self.get_mut_endpoint_by_id(session_id, endpoint_id)?
+            .force_keyframe(mid, Instant::now())
+    }
+
+    /// get loss statistics of the RTP streams received from the endpoint
+    pub fn get_inbound_rtp_stats(
+        &self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+    ) -> Vec<InboundRtpStats> {
+        self.get_session(&session_id)
+            .and_then(|session| session.get_endpoint(&endpoint_id))
+            .map(|endpoint| {
+                endpoint
+                    .get_transports()
+                    .values()
+                    .flat_map(|transport| transport.get_inbound_rtp_stats())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn get_mut_endpoint_by_id(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+    ) -> Result<&mut Endpoint> {
+        self.get_mut_session(&session_id)
+            .and_then(|session| session.get_mut_endpoint(&endpoint_id))
+            .ok_or(Error::Other(format!(
+                "can't find endpoint id {} in session id {}",
+                endpoint_id, session_id
+            )))
+    }
+
     pub(crate) fn metrics(&self) -> &Metrics {
         &self.metrics
     }
@@ -123,7 +426,7 @@ impl ServerStates {
         &mut self,
         session_id: SessionId,
         endpoint_id: EndpointId,
-        _four_tuple: FourTuple,
+        four_tuple: FourTuple,
         mut answer: RTCSessionDescription,
     ) -> Result<()> {
         let parsed = answer.unmarshal()?;
@@ -131,12 +434,103 @@ impl ServerStates {
 
         let session = self.create_or_get_mut_session(session_id);
         if session.has_endpoint(&endpoint_id) {
-            session.set_remote_description(endpoint_id, &answer)?;
+            session.apply_remote_answer(endpoint_id, &answer)?;
+            let local_description = session
+                .get_endpoint(&endpoint_id)
+                .and_then(|endpoint| endpoint.local_description())
+                .cloned()
+                .unwrap_or_default();
+            self.complete_ice_restart(
+                session_id,
+                endpoint_id,
+                four_tuple,
+                &answer,
+                &local_description,
+            )?;
         };
 
         Ok(())
     }
 
+    /// complete_ice_restart adds a candidate with the restarted ICE credentials of the endpoint
+    /// once they are negotiated, so that connectivity checks with them are accepted, e.g., from
+    /// the new network of a roaming endpoint, and retires the candidates of the stale credentials.
+    /// The candidate keeps the DTLS credentials of the transport the description is signaled on.
+    fn complete_ice_restart(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+        four_tuple: FourTuple,
+        remote_description: &RTCSessionDescription,
+        local_description: &RTCSessionDescription,
+    ) -> Result<()> {
+        let Some(endpoint) = self
+            .get_mut_session(&session_id)
+            .and_then(|session| session.get_mut_endpoint(&endpoint_id))
+        else {
+            return Ok(());
+        };
+        // the restart is done once negotiated, even if it fails below
+        let Some(ice_params) = endpoint.ice_restart_params().cloned() else {
+            return Ok(());
+        };
+        endpoint.set_ice_restart_params(None);
+
+        let transport = endpoint
+            .get_transports()
+            .get(&four_tuple)
+            .ok_or(Error::Other(format!(
+                "can't find transport for endpoint id {} with {:?}",
+                endpoint_id, four_tuple
+            )))?;
+        let mut local_conn_cred = transport.candidate().local_connection_credentials().clone();
+        local_conn_cred.ice_params = ice_params;
+        let stale_usernames: HashSet<UserName> = endpoint
+            .get_transports()
+            .values()
+            .map(|transport| transport.candidate().username())
+            .collect();
+        let remote_conn_cred = ConnectionCredentials::from_sdp(
+            remote_description
+                .parsed
+                .as_ref()
+                .ok_or(Error::Other("remote description is not parsed".to_string()))?,
+        )?;
+        let candidate = Rc::new(Candidate::new(
+            session_id,
+            endpoint_id,
+            remote_conn_cred,
+            local_conn_cred,
+            remote_description.clone(),
+            local_description.clone(),
+            Instant::now() + self.server_config.idle_timeout,
+        ));
+
+        // connectivity checks with the stale credentials are no longer accepted, while the
+        // transports established with them are kept until the new ones are nominated
+        let username = candidate.username();
+        for stale_username in stale_usernames {
+            if stale_username != username {
+                self.remove_candidate(&stale_username);
+            }
+        }
+        self.add_candidate(candidate);
+        Ok(())
+    }
+
+    pub(crate) fn accept_rollback(
+        &mut self,
+        session_id: SessionId,
+        endpoint_id: EndpointId,
+    ) -> Result<()> {
+        self.get_mut_session(&session_id)
+            .ok_or(Error::Other(format!(
+                "can't find session id {}",
+                session_id
+            )))?
+            .rollback(endpoint_id)
+    }
+
     pub(crate) fn server_config(&self) -> &Arc<ServerConfig> {
         &self.server_config
     }
@@ -178,12 +572,25 @@ impl ServerStates {
     }
 
     pub(crate) fn add_candidate(&mut self, candidate: Rc<Candidate>) -> Option<Rc<Candidate>> {
-        let username = candidate.username();
+        let mut usernames = candidate.usernames().into_iter();
+        let username = usernames.next()?;
+        for media_username in usernames {
+            self.candidates.insert(media_username, candidate.clone());
+        }
         self.candidates.insert(username, candidate)
     }
 
+    /// remove_candidate removes the candidate with all of its usernames
     pub(crate) fn remove_candidate(&mut self, username: &UserName) -> Option<Rc<Candidate>> {
-        self.candidates.remove(username)
+        let candidate = self.candidates.remove(username)?;
+        for media_username in candidate.usernames() {
+            self.candidates.remove(&media_username);
+        }
+        Some(candidate)
+    }
+
+    pub(crate) fn ice_tie_breaker(&self) -> u64 {
+        self.ice_tie_breaker
     }
 
     pub(crate) fn find_candidate(&self, username: &UserName) -> Option<&Rc<Candidate>> {
@@ -282,7 +689,7 @@ impl ServerStates {
         let transport = endpoint.remove_transport(&four_tuple);
         if endpoint.get_transports().is_empty() {
             session.remove_endpoint(&endpoint_id);
-            if session.get_endpoints().is_empty() {
+            if !session.has_endpoints() {
                 self.remove_session(&session_id);
             }
             self.remove_endpoint(&four_tuple);
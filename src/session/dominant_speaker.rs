@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::description::rtp_transceiver::SSRC;
+use crate::types::EndpointId;
+use shared::error::Result;
+
+/// CSRC_AUDIO_LEVEL_URI is the mixer-to-client audio level header extension, which carries
+/// the audio level of each contributing source <https://datatracker.ietf.org/doc/html/rfc6465>
+pub(crate) const CSRC_AUDIO_LEVEL_URI: &str = "urn:ietf:params:rtp-hdrext:csrc-audio-level";
+
+/// ACTIVE_TALKER_TIMEOUT is the time after the last packet when a talker is no longer active
+const ACTIVE_TALKER_TIMEOUT: Duration = Duration::from_secs(1);
+/// SILENCE_AUDIO_LEVEL is -127 dBov, used when a packet has no audio level
+const SILENCE_AUDIO_LEVEL: u8 = 127;
+/// DOMINANT_SPEAKER_HOLD_TIME is the minimum time a subscriber keeps hearing the dominant
+/// speaker after a switch, so that talkers alternating quickly don't flap the forwarded stream
+pub(crate) const DOMINANT_SPEAKER_HOLD_TIME: Duration = Duration::from_millis(500);
+/// DOMINANT_SPEAKER_LEVEL_MARGIN is how many dB another talker must be louder than the dominant
+/// speaker to take over once the hold time is over
+pub(crate) const DOMINANT_SPEAKER_LEVEL_MARGIN: u8 = 6;
+
+#[derive(Debug, Clone)]
+struct Talker {
+    endpoint_id: EndpointId,
+    audio_level: u8,
+    clock_rate: u32,
+    last_seen: Instant,
+}
+
+#[derive(Debug, Clone)]
+struct SpeakerStream {
+    ssrc: SSRC,
+    sequence_number: u16,
+    timestamp: u32,
+    last_sent: Instant,
+    last_source: (SSRC, u16, u32),
+    // when the subscriber started hearing the current dominant speaker
+    switched_at: Instant,
+    // for audio the marker bit is set on the first packet of a talkspurt
+    marker: bool,
+}
+
+/// DominantSpeakerSelector forwards only the dominant speaker's audio to a subscriber as a single
+/// stream.
+///
+/// Audio is not decoded nor mixed: the payload of the dominant speaker, i.e., the loudest talker
+/// by audio level (RFC 6464) with a hold time and a level margin before switching, is carried as
+/// is in the subscriber's stream, with continuous sequence numbers and timestamps across talker
+/// switches, while the top-N active talkers are reported as contributing sources.
+pub(crate) struct DominantSpeakerSelector {
+    top_n: usize,
+    talkers: HashMap<SSRC, Talker>,
+    speaker_streams: HashMap<EndpointId, SpeakerStream>,
+}
+
+impl DominantSpeakerSelector {
+    pub(crate) fn new(top_n: usize) -> Self {
+        Self {
+            top_n: top_n.max(1),
+            talkers: HashMap::new(),
+            speaker_streams: HashMap::new(),
+        }
+    }
+
+    /// update_talker records the audio level of the packet received from the publisher endpoint,
+    /// lower level is louder since it is expressed in -dBov, clock_rate is the one of the
+    /// negotiated audio codec of the packet
+    pub(crate) fn update_talker(
+        &mut self,
+        now: Instant,
+        endpoint_id: EndpointId,
+        ssrc: SSRC,
+        audio_level: Option<u8>,
+        clock_rate: u32,
+    ) {
+        self.talkers.insert(
+            ssrc,
+            Talker {
+                endpoint_id,
+                audio_level: audio_level.unwrap_or(SILENCE_AUDIO_LEVEL),
+                clock_rate,
+                last_seen: now,
+            },
+        );
+        self.talkers
+            .retain(|_, talker| now.duration_since(talker.last_seen) < ACTIVE_TALKER_TIMEOUT);
+    }
+
+    /// top_talkers returns ssrcs of the loudest active talkers heard by the subscriber endpoint
+    fn top_talkers(&self, subscriber: EndpointId) -> Vec<SSRC> {
+        let mut talkers: Vec<(&SSRC, &Talker)> = self
+            .talkers
+            .iter()
+            .filter(|(_, talker)| talker.endpoint_id != subscriber)
+            .collect();
+        talkers.sort_by_key(|(&ssrc, talker)| (talker.audio_level, ssrc));
+        talkers
+            .into_iter()
+            .take(self.top_n)
+            .map(|(&ssrc, _)| ssrc)
+            .collect()
+    }
+
+    /// dominant_speaker returns ssrc of the talker forwarded to the subscriber endpoint, which is
+    /// the current one until the hold time is over and another talker is louder by the margin,
+    /// or until it is no longer an active talker
+    fn dominant_speaker(
+        &self,
+        now: Instant,
+        subscriber: EndpointId,
+        top_talkers: &[SSRC],
+    ) -> Option<SSRC> {
+        let loudest = *top_talkers.first()?;
+        let Some(speaker_stream) = self.speaker_streams.get(&subscriber) else {
+            return Some(loudest);
+        };
+        let current = speaker_stream.last_source.0;
+        let Some(current_talker) = self
+            .talkers
+            .get(&current)
+            .filter(|talker| talker.endpoint_id != subscriber)
+        else {
+            return Some(loudest);
+        };
+        let loudest_level = self.talkers.get(&loudest)?.audio_level;
+        if now.duration_since(speaker_stream.switched_at) < DOMINANT_SPEAKER_HOLD_TIME
+            || loudest_level.saturating_add(DOMINANT_SPEAKER_LEVEL_MARGIN)
+                > current_talker.audio_level
+        {
+            Some(current)
+        } else {
+            Some(loudest)
+        }
+    }
+
+    /// select returns the packet rewritten into the subscriber's stream, or None
+    /// if the packet is not from the dominant speaker heard by the subscriber,
+    /// ssrc is the one signaled to the subscriber for the packet's stream
+    pub(crate) fn select(
+        &mut self,
+        now: Instant,
+        subscriber: EndpointId,
+        rtp_packet: &rtp::packet::Packet,
+        ssrc: SSRC,
+    ) -> Option<rtp::packet::Packet> {
+        let top_talkers = self.top_talkers(subscriber);
+        if self.dominant_speaker(now, subscriber, &top_talkers) != Some(rtp_packet.header.ssrc) {
+            return None;
+        }
+
+        let header = &rtp_packet.header;
+        let clock_rate = self.talkers.get(&header.ssrc)?.clock_rate as u64;
+        let speaker_stream = self
+            .speaker_streams
+            .entry(subscriber)
+            // the first forwarded stream is signaled to the subscriber, so its ssrc is kept
+            .or_insert(SpeakerStream {
+                ssrc,
+                sequence_number: header.sequence_number,
+                timestamp: header.timestamp,
+                last_sent: now,
+                last_source: (header.ssrc, header.sequence_number, header.timestamp),
+                switched_at: now,
+                marker: true,
+            });
+
+        let (last_ssrc, last_sequence_number, last_timestamp) = speaker_stream.last_source;
+        // the same packet may be selected again for another transport of the subscriber
+        if (last_ssrc, last_sequence_number) != (header.ssrc, header.sequence_number) {
+            let elapsed = if last_ssrc == header.ssrc {
+                header.timestamp.wrapping_sub(last_timestamp)
+            } else {
+                // switching talker, advance timestamp by wall clock to keep it continuous
+                (now.duration_since(speaker_stream.last_sent).as_millis() as u64 * clock_rate
+                    / 1000)
+                    .max(1) as u32
+            };
+            speaker_stream.sequence_number = speaker_stream.sequence_number.wrapping_add(1);
+            speaker_stream.timestamp = speaker_stream.timestamp.wrapping_add(elapsed);
+            speaker_stream.last_sent = now;
+            speaker_stream.last_source = (header.ssrc, header.sequence_number, header.timestamp);
+            if last_ssrc != header.ssrc {
+                speaker_stream.switched_at = now;
+            }
+            // a talker switch starts a new talkspurt in the subscriber's stream
+            speaker_stream.marker = header.marker || last_ssrc != header.ssrc;
+        }
+
+        let mut speaker_packet = rtp_packet.clone();
+        speaker_packet.header.ssrc = speaker_stream.ssrc;
+        speaker_packet.header.sequence_number = speaker_stream.sequence_number;
+        speaker_packet.header.timestamp = speaker_stream.timestamp;
+        speaker_packet.header.marker = speaker_stream.marker;
+        // the dominant speaker is reported first, even if it is kept while others are louder
+        let mut csrc = vec![header.ssrc];
+        csrc.extend(top_talkers.into_iter().filter(|&ssrc| ssrc != header.ssrc));
+        csrc.truncate(self.top_n);
+        speaker_packet.header.csrc = csrc;
+        Some(speaker_packet)
+    }
+
+    /// set_csrc_audio_levels replaces the client-to-mixer audio level of the forwarded packet, which
+    /// is the dominant speaker's one, by the mixer-to-client audio levels of its contributing
+    /// sources in the same order, the extension ids are None if not negotiated
+    pub(crate) fn set_csrc_audio_levels(
+        &self,
+        speaker_packet: &mut rtp::packet::Packet,
+        ssrc_audio_level_id: Option<u8>,
+        csrc_audio_level_id: Option<u8>,
+    ) -> Result<()> {
+        let header = &mut speaker_packet.header;
+        if let Some(id) = ssrc_audio_level_id {
+            if header.get_extension(id).is_some() {
+                header.del_extension(id)?;
+            }
+        }
+        if let Some(id) = csrc_audio_level_id {
+            // each level is 7 bits in -dBov with the leading bit unset
+            let audio_levels: Vec<u8> = header
+                .csrc
+                .iter()
+                .map(|ssrc| {
+                    self.talkers
+                        .get(ssrc)
+                        .map_or(SILENCE_AUDIO_LEVEL, |talker| talker.audio_level)
+                        & 0x7F
+                })
+                .collect();
+            if !audio_levels.is_empty() {
+                header.set_extension(id, audio_levels.into())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// remove_endpoint drops states of the endpoint, both as publisher and subscriber
+    pub(crate) fn remove_endpoint(&mut self, endpoint_id: EndpointId) {
+        self.talkers
+            .retain(|_, talker| talker.endpoint_id != endpoint_id);
+        self.speaker_streams.remove(&endpoint_id);
+    }
+}
@@ -0,0 +1,251 @@
+use super::dominant_speaker::*;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+const OPUS_CLOCK_RATE: u32 = 48000;
+
+fn new_audio_packet(ssrc: u32, sequence_number: u16, timestamp: u32) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            payload_type: 111,
+            sequence_number,
+            timestamp,
+            ssrc,
+            ..Default::default()
+        },
+        payload: bytes::Bytes::from_static(&[0xAA; 8]),
+    }
+}
+
+#[test]
+fn test_dominant_speaker_selector_forwards_single_stream() {
+    let mut dominant_speaker_selector = DominantSpeakerSelector::new(2);
+    let subscriber = 4;
+    // (endpoint id, ssrc, audio level in -dBov)
+    let talkers = [(1, 1111, 30), (2, 2222, 10), (3, 3333, 50)];
+
+    let now = Instant::now();
+    let mut outputs = vec![];
+    for i in 0..10u16 {
+        let now = now + Duration::from_millis(20 * i as u64);
+        for &(endpoint_id, ssrc, audio_level) in &talkers {
+            dominant_speaker_selector.update_talker(
+                now,
+                endpoint_id,
+                ssrc,
+                Some(audio_level),
+                OPUS_CLOCK_RATE,
+            );
+        }
+        for &(_, ssrc, _) in &talkers {
+            let packet = new_audio_packet(ssrc, 100 + i, 960 * i as u32);
+            if let Some(selected) = dominant_speaker_selector.select(now, subscriber, &packet, ssrc)
+            {
+                outputs.push(selected);
+            }
+        }
+    }
+
+    let ssrcs: HashSet<u32> = outputs.iter().map(|p| p.header.ssrc).collect();
+    assert_eq!(ssrcs.len(), 1);
+    // only one packet per packetization interval is forwarded, not one per talker
+    assert_eq!(outputs.len(), 10);
+    let last = outputs.last().unwrap();
+    assert_eq!(last.header.csrc, vec![2222, 1111]);
+    for pair in outputs.windows(2) {
+        assert_eq!(
+            pair[1].header.sequence_number,
+            pair[0].header.sequence_number.wrapping_add(1)
+        );
+        assert!(pair[1].header.timestamp > pair[0].header.timestamp);
+    }
+
+    // a talker doesn't hear itself
+    let packet = new_audio_packet(2222, 200, 96000);
+    assert!(dominant_speaker_selector
+        .select(now, 2, &packet, 2222)
+        .is_none());
+}
+
+#[test]
+fn test_dominant_speaker_selector_keeps_stream_continuous_on_talker_switch() {
+    let mut dominant_speaker_selector = DominantSpeakerSelector::new(1);
+    let now = Instant::now();
+    // the timestamp advances by the clock rate of the talkers' negotiated codec, e.g., PCMU
+    let clock_rate = 8000;
+
+    dominant_speaker_selector.update_talker(now, 1, 1111, Some(10), clock_rate);
+    let first = dominant_speaker_selector
+        .select(now, 3, &new_audio_packet(1111, 500, 10000), 1111)
+        .unwrap();
+
+    let later = now + DOMINANT_SPEAKER_HOLD_TIME;
+    dominant_speaker_selector.update_talker(later, 1, 1111, Some(10), clock_rate);
+    dominant_speaker_selector.update_talker(later, 2, 2222, Some(0), clock_rate);
+    assert!(dominant_speaker_selector
+        .select(later, 3, &new_audio_packet(1111, 501, 10160), 1111)
+        .is_none());
+    let second = dominant_speaker_selector
+        .select(later, 3, &new_audio_packet(2222, 7000, 123456), 2222)
+        .unwrap();
+
+    assert_eq!(second.header.ssrc, first.header.ssrc);
+    assert_eq!(second.header.sequence_number, 501);
+    assert_eq!(
+        second.header.timestamp,
+        10000 + DOMINANT_SPEAKER_HOLD_TIME.as_millis() as u32 * clock_rate / 1000
+    );
+    assert_eq!(second.header.csrc, vec![2222]);
+}
+
+#[test]
+fn test_dominant_speaker_selector_marks_talkspurt_start_on_talker_switch() {
+    let mut dominant_speaker_selector = DominantSpeakerSelector::new(1);
+    let now = Instant::now();
+
+    dominant_speaker_selector.update_talker(now, 1, 1111, Some(10), OPUS_CLOCK_RATE);
+    let first = dominant_speaker_selector
+        .select(now, 3, &new_audio_packet(1111, 500, 10000), 1111)
+        .unwrap();
+    assert!(first.header.marker);
+
+    let later = now + Duration::from_millis(20);
+    let continued = dominant_speaker_selector
+        .select(later, 3, &new_audio_packet(1111, 501, 10960), 1111)
+        .unwrap();
+    assert!(!continued.header.marker);
+
+    let later = now + DOMINANT_SPEAKER_HOLD_TIME;
+    dominant_speaker_selector.update_talker(later, 1, 1111, Some(10), OPUS_CLOCK_RATE);
+    dominant_speaker_selector.update_talker(later, 2, 2222, Some(0), OPUS_CLOCK_RATE);
+    let switched = dominant_speaker_selector
+        .select(later, 3, &new_audio_packet(2222, 7000, 123456), 2222)
+        .unwrap();
+    assert!(switched.header.marker);
+}
+
+#[test]
+fn test_dominant_speaker_selector_holds_speaker_of_alternating_talkers() {
+    let mut dominant_speaker_selector = DominantSpeakerSelector::new(2);
+    let subscriber = 3;
+    let now = Instant::now();
+
+    // (ssrc, audio level in -dBov) of two talkers, which take turns being the loudest
+    let forwarded = |dominant_speaker_selector: &mut DominantSpeakerSelector,
+                     now: Instant,
+                     i: u16,
+                     levels: [(u32, u8); 2]| {
+        for (endpoint_id, &(ssrc, audio_level)) in levels.iter().enumerate() {
+            dominant_speaker_selector.update_talker(
+                now,
+                endpoint_id as u64 + 1,
+                ssrc,
+                Some(audio_level),
+                OPUS_CLOCK_RATE,
+            );
+        }
+        levels
+            .iter()
+            .filter_map(|&(ssrc, _)| {
+                dominant_speaker_selector.select(
+                    now,
+                    subscriber,
+                    &new_audio_packet(ssrc, 100 + i, 960 * i as u32),
+                    ssrc,
+                )
+            })
+            .map(|packet| packet.header.csrc[0])
+            .collect::<Vec<_>>()
+    };
+
+    // talkers alternating within the hold time don't switch the dominant speaker
+    let mut i = 0;
+    let mut at = Duration::ZERO;
+    while at < DOMINANT_SPEAKER_HOLD_TIME {
+        let levels = if i % 10 < 5 {
+            [(1111, 10), (2222, 40)]
+        } else {
+            [(1111, 40), (2222, 0)]
+        };
+        assert_eq!(
+            forwarded(&mut dominant_speaker_selector, now + at, i, levels),
+            vec![1111],
+            "at {at:?}"
+        );
+        i += 1;
+        at += Duration::from_millis(20);
+    }
+
+    // after the hold time, a talker slightly louder than the dominant speaker doesn't take over
+    let levels = [(1111, 10), (2222, 10 - DOMINANT_SPEAKER_LEVEL_MARGIN + 1)];
+    assert_eq!(
+        forwarded(&mut dominant_speaker_selector, now + at, i, levels),
+        vec![1111]
+    );
+
+    // but a talker louder by the margin does, and is then held in turn
+    let levels = [(1111, 10), (2222, 10 - DOMINANT_SPEAKER_LEVEL_MARGIN)];
+    assert_eq!(
+        forwarded(&mut dominant_speaker_selector, now + at, i + 1, levels),
+        vec![2222]
+    );
+    let levels = [(1111, 0), (2222, 40)];
+    assert_eq!(
+        forwarded(
+            &mut dominant_speaker_selector,
+            now + at + Duration::from_millis(20),
+            i + 2,
+            levels
+        ),
+        vec![2222]
+    );
+
+    // a dominant speaker who left is replaced without waiting for the hold time
+    dominant_speaker_selector.remove_endpoint(2);
+    let packet = new_audio_packet(1111, 200, 96000);
+    let selected = dominant_speaker_selector
+        .select(
+            now + at + Duration::from_millis(40),
+            subscriber,
+            &packet,
+            1111,
+        )
+        .unwrap();
+    assert_eq!(selected.header.csrc, vec![1111]);
+}
+
+#[test]
+fn test_dominant_speaker_selector_sets_csrc_audio_levels_of_contributing_sources() {
+    let mut dominant_speaker_selector = DominantSpeakerSelector::new(2);
+    let subscriber = 4;
+    let (ssrc_audio_level_id, csrc_audio_level_id) = (1, 2);
+    let now = Instant::now();
+    dominant_speaker_selector.update_talker(now, 1, 1111, Some(30), OPUS_CLOCK_RATE);
+    dominant_speaker_selector.update_talker(now, 2, 2222, Some(10), OPUS_CLOCK_RATE);
+    dominant_speaker_selector.update_talker(now, 3, 3333, Some(50), OPUS_CLOCK_RATE);
+
+    // the loudest talker's packet carries its client-to-mixer audio level
+    let mut packet = new_audio_packet(2222, 100, 0);
+    packet
+        .header
+        .set_extension(ssrc_audio_level_id, bytes::Bytes::from_static(&[0x80 | 10]))
+        .unwrap();
+    let mut selected = dominant_speaker_selector
+        .select(now, subscriber, &packet, 2222)
+        .unwrap();
+    dominant_speaker_selector
+        .set_csrc_audio_levels(
+            &mut selected,
+            Some(ssrc_audio_level_id),
+            Some(csrc_audio_level_id),
+        )
+        .unwrap();
+
+    assert_eq!(selected.header.csrc, vec![2222, 1111]);
+    assert_eq!(
+        selected.header.get_extension(csrc_audio_level_id),
+        Some(bytes::Bytes::from_static(&[10, 30]))
+    );
+    assert_eq!(selected.header.get_extension(ssrc_audio_level_id), None);
+}
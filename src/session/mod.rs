@@ -1,22 +1,28 @@
 use retty::transport::TransportContext;
+use sdp::description::media::MediaDescription;
 use sdp::description::session::Origin;
 use sdp::util::ConnectionRole;
 use sdp::SessionDescription;
 use shared::error::{Error, Result};
 use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-use crate::configs::session_config::SessionConfig;
+use crate::configs::session_config::{AudioForwardingMode, SessionConfig};
 use crate::description::{
-    codecs_from_media_description, get_cname, get_mid_value, get_msid, get_peer_direction,
-    get_rids, get_ssrc_groups, get_ssrcs, populate_sdp, rtp_extensions_from_media_description,
-    update_sdp_origin, MediaSection, RTCSessionDescription, MEDIA_SECTION_APPLICATION,
+    codecs_from_media_description, get_bundle_group, get_cname, get_media_max_message_size,
+    get_mid_value, get_msid, get_peer_direction, get_rids, get_sctp_port, get_ssrc_attributes,
+    get_ssrc_groups, get_ssrcs, has_extmap_allow_mixed, has_rtcp_mux, has_rtcp_rsize, is_ice_lite,
+    populate_sdp, rtp_extensions_from_media_description, update_sdp_origin, validate_media_protos,
+    LocalCandidate, MediaSection, RTCSessionDescription, MEDIA_SECTION_APPLICATION,
 };
 use crate::description::{
     rtp_codec::{RTCRtpParameters, RTPCodecType},
     rtp_transceiver::{RTCRtpSender, RTCRtpTransceiver},
     rtp_transceiver_direction::RTCRtpTransceiverDirection,
     sdp_type::RTCSdpType,
+    signaling_state::RTCSignalingState,
 };
 use crate::endpoint::{
     candidate::{Candidate, DTLSRole, RTCIceParameters, DEFAULT_DTLS_ROLE_OFFER},
@@ -24,19 +30,48 @@ use crate::endpoint::{
     Endpoint,
 };
 use crate::types::{EndpointId, Mid, SessionId};
+use dominant_speaker::DominantSpeakerSelector;
+
+pub(crate) mod dominant_speaker;
+
+#[cfg(test)]
+mod dominant_speaker_test;
+#[cfg(test)]
+mod session_test;
 
 pub(crate) struct Session {
     session_config: SessionConfig,
     session_id: SessionId,
     endpoints: HashMap<EndpointId, Endpoint>,
+    dominant_speaker_selector: Option<DominantSpeakerSelector>,
+    interceptor_profiles: HashMap<EndpointId, String>,
+    direction_overrides: HashMap<(EndpointId, Mid), RTCRtpTransceiverDirection>,
+    max_bitrates_kbps: HashMap<EndpointId, u64>,
 }
 
 impl Session {
     pub(crate) fn new(session_config: SessionConfig, session_id: SessionId) -> Self {
+        let dominant_speaker_selector =
+            Session::new_dominant_speaker_selector(session_config.audio_forwarding_mode);
         Self {
             session_config,
             session_id,
             endpoints: HashMap::new(),
+            dominant_speaker_selector,
+            interceptor_profiles: HashMap::new(),
+            direction_overrides: HashMap::new(),
+            max_bitrates_kbps: HashMap::new(),
+        }
+    }
+
+    fn new_dominant_speaker_selector(
+        audio_forwarding_mode: AudioForwardingMode,
+    ) -> Option<DominantSpeakerSelector> {
+        match audio_forwarding_mode {
+            AudioForwardingMode::SelectiveForward => None,
+            AudioForwardingMode::DominantSpeaker { top_n } => {
+                Some(DominantSpeakerSelector::new(top_n))
+            }
         }
     }
 
@@ -48,6 +83,344 @@ impl Session {
         &self.session_config
     }
 
+    pub(crate) fn set_audio_forwarding_mode(&mut self, audio_forwarding_mode: AudioForwardingMode) {
+        if self.session_config.audio_forwarding_mode != audio_forwarding_mode {
+            self.session_config.audio_forwarding_mode = audio_forwarding_mode;
+            self.dominant_speaker_selector =
+                Session::new_dominant_speaker_selector(audio_forwarding_mode);
+        }
+    }
+
+    /// set_max_subscribers_per_publisher overrides the limit of ServerConfig for the session,
+    /// None removes the limit, existing subscriptions are kept
+    pub(crate) fn set_max_subscribers_per_publisher(
+        &mut self,
+        max_subscribers_per_publisher: Option<usize>,
+    ) {
+        self.session_config.max_subscribers_per_publisher = max_subscribers_per_publisher;
+    }
+
+    /// set_report_interval overrides the interval of RTCP reports and TWCC feedback configured in
+    /// MediaConfig for the session, None restores it, only endpoints connected afterwards are affected
+    pub(crate) fn set_report_interval(&mut self, report_interval: Option<Duration>) {
+        self.session_config.report_interval = report_interval;
+    }
+
+    /// set_sctp_port overrides the SCTP port advertised in the session's application media sections,
+    /// which still echo the offered one, None restores the port of ServerConfig's SCTP transport
+    pub(crate) fn set_sctp_port(&mut self, sctp_port: Option<u16>) {
+        self.session_config.sctp_port = sctp_port;
+    }
+
+    /// update_codec_preferences excludes the codecs with disabled_codecs mime types, e.g., "video/H264",
+    /// from the session's later offers and answers, endpoints with transceivers using any codec
+    /// whose preference changes are marked for renegotiation, returns the ids of those endpoints
+    pub(crate) fn update_codec_preferences(&mut self, disabled_codecs: &[&str]) -> Vec<EndpointId> {
+        let disabled_codecs: HashSet<String> = disabled_codecs
+            .iter()
+            .map(|mime_type| mime_type.to_lowercase())
+            .collect();
+        let changed_codecs: HashSet<String> = disabled_codecs
+            .symmetric_difference(&self.session_config.disabled_codecs)
+            .cloned()
+            .collect();
+        self.session_config.disabled_codecs = disabled_codecs;
+
+        let mut endpoint_ids = vec![];
+        for (&endpoint_id, endpoint) in self.endpoints.iter_mut() {
+            let is_affected = endpoint.get_transceivers().values().any(|transceiver| {
+                transceiver.rtp_params.codecs.iter().any(|codec| {
+                    changed_codecs.contains(&codec.capability.mime_type.to_lowercase())
+                })
+            });
+            if is_affected {
+                endpoint.set_renegotiation_needed(true);
+                endpoint_ids.push(endpoint_id);
+            }
+        }
+        endpoint_ids
+    }
+
+    /// get_subscriber_count returns the number of endpoints receiving streams of the publisher
+    fn get_subscriber_count(&self, publisher: EndpointId) -> usize {
+        self.endpoints
+            .values()
+            .filter(|endpoint| endpoint.is_subscribed_to(publisher))
+            .count()
+    }
+
+    /// subscribe adds transceivers to the subscriber for the streams received from the publisher,
+    /// returns true if any is added, or error if the publisher already has max subscribers
+    pub(crate) fn subscribe(
+        &mut self,
+        subscriber: EndpointId,
+        publisher: EndpointId,
+    ) -> Result<bool> {
+        let is_subscribed = self
+            .get_endpoint(&subscriber)
+            .ok_or(Error::Other(format!(
+                "can't find endpoint id {}",
+                subscriber
+            )))?
+            .is_subscribed_to(publisher);
+        let mut new_transceivers: Vec<RTCRtpTransceiver> = self
+            .get_endpoint(&publisher)
+            .ok_or(Error::Other(format!(
+                "can't find endpoint id {}",
+                publisher
+            )))?
+            .get_transceivers()
+            .iter()
+            .filter(|(_, transceiver)| {
+                transceiver.direction == RTCRtpTransceiverDirection::Recvonly
+            })
+            .map(|(mid, transceiver)| {
+                let mut transceiver = transceiver.clone();
+                transceiver.mid = format!("{}-{}", publisher, mid);
+                transceiver.direction = RTCRtpTransceiverDirection::Sendonly;
+                transceiver.direction_override = None;
+                transceiver.selected_rid = None;
+                transceiver
+            })
+            .collect();
+        if new_transceivers.is_empty() {
+            return Ok(false);
+        }
+
+        if let Some(max_subscribers) = self.session_config.max_subscribers_per_publisher {
+            if !is_subscribed && self.get_subscriber_count(publisher) >= max_subscribers {
+                return Err(Error::Other(format!(
+                    "ErrMaxSubscribersExceeded {} for publisher {}",
+                    max_subscribers, publisher
+                )));
+            }
+        }
+
+        let endpoint = self.get_mut_endpoint(&subscriber).unwrap();
+        new_transceivers
+            .retain(|transceiver| !endpoint.get_transceivers().contains_key(&transceiver.mid));
+        for mut transceiver in new_transceivers.iter().cloned() {
+            // each subscriber receives the forwarded streams with its own ssrcs
+            let ssrc_allocator = endpoint.get_mut_ssrc_allocator();
+            transceiver.sender = transceiver
+                .sender
+                .map(|sender| sender.map_ssrcs(|ssrc| ssrc_allocator.allocate(publisher, ssrc)));
+            let (mids, transceivers) = endpoint.get_mut_mids_and_transceivers();
+            mids.push(transceiver.mid.clone());
+            transceivers.insert(transceiver.mid.clone(), transceiver);
+        }
+        Ok(!new_transceivers.is_empty())
+    }
+
+    /// set_interceptor_profile selects the named interceptor profile of MediaConfig,
+    /// which builds the interceptor chain once the endpoint is created
+    pub(crate) fn set_interceptor_profile(&mut self, endpoint_id: EndpointId, profile: String) {
+        self.interceptor_profiles.insert(endpoint_id, profile);
+    }
+
+    /// set_direction_override makes the transceiver of the endpoint with mid answer with the direction
+    /// instead of its own one, before intersecting with the offered direction, even if the transceiver
+    /// is only created by a later offer, None restores the default behavior
+    pub(crate) fn set_direction_override(
+        &mut self,
+        endpoint_id: EndpointId,
+        mid: Mid,
+        direction: Option<RTCRtpTransceiverDirection>,
+    ) {
+        if let Some(transceiver) = self
+            .endpoints
+            .get_mut(&endpoint_id)
+            .and_then(|endpoint| endpoint.get_mut_transceivers().get_mut(&mid))
+        {
+            transceiver.direction_override = direction;
+        }
+        if let Some(direction) = direction {
+            self.direction_overrides
+                .insert((endpoint_id, mid), direction);
+        } else {
+            self.direction_overrides.remove(&(endpoint_id, mid));
+        }
+    }
+
+    /// set_max_bitrate_kbps caps the bitrate of the endpoint's audio and video media sections by
+    /// b=AS and b=TIAS lines in the next descriptions to it, None removes the cap
+    pub(crate) fn set_max_bitrate_kbps(
+        &mut self,
+        endpoint_id: EndpointId,
+        max_bitrate_kbps: Option<u64>,
+    ) {
+        if let Some(max_bitrate_kbps) = max_bitrate_kbps {
+            self.max_bitrates_kbps.insert(endpoint_id, max_bitrate_kbps);
+        } else {
+            self.max_bitrates_kbps.remove(&endpoint_id);
+        }
+    }
+
+    /// select_layer makes the subscriber's transceiver with mid forward only the simulcast layer of
+    /// rid from the publisher, e.g., SimulcastLayer::Low.rid() for a small viewport, None
+    /// forwards all layers. A keyframe of the newly selected layer is requested from the publisher,
+    /// since the subscriber can't decode the layer until then
+    pub(crate) fn select_layer(
+        &mut self,
+        endpoint_id: EndpointId,
+        mid: &str,
+        rid: Option<String>,
+    ) -> Result<()> {
+        let transceiver = self
+            .endpoints
+            .get_mut(&endpoint_id)
+            .and_then(|endpoint| endpoint.get_mut_transceivers().get_mut(mid))
+            .ok_or(Error::Other(format!(
+                "can't find transceiver with mid {} for endpoint id {}",
+                mid, endpoint_id
+            )))?;
+        if transceiver.selected_rid == rid {
+            return Ok(());
+        }
+        transceiver.selected_rid = rid.clone();
+        let (Some(rid), RTPCodecType::Video) = (rid, transceiver.kind) else {
+            return Ok(());
+        };
+
+        // mid of a forwarded transceiver is prefixed by the publisher's endpoint id
+        let Some((publisher, publisher_mid)) = mid
+            .split_once('-')
+            .and_then(|(publisher, publisher_mid)| Some((publisher.parse().ok()?, publisher_mid)))
+        else {
+            return Ok(());
+        };
+        if let Some(publisher) = self.get_mut_endpoint(&publisher) {
+            publisher.force_layer_keyframe(publisher_mid, &rid, Instant::now());
+        }
+        Ok(())
+    }
+
+    /// restart_ice generates fresh ICE parameters of the endpoint and flags renegotiation, the next
+    /// offer or answer to it advertises them along with the gathered candidates
+    pub(crate) fn restart_ice(&mut self, endpoint_id: EndpointId) -> Result<RTCIceParameters> {
+        let endpoint = self
+            .get_mut_endpoint(&endpoint_id)
+            .ok_or(Error::Other(format!(
+                "can't find endpoint id {}",
+                endpoint_id
+            )))?;
+        let ice_params = RTCIceParameters::generate();
+        endpoint.set_ice_restart_params(Some(ice_params.clone()));
+        endpoint.set_renegotiation_needed(true);
+        Ok(ice_params)
+    }
+
+    /// add_local_candidate adds a host candidate of addr gathered after the endpoint's description,
+    /// whose a=candidate line is taken by take_local_candidates to be trickled out-of-band
+    pub(crate) fn add_local_candidate(
+        &mut self,
+        endpoint_id: EndpointId,
+        addr: SocketAddr,
+    ) -> Result<()> {
+        self.get_mut_endpoint(&endpoint_id)
+            .ok_or(Error::Other(format!(
+                "can't find endpoint id {}",
+                endpoint_id
+            )))?
+            .add_trickled_candidate(LocalCandidate::host(addr));
+        Ok(())
+    }
+
+    /// take_local_candidates returns the a=candidate lines of the candidates added since the
+    /// last call, paired with the mid of each media section of the local description that has
+    /// its own transport, END_OF_CANDIDATES is signaled separately once gathering completes
+    pub(crate) fn take_local_candidates(
+        &mut self,
+        endpoint_id: EndpointId,
+    ) -> Result<Vec<(Mid, String)>> {
+        let endpoint = self
+            .get_mut_endpoint(&endpoint_id)
+            .ok_or(Error::Other(format!(
+                "can't find endpoint id {}",
+                endpoint_id
+            )))?;
+        // candidates of a transport are trickled to the media sections carrying them, e.g., the
+        // first one of a BUNDLE group <https://datatracker.ietf.org/doc/html/rfc8840#section-4.2>
+        let mids: Vec<Mid> = endpoint
+            .local_description()
+            .and_then(|local_description| local_description.parsed.as_ref())
+            .ok_or(Error::Other(format!(
+                "can't trickle candidates of endpoint id {} without local description",
+                endpoint_id
+            )))?
+            .media_descriptions
+            .iter()
+            .filter(|media| media.attribute("candidate").is_some())
+            .filter_map(|media| get_mid_value(media).map(|mid| mid.to_string()))
+            .collect();
+        Ok(endpoint
+            .take_pending_candidate_lines()
+            .into_iter()
+            .flat_map(|line| mids.iter().map(move |mid| (mid.clone(), line.clone())))
+            .collect())
+    }
+
+    /// add_transceiver_from_kind adds a local transceiver of kind with the registered codecs to the
+    /// endpoint, which is offered in the next offer to it, and returns the mid of the transceiver
+    pub(crate) fn add_transceiver_from_kind(
+        &mut self,
+        endpoint_id: EndpointId,
+        kind: RTPCodecType,
+        direction: RTCRtpTransceiverDirection,
+    ) -> Result<Mid> {
+        if kind == RTPCodecType::Unspecified || direction == RTCRtpTransceiverDirection::Unspecified
+        {
+            return Err(Error::Other(format!(
+                "can't add transceiver of kind {} with direction {}",
+                kind, direction
+            )));
+        }
+        let codecs = self
+            .session_config
+            .server_config
+            .media_config
+            .get_offered_codecs_by_kind(kind)?;
+        let endpoint = self
+            .endpoints
+            .get_mut(&endpoint_id)
+            .ok_or(Error::Other(format!(
+                "can't find endpoint id {}",
+                endpoint_id
+            )))?;
+
+        let (mids, transceivers) = endpoint.get_mut_mids_and_transceivers();
+        let mid = (mids.len()..)
+            .map(|i| i.to_string())
+            .find(|mid| !transceivers.contains_key(mid))
+            .unwrap_or_default();
+        let transceiver = RTCRtpTransceiver {
+            mid: mid.clone(),
+            sender: None,
+            direction,
+            current_direction: RTCRtpTransceiverDirection::Unspecified,
+            direction_override: None,
+            rtp_params: RTCRtpParameters {
+                header_extensions: vec![],
+                codecs,
+            },
+            kind,
+            negotiated_header_extensions: vec![],
+            selected_rid: None,
+        };
+        mids.push(mid.clone());
+        transceivers.insert(mid.clone(), transceiver);
+        endpoint.set_renegotiation_needed(true);
+        Ok(mid)
+    }
+
+    /// get_mut_dominant_speaker_selector returns DominantSpeakerSelector in dominant speaker mode,
+    /// None in selective forward mode
+    pub(crate) fn get_mut_dominant_speaker_selector(
+        &mut self,
+    ) -> Option<&mut DominantSpeakerSelector> {
+        self.dominant_speaker_selector.as_mut()
+    }
+
     pub(crate) fn add_endpoint(
         &mut self,
         candidate: &Rc<Candidate>,
@@ -82,9 +455,18 @@ impl Session {
                 Ok(true)
             }
         } else {
-            let registry = self.session_config.server_config.media_config.registry();
-            let interceptor = registry.build(""); //TODO: use named registry id
+            let profile = self.interceptor_profiles.get(&endpoint_id);
+            let registry = self
+                .session_config
+                .server_config
+                .media_config
+                .get_registry(profile);
+            let interceptor = registry.build_with_report_interval(
+                profile.map_or("", |profile| profile.as_str()),
+                self.session_config.report_interval,
+            );
             let mut endpoint = Endpoint::new(endpoint_id, interceptor);
+            endpoint.reserve_transceivers(self.session_config.server_config.transceiver_pool_size);
             let transport = Transport::new(
                 four_tuple,
                 Rc::clone(candidate),
@@ -95,6 +477,13 @@ impl Session {
             endpoint.add_transport(transport);
             endpoint.set_local_description(candidate.local_description().clone());
             endpoint.set_remote_description(candidate.remote_description().clone());
+            endpoint.set_remote_is_lite(
+                candidate
+                    .remote_description()
+                    .parsed
+                    .as_ref()
+                    .is_some_and(is_ice_lite),
+            );
             self.endpoints.insert(endpoint_id, endpoint);
             Ok(false)
         }
@@ -108,10 +497,47 @@ impl Session {
         self.endpoints.get_mut(endpoint_id)
     }
 
+    /// remove_endpoint removes the endpoint with its transports, and stops the transceivers of the
+    /// other endpoints subscribed to it, which are renegotiated as inactive
     pub(crate) fn remove_endpoint(&mut self, endpoint_id: &EndpointId) -> Option<Endpoint> {
+        if let Some(dominant_speaker_selector) = self.dominant_speaker_selector.as_mut() {
+            dominant_speaker_selector.remove_endpoint(*endpoint_id);
+        }
+        self.interceptor_profiles.remove(endpoint_id);
+        self.max_bitrates_kbps.remove(endpoint_id);
+        self.direction_overrides
+            .retain(|(id, _), _| id != endpoint_id);
+        let prefix = format!("{}-", endpoint_id);
+        for endpoint in self.endpoints.values_mut() {
+            endpoint.get_mut_ssrc_allocator().release(*endpoint_id);
+            let mut is_stopped = false;
+            for (mid, transceiver) in endpoint.get_mut_transceivers().iter_mut() {
+                if mid.starts_with(&prefix)
+                    && transceiver.direction != RTCRtpTransceiverDirection::Inactive
+                {
+                    transceiver.direction = RTCRtpTransceiverDirection::Inactive;
+                    is_stopped = true;
+                }
+            }
+            if is_stopped {
+                endpoint.set_renegotiation_needed(true);
+            }
+        }
         self.endpoints.remove(endpoint_id)
     }
 
+    /// has_endpoints returns false once all endpoints left, so that the session can be removed
+    pub(crate) fn has_endpoints(&self) -> bool {
+        !self.endpoints.is_empty()
+    }
+
+    /// remote_is_lite returns true if the endpoint's remote description is ice-lite
+    pub(crate) fn remote_is_lite(&self, endpoint_id: &EndpointId) -> bool {
+        self.endpoints
+            .get(endpoint_id)
+            .is_some_and(|endpoint| endpoint.remote_is_lite())
+    }
+
     pub(crate) fn has_endpoint(&self, endpoint_id: &EndpointId) -> bool {
         self.endpoints.contains_key(endpoint_id)
     }
@@ -124,6 +550,132 @@ impl Session {
         &mut self.endpoints
     }
 
+    /// update_subscriber_transceivers mirrors the direction of the publisher's transceiver with mid
+    /// on the other endpoints, and adds it to them as send only if they don't have it yet
+    fn update_subscriber_transceivers(
+        &mut self,
+        endpoint_id: EndpointId,
+        mid_value: &str,
+        direction: RTCRtpTransceiverDirection,
+        sender: &Option<RTCRtpSender>,
+        rtp_params: &RTCRtpParameters,
+        kind: RTPCodecType,
+    ) {
+        let session_id = self.session_id;
+        let max_subscribers = self.session_config.max_subscribers_per_publisher;
+        let mut subscriber_count = self.get_subscriber_count(endpoint_id);
+        for (&other_endpoint_id, other_endpoint) in self.get_mut_endpoints().iter_mut() {
+            if other_endpoint_id != endpoint_id {
+                let other_mid_value = format!("{}-{}", endpoint_id, mid_value);
+                let (_, other_transceivers) = other_endpoint.get_mut_mids_and_transceivers();
+                if let Some(other_transceiver) = other_transceivers.get_mut(&other_mid_value) {
+                    if other_transceiver.direction != direction {
+                        other_transceiver.direction = direction;
+                        other_endpoint.set_renegotiation_needed(true);
+                    }
+                } else if direction == RTCRtpTransceiverDirection::Sendonly {
+                    if !other_endpoint.is_subscribed_to(endpoint_id) {
+                        if max_subscribers.is_some_and(|max| subscriber_count >= max) {
+                            log::warn!(
+                                "{}/{}: skip subscriber {} beyond max subscribers {:?}",
+                                session_id,
+                                endpoint_id,
+                                other_endpoint_id,
+                                max_subscribers
+                            );
+                            continue;
+                        }
+                        subscriber_count += 1;
+                    }
+                    // each subscriber receives the forwarded streams with its own ssrcs
+                    let ssrc_allocator = other_endpoint.get_mut_ssrc_allocator();
+                    let other_sender = sender.as_ref().map(|sender| {
+                        sender.map_ssrcs(|ssrc| ssrc_allocator.allocate(endpoint_id, ssrc))
+                    });
+                    let (other_mids, other_transceivers) =
+                        other_endpoint.get_mut_mids_and_transceivers();
+                    let other_transceiver = RTCRtpTransceiver {
+                        mid: other_mid_value.clone(),
+                        sender: other_sender,
+                        direction,
+                        current_direction: RTCRtpTransceiverDirection::Unspecified,
+                        direction_override: None,
+                        rtp_params: rtp_params.clone(),
+                        kind,
+                        negotiated_header_extensions: vec![],
+                        selected_rid: None,
+                    };
+
+                    other_mids.push(other_mid_value.clone());
+                    other_transceivers.insert(other_mid_value, other_transceiver);
+                    other_endpoint.set_renegotiation_needed(true);
+                }
+            }
+        }
+    }
+
+    /// resume_transceiver resumes forwarding the publisher's transceiver with mid, if the remote
+    /// re-offers it as sending while it was negotiated as inactive, e.g., the client un-mutes,
+    /// and requests a keyframe of video streams for subscribers to start decoding
+    fn resume_transceiver(
+        &mut self,
+        endpoint_id: EndpointId,
+        mid_value: &str,
+        media: &MediaDescription,
+        direction: RTCRtpTransceiverDirection,
+        kind: RTPCodecType,
+    ) -> Result<()> {
+        let endpoint = self.get_mut_endpoint(&endpoint_id).unwrap();
+        let Some(transceiver) = endpoint.get_mut_transceivers().get_mut(mid_value) else {
+            return Ok(());
+        };
+        if transceiver.current_direction() != RTCRtpTransceiverDirection::Inactive
+            || !direction.has_send()
+        {
+            return Ok(());
+        }
+
+        // the streams may be signaled only when they are sent
+        if transceiver.sender.is_none() {
+            if let Some(msid) = get_msid(media) {
+                transceiver.sender = Some(RTCRtpSender {
+                    cname: get_cname(media).unwrap_or_else(|| msid.msid_id().to_string()),
+                    msid,
+                    ssrcs: get_ssrcs(media)?,
+                    ssrc_attributes: get_ssrc_attributes(media)?,
+                    ssrc_groups: get_ssrc_groups(media)?,
+                    rids: vec![],
+                });
+            }
+        }
+        transceiver.direction = RTCRtpTransceiverDirection::Recvonly;
+        let sender = transceiver.sender.clone();
+        let rtp_params = transceiver.rtp_params.clone();
+        if let Some(sender) = &sender {
+            endpoint.get_mut_ssrc_allocator().reserve(&sender.ssrcs);
+        }
+        if kind == RTPCodecType::Video {
+            endpoint.request_keyframe(mid_value, Instant::now())?;
+        }
+        log::info!(
+            "{}/{}: resume inactive transceiver with mid {} as {}",
+            self.session_id,
+            endpoint_id,
+            mid_value,
+            direction
+        );
+
+        self.update_subscriber_transceivers(
+            endpoint_id,
+            mid_value,
+            direction,
+            &sender,
+            &rtp_params,
+            kind,
+        );
+        Ok(())
+    }
+
     pub(crate) fn set_remote_description(
         &mut self,
         endpoint_id: EndpointId,
@@ -141,6 +693,19 @@ impl Session {
             .ok_or(Error::Other("Unparsed remote description".to_string()))?;
 
         let we_offer = remote_description.sdp_type == RTCSdpType::Answer;
+        // forwarded video transceivers the answer starts sending, whose publishers are asked for
+        // a keyframe, so that the subscriber starts decoding without waiting for the next one
+        let mut started_video_mids = vec![];
+        let endpoint = self.get_mut_endpoint(&endpoint_id).unwrap();
+        // a remote offer colliding with our pending one is rejected, the remote rolls back
+        if !we_offer && endpoint.signaling_state() == RTCSignalingState::HaveLocalOffer {
+            return Err(Error::Other(format!(
+                "ErrSignalingStateCannotApplyRemoteOffer {}",
+                endpoint.signaling_state()
+            )));
+        }
+        endpoint.set_remote_is_lite(is_ice_lite(parsed));
+        endpoint.set_extmap_allow_mixed(has_extmap_allow_mixed(parsed));
 
         for media in &parsed.media_descriptions {
             if media.media_name.media == MEDIA_SECTION_APPLICATION {
@@ -148,7 +713,7 @@ impl Session {
             }
 
             let kind = RTPCodecType::from(media.media_name.media.as_str());
-            let direction = get_peer_direction(media);
+            let direction = get_peer_direction(parsed, media);
             if kind == RTPCodecType::Unspecified
                 || direction == RTCRtpTransceiverDirection::Unspecified
             {
@@ -178,10 +743,15 @@ impl Session {
                     .contains_key(mid_value);
 
                 if !has_mid_value {
-                    let cname = get_cname(media);
                     let msid = get_msid(media);
                     let ssrc_groups = get_ssrc_groups(media)?;
                     let ssrcs = get_ssrcs(media)?;
+                    // ssrcs forwarded to this endpoint must not collide with its own ones
+                    self.get_mut_endpoint(&endpoint_id)
+                        .unwrap()
+                        .get_mut_ssrc_allocator()
+                        .reserve(&ssrcs);
+                    let ssrc_attributes = get_ssrc_attributes(media)?;
                     let codecs = codecs_from_media_description(media)?;
                     let header_extensions = rtp_extensions_from_media_description(media)?;
                     let rtp_params = RTCRtpParameters {
@@ -195,24 +765,30 @@ impl Session {
                         RTCRtpTransceiverDirection::Recvonly
                     };
 
-                    let sender = if let (Some(cname), Some(msid)) = (cname, msid) {
-                        Some(RTCRtpSender {
-                            cname,
-                            msid,
-                            ssrcs,
-                            ssrc_groups,
-                        })
-                    } else {
-                        None
-                    };
+                    // streams signaled by msid only, e.g., of rid based simulcast, keep their
+                    // MediaStream grouping as well
+                    let sender = msid.map(|msid| RTCRtpSender {
+                        cname: get_cname(media).unwrap_or_else(|| msid.msid_id().to_string()),
+                        msid,
+                        ssrcs,
+                        ssrc_attributes,
+                        ssrc_groups,
+                        rids: vec![],
+                    });
 
                     let transceiver = RTCRtpTransceiver {
                         mid: mid_value.to_string(),
                         sender: sender.clone(),
                         direction: local_direction,
                         current_direction: RTCRtpTransceiverDirection::Unspecified,
+                        direction_override: self
+                            .direction_overrides
+                            .get(&(endpoint_id, mid_value.to_string()))
+                            .copied(),
                         rtp_params: rtp_params.clone(),
                         kind,
+                        negotiated_header_extensions: vec![],
+                        selected_rid: None,
                     };
 
                     {
@@ -223,37 +799,16 @@ impl Session {
                             .insert(mid_value.to_string(), transceiver);
                     }
 
-                    // add it to other endpoints' transceivers as send only
-
-                    for (&other_endpoint_id, other_endpoint) in self.get_mut_endpoints().iter_mut()
-                    {
-                        if other_endpoint_id != endpoint_id {
-                            let other_mid_value = format!("{}-{}", endpoint_id, mid_value);
-                            let (other_mids, other_transceivers) =
-                                other_endpoint.get_mut_mids_and_transceivers();
-                            if let Some(other_transceiver) =
-                                other_transceivers.get_mut(&other_mid_value)
-                            {
-                                if other_transceiver.direction != direction {
-                                    other_transceiver.direction = direction;
-                                    other_endpoint.set_renegotiation_needed(true);
-                                }
-                            } else if direction == RTCRtpTransceiverDirection::Sendonly {
-                                let other_transceiver = RTCRtpTransceiver {
-                                    mid: other_mid_value.clone(),
-                                    sender: sender.clone(),
-                                    direction,
-                                    current_direction: RTCRtpTransceiverDirection::Unspecified,
-                                    rtp_params: rtp_params.clone(),
-                                    kind,
-                                };
-
-                                other_mids.push(other_mid_value.clone());
-                                other_transceivers.insert(other_mid_value, other_transceiver);
-                                other_endpoint.set_renegotiation_needed(true);
-                            }
-                        }
-                    }
+                    self.update_subscriber_transceivers(
+                        endpoint_id,
+                        mid_value,
+                        direction,
+                        &sender,
+                        &rtp_params,
+                        kind,
+                    );
+                } else {
+                    self.resume_transceiver(endpoint_id, mid_value, media, direction, kind)?;
                 }
             } else {
                 // This is an answer from the remote.
@@ -267,10 +822,54 @@ impl Session {
                     // represent this peer's point of view. If the media description is rejected,
                     // set direction to "inactive".
                     let reversed_direction = direction.reverse();
+                    if transceiver.kind == RTPCodecType::Video
+                        && !transceiver.current_direction().has_send()
+                        && reversed_direction.has_send()
+                    {
+                        started_video_mids.push(mid_value.to_string());
+                    }
 
                     // 4.5.9.2.13.2
                     // Set transceiver.[[CurrentDirection]] and transceiver.[[Direction]]s to direction.
                     transceiver.set_current_direction(reversed_direction);
+
+                    // payload types accepted by the remote are the ones used to send to it
+                    let codecs = codecs_from_media_description(media)?;
+                    if !codecs.is_empty() {
+                        transceiver.rtp_params.codecs = codecs;
+                    }
+
+                    // header extensions accepted by the remote reuse the ids of our offer
+                    transceiver.set_negotiated_header_extensions(
+                        rtp_extensions_from_media_description(media)?,
+                    );
+                }
+            }
+        }
+
+        if !we_offer {
+            self.get_mut_endpoint(&endpoint_id)
+                .unwrap()
+                .set_signaling_state(RTCSignalingState::HaveRemoteOffer);
+        }
+        let session_id = self.session_id;
+        for mid in started_video_mids {
+            // mid of a forwarded transceiver is prefixed by the publisher's endpoint id
+            let Some((publisher, publisher_mid)) = mid
+                .split_once('-')
+                .and_then(|(publisher, mid)| Some((publisher.parse().ok()?, mid)))
+            else {
+                continue;
+            };
+            if let Some(publisher) = self.get_mut_endpoint(&publisher) {
+                if let Err(err) = publisher.request_keyframe(publisher_mid, Instant::now()) {
+                    log::debug!(
+                        "{}/{}: can't request keyframe for mid {}: {}",
+                        session_id,
+                        endpoint_id,
+                        mid,
+                        err
+                    );
                 }
             }
         }
@@ -294,8 +893,14 @@ impl Session {
                 endpoint_id
             )))?;
 
-        let transceivers = endpoint.get_mut_transceivers();
         let we_answer = local_description.sdp_type == RTCSdpType::Answer;
+        if we_answer && endpoint.signaling_state() != RTCSignalingState::HaveRemoteOffer {
+            return Err(Error::Other(format!(
+                "ErrSignalingStateCannotApplyLocalAnswer {}",
+                endpoint.signaling_state()
+            )));
+        }
+        let transceivers = endpoint.get_mut_transceivers();
         if we_answer {
             for media in &parsed.media_descriptions {
                 if media.media_name.media == MEDIA_SECTION_APPLICATION {
@@ -303,7 +908,7 @@ impl Session {
                 }
 
                 let kind = RTPCodecType::from(media.media_name.media.as_str());
-                let direction = get_peer_direction(media);
+                let direction = get_peer_direction(parsed, media);
                 if kind == RTPCodecType::Unspecified
                     || direction == RTCRtpTransceiverDirection::Unspecified
                 {
@@ -328,20 +933,136 @@ impl Session {
                     // 4.9.1.7.3 applying a local answer or pranswer
                     // Set transceiver.[[CurrentDirection]] and transceiver.[[FiredDirection]] to direction.
                     transceiver.set_current_direction(direction);
+
+                    // the offerer's ids are kept for the header extensions we accepted
+                    let accepted = rtp_extensions_from_media_description(media)?;
+                    let negotiated = transceiver
+                        .rtp_params
+                        .header_extensions
+                        .iter()
+                        .filter(|offered| accepted.iter().any(|ext| ext.uri == offered.uri))
+                        .cloned()
+                        .collect();
+                    transceiver.set_negotiated_header_extensions(negotiated);
+                }
+            }
+        }
+
+        match local_description.sdp_type {
+            RTCSdpType::Offer => {
+                if endpoint.signaling_state() == RTCSignalingState::HaveRemoteOffer {
+                    return Err(Error::Other(format!(
+                        "ErrSignalingStateCannotApplyLocalOffer {}",
+                        endpoint.signaling_state()
+                    )));
+                }
+                endpoint.set_pending_local_description(local_description.clone());
+            }
+            RTCSdpType::Answer => {
+                endpoint.set_local_description(local_description.clone());
+                endpoint.set_signaling_state(RTCSignalingState::Stable);
+                endpoint.update_interceptor_clock_rates();
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// rollback discards the pending local offer of the endpoint and returns to stable, since
+    /// remote offers are answered at once, only a local offer can be pending
+    pub(crate) fn rollback(&mut self, endpoint_id: EndpointId) -> Result<()> {
+        self.get_mut_endpoint(&endpoint_id)
+            .ok_or(Error::Other(format!(
+                "can't find endpoint id {}",
+                endpoint_id
+            )))?
+            .rollback()
+    }
+
+    /// apply_remote_answer applies the remote answer to the pending local offer of the endpoint,
+    /// which updates transceivers' current directions and negotiated codecs, then returns to stable
+    pub(crate) fn apply_remote_answer(
+        &mut self,
+        endpoint_id: EndpointId,
+        answer: &RTCSessionDescription,
+    ) -> Result<()> {
+        if answer.sdp_type != RTCSdpType::Answer {
+            return Err(Error::Other(format!(
+                "ErrSDPTypeNotAnswer {}",
+                answer.sdp_type
+            )));
+        }
+        let endpoint = self.get_endpoint(&endpoint_id).ok_or(Error::Other(format!(
+            "can't find endpoint id {}",
+            endpoint_id
+        )))?;
+        if endpoint.signaling_state() != RTCSignalingState::HaveLocalOffer {
+            return Err(Error::Other(format!(
+                "ErrSignalingStateCannotApplyRemoteAnswer {}",
+                endpoint.signaling_state()
+            )));
+        }
+
+        let offer = endpoint
+            .local_description()
+            .and_then(|offer| offer.parsed.as_ref())
+            .ok_or(Error::Other("Unparsed local offer".to_string()))?;
+        let parsed = answer
+            .parsed
+            .as_ref()
+            .ok_or(Error::Other("Unparsed remote description".to_string()))?;
+        let offered_mids: Vec<&String> = offer
+            .media_descriptions
+            .iter()
+            .filter_map(get_mid_value)
+            .collect();
+        for media in &parsed.media_descriptions {
+            match get_mid_value(media) {
+                Some(mid) if offered_mids.contains(&mid) => {}
+                mid => {
+                    return Err(Error::Other(format!("ErrAnswerMidNotInOffer {:?}", mid)));
                 }
             }
         }
+        // only the BUNDLE transport is served, whatever the bundle policy of the offer is
+        let bundle_group = get_bundle_group(parsed)?.unwrap_or_default();
+        let accepted_mids: Vec<&String> = parsed
+            .media_descriptions
+            .iter()
+            .filter(|media| media.media_name.port.value != 0)
+            .filter_map(get_mid_value)
+            .collect();
+        if accepted_mids.len() > 1 && accepted_mids.iter().any(|mid| !bundle_group.contains(mid)) {
+            return Err(Error::Other(format!(
+                "ErrAnswerNotBundled {:?}",
+                accepted_mids
+            )));
+        }
+
+        self.set_remote_description(endpoint_id, answer)?;
+
+        let endpoint = self.get_mut_endpoint(&endpoint_id).unwrap();
+        endpoint.set_remote_description(answer.clone());
+        endpoint.set_signaling_state(RTCSignalingState::Stable);
+        endpoint.update_interceptor_clock_rates();
 
         Ok(())
     }
 
+    /// create_offer generates an offer of all transceivers of the endpoint, remote_description is
+    /// None for an initial offer, e.g., to an endpoint which hasn't negotiated yet
     pub(crate) fn create_offer(
         &self,
         endpoint_id: EndpointId,
-        remote_description: &RTCSessionDescription,
+        remote_description: Option<&RTCSessionDescription>,
         local_ice_params: &RTCIceParameters,
     ) -> Result<RTCSessionDescription> {
         let use_identity = false; //TODO: self.config.idp_login_url.is_some();
+        let local_ice_params = self
+            .get_endpoint(&endpoint_id)
+            .and_then(|endpoint| endpoint.ice_restart_params())
+            .unwrap_or(local_ice_params);
 
         let mut d = self.generate_matched_sdp(
             endpoint_id,
@@ -352,8 +1073,7 @@ impl Session {
             DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
         )?;
 
-        let mut sdp_origin = Origin::default();
-        update_sdp_origin(&mut sdp_origin, &mut d);
+        self.update_local_sdp_origin(endpoint_id, &mut d);
 
         let sdp = d.marshal();
 
@@ -371,19 +1091,23 @@ impl Session {
         endpoint: EndpointId,
         remote_description: &RTCSessionDescription,
         local_ice_params: &RTCIceParameters,
+        local_dtls_role: DTLSRole,
     ) -> Result<RTCSessionDescription> {
         let use_identity = false; //TODO: self.config.idp_login_url.is_some();
+        let local_ice_params = self
+            .get_endpoint(&endpoint)
+            .and_then(|endpoint| endpoint.ice_restart_params())
+            .unwrap_or(local_ice_params);
         let mut d = self.generate_matched_sdp(
             endpoint,
-            remote_description,
+            Some(remote_description),
             local_ice_params,
             use_identity,
             false, /*includeUnmatched */
-            DTLSRole::Server.to_connection_role(),
+            local_dtls_role.to_connection_role(),
         )?;
 
-        let mut sdp_origin = Origin::default();
-        update_sdp_origin(&mut sdp_origin, &mut d);
+        self.update_local_sdp_origin(endpoint, &mut d);
 
         let sdp = d.marshal();
 
@@ -396,12 +1120,32 @@ impl Session {
         Ok(answer)
     }
 
+    /// update_local_sdp_origin keeps origin of the endpoint's local description with incremented
+    /// session version, or initializes origin with the configured username and the local address
+    /// if the endpoint has no local description yet
+    fn update_local_sdp_origin(&self, endpoint_id: EndpointId, d: &mut SessionDescription) {
+        let mut sdp_origin = self
+            .get_endpoint(&endpoint_id)
+            .and_then(|endpoint| endpoint.local_description())
+            .and_then(|local_description| local_description.parsed.as_ref())
+            .map_or_else(Origin::default, |parsed| parsed.origin.clone());
+        if sdp_origin.session_version == 0 {
+            let local_ip = self.session_config.local_addr.ip();
+            d.origin
+                .username
+                .clone_from(&self.session_config.server_config.sdp_origin_username);
+            d.origin.address_type = if local_ip.is_ipv4() { "IP4" } else { "IP6" }.to_string();
+            d.origin.unicast_address = local_ip.to_string();
+        }
+        update_sdp_origin(&mut sdp_origin, d);
+    }
+
     /// generate_matched_sdp generates a SDP and takes the remote state into account
     /// this is used everytime we have a remote_description
     pub(crate) fn generate_matched_sdp(
         &self,
         endpoint_id: EndpointId,
-        remote_description: &RTCSessionDescription,
+        remote_description: Option<&RTCSessionDescription>,
         local_ice_params: &RTCIceParameters,
         use_identity: bool,
         include_unmatched: bool,
@@ -409,6 +1153,7 @@ impl Session {
     ) -> Result<SessionDescription> {
         let d = SessionDescription::new_jsep_session_description(use_identity);
         let (empty_mids, empty_transceivers) = (vec![], HashMap::new());
+        let max_bitrate_kbps = self.max_bitrates_kbps.get(&endpoint_id).copied();
 
         let media_sections = {
             let (mids, transceivers) = if let Some(endpoint) = self.get_endpoint(&endpoint_id) {
@@ -420,7 +1165,16 @@ impl Session {
             let mut media_sections = vec![];
             let mut already_have_application_media_section = false;
             let mut matched: HashSet<Mid> = HashSet::new();
-            if let Some(parsed) = remote_description.parsed.as_ref() {
+            if let Some(parsed) = remote_description.and_then(|d| d.parsed.as_ref()) {
+                // the SFU is always ice-lite, and two ice-lite agents never send connectivity
+                // checks to each other <https://datatracker.ietf.org/doc/html/rfc8445#section-6.1.1>
+                if is_ice_lite(parsed) {
+                    log::warn!(
+                        "{}/{}: remote is ice-lite as well, ICE connectivity will fail",
+                        self.session_id,
+                        endpoint_id
+                    );
+                }
                 for media in &parsed.media_descriptions {
                     if let Some(mid_value) = get_mid_value(media) {
                         if mid_value.is_empty() {
@@ -428,11 +1182,65 @@ impl Session {
                                 "ErrPeerConnRemoteDescriptionWithoutMidValue".to_string(),
                             ));
                         }
+                        // a media section offered with a transport protocol we don't support
+                        // is rejected in place, with the offered protocol echoed
+                        let offered_protos = if include_unmatched {
+                            vec![]
+                        } else if let Err(err) = validate_media_protos(media) {
+                            log::warn!(
+                                "{}/{}: reject media section with mid {}: {}",
+                                self.session_id,
+                                endpoint_id,
+                                mid_value,
+                                err
+                            );
+                            media.media_name.protos.clone()
+                        } else {
+                            vec![]
+                        };
 
                         if media.media_name.media == MEDIA_SECTION_APPLICATION {
+                            if !offered_protos.is_empty() {
+                                media_sections.push(MediaSection {
+                                    mid: mid_value.to_owned(),
+                                    data: true,
+                                    rejected: true,
+                                    offered_protos,
+                                    ..Default::default()
+                                });
+                                continue;
+                            }
+                            // only one SCTP association is set up per transport, so the extra
+                            // application media sections are rejected in place
+                            if already_have_application_media_section {
+                                log::warn!(
+                                    "{}/{}: reject extra application media section with mid {}",
+                                    self.session_id,
+                                    endpoint_id,
+                                    mid_value
+                                );
+                                media_sections.push(MediaSection {
+                                    mid: mid_value.to_owned(),
+                                    data: true,
+                                    rejected: true,
+                                    ..Default::default()
+                                });
+                                continue;
+                            }
+
                             media_sections.push(MediaSection {
                                 mid: mid_value.to_owned(),
                                 data: true,
+                                offered_sctp_port: if include_unmatched {
+                                    None
+                                } else {
+                                    get_sctp_port(media)
+                                },
+                                offered_max_message_size: if include_unmatched {
+                                    None
+                                } else {
+                                    get_media_max_message_size(media)
+                                },
                                 ..Default::default()
                             });
                             already_have_application_media_section = true;
@@ -440,7 +1248,7 @@ impl Session {
                         }
 
                         let kind = RTPCodecType::from(media.media_name.media.as_str());
-                        let direction = get_peer_direction(media);
+                        let direction = get_peer_direction(parsed, media);
                         if kind == RTPCodecType::Unspecified
                             || direction == RTCRtpTransceiverDirection::Unspecified
                         {
@@ -448,10 +1256,38 @@ impl Session {
                         }
 
                         if transceivers.contains_key(mid_value) {
+                            let offered_rtcp_mux =
+                                (!include_unmatched).then(|| has_rtcp_mux(media));
+                            let rejected_rtcp_mux = offered_rtcp_mux == Some(false)
+                                && self.session_config.server_config.require_rtcp_mux;
+                            if rejected_rtcp_mux {
+                                log::warn!(
+                                    "{}/{}: reject media section with mid {} without rtcp-mux",
+                                    self.session_id,
+                                    endpoint_id,
+                                    mid_value
+                                );
+                            }
                             media_sections.push(MediaSection {
                                 mid: mid_value.to_owned(),
                                 rid_map: get_rids(media),
                                 offered_direction: (!include_unmatched).then_some(direction),
+                                offered_codecs: if include_unmatched {
+                                    vec![]
+                                } else {
+                                    codecs_from_media_description(media)?
+                                },
+                                offered_header_extensions: if include_unmatched {
+                                    vec![]
+                                } else {
+                                    rtp_extensions_from_media_description(media)?
+                                },
+                                offered_rtcp_mux,
+                                offered_rtcp_rsize: (!include_unmatched)
+                                    .then(|| has_rtcp_rsize(media)),
+                                max_bitrate_kbps,
+                                rejected: rejected_rtcp_mux || !offered_protos.is_empty(),
+                                offered_protos,
                                 ..Default::default()
                             });
                             matched.insert(mid_value.to_string());
@@ -468,6 +1304,7 @@ impl Session {
                     if !matched.contains::<Mid>(mid) {
                         media_sections.push(MediaSection {
                             mid: mid.clone(),
+                            max_bitrate_kbps,
                             ..Default::default()
                         });
                     }
@@ -492,10 +1329,19 @@ impl Session {
                 return Err(Error::Other("ErrNonCertificate".to_string()));
             };
 
-        let transceivers = if let Some(endpoint) = self.get_endpoint(&endpoint_id) {
-            endpoint.get_transceivers()
+        let (transceivers, trickled_candidates) =
+            if let Some(endpoint) = self.get_endpoint(&endpoint_id) {
+                (endpoint.get_transceivers(), endpoint.trickled_candidates())
+            } else {
+                (&empty_transceivers, &[][..])
+            };
+
+        let offered_bundle_group = if include_unmatched {
+            None
+        } else if let Some(parsed) = remote_description.and_then(|d| d.parsed.as_ref()) {
+            get_bundle_group(parsed)?
         } else {
-            &empty_transceivers
+            None
         };
 
         populate_sdp(
@@ -507,6 +1353,13 @@ impl Session {
             &media_sections,
             transceivers,
             true,
+            offered_bundle_group.as_deref(),
+            // extmap-allow-mixed is offered, but only answered if offered
+            include_unmatched
+                || remote_description
+                    .and_then(|d| d.parsed.as_ref())
+                    .is_some_and(has_extmap_allow_mixed),
+            trickled_candidates,
         )
     }
 }
@@ -0,0 +1,1423 @@
+use super::*;
+use crate::configs::media_config::MediaConfig;
+use crate::configs::server_config::ServerConfig;
+use crate::description::bundle_policy::RTCBundlePolicy;
+use crate::description::rtcp_rsize_policy::RtcpRsizePolicy;
+use crate::description::rtp_codec::RTCRtpHeaderExtensionCapability;
+use crate::description::rtp_transceiver::SimulcastLayer;
+use crate::endpoint::candidate::ConnectionCredentials;
+use crate::interceptors::bitrate_limiter::limiter::Limiter;
+use crate::interceptors::tmmbr::responder::Responder;
+use crate::interceptors::{InterceptorEvent, Registry};
+use crate::messages::{MessageEvent, RTPMessageEvent, TaggedMessageEvent};
+use crate::server::certificate::RTCCertificate;
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::Instant;
+
+const OFFER_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=extmap:2 http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time\r\n\
+a=extmap:4 urn:3gpp:video-orientation\r\n\
+a=sendonly\r\n\
+a=rtpmap:96 VP8/90000\r\n";
+
+fn new_session() -> Result<Session> {
+    new_session_with_server_config(|server_config| server_config)
+}
+
+fn new_session_with_server_config(
+    with_server_config: impl FnOnce(ServerConfig) -> ServerConfig,
+) -> Result<Session> {
+    let mut media_config = MediaConfig::default();
+    media_config.register_default_codecs()?;
+    media_config.register_header_extension(
+        RTCRtpHeaderExtensionCapability {
+            uri: sdp::extmap::ABS_SEND_TIME_URI.to_owned(),
+        },
+        RTPCodecType::Video,
+        None,
+    )?;
+    let certificate =
+        RTCCertificate::from_key_pair(rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+    let server_config = Arc::new(with_server_config(
+        ServerConfig::new(vec![certificate]).with_media_config(media_config),
+    ));
+    let session_config = SessionConfig::new(server_config, "127.0.0.1:3478".parse().unwrap());
+
+    let mut session = Session::new(session_config, 1);
+    let interceptor = session
+        .session_config()
+        .server_config
+        .media_config
+        .registry()
+        .build("");
+    session
+        .get_mut_endpoints()
+        .insert(1, Endpoint::new(1, interceptor));
+    Ok(session)
+}
+
+#[test]
+fn test_negotiated_header_extensions_after_answer() -> Result<()> {
+    let mut session = new_session()?;
+
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    assert_eq!(
+        session
+            .get_endpoint(&1)
+            .and_then(|endpoint| endpoint.get_negotiated_header_extensions("0")),
+        Some(HashMap::new())
+    );
+
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    session.set_local_description(1, &answer)?;
+
+    // only the offered extension which is also supported locally is negotiated, with offered id
+    let negotiated = session
+        .get_endpoint(&1)
+        .and_then(|endpoint| endpoint.get_negotiated_header_extensions("0"));
+    assert_eq!(
+        negotiated,
+        Some(HashMap::from([(
+            2,
+            sdp::extmap::ABS_SEND_TIME_URI.to_string()
+        )]))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_set_remote_description_with_ice_lite() -> Result<()> {
+    let mut session = new_session()?;
+
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    assert!(!session.remote_is_lite(&1));
+
+    let offer =
+        RTCSessionDescription::offer(OFFER_SDP.replace("t=0 0\r\n", "t=0 0\r\na=ice-lite\r\n"))?;
+    session.set_remote_description(1, &offer)?;
+    assert!(session.remote_is_lite(&1));
+    // the answer is still generated, though connectivity checks will never succeed
+    assert!(session
+        .create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)
+        .is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_local_descriptions_keep_sdp_origin() -> Result<()> {
+    let mut session = new_session_with_server_config(|server_config| {
+        server_config.with_sdp_origin_username("sfu".to_string())
+    })?;
+
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    session.set_local_description(1, &answer)?;
+    let reoffer = session.create_offer(1, Some(&offer), &RTCIceParameters::default())?;
+
+    let answer_origin = &answer.parsed.as_ref().unwrap().origin;
+    let reoffer_origin = &reoffer.parsed.as_ref().unwrap().origin;
+    assert_eq!(answer_origin.username, "sfu");
+    assert_eq!(answer_origin.unicast_address, "127.0.0.1");
+    assert_eq!(reoffer_origin.username, answer_origin.username);
+    assert_eq!(
+        reoffer_origin.unicast_address,
+        answer_origin.unicast_address
+    );
+    assert_eq!(reoffer_origin.session_id, answer_origin.session_id);
+    assert_eq!(
+        reoffer_origin.session_version,
+        answer_origin.session_version + 1
+    );
+
+    Ok(())
+}
+
+const AUDIO_LEVEL_OFFER_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=extmap:3 urn:ietf:params:rtp-hdrext:ssrc-audio-level\r\n\
+a=extmap:4 urn:ietf:params:rtp-hdrext:sdes:mid\r\n\
+a=sendonly\r\n\
+a=rtpmap:111 opus/48000/2\r\n";
+
+#[test]
+fn test_create_answer_echoes_offered_header_extension_ids() -> Result<()> {
+    let mut media_config = MediaConfig::default();
+    media_config.register_default_codecs()?;
+    media_config.configure_audio_level_extension_headers()?;
+    let mut session = new_session_with_server_config(|server_config| {
+        server_config.with_media_config(media_config)
+    })?;
+
+    let offer = RTCSessionDescription::offer(AUDIO_LEVEL_OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+
+    // the unsupported sdes:mid is not answered, nor csrc-audio-level which isn't offered
+    let extmaps: Vec<&str> = answer.parsed.as_ref().unwrap().media_descriptions[0]
+        .attributes
+        .iter()
+        .filter(|a| a.key == "extmap")
+        .filter_map(|a| a.value.as_deref())
+        .collect();
+    assert_eq!(
+        extmaps,
+        vec!["3 urn:ietf:params:rtp-hdrext:ssrc-audio-level"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_reoffer_resumes_inactive_video() -> Result<()> {
+    let mut session = new_session()?;
+    let interceptor = session
+        .session_config()
+        .server_config
+        .media_config
+        .registry()
+        .build("");
+    session
+        .get_mut_endpoints()
+        .insert(2, Endpoint::new(2, interceptor));
+
+    let offer = RTCSessionDescription::offer(OFFER_SDP.replace("a=sendonly", "a=inactive"))?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    session.set_local_description(1, &answer)?;
+    let subscriber = session.get_endpoint(&2).unwrap();
+    assert!(subscriber.get_transceivers().get("1-0").is_none());
+    assert!(!subscriber.is_renegotiation_needed());
+
+    // the client un-mutes and signals its stream
+    let reoffer = RTCSessionDescription::offer(OFFER_SDP.replace(
+        "a=sendonly\r\n",
+        "a=sendonly\r\na=msid:stream track\r\na=ssrc:1111 cname:publisher\r\n",
+    ))?;
+    session.set_remote_description(1, &reoffer)?;
+
+    let subscriber = session.get_endpoint(&2).unwrap();
+    assert_eq!(
+        subscriber
+            .get_transceivers()
+            .get("1-0")
+            .map(|transceiver| transceiver.direction),
+        Some(RTCRtpTransceiverDirection::Sendonly)
+    );
+    assert!(subscriber.is_renegotiation_needed());
+
+    let rtcp_packets = session
+        .get_mut_endpoint(&1)
+        .unwrap()
+        .take_pending_rtcp_packets();
+    assert_eq!(rtcp_packets.len(), 1);
+    let pli = rtcp_packets[0]
+        .as_any()
+        .downcast_ref::<rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication>()
+        .unwrap();
+    assert_eq!(pli.media_ssrc, 1111);
+
+    Ok(())
+}
+
+#[test]
+fn test_create_answer_with_index_mids() -> Result<()> {
+    let mut session = new_session()?;
+
+    let sdp = OFFER_SDP.replace("a=mid:0\r\n", "");
+    let mut offer = RTCSessionDescription::offer(sdp.clone())?;
+    crate::description::with_index_mids(offer.parsed.as_mut().unwrap())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    let media = &answer.parsed.as_ref().unwrap().media_descriptions[0];
+    assert_eq!(media.media_name.media, "video");
+    assert_eq!(get_mid_value(media).map(|mid| mid.as_str()), Some("0"));
+
+    // mids are still required under BUNDLE
+    let mut offer =
+        RTCSessionDescription::offer(sdp.replace("t=0 0\r\n", "t=0 0\r\na=group:BUNDLE 0\r\n"))?;
+    assert!(crate::description::with_index_mids(offer.parsed.as_mut().unwrap()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_create_answer_rejects_unencrypted_rtp() -> Result<()> {
+    let mut session = new_session()?;
+
+    let offer = RTCSessionDescription::offer(
+        AUDIO_AND_VIDEO_OFFER_SDP.replace("m=video 9 UDP/TLS/RTP/SAVPF 96", "m=video 9 RTP/AVP 96"),
+    )?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    let answer = answer.unmarshal()?;
+
+    // only the unencrypted media section is rejected, echoing the offered protocol
+    let media_descriptions = &answer.media_descriptions;
+    assert_eq!(media_descriptions[0].media_name.port.value, 9);
+    assert_eq!(media_descriptions[1].media_name.port.value, 0);
+    assert_eq!(media_descriptions[1].media_name.protos, vec!["RTP", "AVP"]);
+    assert_eq!(media_descriptions[1].media_name.formats, vec!["96"]);
+    assert!(media_descriptions[1].attribute("inactive").is_some());
+    assert_eq!(
+        answer.attribute(sdp::description::session::ATTR_KEY_GROUP),
+        Some("BUNDLE 0")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_apply_remote_answer_to_local_offer() -> Result<()> {
+    let mut session = new_session()?;
+
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    assert_eq!(
+        session.get_endpoint(&1).unwrap().signaling_state(),
+        RTCSignalingState::HaveRemoteOffer
+    );
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    // a local offer can't be applied until the remote offer is answered
+    let local_offer = session.create_offer(1, Some(&offer), &RTCIceParameters::default())?;
+    assert!(session.set_local_description(1, &local_offer).is_err());
+    session.set_local_description(1, &answer)?;
+    assert_eq!(
+        session.get_endpoint(&1).unwrap().signaling_state(),
+        RTCSignalingState::Stable
+    );
+    // the answer can't be applied again without a remote offer
+    assert!(session.set_local_description(1, &answer).is_err());
+
+    // an answer without pending local offer is rejected
+    let remote_answer = RTCSessionDescription::answer(answer.sdp.clone())?;
+    assert!(session.apply_remote_answer(1, &remote_answer).is_err());
+
+    let local_offer = session.create_offer(1, Some(&offer), &RTCIceParameters::default())?;
+    session.set_local_description(1, &local_offer)?;
+    assert_eq!(
+        session.get_endpoint(&1).unwrap().signaling_state(),
+        RTCSignalingState::HaveLocalOffer
+    );
+    // a remote offer colliding with the pending local one is rejected
+    assert!(session.set_remote_description(1, &offer).is_err());
+
+    let remote_answer = RTCSessionDescription::answer(
+        local_offer
+            .sdp
+            .replace("a=recvonly", "a=sendonly")
+            .replace("a=setup:actpass", "a=setup:active"),
+    )?;
+    session.apply_remote_answer(1, &remote_answer)?;
+
+    let endpoint = session.get_endpoint(&1).unwrap();
+    assert_eq!(endpoint.signaling_state(), RTCSignalingState::Stable);
+    assert_eq!(
+        endpoint.get_transceivers()["0"].current_direction(),
+        RTCRtpTransceiverDirection::Recvonly
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_apply_remote_answer_without_bundle_is_rejected() -> Result<()> {
+    let mut session = new_session_with_server_config(|server_config| {
+        server_config.with_bundle_policy(RTCBundlePolicy::MaxCompat)
+    })?;
+
+    let media = |mid: u32, ssrc: u32| {
+        format!(
+            "m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:{mid}\r\n\
+a=sendonly\r\n\
+a=msid:stream track{mid}\r\n\
+a=ssrc:{ssrc} cname:publisher\r\n\
+a=rtpmap:96 VP8/90000\r\n"
+        )
+    };
+    let publisher_offer = RTCSessionDescription::offer(format!(
+        "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=group:BUNDLE 0 1\r\n\
+{}{}",
+        media(0, 1111),
+        media(1, 2222)
+    ))?;
+    session.set_remote_description(1, &publisher_offer)?;
+    let answer = session.create_answer(
+        1,
+        &publisher_offer,
+        &RTCIceParameters::default(),
+        DTLSRole::Server,
+    )?;
+    session.set_local_description(1, &answer)?;
+    let interceptor = session
+        .session_config()
+        .server_config
+        .media_config
+        .registry()
+        .build("");
+    session
+        .get_mut_endpoints()
+        .insert(2, Endpoint::new(2, interceptor));
+    assert!(session.subscribe(2, 1)?);
+    let local_offer = session.create_offer(2, None, &RTCIceParameters::default())?;
+    session.set_local_description(2, &local_offer)?;
+
+    let remote_answer = local_offer
+        .sdp
+        .replace("a=sendonly", "a=recvonly")
+        .replace("a=setup:actpass", "a=setup:active");
+    // the max-compat offer has a transport per media section, but only the BUNDLE one is served
+    let non_bundled_answer: String = remote_answer
+        .split_inclusive("\r\n")
+        .filter(|line| !line.starts_with("a=group:BUNDLE"))
+        .collect();
+    assert!(session
+        .apply_remote_answer(2, &RTCSessionDescription::answer(non_bundled_answer)?)
+        .is_err());
+    assert_eq!(
+        session.get_endpoint(&2).unwrap().signaling_state(),
+        RTCSignalingState::HaveLocalOffer
+    );
+
+    session.apply_remote_answer(2, &RTCSessionDescription::answer(remote_answer)?)?;
+    assert_eq!(
+        session.get_endpoint(&2).unwrap().signaling_state(),
+        RTCSignalingState::Stable
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_rollback_local_offer() -> Result<()> {
+    let mut session = new_session()?;
+
+    // nothing to roll back before any local offer
+    assert!(session.rollback(1).is_err());
+
+    let publisher_sdp = OFFER_SDP.replace(
+        "a=sendonly\r\n",
+        "a=sendonly\r\na=msid:stream track\r\na=ssrc:1111 cname:publisher\r\n",
+    );
+    let offer = RTCSessionDescription::offer(publisher_sdp.clone())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    session.set_local_description(1, &answer)?;
+    let interceptor = session
+        .session_config()
+        .server_config
+        .media_config
+        .registry()
+        .build("");
+    session
+        .get_mut_endpoints()
+        .insert(2, Endpoint::new(2, interceptor));
+    assert!(session.subscribe(2, 1)?);
+    let first_offer = session.create_offer(2, None, &RTCIceParameters::default())?;
+    session.set_local_description(2, &first_offer)?;
+    session.apply_remote_answer(
+        2,
+        &RTCSessionDescription::answer(
+            first_offer
+                .sdp
+                .replace("a=sendonly", "a=recvonly")
+                .replace("a=setup:actpass", "a=setup:active"),
+        )?,
+    )?;
+
+    // the publisher stops sending, which the subscriber's next offer is to negotiate
+    let inactive_offer =
+        RTCSessionDescription::offer(publisher_sdp.replace("a=sendonly", "a=inactive"))?;
+    session.set_remote_description(1, &inactive_offer)?;
+    let snapshot = |session: &Session| {
+        let endpoint = session.get_endpoint(&2).unwrap();
+        let mut transceivers: Vec<_> = endpoint
+            .get_transceivers()
+            .values()
+            .map(|t| (t.mid.clone(), t.direction, t.current_direction()))
+            .collect();
+        transceivers.sort_by(|a, b| a.0.cmp(&b.0));
+        (endpoint.get_mids().clone(), transceivers)
+    };
+    let before_offer = snapshot(&session);
+    let stable_local_description = session
+        .get_endpoint(&2)
+        .unwrap()
+        .local_description()
+        .map(|d| d.sdp.clone());
+
+    let local_offer = session.create_offer(2, None, &RTCIceParameters::default())?;
+    session.set_local_description(2, &local_offer)?;
+    let remote_answer = RTCSessionDescription::answer(
+        local_offer.sdp.replace("a=setup:actpass", "a=setup:active"),
+    )?;
+    session.rollback(2)?;
+
+    let endpoint = session.get_endpoint(&2).unwrap();
+    assert_eq!(endpoint.signaling_state(), RTCSignalingState::Stable);
+    assert_eq!(
+        endpoint.local_description().map(|d| d.sdp.clone()),
+        stable_local_description
+    );
+    assert_eq!(snapshot(&session), before_offer);
+    assert!(session.get_endpoint(&2).unwrap().is_renegotiation_needed());
+
+    // the rolled back offer can't be answered or rolled back again
+    assert!(session.apply_remote_answer(2, &remote_answer).is_err());
+    assert!(session.rollback(2).is_err());
+    assert_eq!(snapshot(&session), before_offer);
+
+    // had it not been rolled back, the answer would have changed the transceivers
+    session.set_local_description(2, &local_offer)?;
+    session.apply_remote_answer(2, &remote_answer)?;
+    assert_ne!(snapshot(&session), before_offer);
+
+    let rollback = RTCSessionDescription::rollback();
+    assert_eq!(rollback.sdp_type, RTCSdpType::Rollback);
+    assert!(rollback.sdp.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_create_initial_offer_without_remote_description() -> Result<()> {
+    let session = new_session()?;
+    assert!(session
+        .get_endpoint(&1)
+        .unwrap()
+        .get_transceivers()
+        .is_empty());
+
+    let offer = session.create_offer(1, None, &RTCIceParameters::default())?;
+    assert_eq!(offer.sdp_type, RTCSdpType::Offer);
+    let parsed = offer.parsed.as_ref().unwrap();
+    assert_eq!(parsed.media_descriptions.len(), 1);
+    assert_eq!(
+        parsed.media_descriptions[0].media_name.media,
+        MEDIA_SECTION_APPLICATION
+    );
+    assert_eq!(parsed.attribute("group"), Some("BUNDLE 0"));
+    assert!(offer.sdp.contains("a=setup:actpass\r\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_apply_remote_answer_rejects_unknown_mid() -> Result<()> {
+    let mut session = new_session()?;
+
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    session.set_local_description(1, &answer)?;
+    let local_offer = session.create_offer(1, Some(&offer), &RTCIceParameters::default())?;
+    session.set_local_description(1, &local_offer)?;
+
+    let remote_answer =
+        RTCSessionDescription::answer(local_offer.sdp.replace("a=mid:0", "a=mid:5"))?;
+    assert!(session.apply_remote_answer(1, &remote_answer).is_err());
+    assert_eq!(
+        session.get_endpoint(&1).unwrap().signaling_state(),
+        RTCSignalingState::HaveLocalOffer
+    );
+
+    Ok(())
+}
+
+const DATA_CHANNEL_OFFER_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=sctp-port:5001\r\n\
+a=max-message-size:262144\r\n";
+
+#[test]
+fn test_create_answer_echoes_offered_sctp_port() -> Result<()> {
+    let session = new_session()?;
+
+    let offer = RTCSessionDescription::offer(DATA_CHANNEL_OFFER_SDP.to_string())?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    let parsed = answer.parsed.as_ref().unwrap();
+    assert_eq!(get_sctp_port(&parsed.media_descriptions[0]), Some(5001));
+    assert!(answer.sdp.contains("a=sctp-port:5001\r\n"));
+
+    let offer =
+        RTCSessionDescription::offer(DATA_CHANNEL_OFFER_SDP.replace("a=sctp-port:5001\r\n", ""))?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    let parsed = answer.parsed.as_ref().unwrap();
+    assert_eq!(get_sctp_port(&parsed.media_descriptions[0]), Some(5000));
+
+    Ok(())
+}
+
+#[test]
+fn test_create_answer_clamps_max_message_size() -> Result<()> {
+    let session = new_session()?;
+    let local_max_message_size = ServerConfig::new(vec![])
+        .sctp_server_config
+        .transport
+        .max_message_size();
+
+    // the remote offers a smaller limit than the local one
+    let offer = RTCSessionDescription::offer(DATA_CHANNEL_OFFER_SDP.replace(
+        "a=max-message-size:262144\r\n",
+        "a=max-message-size:16384\r\n",
+    ))?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(answer.sdp.contains("a=max-message-size:16384\r\n"));
+
+    // the local limit is advertised if the remote one is absent, larger or unlimited
+    for max_message_size in [
+        "",
+        "a=max-message-size:1073741823\r\n",
+        "a=max-message-size:0\r\n",
+    ] {
+        let offer = RTCSessionDescription::offer(
+            DATA_CHANNEL_OFFER_SDP.replace("a=max-message-size:262144\r\n", max_message_size),
+        )?;
+        let answer =
+            session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+        assert!(answer.sdp.contains(&format!(
+            "a=max-message-size:{}\r\n",
+            local_max_message_size
+        )));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_create_answer_with_session_sctp_port() -> Result<()> {
+    let mut session = new_session()?;
+    session.set_sctp_port(Some(5022));
+
+    let offer =
+        RTCSessionDescription::offer(DATA_CHANNEL_OFFER_SDP.replace("a=sctp-port:5001\r\n", ""))?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(answer.sdp.contains("a=sctp-port:5022\r\n"));
+    let answer = RTCSessionDescription::answer(answer.sdp)?;
+    let parsed = answer.parsed.as_ref().unwrap();
+    assert_eq!(get_sctp_port(&parsed.media_descriptions[0]), Some(5022));
+
+    // the offered port is still echoed
+    let offer = RTCSessionDescription::offer(DATA_CHANNEL_OFFER_SDP.to_string())?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(answer.sdp.contains("a=sctp-port:5001\r\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_create_answer_with_setup_active_as_dtls_client() -> Result<()> {
+    for (remote_setup, client_supported, local_setup) in [
+        ("a=setup:actpass", false, "a=setup:passive"),
+        ("a=setup:actpass", true, "a=setup:active"),
+        ("a=setup:passive", false, "a=setup:active"),
+        ("a=setup:active", true, "a=setup:passive"),
+    ] {
+        let offer =
+            RTCSessionDescription::offer(OFFER_SDP.replace("a=setup:actpass", remote_setup))?;
+        let mut session = new_session()?;
+        session.set_remote_description(1, &offer)?;
+        let remote_dtls_role = DTLSRole::from(offer.parsed.as_ref().unwrap());
+        let local_conn_cred =
+            ConnectionCredentials::new(vec![], remote_dtls_role, client_supported);
+        let answer = session.create_answer(
+            1,
+            &offer,
+            &local_conn_cred.ice_params,
+            local_conn_cred.dtls_params.role,
+        )?;
+        assert!(
+            answer.sdp.contains(local_setup),
+            "answer to {remote_setup} should contain {local_setup}"
+        );
+    }
+
+    Ok(())
+}
+
+// TMMBR from SSRC 0x11223344 for media SSRC 0x55667788 with 1_000_000 bps
+const TMMBR_PACKET: [u8; 20] = [
+    0x83, 0xcd, 0x00, 0x04, 0x11, 0x22, 0x33, 0x44, 0x00, 0x00, 0x00, 0x00, 0x55, 0x66, 0x77, 0x88,
+    0x14, 0xf4, 0x24, 0x28,
+];
+
+fn new_transport_context(peer_addr: &str) -> TransportContext {
+    TransportContext {
+        local_addr: "127.0.0.1:3478".parse().unwrap(),
+        peer_addr: peer_addr.parse().unwrap(),
+        ecn: None,
+    }
+}
+
+fn read_interceptor(
+    session: &mut Session,
+    endpoint_id: EndpointId,
+    transport: TransportContext,
+    message: MessageEvent,
+) -> Vec<InterceptorEvent> {
+    let mut msg = TaggedMessageEvent {
+        now: Instant::now(),
+        transport,
+        message,
+    };
+    session
+        .get_mut_endpoint(&endpoint_id)
+        .unwrap()
+        .get_mut_interceptor()
+        .read(&mut msg)
+}
+
+#[test]
+fn test_endpoint_interceptor_profiles() -> Result<()> {
+    let mut publisher_registry = Registry::new();
+    publisher_registry.add(Box::new(Limiter::builder().with_max_bitrate(0)));
+    let mut subscriber_registry = Registry::new();
+    subscriber_registry.add(Box::new(Responder::builder()));
+
+    let mut media_config = MediaConfig::default();
+    media_config.register_interceptor_profile("publisher".to_string(), publisher_registry);
+    media_config.register_interceptor_profile("subscriber".to_string(), subscriber_registry);
+    let certificate =
+        RTCCertificate::from_key_pair(rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+    let server_config =
+        Arc::new(ServerConfig::new(vec![certificate]).with_media_config(media_config));
+    let mut session = Session::new(
+        SessionConfig::new(server_config, "127.0.0.1:3478".parse().unwrap()),
+        1,
+    );
+
+    let (publisher, subscriber) = (1, 2);
+    session.set_interceptor_profile(publisher, "publisher".to_string());
+    session.set_interceptor_profile(subscriber, "subscriber".to_string());
+    let transports = [
+        (publisher, new_transport_context("127.0.0.1:5001")),
+        (subscriber, new_transport_context("127.0.0.1:5002")),
+    ];
+    for (endpoint_id, transport) in &transports {
+        let candidate = Rc::new(Candidate::new(
+            1,
+            *endpoint_id,
+            ConnectionCredentials::default(),
+            ConnectionCredentials::default(),
+            RTCSessionDescription::default(),
+            RTCSessionDescription::default(),
+            Instant::now(),
+        ));
+        session.add_endpoint(&candidate, transport)?;
+    }
+
+    for (endpoint_id, transport) in transports {
+        let rtp_packet = || {
+            MessageEvent::Rtp(RTPMessageEvent::Rtp(rtp::packet::Packet {
+                header: rtp::header::Header {
+                    version: 2,
+                    ssrc: 1,
+                    ..Default::default()
+                },
+                payload: Bytes::from(vec![0u8; 1400]),
+            }))
+        };
+        read_interceptor(&mut session, endpoint_id, transport, rtp_packet());
+        let events = read_interceptor(&mut session, endpoint_id, transport, rtp_packet());
+        let dropped = events
+            .iter()
+            .any(|event| matches!(event, InterceptorEvent::Drop));
+        // only the publisher's chain limits inbound bitrate
+        assert_eq!(dropped, endpoint_id == publisher);
+
+        let tmmbr = rtcp::packet::unmarshal(&mut Bytes::from_static(&TMMBR_PACKET))?;
+        let events = read_interceptor(
+            &mut session,
+            endpoint_id,
+            transport,
+            MessageEvent::Rtp(RTPMessageEvent::Rtcp(tmmbr)),
+        );
+        let responded = events
+            .iter()
+            .any(|event| matches!(event, InterceptorEvent::Outbound(_)));
+        // only the subscriber's chain responds TMMBR with TMMBN
+        assert_eq!(responded, endpoint_id == subscriber);
+    }
+
+    Ok(())
+}
+
+const TWO_APPLICATION_SECTIONS_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=group:BUNDLE 0 1\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=sctp-port:5000\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:1\r\n\
+a=sctp-port:5001\r\n";
+
+#[test]
+fn test_create_answer_rejects_extra_application_media_sections() -> Result<()> {
+    let mut session = new_session()?;
+
+    let offer = RTCSessionDescription::offer(TWO_APPLICATION_SECTIONS_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    let parsed = answer.unmarshal()?;
+
+    // m-line order is kept, only the first application media section is accepted
+    let sections: Vec<(Option<&String>, isize)> = parsed
+        .media_descriptions
+        .iter()
+        .map(|media| (get_mid_value(media), media.media_name.port.value))
+        .collect();
+    assert_eq!(
+        sections,
+        vec![(Some(&"0".to_string()), 9), (Some(&"1".to_string()), 0)]
+    );
+    assert_eq!(get_sctp_port(&parsed.media_descriptions[0]), Some(5000));
+    assert_eq!(
+        parsed.attribute(sdp::description::session::ATTR_KEY_GROUP),
+        Some("BUNDLE 0")
+    );
+
+    Ok(())
+}
+
+fn create_answer_with_rtcp_mux(
+    require_rtcp_mux: bool,
+    offer_sdp: &str,
+) -> Result<sdp::MediaDescription> {
+    let mut session = new_session_with_server_config(|server_config| {
+        server_config.with_require_rtcp_mux(require_rtcp_mux)
+    })?;
+    let offer = RTCSessionDescription::offer(offer_sdp.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    Ok(answer.unmarshal()?.media_descriptions[0].clone())
+}
+
+#[test]
+fn test_create_answer_with_rtcp_mux_policy() -> Result<()> {
+    let muxed_offer_sdp = format!("{}a=rtcp-mux\r\n", OFFER_SDP);
+
+    // the section without rtcp-mux is rejected when it is required
+    let media = create_answer_with_rtcp_mux(true, OFFER_SDP)?;
+    assert_eq!(media.media_name.port.value, 0);
+    assert!(media.attribute("inactive").is_some());
+    let media = create_answer_with_rtcp_mux(true, &muxed_offer_sdp)?;
+    assert_eq!(media.media_name.port.value, 9);
+    assert!(media.attribute("rtcp-mux").is_some());
+
+    // otherwise it falls back to non-muxed
+    let media = create_answer_with_rtcp_mux(false, OFFER_SDP)?;
+    assert_eq!(media.media_name.port.value, 9);
+    assert!(media.attribute("rtcp-mux").is_none());
+    let media = create_answer_with_rtcp_mux(false, &muxed_offer_sdp)?;
+    assert!(media.attribute("rtcp-mux").is_some());
+
+    Ok(())
+}
+
+const INTERLEAVED_APPLICATION_SECTION_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=group:BUNDLE 0 1 2\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=sendonly\r\n\
+a=rtpmap:96 VP8/90000\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:1\r\n\
+a=sctp-port:5000\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:2\r\n\
+a=sendonly\r\n\
+a=rtpmap:111 opus/48000/2\r\n";
+
+fn get_media_sections(desc: &RTCSessionDescription) -> Result<Vec<(String, String)>> {
+    Ok(desc
+        .unmarshal()?
+        .media_descriptions
+        .iter()
+        .map(|media| {
+            (
+                media.media_name.media.clone(),
+                get_mid_value(media).cloned().unwrap_or_default(),
+            )
+        })
+        .collect())
+}
+
+#[test]
+fn test_create_answer_keeps_interleaved_application_section_order() -> Result<()> {
+    let mut session = new_session()?;
+
+    let offer = RTCSessionDescription::offer(INTERLEAVED_APPLICATION_SECTION_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    let expected = vec![
+        ("video".to_string(), "0".to_string()),
+        ("application".to_string(), "1".to_string()),
+        ("audio".to_string(), "2".to_string()),
+    ];
+    assert_eq!(get_media_sections(&answer)?, expected);
+    session.set_local_description(1, &answer)?;
+
+    // a later local offer keeps the negotiated order too
+    let offer = session.create_offer(1, Some(&offer), &RTCIceParameters::default())?;
+    assert_eq!(get_media_sections(&offer)?, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_subscribe_beyond_max_subscribers_per_publisher() -> Result<()> {
+    let mut session = new_session_with_server_config(|server_config| {
+        server_config.with_max_subscribers_per_publisher(1)
+    })?;
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    for subscriber in [2, 3] {
+        let interceptor = session
+            .session_config()
+            .server_config
+            .media_config
+            .registry()
+            .build("");
+        session
+            .get_mut_endpoints()
+            .insert(subscriber, Endpoint::new(subscriber, interceptor));
+    }
+
+    assert!(session.subscribe(2, 1)?);
+    assert!(session.subscribe(3, 1).is_err());
+    // the existing subscription stays active
+    assert!(session
+        .get_endpoint(&2)
+        .is_some_and(|endpoint| endpoint.get_transceivers().contains_key("1-0")));
+    assert!(session
+        .get_endpoint(&3)
+        .is_some_and(|endpoint| !endpoint.is_subscribed_to(1)));
+    // subscribing again is not counted as another subscriber
+    assert!(!session.subscribe(2, 1)?);
+
+    // the session limit overrides the one of ServerConfig
+    session.set_max_subscribers_per_publisher(None);
+    assert!(session.subscribe(3, 1)?);
+
+    Ok(())
+}
+
+const H264_AND_VP8_OFFER_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96 102\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=sendonly\r\n\
+a=rtpmap:96 VP8/90000\r\n\
+a=rtpmap:102 H264/90000\r\n\
+a=fmtp:102 level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42001f\r\n";
+
+#[test]
+fn test_update_codec_preferences_removes_disabled_codec() -> Result<()> {
+    let mut session = new_session()?;
+    let offer = RTCSessionDescription::offer(H264_AND_VP8_OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(answer.sdp.contains("a=rtpmap:102 H264/90000"));
+    session.set_local_description(1, &answer)?;
+    assert!(!session.get_endpoint(&1).unwrap().is_renegotiation_needed());
+
+    // codecs not negotiated by any transceiver don't trigger renegotiation
+    assert!(session.update_codec_preferences(&["video/AV1"]).is_empty());
+    assert_eq!(session.update_codec_preferences(&["video/h264"]), vec![1]);
+    assert!(session.get_endpoint(&1).unwrap().is_renegotiation_needed());
+
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(answer.sdp.contains("a=rtpmap:96 VP8/90000"));
+    assert!(!answer.sdp.contains("H264"));
+    let local_offer = session.create_offer(1, Some(&offer), &RTCIceParameters::default())?;
+    assert!(local_offer.sdp.contains("VP8/90000"));
+    assert!(!local_offer.sdp.contains("H264"));
+
+    // enabling it again restores the codec
+    assert_eq!(session.update_codec_preferences(&[]), vec![1]);
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(answer.sdp.contains("a=rtpmap:102 H264/90000"));
+
+    Ok(())
+}
+
+const AUDIO_AND_VIDEO_OFFER_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=sendonly\r\n\
+a=rtcp-mux\r\n\
+a=rtcp-rsize\r\n\
+a=rtpmap:111 opus/48000/2\r\n\
+a=fmtp:111 minptime=10;useinbandfec=1\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:1\r\n\
+a=sendonly\r\n\
+a=rtcp-mux\r\n\
+a=rtcp-rsize\r\n\
+a=rtpmap:96 VP8/90000\r\n";
+
+#[test]
+fn test_create_answer_with_rtcp_rsize_policy() -> Result<()> {
+    let mut session = new_session_with_server_config(|server_config| {
+        server_config.with_rtcp_rsize_policy(RtcpRsizePolicy::VideoOnly)
+    })?;
+    let offer = RTCSessionDescription::offer(AUDIO_AND_VIDEO_OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    let media_descriptions = answer.unmarshal()?.media_descriptions;
+    assert_eq!(media_descriptions[0].media_name.media, "audio");
+    assert!(media_descriptions[0].attribute("rtcp-rsize").is_none());
+    assert_eq!(media_descriptions[1].media_name.media, "video");
+    assert!(media_descriptions[1].attribute("rtcp-rsize").is_some());
+
+    // rtcp-rsize is not answered if not offered, even if the policy allows it
+    let offer =
+        RTCSessionDescription::offer(AUDIO_AND_VIDEO_OFFER_SDP.replace("a=rtcp-rsize\r\n", ""))?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    let media_descriptions = answer.unmarshal()?.media_descriptions;
+    assert!(media_descriptions[1].attribute("rtcp-rsize").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_create_offer_with_rtcp_rsize_policy() -> Result<()> {
+    for (policy, has_rtcp_rsize) in [
+        (RtcpRsizePolicy::All, true),
+        (RtcpRsizePolicy::Disabled, false),
+    ] {
+        let mut session = new_session_with_server_config(|server_config| {
+            server_config.with_rtcp_rsize_policy(policy)
+        })?;
+        session.add_transceiver_from_kind(
+            1,
+            RTPCodecType::Video,
+            RTCRtpTransceiverDirection::Recvonly,
+        )?;
+        let offer = session.create_offer(1, None, &RTCIceParameters::default())?;
+        let media_descriptions = offer.unmarshal()?.media_descriptions;
+        assert_eq!(media_descriptions[0].media_name.media, "video");
+        assert_eq!(
+            media_descriptions[0].attribute("rtcp-rsize").is_some(),
+            has_rtcp_rsize
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_add_transceiver_from_kind_is_offered() -> Result<()> {
+    let mut session = new_session()?;
+
+    let mid = session.add_transceiver_from_kind(
+        1,
+        RTPCodecType::Video,
+        RTCRtpTransceiverDirection::Recvonly,
+    )?;
+    assert_eq!(mid, "0");
+    assert!(session
+        .get_endpoint(&1)
+        .is_some_and(|endpoint| endpoint.is_renegotiation_needed()));
+    assert!(session
+        .add_transceiver_from_kind(2, RTPCodecType::Video, RTCRtpTransceiverDirection::Recvonly)
+        .is_err());
+
+    let offer = session.create_offer(1, None, &RTCIceParameters::default())?;
+    let parsed = offer.parsed.as_ref().unwrap();
+    let video = parsed
+        .media_descriptions
+        .iter()
+        .find(|media| media.media_name.media == "video")
+        .expect("video media section");
+    assert_eq!(get_mid_value(video), Some(&mid));
+    assert!(video.attribute("recvonly").is_some());
+    assert!(video.media_name.formats.contains(&"96".to_string()));
+    Ok(())
+}
+
+#[test]
+fn test_remove_endpoint_stops_subscribed_transceivers() -> Result<()> {
+    let mut session = new_session()?;
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    let interceptor = session
+        .session_config()
+        .server_config
+        .media_config
+        .registry()
+        .build("");
+    session
+        .get_mut_endpoints()
+        .insert(2, Endpoint::new(2, interceptor));
+    assert!(session.subscribe(2, 1)?);
+    session
+        .get_mut_endpoint(&2)
+        .unwrap()
+        .set_renegotiation_needed(false);
+
+    assert!(session.remove_endpoint(&1).is_some());
+    assert_eq!(session.get_endpoints().len(), 1);
+    assert!(session.has_endpoints());
+    let subscriber = session.get_endpoint(&2).unwrap();
+    assert_eq!(
+        subscriber
+            .get_transceivers()
+            .get("1-0")
+            .map(|t| t.direction),
+        Some(RTCRtpTransceiverDirection::Inactive)
+    );
+    assert!(subscriber.is_renegotiation_needed());
+
+    assert!(session.remove_endpoint(&1).is_none());
+    assert!(session.remove_endpoint(&2).is_some());
+    assert!(!session.has_endpoints());
+    Ok(())
+}
+
+#[test]
+fn test_subscriber_answer_requests_keyframe() -> Result<()> {
+    let mut session = new_session()?;
+    let offer = RTCSessionDescription::offer(OFFER_SDP.replace(
+        "a=sendonly\r\n",
+        "a=sendonly\r\na=msid:stream track\r\na=ssrc:1111 cname:publisher\r\n",
+    ))?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    session.set_local_description(1, &answer)?;
+    let interceptor = session
+        .session_config()
+        .server_config
+        .media_config
+        .registry()
+        .build("");
+    session
+        .get_mut_endpoints()
+        .insert(2, Endpoint::new(2, interceptor));
+    assert!(session.subscribe(2, 1)?);
+
+    // no keyframe is requested until the subscriber accepts the forwarded stream
+    let local_offer = session.create_offer(2, None, &RTCIceParameters::default())?;
+    session.set_local_description(2, &local_offer)?;
+    let publisher = session.get_mut_endpoint(&1).unwrap();
+    assert!(!publisher.has_pending_rtcp_packets());
+
+    let remote_answer = RTCSessionDescription::answer(
+        local_offer
+            .sdp
+            .replace("a=sendonly", "a=recvonly")
+            .replace("a=setup:actpass", "a=setup:active"),
+    )?;
+    session.apply_remote_answer(2, &remote_answer)?;
+    let rtcp_packets = session
+        .get_mut_endpoint(&1)
+        .unwrap()
+        .take_pending_rtcp_packets();
+    assert_eq!(rtcp_packets.len(), 1);
+    let pli = rtcp_packets[0]
+        .as_any()
+        .downcast_ref::<rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication>()
+        .unwrap();
+    assert_eq!(pli.media_ssrc, 1111);
+
+    Ok(())
+}
+
+const SIMULCAST_OFFER_SDP: &str = "v=0\r\n\
+o=- 4596489990601351948 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=extmap:4 urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id\r\n\
+a=sendonly\r\n\
+a=rtpmap:96 VP8/90000\r\n\
+a=rid:q send\r\n\
+a=rid:h send\r\n\
+a=rid:f send\r\n\
+a=simulcast:send q;h;f\r\n";
+
+#[test]
+fn test_select_simulcast_layer() -> Result<()> {
+    let mut session = new_session()?;
+    let offer = RTCSessionDescription::offer(SIMULCAST_OFFER_SDP.to_string())?;
+    let rid_map = get_rids(&offer.parsed.as_ref().unwrap().media_descriptions[0]);
+    assert_eq!(rid_map.len(), 3);
+    assert!(["q", "h", "f"].iter().all(|rid| rid_map.contains_key(*rid)));
+
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    let video = &answer.parsed.as_ref().unwrap().media_descriptions[0];
+    let mut simulcast = video
+        .attribute("simulcast")
+        .flatten()
+        .and_then(|simulcast| simulcast.strip_prefix("recv "))
+        .map(|rids| rids.split(';').collect::<Vec<_>>())
+        .unwrap_or_default();
+    simulcast.sort();
+    assert_eq!(simulcast, vec!["f", "h", "q"]);
+
+    let interceptor = session
+        .session_config()
+        .server_config
+        .media_config
+        .registry()
+        .build("");
+    session
+        .get_mut_endpoints()
+        .insert(2, Endpoint::new(2, interceptor));
+    assert!(session.subscribe(2, 1)?);
+    session.select_layer(2, "1-0", Some(SimulcastLayer::Low.rid().to_string()))?;
+    assert_eq!(
+        session
+            .get_endpoint(&2)
+            .and_then(|endpoint| endpoint.get_transceivers().get("1-0"))
+            .and_then(|transceiver| transceiver.selected_rid.as_deref())
+            .and_then(SimulcastLayer::from_rid),
+        Some(SimulcastLayer::Low)
+    );
+    assert!(session.select_layer(2, "1-1", None).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_extmap_allow_mixed() -> Result<()> {
+    let mut session = new_session()?;
+
+    let offer = RTCSessionDescription::offer(
+        OFFER_SDP.replace("t=0 0\r\n", "t=0 0\r\na=extmap-allow-mixed\r\n"),
+    )?;
+    session.set_remote_description(1, &offer)?;
+    assert!(session
+        .get_endpoint(&1)
+        .is_some_and(|endpoint| endpoint.extmap_allow_mixed()));
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(answer.sdp.contains("a=extmap-allow-mixed\r\n"));
+
+    // it is not answered if not offered
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    assert!(session
+        .get_endpoint(&1)
+        .is_some_and(|endpoint| !endpoint.extmap_allow_mixed()));
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(!answer.sdp.contains("a=extmap-allow-mixed"));
+
+    // while it is always offered
+    let reoffer = session.create_offer(1, Some(&answer), &RTCIceParameters::default())?;
+    assert!(reoffer.sdp.contains("a=extmap-allow-mixed\r\n"));
+    Ok(())
+}
+
+#[test]
+fn test_create_answer_with_fingerprint_per_algorithm() -> Result<()> {
+    let mut session = new_session()?;
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    let media = &answer.unmarshal()?.media_descriptions[0];
+    let algorithms: Vec<&str> = media
+        .attributes
+        .iter()
+        .filter(|a| a.key == "fingerprint")
+        .filter_map(|a| a.value.as_ref()?.split(' ').next())
+        .collect();
+    assert_eq!(algorithms, vec!["sha-256", "sha-512"]);
+    Ok(())
+}
+
+#[test]
+fn test_create_answer_with_max_bitrate() -> Result<()> {
+    let mut session = new_session()?;
+    session.set_max_bitrate_kbps(1, Some(512));
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(answer.sdp.contains("b=AS:512\r\nb=TIAS:512000\r\n"));
+
+    session.set_max_bitrate_kbps(1, None);
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(!answer.sdp.contains("b=AS"));
+    Ok(())
+}
+
+#[test]
+fn test_msid_without_ssrc_attributes() -> Result<()> {
+    let mut session = new_session()?;
+    let offer = RTCSessionDescription::offer(format!("{}a=msid:stream0 audio0\r\n", OFFER_SDP))?;
+    session.set_remote_description(1, &offer)?;
+    assert_eq!(
+        session
+            .get_endpoint(&1)
+            .and_then(|endpoint| endpoint.get_stream_id("0")),
+        Some("stream0")
+    );
+
+    // the subscriber receives the track in the publisher's MediaStream
+    let interceptor = session
+        .session_config()
+        .server_config
+        .media_config
+        .registry()
+        .build("");
+    session
+        .get_mut_endpoints()
+        .insert(2, Endpoint::new(2, interceptor));
+    assert!(session.subscribe(2, 1)?);
+    assert_eq!(
+        session
+            .get_endpoint(&2)
+            .and_then(|endpoint| endpoint.get_stream_id("1-0")),
+        Some("stream0")
+    );
+    let offer = session.create_offer(2, None, &RTCIceParameters::default())?;
+    assert!(offer.sdp.contains("a=msid:stream0 audio0\r\n"));
+    Ok(())
+}
+
+#[test]
+fn test_restart_ice_advertises_fresh_credentials() -> Result<()> {
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    let mut session = new_session()?;
+    session.set_remote_description(1, &offer)?;
+    assert!(session.restart_ice(2).is_err());
+
+    let first = session.restart_ice(1)?;
+    let second = session.restart_ice(1)?;
+    assert_ne!(first.username_fragment, second.username_fragment);
+    assert_ne!(first.password, second.password);
+    assert!(session
+        .get_endpoint(&1)
+        .is_some_and(|endpoint| endpoint.is_renegotiation_needed()));
+
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(answer
+        .sdp
+        .contains(&format!("a=ice-ufrag:{}", second.username_fragment)));
+    assert!(answer
+        .sdp
+        .contains(&format!("a=ice-pwd:{}", second.password)));
+    Ok(())
+}
+
+#[test]
+fn test_add_local_candidate_after_answer() -> Result<()> {
+    let offer = RTCSessionDescription::offer(OFFER_SDP.to_string())?;
+    let mut session = new_session()?;
+    session.set_remote_description(1, &offer)?;
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(session
+        .add_local_candidate(2, "10.0.0.2:3478".parse().unwrap())
+        .is_err());
+
+    session.add_local_candidate(1, "10.0.0.2:3478".parse().unwrap())?;
+    session.add_local_candidate(1, "10.0.0.2:3478".parse().unwrap())?;
+    // candidates are trickled to media sections of the local description
+    assert!(session.take_local_candidates(1).is_err());
+    session.set_local_description(1, &answer)?;
+    let candidates = session.take_local_candidates(1)?;
+    assert_eq!(
+        candidates,
+        vec![(
+            "0".to_string(),
+            "a=candidate:3716451231 1 UDP 2130706175 10.0.0.2 3478 typ host".to_string()
+        )]
+    );
+    assert!(session.take_local_candidates(1)?.is_empty());
+
+    // trickled candidates are advertised by later descriptions as well
+    let answer =
+        session.create_answer(1, &offer, &RTCIceParameters::default(), DTLSRole::Server)?;
+    assert!(answer.sdp.contains(&candidates[0].1));
+    Ok(())
+}